@@ -0,0 +1,241 @@
+//! Decoding, resampling and whole conversions of MP3, Ogg Vorbis and FLAC inputs of several
+//! lengths, to validate performance work such as streaming or SIMD with `cargo bench --bench
+//! convert`.
+//!
+//! The long fixtures are generated on the first run into `target/bench-fixtures`, so no large
+//! files are checked in: the MP3 and Ogg Vorbis ones repeat the frames of the samples in `audio/`
+//! and the FLAC ones encode a synthetic signal with wavup itself.
+//!
+//! Throughputs are in seconds of audio, so the reported rate is the speed relative to real time.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ogg::{
+    reading::PacketReader,
+    writing::{PacketWriteEndInfo, PacketWriter},
+};
+use std::{
+    f32::consts::PI,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+use wavup::{AudioConverterBuilder, AudioInspector, OutputFormat, SampleFormat};
+
+/// The approximate lengths of the fixtures, in seconds.
+const LENGTHS_SECS: [u32; 2] = [30, 300];
+
+/// The sample rate of the synthetic FLAC fixtures and of the resampling benchmark input.
+const SYNTHETIC_RATE: u32 = 48000;
+
+/// An input of the benchmarks, generated if it does not exist yet.
+struct Fixture {
+    name: String,
+    path: PathBuf,
+    secs: u32,
+    sample_rate: u32,
+}
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/bench-fixtures")
+}
+
+fn sample_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("audio")
+        .join(name)
+}
+
+/// Create the fixture `name` with `generate` unless a previous run left it.
+fn fixture(name: String, secs: u32, generate: impl FnOnce(&Path)) -> Fixture {
+    let path = fixture_dir().join(&name);
+    if !path.exists() {
+        fs::create_dir_all(fixture_dir()).unwrap();
+        generate(&path);
+    }
+    let sample_rate = AudioInspector::probe(&path).unwrap().sample_rate;
+
+    Fixture {
+        name,
+        path,
+        secs,
+        sample_rate,
+    }
+}
+
+/// The MP3 sample repeated to last at least `secs` seconds. MP3 frames decode on their own, so
+/// the copies are simply concatenated.
+fn mp3_fixture(secs: u32) -> Fixture {
+    fixture(format!("mp3_{}s.mp3", secs), secs, |path| {
+        let sample_path = sample_path("dual_ch_audio.mp3");
+        let sample_secs = AudioInspector::probe(&sample_path)
+            .unwrap()
+            .duration_secs
+            .unwrap();
+        let sample = fs::read(sample_path).unwrap();
+        let copies = (secs as f64 / sample_secs).ceil() as usize;
+        fs::write(path, sample.repeat(copies)).unwrap();
+    })
+}
+
+/// The Ogg Vorbis sample repeated to last at least `secs` seconds, as one logical stream: the
+/// headers once, then the audio packets of each copy with shifted granule positions.
+fn vorbis_fixture(secs: u32) -> Fixture {
+    fixture(format!("vorbis_{}s.ogg", secs), secs, |path| {
+        let sample_path = sample_path("example_vorbris.ogg");
+        let info = AudioInspector::probe(&sample_path).unwrap();
+        let copies = (secs as f64 / info.duration_secs.unwrap()).ceil() as u64;
+
+        let mut reader = PacketReader::new(Cursor::new(fs::read(sample_path).unwrap()));
+        let mut packets = Vec::new();
+        while let Some(packet) = reader.read_packet().unwrap() {
+            packets.push(packet);
+        }
+        let (headers, audio) = packets.split_at(3);
+        let granules = audio.last().unwrap().absgp_page();
+        let serial = headers[0].stream_serial();
+
+        let mut writer = PacketWriter::new(Vec::new());
+        for packet in headers {
+            let end = match packet.last_in_page() {
+                true => PacketWriteEndInfo::EndPage,
+                false => PacketWriteEndInfo::NormalPacket,
+            };
+            writer
+                .write_packet(packet.data.clone().into(), serial, end, 0)
+                .unwrap();
+        }
+        for copy in 0..copies {
+            for packet in audio {
+                let end = if copy + 1 == copies && packet.last_in_stream() {
+                    PacketWriteEndInfo::EndStream
+                } else if packet.last_in_page() {
+                    PacketWriteEndInfo::EndPage
+                } else {
+                    PacketWriteEndInfo::NormalPacket
+                };
+                let granule = packet.absgp_page() + copy * granules;
+                writer
+                    .write_packet(packet.data.clone().into(), serial, end, granule)
+                    .unwrap();
+            }
+        }
+        fs::write(path, writer.into_inner()).unwrap();
+    })
+}
+
+/// A synthetic signal of `secs` seconds encoded to 16-bit stereo FLAC by wavup.
+fn flac_fixture(secs: u32) -> Fixture {
+    fixture(format!("flac_{}s.flac", secs), secs, |path| {
+        let converter = AudioConverterBuilder::new(path, SYNTHETIC_RATE)
+            .with_output_format(OutputFormat::Flac {
+                compression_level: 5,
+            })
+            .build()
+            .unwrap();
+        converter
+            .convert_audio_from_bytes(&synthetic_wav(secs))
+            .unwrap();
+    })
+}
+
+/// A stereo 32-bit float WAV file of `secs` seconds: a tone sweeping through the speech band
+/// over a little noise, so the lossless encoders cannot shortcut it.
+fn synthetic_wav(secs: u32) -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: SYNTHETIC_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+    let mut phase = 0.0_f32;
+    let mut noise = 1_u32;
+    for frame in 0..SYNTHETIC_RATE * secs {
+        let frequency = 200.0 + 3000.0 * (frame % SYNTHETIC_RATE) as f32 / SYNTHETIC_RATE as f32;
+        phase = (phase + 2.0 * PI * frequency / SYNTHETIC_RATE as f32) % (2.0 * PI);
+        noise = noise.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let dither = (noise >> 8) as f32 / (1 << 24) as f32 - 0.5;
+        writer
+            .write_sample(0.5 * phase.sin() + 0.01 * dither)
+            .unwrap();
+        writer
+            .write_sample(0.5 * phase.cos() + 0.01 * dither)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+    bytes.into_inner()
+}
+
+fn fixtures() -> Vec<Fixture> {
+    LENGTHS_SECS
+        .iter()
+        .flat_map(|secs| {
+            [
+                mp3_fixture(*secs),
+                vorbis_fixture(*secs),
+                flac_fixture(*secs),
+            ]
+        })
+        .collect()
+}
+
+/// Decoding alone: the output keeps the sample rate of the input, so nothing is resampled.
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    for fixture in fixtures() {
+        let converter = AudioConverterBuilder::new("", fixture.sample_rate)
+            .with_input_path(&fixture.path)
+            .build()
+            .unwrap();
+        group.throughput(Throughput::Elements(fixture.secs as u64));
+        group.bench_function(BenchmarkId::from_parameter(&fixture.name), |b| {
+            b.iter(|| converter.decode_to_f32().unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Resampling 48 kHz stereo audio to 16 kHz, from float WAV input that costs next to nothing to
+/// decode.
+fn resample(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resample_48k_to_16k");
+    group.sample_size(10);
+    for secs in LENGTHS_SECS {
+        // Shared, so each iteration hands the converter the input without copying it
+        let input: std::sync::Arc<[u8]> = synthetic_wav(secs).into();
+        let converter = AudioConverterBuilder::new("", 16000)
+            .with_sample_format(SampleFormat::Float)
+            .with_bits_per_sample(32)
+            .build()
+            .unwrap();
+        group.throughput(Throughput::Elements(secs as u64));
+        group.bench_function(BenchmarkId::from_parameter(format!("{}s", secs)), |b| {
+            b.iter(|| converter.convert_bytes_to_vec(input.clone()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Whole conversions to the 16 kHz mono 16-bit WAV files speech models take.
+fn convert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert_to_16k_mono");
+    group.sample_size(10);
+    for fixture in fixtures() {
+        let output_path = fixture_dir().join(format!("{}.wav", fixture.name));
+        let converter = AudioConverterBuilder::new(&output_path, 16000)
+            .with_input_path(&fixture.path)
+            .with_target_channels(1)
+            .build()
+            .unwrap();
+        group.throughput(Throughput::Elements(fixture.secs as u64));
+        group.bench_function(BenchmarkId::from_parameter(&fixture.name), |b| {
+            b.iter(|| converter.convert_audio().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, resample, convert);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! The sample conversion loops around the resampler: quantizing to 16 bits and splitting the
+//! channels apart and back. Compare `cargo bench --bench sample_conversion` with and without
+//! `--features simd`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::io::Cursor;
+use wavup::AudioConverterBuilder;
+
+const SAMPLE_RATE: u32 = 48000;
+const SECS: u32 = 10;
+
+/// A stereo 32-bit float WAV file of a 440 Hz tone in the left channel and a 660 Hz tone in the
+/// right one.
+fn float_wav() -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+    for frame in 0..SAMPLE_RATE * SECS {
+        let t = frame as f32 / SAMPLE_RATE as f32;
+        writer
+            .write_sample(0.8 * (2.0 * std::f32::consts::PI * 440.0 * t).sin())
+            .unwrap();
+        writer
+            .write_sample(0.8 * (2.0 * std::f32::consts::PI * 660.0 * t).sin())
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+    bytes.into_inner()
+}
+
+fn sample_conversion(c: &mut Criterion) {
+    let input = float_wav();
+    let mut group = c.benchmark_group("sample_conversion");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements((SAMPLE_RATE * SECS) as u64));
+
+    // Same rate, so the time goes to decoding and quantizing to 16 bits
+    let converter = AudioConverterBuilder::new("", SAMPLE_RATE).build().unwrap();
+    group.bench_function("quantize_16bit", |b| {
+        b.iter_batched(
+            || input.clone(),
+            |input| converter.convert_bytes_to_vec(input).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // Resampling splits the channels before and interleaves them after
+    let converter = AudioConverterBuilder::new("", 16000).build().unwrap();
+    group.bench_function("resample_48k_to_16k", |b| {
+        b.iter_batched(
+            || input.clone(),
+            |input| converter.convert_bytes_to_vec(input).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, sample_conversion);
+criterion_main!(benches);
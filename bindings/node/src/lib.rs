@@ -0,0 +1,67 @@
+use napi::{
+    bindgen_prelude::{AsyncTask, Buffer},
+    Env, Error, Result, Task,
+};
+use napi_derive::napi;
+
+/// The sample rate of the WAV file when the options do not set it, the rate speech models expect.
+const DEFAULT_SAMPLE_RATE: u32 = 16000;
+
+/// The options of `convert`.
+#[napi(object)]
+#[derive(Default)]
+pub struct ConvertOptions {
+    /// The sample rate of the WAV file in Hz. Defaults to 16000.
+    pub sample_rate: Option<u32>,
+    /// The number of channels of the WAV file. Defaults to the number of channels of the input.
+    pub channels: Option<u32>,
+    /// The number of bits per sample of the WAV file. Defaults to 16.
+    pub bits_per_sample: Option<u32>,
+}
+
+/// Converts the input on the libuv thread pool, off the JavaScript thread.
+pub struct ConvertTask {
+    input: Vec<u8>,
+    options: ConvertOptions,
+}
+impl Task for ConvertTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let options = &self.options;
+        let mut builder = wavup::AudioConverterBuilder::new(
+            "",
+            options.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE),
+        );
+        if let Some(channels) = options.channels {
+            builder = builder.with_target_channels(channels as usize);
+        }
+        if let Some(bits) = options.bits_per_sample {
+            let bits = u16::try_from(bits)
+                .map_err(|_| Error::from_reason(format!("Invalid bits per sample: {}", bits)))?;
+            builder = builder.with_bits_per_sample(bits);
+        }
+
+        builder
+            .build()
+            .and_then(|converter| converter.convert_bytes_to_vec(std::mem::take(&mut self.input)))
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into())
+    }
+}
+
+/// Convert the content of an audio file, e.g. an MP3 or M4A recording, to a WAV file.
+///
+/// The conversion runs on the libuv thread pool, and the promise rejects with the reason the
+/// input could not be converted.
+#[napi]
+pub fn convert(input: Buffer, options: Option<ConvertOptions>) -> AsyncTask<ConvertTask> {
+    AsyncTask::new(ConvertTask {
+        input: input.to_vec(),
+        options: options.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,21 @@
+use wasm_bindgen::prelude::*;
+
+/// Convert the content of an audio file, e.g. an MP3 or the audio track of an MP4 video, to a
+/// 16-bit WAV file.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the input file.
+///
+/// * `sample_rate` - The sample rate of the WAV file in Hz.
+///
+/// * `mono` - Whether to fold the channels down to mono.
+#[wasm_bindgen]
+pub fn convert_to_wav(bytes: Vec<u8>, sample_rate: u32, mono: bool) -> Result<Vec<u8>, JsError> {
+    let mut builder = wavup::AudioConverterBuilder::new("", sample_rate);
+    if mono {
+        builder = builder.with_target_channels(1);
+    }
+
+    Ok(builder.build()?.convert_bytes_to_vec(bytes)?)
+}
@@ -0,0 +1,229 @@
+use crate::quantize::quantize;
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The size of the RIFF header, the `fmt ` and `fact` chunks and the `data` chunk header.
+const HEADER_LEN: u64 = 12 + 28 + 12 + 8;
+
+/// The WAV format tag of IMA ADPCM.
+const FORMAT_TAG: u16 = 0x11;
+
+/// The quantizer step sizes, indexed by the step index.
+const STEP_SIZES: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// The change of the step index after each code, indexed by the magnitude bits of the code.
+const INDEX_ADJUSTMENTS: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Whether `block_align` bytes hold whole blocks of IMA ADPCM audio with `channels` channels,
+/// i.e. a 4-byte header per channel followed by 4-byte groups of 8 codes per channel.
+pub(crate) fn is_valid_block_align(block_align: u16, channels: u16) -> bool {
+    let header_len = 4 * channels as usize;
+    let block_align = block_align as usize;
+    block_align > header_len && (block_align - header_len).is_multiple_of(header_len)
+}
+
+/// The number of frames of a block of `block_align` bytes with `channels` channels, including
+/// the frame stored in its header.
+pub(crate) fn frames_per_block(block_align: u16, channels: u16) -> usize {
+    (block_align as usize - 4 * channels as usize) * 2 / channels as usize + 1
+}
+
+/// The state of the encoder of one channel, mirrored from the decoder so the prediction follows
+/// the decoded signal.
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelState {
+    predictor: i32,
+    step_index: usize,
+}
+impl ChannelState {
+    /// Encode the sample as a 4-bit code and update the state as the decoder will.
+    fn encode(&mut self, sample: i32) -> u8 {
+        let step = STEP_SIZES[self.step_index];
+        let mut diff = sample - self.predictor;
+        let mut code = 0;
+        if diff < 0 {
+            code = 8;
+            diff = -diff;
+        }
+
+        // Approximate the difference with the step size and its halves, as the decoder sums them
+        let mut delta = step >> 3;
+        let mut part = step;
+        for bit in [4, 2, 1] {
+            if diff >= part {
+                code |= bit;
+                diff -= part;
+                delta += part;
+            }
+            part >>= 1;
+        }
+
+        self.predictor = match code & 8 {
+            0 => self.predictor + delta,
+            _ => self.predictor - delta,
+        }
+        .clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index as i32 + INDEX_ADJUSTMENTS[code as usize & 7])
+            .clamp(0, STEP_SIZES.len() as i32 - 1) as usize;
+        code
+    }
+}
+
+/// Writes IMA ADPCM blocks in a WAV file with `fmt `, `fact` and `data` chunks, as read by
+/// embedded audio decoders.
+///
+/// Samples are buffered until a block is complete. `finalize` pads the last block with silence;
+/// the `fact` chunk declares the number of frames without the padding.
+pub(crate) struct AdpcmWriter<W: Write + Seek> {
+    writer: W,
+    channels: usize,
+    sample_rate: u32,
+    block_align: u16,
+    /// The number of frames of a block, including the frame stored in its header.
+    frames_per_block: usize,
+    states: Vec<ChannelState>,
+    /// The interleaved 16-bit samples of the incomplete block.
+    pending: Vec<i32>,
+    /// The number of samples written, excluding padding.
+    samples: u32,
+    /// The number of bytes of the blocks written.
+    data_len: u32,
+    /// The position of the RIFF header in the writer.
+    start: u64,
+}
+impl<W: Write + Seek> AdpcmWriter<W> {
+    /// Write the header and return the writer of the samples.
+    ///
+    /// `block_align` must hold whole blocks, as checked by `is_valid_block_align`.
+    pub(crate) fn new(
+        mut writer: W,
+        channels: usize,
+        sample_rate: u32,
+        block_align: u16,
+    ) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        let mut adpcm_writer = Self {
+            writer,
+            channels,
+            sample_rate,
+            block_align,
+            frames_per_block: frames_per_block(block_align, channels as u16),
+            states: vec![ChannelState::default(); channels],
+            pending: Vec::new(),
+            samples: 0,
+            data_len: 0,
+            start,
+        };
+        let header = adpcm_writer.header();
+        adpcm_writer.writer.write_all(&header)?;
+
+        Ok(adpcm_writer)
+    }
+
+    /// Quantize the interleaved samples to 16 bits and encode every completed block.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.pending
+            .extend(samples.iter().map(|sample| quantize(*sample, 16)));
+        self.samples += samples.len() as u32;
+
+        let block_len = self.frames_per_block * self.channels;
+        if self.pending.len() >= block_len {
+            let complete = self.pending.len() / block_len * block_len;
+            let pending = std::mem::take(&mut self.pending);
+            for block in pending[..complete].chunks_exact(block_len) {
+                self.write_block(block)?;
+            }
+            self.pending = pending[complete..].to_vec();
+        }
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Encode the last block, padded with silence, and write the final header.
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let mut block = std::mem::take(&mut self.pending);
+            block.resize(self.frames_per_block * self.channels, 0);
+            self.write_block(&block)?;
+        }
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        let header = self.header();
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// Encode one block of interleaved samples.
+    fn write_block(&mut self, block: &[i32]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.block_align as usize);
+
+        // The first frame is stored as is in the header of each channel
+        for (channel, state) in self.states.iter_mut().enumerate() {
+            state.predictor = block[channel];
+            bytes.extend_from_slice(&(state.predictor as i16).to_le_bytes());
+            bytes.push(state.step_index as u8);
+            bytes.push(0);
+        }
+
+        // Then groups of 8 codes per channel, two codes per byte with the earlier one in the low
+        // nibble
+        for group in block[self.channels..].chunks_exact(8 * self.channels) {
+            for (channel, state) in self.states.iter_mut().enumerate() {
+                let mut codes = group[channel..]
+                    .iter()
+                    .step_by(self.channels)
+                    .map(|sample| state.encode(*sample));
+                while let (Some(low), Some(high)) = (codes.next(), codes.next()) {
+                    bytes.push(low | (high << 4));
+                }
+            }
+        }
+
+        self.writer.write_all(&bytes)?;
+        self.data_len += bytes.len() as u32;
+        Ok(())
+    }
+
+    /// The RIFF header, the `fmt ` and `fact` chunks and the `data` chunk header.
+    fn header(&self) -> Vec<u8> {
+        let frames = self.samples / self.channels as u32;
+        let bytes_per_sec =
+            self.sample_rate as u64 * self.block_align as u64 / self.frames_per_block as u64;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(HEADER_LEN as u32 - 8 + self.data_len).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+
+        // The extra bytes of the format hold the number of frames per block
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&20u32.to_le_bytes());
+        header.extend_from_slice(&FORMAT_TAG.to_le_bytes());
+        header.extend_from_slice(&(self.channels as u16).to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&(bytes_per_sec as u32).to_le_bytes());
+        header.extend_from_slice(&self.block_align.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&(self.frames_per_block as u16).to_le_bytes());
+
+        header.extend_from_slice(b"fact");
+        header.extend_from_slice(&4u32.to_le_bytes());
+        header.extend_from_slice(&frames.to_le_bytes());
+
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&self.data_len.to_le_bytes());
+        header
+    }
+}
@@ -0,0 +1,155 @@
+use crate::quantize::quantize;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The size of the `FORM` header, the `COMM` chunk and the `SSND` chunk header.
+const HEADER_LEN: u64 = 12 + 26 + 16;
+
+/// Writes big-endian PCM in an AIFF file with `COMM` and `SSND` chunks.
+///
+/// The chunk sizes and the number of frames are written as zero, then set by `finalize` once the
+/// length of the audio is known.
+pub(crate) struct AiffWriter<W: Write + Seek> {
+    writer: W,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    /// The number of samples written.
+    samples: u32,
+    /// The position of the `FORM` header in the writer.
+    start: u64,
+}
+impl<W: Write + Seek> AiffWriter<W> {
+    /// Write the header and return the writer of the samples.
+    pub(crate) fn new(
+        mut writer: W,
+        channels: u16,
+        sample_rate: u32,
+        bits_per_sample: u16,
+    ) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        let mut aiff_writer = Self {
+            writer,
+            channels,
+            sample_rate,
+            bits_per_sample,
+            samples: 0,
+            start,
+        };
+        let header = aiff_writer.header();
+        aiff_writer.writer.write_all(&header)?;
+
+        Ok(aiff_writer)
+    }
+
+    /// Quantize the interleaved samples and write them.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let bytes_per_sample = self.bits_per_sample as usize / 8;
+        let mut bytes = Vec::with_capacity(samples.len() * bytes_per_sample);
+        for sample in samples.iter() {
+            let sample = quantize(*sample, self.bits_per_sample).to_be_bytes();
+            bytes.extend_from_slice(&sample[4 - bytes_per_sample..]);
+        }
+        self.writer.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Pad the sound data to an even length and write the final header.
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        if self.data_len() % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        let header = self.header();
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// The number of bytes of sound data.
+    fn data_len(&self) -> u64 {
+        self.samples as u64 * (self.bits_per_sample / 8) as u64
+    }
+
+    /// The `FORM` header, the `COMM` chunk and the `SSND` chunk header.
+    fn header(&self) -> Vec<u8> {
+        let data_len = self.data_len();
+        let frames = self.samples / self.channels as u32;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(b"FORM");
+        // The pad byte of odd-length sound data counts toward the form but not the chunk
+        let form_len = HEADER_LEN - 8 + data_len + data_len % 2;
+        header.extend_from_slice(&(form_len as u32).to_be_bytes());
+        header.extend_from_slice(b"AIFF");
+
+        header.extend_from_slice(b"COMM");
+        header.extend_from_slice(&18u32.to_be_bytes());
+        header.extend_from_slice(&self.channels.to_be_bytes());
+        header.extend_from_slice(&frames.to_be_bytes());
+        header.extend_from_slice(&self.bits_per_sample.to_be_bytes());
+        header.extend_from_slice(&extended(self.sample_rate));
+
+        header.extend_from_slice(b"SSND");
+        header.extend_from_slice(&(8 + data_len as u32).to_be_bytes());
+        // No offset or block alignment
+        header.extend_from_slice(&0u32.to_be_bytes());
+        header.extend_from_slice(&0u32.to_be_bytes());
+        header
+    }
+}
+
+/// The number of channels, the number of frames, the bits per sample and the sample rate declared
+/// by the `COMM` chunk of the AIFF file read by `reader`.
+pub(crate) fn read_comm<R: Read + Seek>(mut reader: R) -> io::Result<(u16, u32, u16, u32)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut form = [0u8; 12];
+    reader.read_exact(&mut form)?;
+    if &form[..4] != b"FORM" || &form[8..] != b"AIFF" {
+        return Err(invalid("not an AIFF file"));
+    }
+
+    loop {
+        let mut chunk = [0u8; 8];
+        reader.read_exact(&mut chunk)?;
+        let len = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        if &chunk[..4] != b"COMM" {
+            reader.seek(SeekFrom::Current((len + len % 2) as i64))?;
+            continue;
+        }
+
+        let mut comm = [0u8; 18];
+        reader.read_exact(&mut comm)?;
+        let channels = u16::from_be_bytes([comm[0], comm[1]]);
+        let frames = u32::from_be_bytes([comm[2], comm[3], comm[4], comm[5]]);
+        let bits_per_sample = u16::from_be_bytes([comm[6], comm[7]]);
+        let exponent = u16::from_be_bytes([comm[8], comm[9]]) as i32 - 16383;
+        let mantissa = u64::from_be_bytes(comm[10..].try_into().unwrap());
+        let sample_rate = match exponent {
+            0..=31 => (mantissa >> (63 - exponent)) as u32,
+            _ => return Err(invalid("invalid sample rate")),
+        };
+        return Ok((channels, frames, bits_per_sample, sample_rate));
+    }
+}
+
+/// The sample rate as an 80-bit IEEE 754 extended precision number, as stored in `COMM` chunks.
+fn extended(sample_rate: u32) -> [u8; 10] {
+    let mut bytes = [0u8; 10];
+    if sample_rate == 0 {
+        return bytes;
+    }
+
+    let exponent = 31 - sample_rate.leading_zeros();
+    bytes[..2].copy_from_slice(&(16383 + exponent as u16).to_be_bytes());
+    bytes[2..].copy_from_slice(&((sample_rate as u64) << (63 - exponent)).to_be_bytes());
+    bytes
+}
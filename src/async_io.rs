@@ -0,0 +1,125 @@
+use crate::{AudioConversionError, AudioConverter, ConversionReport};
+use std::io::{self, Read};
+use symphonia::core::io::ReadOnlySource;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::mpsc,
+};
+
+/// The size of the blocks read from the async source.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// The number of blocks queued between the async source and the decoder, which bounds the memory
+/// held whatever the length of the input.
+const QUEUED_BLOCKS: usize = 4;
+
+impl AudioConverter {
+    /// Convert the audio read from an async source, e.g. the body of an HTTP request, to the
+    /// output WAV file.
+    ///
+    /// The source is read asynchronously in blocks handed to the decoder through a bounded
+    /// queue, while decoding, resampling and writing run on the blocking thread pool of the
+    /// runtime. The input is thus never held in memory as a whole, and the conversion never
+    /// occupies an async worker thread. The source is read once from start to end, so
+    /// containers that need seeking, e.g. MP4 files with the index at the end, are not supported.
+    ///
+    /// If reading the source fails, the conversion fails with an `AudioConversionError::IoError`
+    /// of the same kind and the incomplete output file is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the input audio.
+    pub async fn convert_audio_async<R: AsyncRead + Unpin>(
+        &self,
+        mut reader: R,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let (sender, receiver) = mpsc::channel(QUEUED_BLOCKS);
+        let converter = self.clone();
+        let conversion = tokio::task::spawn_blocking(move || {
+            converter.convert_audio_from_source(ReadOnlySource::new(ChannelReader::new(receiver)))
+        });
+
+        let mut read_bytes = 0;
+        let mut read_error = None;
+        loop {
+            let mut block = vec![0; BLOCK_SIZE];
+            let block = match reader.read(&mut block).await {
+                Ok(0) => break,
+                Ok(len) => {
+                    read_bytes += len;
+                    block.truncate(len);
+                    Ok(block)
+                }
+                Err(e) => {
+                    // Stop the decoder, the error is reported once the conversion returns
+                    read_error = Some(e.kind());
+                    Err(e)
+                }
+            };
+            // The conversion stops reading early if it fails
+            if sender.send(block).await.is_err() || read_error.is_some() {
+                break;
+            }
+        }
+        drop(sender);
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Read {} bytes from the async source", read_bytes);
+
+        let result = conversion.await.map_err(|e| match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(e) => AudioConversionError::IoError(io::Error::other(e)),
+        })?;
+        let Some(kind) = read_error else {
+            return result;
+        };
+
+        // The decoder takes the failure for the end of a truncated input, so the output only
+        // holds the audio read before it
+        if result.is_ok() && !self.append {
+            std::fs::remove_file(&self.output_path)?;
+        }
+        let err_msg = format!("Failed to read the async source after {} bytes", read_bytes);
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::IoError(io::Error::new(kind, err_msg)))
+    }
+}
+
+/// Reads the blocks sent by the async side of `convert_audio_async`, blocking until each one
+/// arrives.
+struct ChannelReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    block: Vec<u8>,
+    position: usize,
+}
+impl ChannelReader {
+    fn new(receiver: mpsc::Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            receiver,
+            block: Vec::new(),
+            position: 0,
+        }
+    }
+}
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.block.len() {
+            match self.receiver.blocking_recv() {
+                Some(block) => {
+                    self.block = block?;
+                    self.position = 0;
+                }
+                // The source is exhausted
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.block.len() - self.position);
+        buf[..len].copy_from_slice(&self.block[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{AudioConversionError, AudioConverter, ConversionReport, Progress, ProgressCallback};
+use rayon::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The outcome of the conversion of one file of a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// The path of the input file.
+    pub input_path: PathBuf,
+    /// The path of the output file.
+    pub output_path: PathBuf,
+    /// The report of the conversion, or the error that stopped it.
+    pub result: Result<ConversionReport, AudioConversionError>,
+}
+
+type CompletionFn = dyn Fn(&BatchResult) + Send + Sync;
+
+/// A callback receiving the result of each file as soon as it is converted.
+#[derive(Clone)]
+struct CompletionCallback(Arc<CompletionFn>);
+impl std::fmt::Debug for CompletionCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CompletionCallback")
+    }
+}
+
+type FileProgressFn = dyn Fn(&Path, Progress) + Send + Sync;
+
+/// A callback receiving the progress of each file with its input path.
+#[derive(Clone)]
+struct FileProgressCallback(Arc<FileProgressFn>);
+impl std::fmt::Debug for FileProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FileProgressCallback")
+    }
+}
+
+/// Converts many files with the same settings on a thread pool.
+///
+/// The settings come from an `AudioConverter`, whose input and output paths are replaced by those
+/// of each job. A failed conversion does not stop the others; every job has its own
+/// `BatchResult`.
+#[derive(Debug, Clone)]
+pub struct BatchConverter {
+    converter: AudioConverter,
+    jobs: Vec<(PathBuf, PathBuf)>,
+    threads: usize,
+    completion_callback: Option<CompletionCallback>,
+    progress_callback: Option<FileProgressCallback>,
+}
+impl BatchConverter {
+    /// Create a batch without jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `converter` - The converter whose settings are used for every job.
+    pub fn new(converter: AudioConverter) -> Self {
+        Self {
+            converter,
+            jobs: Vec::new(),
+            threads: 0,
+            completion_callback: None,
+            progress_callback: None,
+        }
+    }
+
+    /// Add the conversion of one file.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_path` - The path of the input file.
+    ///
+    /// * `output_path` - The path of the output file.
+    pub fn with_job(mut self, input_path: impl AsRef<Path>, output_path: impl AsRef<Path>) -> Self {
+        self.jobs.push((
+            input_path.as_ref().to_path_buf(),
+            output_path.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    /// Add the conversion of each pair of input and output paths.
+    pub fn with_jobs<I, P, Q>(mut self, jobs: I) -> Self
+    where
+        I: IntoIterator<Item = (P, Q)>,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        self.jobs.extend(
+            jobs.into_iter().map(|(input, output)| {
+                (input.as_ref().to_path_buf(), output.as_ref().to_path_buf())
+            }),
+        );
+        self
+    }
+
+    /// Add the conversion of every file of `input_dir` matching the glob pattern, e.g. `*.mp3` or
+    /// `**/*.flac`. Each output is written under `output_dir` at the same relative path, with the
+    /// extension of the output format.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_dir` - The directory searched for input files.
+    ///
+    /// * `pattern` - The glob pattern, relative to `input_dir`.
+    ///
+    /// * `output_dir` - The directory of the output files.
+    pub fn with_directory(
+        mut self,
+        input_dir: impl AsRef<Path>,
+        pattern: &str,
+        output_dir: impl AsRef<Path>,
+    ) -> Result<Self, AudioConversionError> {
+        let input_dir = input_dir.as_ref();
+        let full_pattern = format!(
+            "{}/{}",
+            glob::Pattern::escape(&input_dir.to_string_lossy()),
+            pattern
+        );
+        let paths = glob::glob(&full_pattern)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let extension = self.converter.output_format.extension();
+        #[cfg(feature = "logging")]
+        let before = self.jobs.len();
+        for path in paths {
+            let input_path = path.map_err(io::Error::from)?;
+            if !input_path.is_file() {
+                continue;
+            }
+
+            let relative = input_path.strip_prefix(input_dir).unwrap_or(&input_path);
+            let output_path = output_dir.as_ref().join(relative).with_extension(extension);
+            self.jobs.push((input_path, output_path));
+        }
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Found {} files matching {}", self.jobs.len() - before, full_pattern);
+
+        Ok(self)
+    }
+
+    /// Set the number of conversions run at the same time. `0`, the default, uses one per CPU
+    /// core.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Report the result of each file to a callback as soon as it is converted, e.g. to render
+    /// the progress of the batch.
+    ///
+    /// The callback is called from the threads of the pool, in the order the files complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback receiving the result of each file.
+    pub fn with_completion_callback(
+        mut self,
+        callback: impl Fn(&BatchResult) + Send + Sync + 'static,
+    ) -> Self {
+        self.completion_callback = Some(CompletionCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Report the progress of each file to a callback, with the input path of the file, e.g. to
+    /// render the overall progress of the batch. Replaces the progress callback of the converter.
+    ///
+    /// The callback is called from the threads of the pool, for several files at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback receiving the input path and the progress of each file.
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl Fn(&Path, Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress_callback = Some(FileProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// The input and output paths of the jobs, in the order they were added.
+    pub fn jobs(&self) -> &[(PathBuf, PathBuf)] {
+        &self.jobs
+    }
+
+    /// Convert every file and return the results in the order the jobs were added.
+    ///
+    /// The parent directories of the outputs are created as needed. If the platform cannot spawn
+    /// threads, e.g. WebAssembly without threads, the files are converted one after the other.
+    pub fn convert(&self) -> Vec<BatchResult> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Converting {} files", self.jobs.len());
+
+        let pool = match self.threads {
+            1 => None,
+            threads => rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .inspect_err(|_e| {
+                    #[cfg(feature = "logging")]
+                    warn!(target: "stdout", "Failed to start the thread pool, converting sequentially: {}", _e);
+                })
+                .ok(),
+        };
+
+        let results: Vec<BatchResult> = match pool {
+            Some(pool) => pool.install(|| {
+                self.jobs
+                    .par_iter()
+                    .map(|(input, output)| self.convert_job(input, output))
+                    .collect()
+            }),
+            None => self
+                .jobs
+                .iter()
+                .map(|(input, output)| self.convert_job(input, output))
+                .collect(),
+        };
+
+        #[cfg(feature = "logging")]
+        info!(
+            target: "stdout",
+            "Converted {} of {} files",
+            results.iter().filter(|result| result.result.is_ok()).count(),
+            results.len()
+        );
+
+        results
+    }
+
+    /// Convert one file with the settings of the converter.
+    fn convert_job(&self, input_path: &Path, output_path: &Path) -> BatchResult {
+        let mut converter = self.converter.clone();
+        converter.input_path = input_path.to_path_buf();
+        converter.output_path = output_path.to_path_buf();
+        if let Some(FileProgressCallback(callback)) = &self.progress_callback {
+            let callback = callback.clone();
+            let path = input_path.to_path_buf();
+            converter.progress_callback = Some(ProgressCallback(Arc::new(move |progress| {
+                callback(&path, progress)
+            })));
+        }
+
+        let result = match output_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+        .map_err(AudioConversionError::from)
+        .and_then(|_| converter.convert_audio());
+
+        #[cfg(feature = "logging")]
+        match &result {
+            Ok(_) => debug!(target: "stdout", "Converted {}", input_path.display()),
+            Err(e) => error!(target: "stdout", "Failed to convert {}: {}", input_path.display(), e),
+        }
+
+        let batch_result = BatchResult {
+            input_path: input_path.to_path_buf(),
+            output_path: output_path.to_path_buf(),
+            result,
+        };
+        if let Some(CompletionCallback(callback)) = &self.completion_callback {
+            callback(&batch_result);
+        }
+        batch_result
+    }
+}
@@ -0,0 +1,70 @@
+use crate::chunk;
+use std::{io, path::Path};
+
+/// The version of the Broadcast Wave Format the `bext` chunk follows, i.e. with a UMID field but
+/// no loudness fields.
+const BEXT_VERSION: u16 = 1;
+
+/// The Broadcast Wave Format (EBU Tech 3285) metadata written into a `bext` chunk of the output
+/// WAV file, as required for broadcast delivery.
+///
+/// Text fields are stored as ASCII: other characters are replaced with `?`, and text longer than
+/// its field is cut.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BextChunk {
+    /// A free description of the sound sequence, up to 256 characters.
+    pub description: String,
+    /// The name of the originator of the audio, up to 32 characters.
+    pub originator: String,
+    /// A reference unambiguously identifying the audio for the originator, up to 32 characters.
+    pub originator_reference: String,
+    /// The date the audio was created, as `yyyy-mm-dd`.
+    pub origination_date: String,
+    /// The time the audio was created, as `hh:mm:ss`.
+    pub origination_time: String,
+    /// The timecode of the first sample, counted in samples since midnight at the output sample
+    /// rate.
+    pub time_reference: u64,
+    /// The coding history, one line per process the audio went through, e.g.
+    /// `A=PCM,F=48000,W=24,M=stereo,T=wavup`.
+    pub coding_history: String,
+}
+impl BextChunk {
+    /// The body of the `bext` chunk.
+    fn body(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(602 + self.coding_history.len() + 2);
+        push_ascii(&mut body, &self.description, 256);
+        push_ascii(&mut body, &self.originator, 32);
+        push_ascii(&mut body, &self.originator_reference, 32);
+        push_ascii(&mut body, &self.origination_date, 10);
+        push_ascii(&mut body, &self.origination_time, 8);
+        body.extend_from_slice(&(self.time_reference as u32).to_le_bytes());
+        body.extend_from_slice(&((self.time_reference >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&BEXT_VERSION.to_le_bytes());
+        // No UMID, and the reserved bytes
+        body.resize(body.len() + 64 + 190, 0);
+
+        // Each line of the coding history ends with CR/LF
+        for line in self.coding_history.lines() {
+            push_ascii(&mut body, line, line.chars().count());
+            body.extend_from_slice(b"\r\n");
+        }
+        body
+    }
+}
+
+/// Append a `bext` chunk holding the metadata to the finalized WAV file at `path`.
+pub(crate) fn append_bext_chunk(path: impl AsRef<Path>, bext: &BextChunk) -> io::Result<()> {
+    chunk::append_chunk(path, b"bext", &bext.body())
+}
+
+/// Append the text as ASCII to `bytes`, cut or padded with NULs to `len` bytes.
+fn push_ascii(bytes: &mut Vec<u8>, text: &str, len: usize) {
+    let start = bytes.len();
+    bytes.extend(
+        text.chars()
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            .take(len),
+    );
+    bytes.resize(start + len, 0);
+}
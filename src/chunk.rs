@@ -0,0 +1,96 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Append a chunk with the given id and body to the finalized WAV file at `path`, and update the
+/// RIFF chunk size to include it.
+///
+/// hound only writes the `fmt ` and `data` chunks, so other chunks are spliced in after the data
+/// chunk, where readers that do not understand them skip them.
+pub(crate) fn append_chunk(path: impl AsRef<Path>, id: &[u8; 4], body: &[u8]) -> io::Result<()> {
+    let mut chunk = Vec::with_capacity(8 + body.len() + 1);
+    chunk.extend_from_slice(id);
+    chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(body);
+    // The pad byte of an odd-length body is not counted in the chunk size
+    if body.len() % 2 == 1 {
+        chunk.push(0);
+    }
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let file_len = file.seek(SeekFrom::End(0))?;
+
+    // Chunks start at even offsets
+    if file_len % 2 == 1 {
+        file.write_all(&[0])?;
+    }
+    file.write_all(&chunk)?;
+
+    let riff_len = file.stream_position()? - 8;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_len as u32).to_le_bytes())?;
+
+    Ok(())
+}
+
+/// The chunks that follow the `data` chunk of a WAV file, such as the `LIST`, `bext` and `cue `
+/// chunks written after it.
+///
+/// hound appends samples right after the data it finds, over anything that follows it, so these
+/// chunks are detached from the file before appending and restored once it is finalized.
+#[derive(Debug, Default)]
+pub(crate) struct TrailingChunks(Vec<([u8; 4], Vec<u8>)>);
+impl TrailingChunks {
+    /// Read the chunks that follow the `data` chunk of the WAV file at `path` and truncate the
+    /// file at the end of the data. Files without such chunks are left untouched.
+    pub(crate) fn detach(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut header = [0; 12];
+        file.read_exact(&mut header)?;
+        if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+            return Ok(Self::default());
+        }
+
+        // Find the end of the data chunk
+        let data_end = loop {
+            let mut chunk_header = [0; 8];
+            file.read_exact(&mut chunk_header)?;
+            let size = u32::from_le_bytes(chunk_header[4..].try_into().unwrap()) as u64;
+            let body_pos = file.stream_position()?;
+            if &chunk_header[..4] == b"data" {
+                break body_pos + size;
+            }
+            file.seek(SeekFrom::Start(body_pos + size + size % 2))?;
+        };
+
+        let mut rest = Vec::new();
+        file.seek(SeekFrom::Start(data_end))?;
+        file.read_to_end(&mut rest)?;
+
+        // The data chunk may be followed by its pad byte
+        let mut chunks = Vec::new();
+        let mut pos = (data_end % 2) as usize;
+        while pos + 8 <= rest.len() {
+            let id: [u8; 4] = rest[pos..pos + 4].try_into().unwrap();
+            let size = u32::from_le_bytes(rest[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let body = &rest[pos + 8..(pos + 8 + size).min(rest.len())];
+            chunks.push((id, body.to_vec()));
+            pos += 8 + size + size % 2;
+        }
+
+        if !rest.is_empty() {
+            file.set_len(data_end)?;
+        }
+        Ok(Self(chunks))
+    }
+
+    /// Append the detached chunks to the finalized WAV file at `path`, in their original order.
+    pub(crate) fn restore(self, path: impl AsRef<Path>) -> io::Result<()> {
+        for (id, body) in &self.0 {
+            append_chunk(path.as_ref(), id, body)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+/// Samples whose magnitude reaches this level are considered to be at full scale. It is slightly
+/// below 1.0 to catch overs that the decoder reconstructed just under full scale.
+const CLIP_THRESHOLD: f32 = 0.999;
+
+/// The number of consecutive full-scale samples on a channel that make up a clip run.
+const MIN_CLIP_RUN: usize = 3;
+
+/// Detects runs of consecutive full-scale samples in interleaved audio.
+#[derive(Debug)]
+pub(crate) struct ClipDetector {
+    run_lengths: Vec<usize>,
+    clipped_sample_count: usize,
+    clip_runs: usize,
+}
+impl ClipDetector {
+    pub(crate) fn new(channels: usize) -> Self {
+        Self {
+            run_lengths: vec![0; channels],
+            clipped_sample_count: 0,
+            clip_runs: 0,
+        }
+    }
+
+    /// Scan the next block of interleaved samples. Runs spanning several blocks are tracked.
+    pub(crate) fn feed(&mut self, samples: &[f32]) {
+        let channels = self.run_lengths.len();
+        for frame in samples.chunks(channels) {
+            for (ch, sample) in frame.iter().enumerate() {
+                if sample.abs() >= CLIP_THRESHOLD {
+                    self.run_lengths[ch] += 1;
+                } else {
+                    self.end_run(ch);
+                }
+            }
+        }
+    }
+
+    /// Close any run still open at the end of the input and return the number of clipped samples
+    /// and the number of clip runs.
+    pub(crate) fn finish(mut self) -> (usize, usize) {
+        for ch in 0..self.run_lengths.len() {
+            self.end_run(ch);
+        }
+        (self.clipped_sample_count, self.clip_runs)
+    }
+
+    fn end_run(&mut self, ch: usize) {
+        let run_length = std::mem::take(&mut self.run_lengths[ch]);
+        if run_length >= MIN_CLIP_RUN {
+            self.clipped_sample_count += run_length;
+            self.clip_runs += 1;
+        }
+    }
+}
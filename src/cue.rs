@@ -0,0 +1,20 @@
+use crate::chunk;
+use std::{io, path::Path};
+
+/// Append a `cue ` chunk with a marker at each of the frame `positions` to the finalized WAV file
+/// at `path`.
+pub(crate) fn append_cue_chunk(path: impl AsRef<Path>, positions: &[u32]) -> io::Result<()> {
+    let mut body = Vec::with_capacity(4 + 24 * positions.len());
+    body.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+    for (id, position) in positions.iter().enumerate() {
+        // Identifier, play order position, chunk id, chunk start, block start, sample offset
+        body.extend_from_slice(&(id as u32 + 1).to_le_bytes());
+        body.extend_from_slice(&position.to_le_bytes());
+        body.extend_from_slice(b"data");
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&position.to_le_bytes());
+    }
+
+    chunk::append_chunk(path, b"cue ", &body)
+}
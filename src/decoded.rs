@@ -0,0 +1,68 @@
+/// Converted audio held in memory, as returned by `AudioConverter::decode_to_f32`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAudio {
+    samples: Vec<f32>,
+    channels: usize,
+    sample_rate: u32,
+}
+impl DecodedAudio {
+    pub(crate) fn new(samples: Vec<f32>, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// The number of channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The number of frames, i.e. samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// The duration in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    /// The interleaved samples, e.g. `[l0, r0, l1, r1, ...]` for stereo audio.
+    pub fn interleaved(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Take the interleaved samples without copying them.
+    pub fn into_interleaved(self) -> Vec<f32> {
+        self.samples
+    }
+
+    /// The samples of the channel at `index`, without copying them. Empty if there is no such
+    /// channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the channel, e.g. `0` for the left channel.
+    pub fn channel(&self, index: usize) -> impl Iterator<Item = f32> + '_ {
+        let samples = if index < self.channels {
+            &self.samples[index..]
+        } else {
+            &[]
+        };
+        samples.iter().step_by(self.channels).copied()
+    }
+
+    /// Copy the samples into one buffer per channel, e.g. `[[l0, l1, ...], [r0, r1, ...]]`.
+    pub fn planar(&self) -> Vec<Vec<f32>> {
+        (0..self.channels)
+            .map(|index| self.channel(index).collect())
+            .collect()
+    }
+}
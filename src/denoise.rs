@@ -0,0 +1,131 @@
+use realfft::RealFftPlanner;
+use std::f32::consts::PI;
+
+/// The length of the analysis frames of the denoiser, in samples.
+const FRAME_LEN: usize = 1024;
+
+/// The distance between consecutive analysis frames, in samples.
+const HOP_LEN: usize = FRAME_LEN / 2;
+
+/// The fraction of the frames, the quietest ones, whose spectrum is taken as the noise profile.
+const NOISE_FRAME_FRACTION: f32 = 0.1;
+
+/// How much noise the spectral-subtraction denoiser of `AudioConverterBuilder::with_denoise`
+/// removes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseOptions {
+    /// The multiple of the noise profile subtracted from each frame. Values above `1.0` remove
+    /// more noise at the cost of more artifacts.
+    pub strength: f32,
+    /// The lowest gain applied to a frequency, in dB, so noisy frequencies are attenuated rather
+    /// than muted, which avoids "musical noise".
+    pub floor_db: f32,
+}
+impl Default for DenoiseOptions {
+    fn default() -> Self {
+        Self {
+            strength: 1.5,
+            floor_db: -25.0,
+        }
+    }
+}
+
+/// Remove the stationary noise of each channel of the interleaved samples by spectral
+/// subtraction.
+///
+/// The noise profile of a channel is the average spectrum of its quietest frames. Each frame is
+/// then attenuated, frequency by frequency, by the share of its magnitude that the profile
+/// accounts for, and the frames are overlapped again with a square-root Hann window.
+pub(crate) fn denoise(samples: &mut [f32], channels: usize, options: &DenoiseOptions) {
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return;
+    }
+
+    let mut planner = RealFftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(FRAME_LEN);
+    let inverse = planner.plan_fft_inverse(FRAME_LEN);
+    let window: Vec<f32> = (0..FRAME_LEN)
+        .map(|n| (PI * n as f32 / FRAME_LEN as f32).sin())
+        .collect();
+    let floor = 10f32.powf(options.floor_db / 20.0);
+
+    // Pad the signal so every sample is covered by two frames
+    let padded_len = (frames + HOP_LEN).div_ceil(HOP_LEN) * HOP_LEN + HOP_LEN;
+    let frame_starts: Vec<usize> = (0..=padded_len - FRAME_LEN).step_by(HOP_LEN).collect();
+
+    let mut input = forward.make_input_vec();
+    let mut spectrum = forward.make_output_vec();
+    let mut output = inverse.make_output_vec();
+    for channel in 0..channels {
+        let mut signal = vec![0.0; padded_len];
+        for (frame, sample) in samples.iter().skip(channel).step_by(channels).enumerate() {
+            signal[HOP_LEN + frame] = *sample;
+        }
+
+        // Average the magnitude spectra of the quietest frames
+        let mut energies: Vec<(f32, usize)> = frame_starts
+            .iter()
+            .map(|start| {
+                let energy = signal[*start..*start + FRAME_LEN]
+                    .iter()
+                    .map(|sample| sample * sample)
+                    .sum();
+                (energy, *start)
+            })
+            .collect();
+        energies.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let noise_frames = ((energies.len() as f32 * NOISE_FRAME_FRACTION) as usize).max(1);
+        let mut noise = vec![0.0_f32; spectrum.len()];
+        for (_, start) in &energies[..noise_frames] {
+            window_frame(&signal[*start..*start + FRAME_LEN], &window, &mut input);
+            forward
+                .process(&mut input, &mut spectrum)
+                .expect("the buffers have the length of the FFT");
+            for (noise, bin) in noise.iter_mut().zip(spectrum.iter()) {
+                *noise += bin.norm() / noise_frames as f32;
+            }
+        }
+
+        let mut denoised = vec![0.0; padded_len];
+        for start in &frame_starts {
+            window_frame(&signal[*start..*start + FRAME_LEN], &window, &mut input);
+            forward
+                .process(&mut input, &mut spectrum)
+                .expect("the buffers have the length of the FFT");
+            for (bin, noise) in spectrum.iter_mut().zip(noise.iter()) {
+                let magnitude = bin.norm();
+                if magnitude > 0.0 {
+                    *bin *= (1.0 - options.strength * noise / magnitude).max(floor);
+                }
+            }
+            // Scaling keeps the first and last bins real, as the inverse transform requires
+            inverse
+                .process(&mut spectrum, &mut output)
+                .expect("the first and last bins of the spectrum are real");
+            for ((denoised, sample), window) in denoised[*start..*start + FRAME_LEN]
+                .iter_mut()
+                .zip(output.iter())
+                .zip(window.iter())
+            {
+                *denoised += sample * window / FRAME_LEN as f32;
+            }
+        }
+
+        for (frame, sample) in samples
+            .iter_mut()
+            .skip(channel)
+            .step_by(channels)
+            .enumerate()
+        {
+            *sample = denoised[HOP_LEN + frame];
+        }
+    }
+}
+
+/// Copy the frame multiplied by the window into the input of the FFT.
+fn window_frame(frame: &[f32], window: &[f32], input: &mut [f32]) {
+    for ((input, sample), window) in input.iter_mut().zip(frame).zip(window) {
+        *input = sample * window;
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+
+use crate::track::numbered_output_path;
+
+/// The placeholder of the output path replaced by the channel label when extracting channels.
+const CHANNEL_PLACEHOLDER: &str = "{channel}";
+
+/// How multichannel audio is folded down to mono.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownmixMode {
+    /// Average all channels.
+    #[default]
+    Average,
+    /// Keep only the first (left) channel.
+    LeftOnly,
+    /// Keep only the second (right) channel, or the only channel of mono audio.
+    RightOnly,
+}
+
+/// Bring the interleaved `samples` from `in_channels` to `out_channels` channels.
+///
+/// Mono output is folded down according to `mode`, mono input is copied to every output channel,
+/// and any other reduction keeps the first `out_channels` channels, e.g. the front left and right
+/// channels of 5.1 audio.
+pub(crate) fn remix(
+    samples: &[f32],
+    in_channels: usize,
+    out_channels: usize,
+    mode: DownmixMode,
+) -> Vec<f32> {
+    let frames = samples.chunks_exact(in_channels);
+
+    if out_channels == 1 {
+        return match mode {
+            DownmixMode::Average => frames
+                .map(|frame| frame.iter().sum::<f32>() / in_channels as f32)
+                .collect(),
+            DownmixMode::LeftOnly => frames.map(|frame| frame[0]).collect(),
+            DownmixMode::RightOnly => frames.map(|frame| frame[1.min(in_channels - 1)]).collect(),
+        };
+    }
+
+    let mut remixed = Vec::with_capacity(samples.len() / in_channels * out_channels);
+    for frame in frames {
+        if in_channels == 1 {
+            remixed.extend(std::iter::repeat_n(frame[0], out_channels));
+        } else {
+            remixed.extend_from_slice(&frame[..out_channels]);
+        }
+    }
+
+    remixed
+}
+
+/// Mix the interleaved `samples` of `in_channels` channels through `matrix`, which holds one row
+/// of input channel gains per output channel.
+pub(crate) fn apply_matrix(samples: &[f32], in_channels: usize, matrix: &[Vec<f32>]) -> Vec<f32> {
+    let mut mixed = Vec::with_capacity(samples.len() / in_channels * matrix.len());
+    for frame in samples.chunks_exact(in_channels) {
+        mixed.extend(matrix.iter().map(|gains| {
+            gains
+                .iter()
+                .zip(frame)
+                .map(|(gain, sample)| gain * sample)
+                .sum::<f32>()
+        }));
+    }
+
+    mixed
+}
+
+/// The output path of the channel at `index` of audio with `channels` channels when extracting
+/// channels.
+///
+/// The channels of stereo audio are labelled `L` and `R`, those of other layouts by their index.
+/// The `{channel}` placeholder of `output_path` is replaced by the label, e.g. `mic-{channel}.wav`
+/// becomes `mic-L.wav`. Without a placeholder, the label is appended to the file stem, e.g.
+/// `interview.wav` becomes `interview_L.wav`.
+pub(crate) fn channel_output_path(output_path: &Path, index: usize, channels: usize) -> PathBuf {
+    let label = match (channels, index) {
+        (2, 0) => "L".to_string(),
+        (2, 1) => "R".to_string(),
+        _ => index.to_string(),
+    };
+    numbered_output_path(output_path, CHANNEL_PLACEHOLDER, &label)
+}
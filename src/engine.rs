@@ -0,0 +1,107 @@
+use crate::{resampler::ReusableResampler, AudioConversionError, AudioConverter, ConversionReport};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// The input sample rate, output sample rate and number of channels of a resampler.
+pub(crate) type ResamplerKey = (u32, u32, usize);
+
+/// The idle resamplers of the conversions of a `ConverterEngine`, ready to be reused.
+#[derive(Default)]
+pub(crate) struct ResamplerPool {
+    resamplers: Mutex<HashMap<ResamplerKey, Vec<Box<dyn ReusableResampler>>>>,
+}
+impl ResamplerPool {
+    /// Take an idle resampler for `key`, if a previous conversion left one.
+    pub(crate) fn take(&self, key: ResamplerKey) -> Option<Box<dyn ReusableResampler>> {
+        let mut resamplers = self.resamplers.lock().unwrap_or_else(|e| e.into_inner());
+        resamplers.get_mut(&key).and_then(Vec::pop)
+    }
+
+    /// Reset the resampler of a finished conversion and keep it for the next one with `key`.
+    pub(crate) fn put(&self, key: ResamplerKey, mut resampler: Box<dyn ReusableResampler>) {
+        resampler.reset();
+        let mut resamplers = self.resamplers.lock().unwrap_or_else(|e| e.into_inner());
+        resamplers.entry(key).or_default().push(resampler);
+    }
+
+    /// The number of idle resamplers.
+    fn len(&self) -> usize {
+        let resamplers = self.resamplers.lock().unwrap_or_else(|e| e.into_inner());
+        resamplers.values().map(Vec::len).sum()
+    }
+}
+impl std::fmt::Debug for ResamplerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ResamplerPool({} idle)", self.len())
+    }
+}
+
+/// Converts many inputs with the same settings, reusing the resamplers between conversions.
+///
+/// Creating a resampler, e.g. planning the FFTs of `ResamplerKind::Fft`, can take longer than
+/// converting a short clip. The engine keeps the resampler of each finished conversion, keyed by
+/// the input sample rate, the output sample rate and the number of channels, and hands it to the
+/// next conversion with the same key. Buffered, streamed, resumed and parallel conversions all
+/// draw from the pool; only the resamplers created when the channel layout changes mid-stream are
+/// dropped after use.
+///
+/// The settings come from an `AudioConverter`, whose input and output paths are replaced by those
+/// of each call. The engine may be shared between threads, e.g. behind an `Arc`: conversions
+/// running at the same time each take their own resampler. Clones share the idle resamplers.
+#[derive(Debug, Clone)]
+pub struct ConverterEngine {
+    converter: AudioConverter,
+}
+impl ConverterEngine {
+    /// Create an engine without idle resamplers.
+    ///
+    /// # Arguments
+    ///
+    /// * `converter` - The converter whose settings are used for every conversion.
+    pub fn new(mut converter: AudioConverter) -> Self {
+        converter.resampler_pool = Some(Arc::default());
+        Self { converter }
+    }
+
+    /// Convert one file.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_path` - The path of the input file.
+    ///
+    /// * `output_path` - The path of the output file.
+    pub fn convert(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let mut converter = self.converter.clone();
+        converter.input_path = input_path.as_ref().to_path_buf();
+        converter.output_path = output_path.as_ref().to_path_buf();
+        converter.convert_audio()
+    }
+
+    /// Convert the audio file held in `bytes`, without copying it, and return the bytes of the
+    /// output file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the input audio file, e.g. a `Vec<u8>` or an `Arc<[u8]>`.
+    pub fn convert_bytes_to_vec<B: AsRef<[u8]> + Send + Sync + 'static>(
+        &self,
+        bytes: B,
+    ) -> Result<Vec<u8>, AudioConversionError> {
+        self.converter.convert_bytes_to_vec(bytes)
+    }
+
+    /// The number of resamplers kept for the next conversions.
+    pub fn idle_resamplers(&self) -> usize {
+        self.converter
+            .resampler_pool
+            .as_ref()
+            .map_or(0, |pool| pool.len())
+    }
+}
@@ -1,31 +1,107 @@
 use std::error::Error;
 use std::fmt;
 
+/// How packets the decoder fails to decode are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Fail the conversion at the first corrupt packet.
+    #[default]
+    Strict,
+    /// Log and skip corrupt packets, resetting the decoder so it resyncs on the next packet. The
+    /// conversion fails once more than `max_skipped` packets are skipped.
+    SkipCorrupt {
+        /// The maximum number of packets skipped before the conversion fails.
+        max_skipped: usize,
+    },
+}
+
 #[derive(Debug)]
 pub enum AudioConversionError {
     IoError(std::io::Error),
+    ProbeError(String),
+    UnsupportedCodec(String),
     DecoderError(String),
+    ResamplerConstructionError(rubato::ResamplerConstructionError),
     ResamplerError(String),
+    WavWriteError(hound::Error),
+    EncoderError(String),
     UnsupportedFormat(String),
     InvalidSampleCount(String),
+    VerificationFailed(String),
+    Cancelled,
 }
 
 impl fmt::Display for AudioConversionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::IoError(e) => write!(f, "IO error: {}", e),
+            Self::ProbeError(e) => write!(f, "Probe error: {}", e),
+            Self::UnsupportedCodec(e) => write!(f, "Unsupported codec: {}", e),
             Self::DecoderError(e) => write!(f, "Decoder error: {}", e),
+            Self::ResamplerConstructionError(e) => {
+                write!(f, "Resampler construction error: {}", e)
+            }
             Self::ResamplerError(e) => write!(f, "Resampler error: {}", e),
+            Self::WavWriteError(e) => write!(f, "WAV write error: {}", e),
+            Self::EncoderError(e) => write!(f, "Encoder error: {}", e),
             Self::UnsupportedFormat(e) => write!(f, "Unsupported format: {}", e),
             Self::InvalidSampleCount(e) => write!(f, "Invalid sample count: {}", e),
+            Self::VerificationFailed(e) => write!(f, "Output verification failed: {}", e),
+            Self::Cancelled => write!(f, "Conversion cancelled"),
         }
     }
 }
 
-impl Error for AudioConversionError {}
+impl Error for AudioConversionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IoError(e) => Some(e),
+            Self::ResamplerConstructionError(e) => Some(e),
+            Self::WavWriteError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for AudioConversionError {
     fn from(err: std::io::Error) -> Self {
         Self::IoError(err)
     }
 }
+
+impl From<hound::Error> for AudioConversionError {
+    fn from(err: hound::Error) -> Self {
+        Self::WavWriteError(err)
+    }
+}
+
+#[cfg(feature = "opus-encoder")]
+impl From<audiopus::Error> for AudioConversionError {
+    fn from(err: audiopus::Error) -> Self {
+        Self::EncoderError(err.to_string())
+    }
+}
+
+impl From<symphonia::core::errors::Error> for AudioConversionError {
+    fn from(err: symphonia::core::errors::Error) -> Self {
+        match err {
+            symphonia::core::errors::Error::IoError(e) => Self::IoError(e),
+            symphonia::core::errors::Error::Unsupported(e) => {
+                Self::UnsupportedFormat(e.to_string())
+            }
+            e => Self::DecoderError(e.to_string()),
+        }
+    }
+}
+
+impl From<rubato::ResamplerConstructionError> for AudioConversionError {
+    fn from(err: rubato::ResamplerConstructionError) -> Self {
+        Self::ResamplerConstructionError(err)
+    }
+}
+
+impl From<rubato::ResampleError> for AudioConversionError {
+    fn from(err: rubato::ResampleError) -> Self {
+        Self::ResamplerError(err.to_string())
+    }
+}
@@ -0,0 +1,53 @@
+use std::f32::consts::PI;
+
+/// The shape of the fades applied by `AudioConverterBuilder::with_fade`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FadeShape {
+    /// Change the gain at a constant rate.
+    #[default]
+    Linear,
+    /// Follow half a cosine period, which starts and ends the fade gently.
+    Cosine,
+}
+impl FadeShape {
+    /// The gain at `position` through the fade in, from `0.0` at the start to `1.0` at the end.
+    fn gain(self, position: f32) -> f32 {
+        match self {
+            Self::Linear => position,
+            Self::Cosine => 0.5 - 0.5 * (position * PI).cos(),
+        }
+    }
+}
+
+/// Fade the interleaved samples in over their first `fade_in_frames` frames and out over their
+/// last `fade_out_frames` frames. Fades longer than the audio are shortened to its length.
+pub(crate) fn apply_fades(
+    samples: &mut [f32],
+    channels: usize,
+    fade_in_frames: usize,
+    fade_out_frames: usize,
+    shape: FadeShape,
+) {
+    let frames = samples.len() / channels;
+
+    let fade_in_frames = fade_in_frames.min(frames);
+    for (frame, samples) in samples
+        .chunks_exact_mut(channels)
+        .take(fade_in_frames)
+        .enumerate()
+    {
+        let gain = shape.gain(frame as f32 / fade_in_frames as f32);
+        samples.iter_mut().for_each(|sample| *sample *= gain);
+    }
+
+    let fade_out_frames = fade_out_frames.min(frames);
+    for (frame, samples) in samples
+        .chunks_exact_mut(channels)
+        .rev()
+        .take(fade_out_frames)
+        .enumerate()
+    {
+        let gain = shape.gain(frame as f32 / fade_out_frames as f32);
+        samples.iter_mut().for_each(|sample| *sample *= gain);
+    }
+}
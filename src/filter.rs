@@ -0,0 +1,176 @@
+use std::f64::consts::PI;
+
+/// The number of taps of the anti-alias filter per unit of the downsampling ratio.
+const TAPS_PER_RATIO: usize = 64;
+
+/// The cutoff of the anti-alias filter relative to the output Nyquist frequency.
+const CUTOFF_RATIO: f64 = 0.9;
+
+/// A linear-phase windowed-sinc FIR low-pass filter for interleaved audio.
+///
+/// The output is aligned with the input: the group delay of the filter is discarded at the start
+/// and recovered by `flush`, so the filtered audio has exactly as many frames as the input.
+pub(crate) struct FirLowpass {
+    taps: Vec<f32>,
+    channels: usize,
+    /// The input of each channel that is not fully consumed yet, starting with the last
+    /// `taps.len() - 1` frames already filtered.
+    history: Vec<Vec<f32>>,
+    /// The number of leading output frames still to discard.
+    skip_frames: usize,
+}
+impl FirLowpass {
+    /// Create the filter that suppresses the content above the Nyquist frequency of
+    /// `output_rate` before downsampling from `input_rate`.
+    pub(crate) fn anti_alias(channels: usize, input_rate: u32, output_rate: u32) -> Self {
+        let ratio = (input_rate as f64 / output_rate as f64).ceil() as usize;
+        let num_taps = TAPS_PER_RATIO * ratio + 1;
+        let cutoff = CUTOFF_RATIO * (output_rate as f64 / 2.0) / input_rate as f64;
+
+        // Blackman-windowed sinc, normalized to unity gain at DC
+        let center = (num_taps - 1) as f64 / 2.0;
+        let mut taps: Vec<f64> = (0..num_taps)
+            .map(|n| {
+                let x = n as f64 - center;
+                let sinc = if x == 0.0 {
+                    2.0 * cutoff
+                } else {
+                    (2.0 * PI * cutoff * x).sin() / (PI * x)
+                };
+                let phase = 2.0 * PI * n as f64 / (num_taps - 1) as f64;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * window
+            })
+            .collect();
+        let sum: f64 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
+
+        Self {
+            taps: taps.into_iter().map(|tap| tap as f32).collect(),
+            channels,
+            history: vec![vec![0.0; num_taps - 1]; channels],
+            skip_frames: (num_taps - 1) / 2,
+        }
+    }
+
+    /// Filter the interleaved `samples` and return the frames that are ready.
+    pub(crate) fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        for frame in samples.chunks_exact(channels) {
+            for (history, sample) in self.history.iter_mut().zip(frame.iter()) {
+                history.push(*sample);
+            }
+        }
+
+        let frames = samples.len() / channels;
+        let skip = self.skip_frames.min(frames);
+        self.skip_frames -= skip;
+
+        // The taps are symmetric, so the convolution is a dot product with the input window
+        let num_taps = self.taps.len();
+        let mut output = vec![0.0; (frames - skip) * channels];
+        for (ch, history) in self.history.iter_mut().enumerate() {
+            for frame in skip..frames {
+                output[(frame - skip) * channels + ch] =
+                    dot(&self.taps, &history[frame..frame + num_taps]);
+            }
+            history.drain(..frames);
+        }
+
+        output
+    }
+
+    /// Return the frames delayed by the filter, completing the output.
+    pub(crate) fn flush(&mut self) -> Vec<f32> {
+        let delay = (self.taps.len() - 1) / 2;
+        self.process(&vec![0.0; delay * self.channels])
+    }
+}
+
+/// The quality factor of the biquad filters, which makes them second-order Butterworth filters.
+const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// A second-order IIR filter for interleaved audio, designed with the bilinear transform as in
+/// the Audio EQ Cookbook. The state of each channel is kept between calls, so the audio may be
+/// filtered block by block.
+pub(crate) struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    /// The last two inputs and outputs of each channel.
+    state: Vec<[f64; 4]>,
+}
+impl Biquad {
+    /// Create the filter that removes the content below `cutoff_hz`, e.g. rumble.
+    pub(crate) fn highpass(channels: usize, cutoff_hz: f64, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::prewarp(cutoff_hz, sample_rate);
+        Self::normalized(
+            channels,
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Create the filter that removes the content above `cutoff_hz`, e.g. hiss.
+    pub(crate) fn lowpass(channels: usize, cutoff_hz: f64, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::prewarp(cutoff_hz, sample_rate);
+        Self::normalized(
+            channels,
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// The cosine of the normalized cutoff frequency and the bandwidth term of the cookbook.
+    fn prewarp(cutoff_hz: f64, sample_rate: u32) -> (f64, f64) {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f64;
+        (w0.cos(), w0.sin() / (2.0 * BUTTERWORTH_Q))
+    }
+
+    fn normalized(channels: usize, b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            state: vec![[0.0; 4]; channels],
+        }
+    }
+
+    /// Filter the interleaved `samples` in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.state.len();
+        for frame in samples.chunks_exact_mut(channels) {
+            for (sample, [x1, x2, y1, y2]) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let x = *sample as f64;
+                let y = self.b0 * x + self.b1 * *x1 + self.b2 * *x2 - self.a1 * *y1 - self.a2 * *y2;
+                (*x2, *x1, *y2, *y1) = (*x1, x, *y1, y);
+                *sample = y as f32;
+            }
+        }
+    }
+}
+
+/// The dot product of two slices of equal length, accumulated in independent lanes so it
+/// vectorizes.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    const LANES: usize = 8;
+    let mut sums = [0.0f32; LANES];
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let remainder: f32 = chunks_a
+        .remainder()
+        .iter()
+        .zip(chunks_b.remainder().iter())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for lane in 0..LANES {
+            sums[lane] += x[lane] * y[lane];
+        }
+    }
+    sums.iter().sum::<f32>() + remainder
+}
@@ -0,0 +1,529 @@
+use crate::{quantize::quantize, Tags};
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The largest Rice parameter of the 4-bit partitioned Rice coding method.
+const MAX_RICE_PARAM: u32 = 14;
+
+/// The highest supported compression level.
+pub(crate) const MAX_COMPRESSION_LEVEL: u8 = 8;
+
+/// The encoder settings of a compression level.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    /// The number of frames of each block.
+    block_size: usize,
+    /// The highest order of the fixed predictors tried on each subframe.
+    max_order: usize,
+    /// The highest partition order of the Rice coded residual.
+    max_partition_order: u32,
+    /// Whether stereo blocks may be coded as left/side, right/side or mid/side.
+    decorrelate: bool,
+}
+impl Settings {
+    fn new(level: u8) -> Self {
+        let level = level.min(MAX_COMPRESSION_LEVEL);
+        Self {
+            block_size: if level <= 2 { 1152 } else { 4096 },
+            max_order: (level as usize + 2).min(4),
+            max_partition_order: [3, 3, 3, 4, 4, 5, 6, 6, 8][level as usize],
+            decorrelate: level >= 1,
+        }
+    }
+}
+
+/// Writes a FLAC stream with fixed predictors and Rice coded residuals, the subset of FLAC that
+/// every decoder supports.
+///
+/// Samples are buffered until a block is complete. `finalize` writes the last, shorter block and
+/// then seeks back to record the number of frames and the frame sizes in the STREAMINFO block.
+pub(crate) struct FlacWriter<W: Write + Seek> {
+    writer: W,
+    channels: usize,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    settings: Settings,
+    /// The interleaved samples of the incomplete block.
+    pending: Vec<i64>,
+    /// The number of samples written, including the pending ones.
+    samples: u32,
+    frame_number: u32,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    /// The position of the STREAMINFO block in the writer.
+    streaminfo_pos: u64,
+}
+impl<W: Write + Seek> FlacWriter<W> {
+    /// Write the stream header and return the writer of the frames.
+    pub(crate) fn new(
+        mut writer: W,
+        channels: usize,
+        sample_rate: u32,
+        bits_per_sample: u16,
+        compression_level: u8,
+        tags: &Tags,
+    ) -> io::Result<Self> {
+        writer.write_all(b"fLaC")?;
+        // The STREAMINFO block is 34 bytes long, and the last metadata block without tags
+        let last_block = if tags.is_empty() { 0x80 } else { 0 };
+        writer.write_all(&[last_block, 0, 0, 34])?;
+        let streaminfo_pos = writer.stream_position()?;
+
+        let mut flac_writer = Self {
+            writer,
+            channels,
+            sample_rate,
+            bits_per_sample,
+            settings: Settings::new(compression_level),
+            pending: Vec::new(),
+            samples: 0,
+            frame_number: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            streaminfo_pos,
+        };
+        let streaminfo = flac_writer.streaminfo();
+        flac_writer.writer.write_all(&streaminfo)?;
+
+        if !tags.is_empty() {
+            let comment = tags.vorbis_comment(concat!("wavup ", env!("CARGO_PKG_VERSION")));
+            // The last metadata block, of type VORBIS_COMMENT
+            flac_writer.writer.write_all(&[0x84])?;
+            flac_writer
+                .writer
+                .write_all(&(comment.len() as u32).to_be_bytes()[1..])?;
+            flac_writer.writer.write_all(&comment)?;
+        }
+
+        Ok(flac_writer)
+    }
+
+    /// Quantize the interleaved samples and encode every completed block.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.pending.extend(
+            samples
+                .iter()
+                .map(|sample| quantize(*sample, self.bits_per_sample) as i64),
+        );
+        self.samples += samples.len() as u32;
+
+        let block_len = self.settings.block_size * self.channels;
+        if self.pending.len() >= block_len {
+            let complete = self.pending.len() / block_len * block_len;
+            let pending = std::mem::take(&mut self.pending);
+            for block in pending[..complete].chunks_exact(block_len) {
+                self.write_frame(block)?;
+            }
+            self.pending = pending[complete..].to_vec();
+        }
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Encode the last block and complete the STREAMINFO block.
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        // Keep whole frames; a trailing partial frame cannot be encoded
+        let frames = pending.len() / self.channels;
+        if frames > 0 {
+            self.write_frame(&pending[..frames * self.channels])?;
+        }
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.streaminfo_pos))?;
+        let streaminfo = self.streaminfo();
+        self.writer.write_all(&streaminfo)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// The contents of the STREAMINFO block. The MD5 signature of the audio is left unset, which
+    /// decoders take as unknown.
+    fn streaminfo(&self) -> Vec<u8> {
+        let mut bits = BitWriter::default();
+        bits.write(self.settings.block_size as u64, 16);
+        bits.write(self.settings.block_size as u64, 16);
+        bits.write(self.min_frame_size as u64, 24);
+        bits.write(self.max_frame_size as u64, 24);
+        bits.write(self.sample_rate as u64, 20);
+        bits.write(self.channels as u64 - 1, 3);
+        bits.write(self.bits_per_sample as u64 - 1, 5);
+        let frames = (self.samples as usize / self.channels) as u64;
+        bits.write(frames >> 32, 4);
+        bits.write(frames & 0xffff_ffff, 32);
+        for _ in 0..4 {
+            bits.write(0, 32);
+        }
+        bits.into_bytes()
+    }
+
+    /// Encode one block of interleaved samples as a frame.
+    fn write_frame(&mut self, block: &[i64]) -> io::Result<()> {
+        let frames = block.len() / self.channels;
+        let bps = self.bits_per_sample as u32;
+        let planar: Vec<Vec<i64>> = (0..self.channels)
+            .map(|channel| {
+                block[channel..]
+                    .iter()
+                    .step_by(self.channels)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        let independent: Vec<Subframe> = planar
+            .iter()
+            .map(|samples| Subframe::plan(samples, bps, &self.settings))
+            .collect();
+        let (assignment, subframes) = if self.channels == 2 && self.settings.decorrelate {
+            let (left, right) = (&planar[0], &planar[1]);
+            let side: Vec<i64> = left.iter().zip(right).map(|(l, r)| l - r).collect();
+            let mid: Vec<i64> = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+            let side = Subframe::plan(&side, bps + 1, &self.settings);
+            let mid = Subframe::plan(&mid, bps, &self.settings);
+            let mut independent = independent.into_iter();
+            let (left, right) = (independent.next().unwrap(), independent.next().unwrap());
+
+            // Pick the smallest of the independent, left/side, right/side and mid/side codings
+            let bits = [
+                left.bits + right.bits,
+                left.bits + side.bits,
+                side.bits + right.bits,
+                mid.bits + side.bits,
+            ];
+            match (0..bits.len()).min_by_key(|i| bits[*i]) {
+                Some(1) => (0b1000, vec![left, side]),
+                Some(2) => (0b1001, vec![side, right]),
+                Some(3) => (0b1010, vec![mid, side]),
+                _ => (0b0001, vec![left, right]),
+            }
+        } else {
+            (self.channels as u64 - 1, independent)
+        };
+
+        let mut bits = BitWriter::default();
+        bits.write(0b1111_1111_1111_1000, 16);
+        // The block size is stored after the frame number, the sample rate in STREAMINFO
+        bits.write(0b0111, 4);
+        bits.write(0b0000, 4);
+        bits.write(assignment, 4);
+        bits.write(sample_size_code(self.bits_per_sample), 3);
+        bits.write(0, 1);
+        for byte in utf8_coded(self.frame_number) {
+            bits.write(byte as u64, 8);
+        }
+        bits.write(frames as u64 - 1, 16);
+        let crc = crc8(bits.bytes());
+        bits.write(crc as u64, 8);
+
+        for subframe in subframes.iter() {
+            subframe.write(&mut bits);
+        }
+        bits.align();
+        let crc = crc16(bits.bytes());
+        bits.write(crc as u64, 16);
+
+        let frame = bits.into_bytes();
+        let size = frame.len() as u32;
+        self.min_frame_size = match self.frame_number {
+            0 => size,
+            _ => self.min_frame_size.min(size),
+        };
+        self.max_frame_size = self.max_frame_size.max(size);
+        self.frame_number += 1;
+        self.writer.write_all(&frame)
+    }
+}
+
+/// An encoded channel of a frame.
+struct Subframe {
+    kind: SubframeKind,
+    /// The number of bits of the subframe.
+    bits: u64,
+}
+enum SubframeKind {
+    Constant {
+        value: i64,
+        bps: u32,
+    },
+    Verbatim {
+        samples: Vec<i64>,
+        bps: u32,
+    },
+    Fixed {
+        warmup: Vec<i64>,
+        bps: u32,
+        partition_order: u32,
+        rice_params: Vec<u32>,
+        residual: Vec<u64>,
+    },
+}
+impl Subframe {
+    /// Find the smallest coding of the samples, each of `bps` bits.
+    fn plan(samples: &[i64], bps: u32, settings: &Settings) -> Self {
+        if samples.iter().all(|sample| *sample == samples[0]) {
+            return Self {
+                kind: SubframeKind::Constant {
+                    value: samples[0],
+                    bps,
+                },
+                bits: 8 + bps as u64,
+            };
+        }
+
+        let mut best = Self {
+            kind: SubframeKind::Verbatim {
+                samples: samples.to_vec(),
+                bps,
+            },
+            bits: 8 + samples.len() as u64 * bps as u64,
+        };
+        for order in 0..=settings.max_order.min(samples.len() - 1) {
+            let Some(residual) = fixed_residual(samples, order) else {
+                continue;
+            };
+            let (partition_order, rice_params, residual_bits) = rice_partitions(
+                &residual,
+                samples.len(),
+                order,
+                settings.max_partition_order,
+            );
+
+            let bits = 8 + order as u64 * bps as u64 + 6 + residual_bits;
+            if bits < best.bits {
+                best = Self {
+                    kind: SubframeKind::Fixed {
+                        warmup: samples[..order].to_vec(),
+                        bps,
+                        partition_order,
+                        rice_params,
+                        residual,
+                    },
+                    bits,
+                };
+            }
+        }
+        best
+    }
+
+    fn write(&self, bits: &mut BitWriter) {
+        match &self.kind {
+            SubframeKind::Constant { value, bps } => {
+                bits.write(0b0000_0000, 8);
+                bits.write_signed(*value, *bps);
+            }
+            SubframeKind::Verbatim { samples, bps } => {
+                bits.write(0b0000_0010, 8);
+                for sample in samples.iter() {
+                    bits.write_signed(*sample, *bps);
+                }
+            }
+            SubframeKind::Fixed {
+                warmup,
+                bps,
+                partition_order,
+                rice_params,
+                residual,
+            } => {
+                bits.write(0b0001_0000 | ((warmup.len() as u64) << 1), 8);
+                for sample in warmup.iter() {
+                    bits.write_signed(*sample, *bps);
+                }
+
+                // Partitioned Rice coding with 4-bit parameters
+                bits.write(0b00, 2);
+                bits.write(*partition_order as u64, 4);
+                let partition_len = (residual.len() + warmup.len()) >> partition_order;
+                let mut start = 0;
+                for (partition, param) in rice_params.iter().enumerate() {
+                    let end = (partition + 1) * partition_len - warmup.len();
+                    bits.write(*param as u64, 4);
+                    for value in residual[start..end].iter() {
+                        bits.write_unary(value >> param);
+                        bits.write(value & ((1 << param) - 1), *param);
+                    }
+                    start = end;
+                }
+            }
+        }
+    }
+}
+
+/// The zigzag encoded residual of the fixed predictor of `order`, or `None` if a residual does
+/// not fit in 32 bits.
+fn fixed_residual(samples: &[i64], order: usize) -> Option<Vec<u64>> {
+    let residual = samples.windows(order + 1).map(|window| {
+        let x = |i: usize| window[order - i];
+        match order {
+            0 => x(0),
+            1 => x(0) - x(1),
+            2 => x(0) - 2 * x(1) + x(2),
+            3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+            _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+        }
+    });
+
+    residual
+        .map(|value| i32::try_from(value).ok().map(|_| zigzag(value)))
+        .collect()
+}
+
+/// Choose the partition order and the Rice parameter of each partition that code the residual in
+/// the fewest bits, and return them with that number of bits.
+///
+/// The number of bits of a partition is estimated from the sum of its values, like libFLAC does.
+fn rice_partitions(
+    residual: &[u64],
+    frames: usize,
+    order: usize,
+    max_partition_order: u32,
+) -> (u32, Vec<u32>, u64) {
+    let mut best = (0, Vec::new(), u64::MAX);
+    for partition_order in 0..=max_partition_order {
+        let partition_len = frames >> partition_order;
+        // The partitions must divide the block, and the first must hold the warm-up samples
+        if frames & ((1 << partition_order) - 1) != 0 || partition_len <= order {
+            break;
+        }
+
+        let mut params = Vec::with_capacity(1 << partition_order);
+        let mut bits = 0;
+        let mut start = 0;
+        for partition in 0..1 << partition_order {
+            let end = (partition + 1) * partition_len - order;
+            let values = &residual[start..end];
+            let sum: u64 = values.iter().sum();
+            let (param, param_bits) = (0..=MAX_RICE_PARAM)
+                .map(|param| {
+                    let bits = values.len() as u64 * (param as u64 + 1) + (sum >> param);
+                    (param, bits)
+                })
+                .min_by_key(|(_, bits)| *bits)
+                .unwrap();
+            params.push(param);
+            bits += 4 + param_bits;
+            start = end;
+        }
+
+        if bits < best.2 {
+            best = (partition_order, params, bits);
+        }
+    }
+    best
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// The code of the bits per sample in the frame header.
+fn sample_size_code(bits_per_sample: u16) -> u64 {
+    match bits_per_sample {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        _ => 0b000,
+    }
+}
+
+/// The frame number in the UTF-8 like variable-length coding of frame headers.
+fn utf8_coded(value: u32) -> Vec<u8> {
+    if value < 0x80 {
+        return vec![value as u8];
+    }
+
+    let continuation_bytes = match value {
+        0..=0x7ff => 1,
+        0x800..=0xffff => 2,
+        0x1_0000..=0x1f_ffff => 3,
+        0x20_0000..=0x3ff_ffff => 4,
+        _ => 5,
+    };
+    let mut bytes = vec![0u8; continuation_bytes + 1];
+    let mut value = value;
+    for byte in bytes[1..].iter_mut().rev() {
+        *byte = 0x80 | (value & 0x3f) as u8;
+        value >>= 6;
+    }
+    let marker = !(0xffu8 >> (continuation_bytes + 1));
+    bytes[0] = marker | value as u8;
+    bytes
+}
+
+/// The CRC-8 of frame headers, with polynomial `x^8 + x^2 + x + 1`.
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x07,
+        })
+    })
+}
+
+/// The CRC-16 of frames, with polynomial `x^16 + x^15 + x^2 + 1`.
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x8005,
+        })
+    })
+}
+
+/// Packs values into bytes, most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+impl BitWriter {
+    /// Write the lowest `bits` bits of `value`, at most 32.
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+    }
+
+    /// Write `value` as a two's complement integer of `bits` bits.
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    /// Write `value` zero bits followed by a one bit.
+    fn write_unary(&mut self, mut value: u64) {
+        while value >= 32 {
+            self.write(0, 32);
+            value -= 32;
+        }
+        self.write(1, value as u32 + 1);
+    }
+
+    /// Pad with zero bits to the next byte boundary.
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+
+    /// The complete bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
@@ -0,0 +1,17 @@
+/// The sample format of the output WAV file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Signed integer PCM, or unsigned for 8-bit samples.
+    #[default]
+    Int,
+    /// IEEE floating point PCM, only available with 32 bits per sample.
+    Float,
+}
+impl From<SampleFormat> for hound::SampleFormat {
+    fn from(format: SampleFormat) -> Self {
+        match format {
+            SampleFormat::Int => hound::SampleFormat::Int,
+            SampleFormat::Float => hound::SampleFormat::Float,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::quantize::quantize;
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The size of the RIFF header, the `fmt ` and `fact` chunks and the `data` chunk header.
+const HEADER_LEN: u64 = 12 + 26 + 12 + 8;
+
+/// The upper bounds of the segments of the companding curves, as magnitudes of 13-bit A-law and
+/// biased 14-bit μ-law input.
+const ALAW_SEGMENT_ENDS: [i32; 8] = [0x1f, 0x3f, 0x7f, 0xff, 0x1ff, 0x3ff, 0x7ff, 0xfff];
+const MULAW_SEGMENT_ENDS: [i32; 8] = [0x3f, 0x7f, 0xff, 0x1ff, 0x3ff, 0x7ff, 0xfff, 0x1fff];
+
+/// The largest magnitude of 14-bit μ-law input, and the bias added to it.
+const MULAW_CLIP: i32 = 8159;
+const MULAW_BIAS: i32 = 0x21;
+
+/// How the samples of WAV output are encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PcmEncoding {
+    /// Linear PCM with the sample format set by `with_bits_per_sample` and `with_sample_format`.
+    #[default]
+    Linear,
+    /// 8-bit G.711 μ-law, the telephony encoding of North America and Japan.
+    MuLaw,
+    /// 8-bit G.711 A-law, the telephony encoding of Europe and most other regions.
+    ALaw,
+}
+impl PcmEncoding {
+    /// The WAV format tag of the encoding.
+    fn format_tag(self) -> u16 {
+        match self {
+            Self::Linear => 1,
+            Self::ALaw => 6,
+            Self::MuLaw => 7,
+        }
+    }
+
+    /// Encode a 16-bit sample as the 8-bit code of the encoding, i.e. as unsigned 8-bit PCM for
+    /// `Linear`.
+    fn encode(self, sample: i16) -> u8 {
+        match self {
+            Self::Linear => (sample >> 8) as u8 ^ 0x80,
+            Self::MuLaw => mulaw(sample),
+            Self::ALaw => alaw(sample),
+        }
+    }
+}
+
+/// Writes G.711 companded samples in a WAV file with `fmt `, `fact` and `data` chunks, which
+/// hound cannot write.
+///
+/// The chunk sizes and the number of frames are written as zero, then set by `finalize` once the
+/// length of the audio is known.
+pub(crate) struct G711Writer<W: Write + Seek> {
+    writer: W,
+    encoding: PcmEncoding,
+    channels: u16,
+    sample_rate: u32,
+    /// The number of samples written.
+    samples: u32,
+    /// The position of the RIFF header in the writer.
+    start: u64,
+}
+impl<W: Write + Seek> G711Writer<W> {
+    /// Write the header and return the writer of the samples.
+    pub(crate) fn new(
+        mut writer: W,
+        encoding: PcmEncoding,
+        channels: u16,
+        sample_rate: u32,
+    ) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        let mut g711_writer = Self {
+            writer,
+            encoding,
+            channels,
+            sample_rate,
+            samples: 0,
+            start,
+        };
+        let header = g711_writer.header();
+        g711_writer.writer.write_all(&header)?;
+
+        Ok(g711_writer)
+    }
+
+    /// Quantize the interleaved samples to 16 bits, compand them and write them.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .map(|sample| self.encoding.encode(quantize(*sample, 16) as i16))
+            .collect();
+        self.writer.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Pad the data chunk to an even length and write the final header.
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        if self.samples % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        let header = self.header();
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// The RIFF header, the `fmt ` and `fact` chunks and the `data` chunk header.
+    fn header(&self) -> Vec<u8> {
+        let data_len = self.samples;
+        let frames = self.samples / self.channels as u32;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(b"RIFF");
+        let riff_len = HEADER_LEN as u32 - 8 + data_len + data_len % 2;
+        header.extend_from_slice(&riff_len.to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+
+        // Formats other than PCM have a cbSize field, here without extra bytes
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&18u32.to_le_bytes());
+        header.extend_from_slice(&self.encoding.format_tag().to_le_bytes());
+        header.extend_from_slice(&self.channels.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&(self.sample_rate * self.channels as u32).to_le_bytes());
+        header.extend_from_slice(&self.channels.to_le_bytes());
+        header.extend_from_slice(&8u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        // Formats other than PCM declare their number of frames in a fact chunk
+        header.extend_from_slice(b"fact");
+        header.extend_from_slice(&4u32.to_le_bytes());
+        header.extend_from_slice(&frames.to_le_bytes());
+
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_len.to_le_bytes());
+        header
+    }
+}
+
+/// Compand a 16-bit sample to μ-law, as in the reference implementation of ITU-T G.711.
+fn mulaw(sample: i16) -> u8 {
+    let value = sample as i32 >> 2;
+    let (magnitude, mask) = match value {
+        0.. => (value, 0xff),
+        _ => (-value, 0x7f),
+    };
+    let magnitude = magnitude.min(MULAW_CLIP) + MULAW_BIAS;
+
+    let code = match segment(magnitude, &MULAW_SEGMENT_ENDS) {
+        Some(segment) => (segment << 4) as u8 | ((magnitude >> (segment + 1)) & 0x0f) as u8,
+        None => 0x7f,
+    };
+    code ^ mask
+}
+
+/// Compand a 16-bit sample to A-law, as in the reference implementation of ITU-T G.711.
+fn alaw(sample: i16) -> u8 {
+    let value = sample as i32 >> 3;
+    let (magnitude, mask) = match value {
+        0.. => (value, 0xd5),
+        _ => (-value - 1, 0x55),
+    };
+
+    let code = match segment(magnitude, &ALAW_SEGMENT_ENDS) {
+        Some(0) => ((magnitude >> 1) & 0x0f) as u8,
+        Some(segment) => (segment << 4) as u8 | ((magnitude >> segment) & 0x0f) as u8,
+        None => 0x7f,
+    };
+    code ^ mask
+}
+
+/// The index of the first segment whose upper bound is at least `magnitude`.
+fn segment(magnitude: i32, segment_ends: &[i32; 8]) -> Option<i32> {
+    segment_ends
+        .iter()
+        .position(|end| magnitude <= *end)
+        .map(|segment| segment as i32)
+}
@@ -0,0 +1,65 @@
+/// A noise gate for interleaved audio, muting the audio while its level stays below a threshold,
+/// e.g. the room noise between the sentences of a voice memo.
+///
+/// The gate opens over the attack time when the level rises above the threshold and closes over
+/// the release time once it falls below. The state is kept between calls, so the audio may be
+/// gated block by block.
+pub(crate) struct NoiseGate {
+    channels: usize,
+    /// The linear amplitude of the threshold.
+    threshold: f32,
+    /// The change of the gain per frame while the gate opens.
+    attack_step: f32,
+    /// The change of the gain per frame while the gate closes.
+    release_step: f32,
+    /// The factor applied to the level per frame, so short dips do not close the gate.
+    level_decay: f32,
+    level: f32,
+    gain: f32,
+}
+impl NoiseGate {
+    /// Create the gate for audio with `channels` channels at `sample_rate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold_db` - The level below which the audio is muted, in dBFS.
+    ///
+    /// * `attack_secs` - The time the gate takes to open.
+    ///
+    /// * `release_secs` - The time the gate takes to close.
+    pub(crate) fn new(
+        channels: usize,
+        sample_rate: u32,
+        threshold_db: f32,
+        attack_secs: f32,
+        release_secs: f32,
+    ) -> Self {
+        let frames = |secs: f32| (secs * sample_rate as f32).max(1.0);
+        Self {
+            channels,
+            threshold: 10f32.powf(threshold_db / 20.0),
+            attack_step: 1.0 / frames(attack_secs),
+            release_step: 1.0 / frames(release_secs),
+            // The level falls by 60 dB over the release time
+            level_decay: 0.001f32.powf(1.0 / frames(release_secs)),
+            level: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    /// Gate the interleaved `samples` in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame
+                .iter()
+                .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+            self.level = peak.max(self.level * self.level_decay);
+            self.gain = if self.level >= self.threshold {
+                (self.gain + self.attack_step).min(1.0)
+            } else {
+                (self.gain - self.release_step).max(0.0)
+            };
+            frame.iter_mut().for_each(|sample| *sample *= self.gain);
+        }
+    }
+}
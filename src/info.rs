@@ -0,0 +1,25 @@
+use crate::Tags;
+
+/// Properties of the input, known after probing and before any audio is decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+    /// The short name of the codec of the input track, e.g. `"vorbis"`, if it is known.
+    pub codec: Option<&'static str>,
+    /// The short name of the container, e.g. `"ogg"`, if it is known.
+    pub container: Option<&'static str>,
+    /// The number of channels.
+    pub channels: usize,
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// The duration in seconds, if the container declares the number of frames.
+    pub duration_secs: Option<f64>,
+    /// The bits per sample, if the container or codec declares it.
+    pub bits_per_sample: Option<u16>,
+    /// Whether the codec is lossy.
+    pub is_lossy: bool,
+    /// The embedded metadata tags as key and value pairs, e.g. `("TITLE", "Intro")`. Keys are
+    /// named as in the source format.
+    pub metadata: Vec<(String, String)>,
+    /// The title, artist, album and comment among the metadata tags.
+    pub tags: Tags,
+}
@@ -0,0 +1,84 @@
+use crate::{probe, AudioConversionError, AudioInfo};
+use std::{
+    fs::File,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::{MediaSource, MediaSourceStream},
+    meta::MetadataOptions,
+};
+
+/// The input of `AudioInspector::probe`: a file or an in-memory buffer.
+#[derive(Debug, Clone, Copy)]
+pub enum ProbeInput<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
+}
+impl<'a> From<&'a Path> for ProbeInput<'a> {
+    fn from(path: &'a Path) -> Self {
+        Self::Path(path)
+    }
+}
+impl<'a> From<&'a PathBuf> for ProbeInput<'a> {
+    fn from(path: &'a PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+impl<'a> From<&'a str> for ProbeInput<'a> {
+    fn from(path: &'a str) -> Self {
+        Self::Path(Path::new(path))
+    }
+}
+impl<'a> From<&'a String> for ProbeInput<'a> {
+    fn from(path: &'a String) -> Self {
+        Self::Path(Path::new(path))
+    }
+}
+impl<'a> From<&'a [u8]> for ProbeInput<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+impl<'a> From<&'a Vec<u8>> for ProbeInput<'a> {
+    fn from(bytes: &'a Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+/// Inspects audio without converting it.
+pub struct AudioInspector;
+impl AudioInspector {
+    /// Probe the container of the input and return the properties of its default audio track, e.g. to
+    /// validate uploads before paying the cost of a full conversion. No audio is decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The path of the input file, or its bytes.
+    pub fn probe<'a>(input: impl Into<ProbeInput<'a>>) -> Result<AudioInfo, AudioConversionError> {
+        let input = input.into();
+        let source: Box<dyn MediaSource> = match input {
+            ProbeInput::Path(path) => Box::new(File::open(path)?),
+            ProbeInput::Bytes(bytes) => Box::new(Cursor::new(bytes.to_vec())),
+        };
+        let media_source = MediaSourceStream::new(source, Default::default());
+        let hint = match input {
+            ProbeInput::Path(path) => path.extension().and_then(|extension| extension.to_str()),
+            ProbeInput::Bytes(_) => None,
+        };
+
+        let mut probed = probe::probe(
+            media_source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+            hint,
+        )?;
+        let info = probed.audio_info(None)?;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Probed input: {:?}", info);
+
+        Ok(info)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    downmix::{self, DownmixMode},
+    streaming::{self, StreamingResampler},
+    AudioConversionError,
+};
+
+/// Brings decoded audio to the channel count and sample rate at the start of the input, so the
+/// rest of the conversion sees a single layout.
+///
+/// The layout may change mid-stream, e.g. in DVB captures switching between stereo and 5.1, or
+/// from one stream to the next of a chained Ogg input, as produced by Icecast dumps and some
+/// recorders. The channels are remixed first and then resampled incrementally.
+pub(crate) struct LayoutAdapter {
+    in_channels: usize,
+    out_channels: usize,
+    resampler: Option<StreamingResampler>,
+}
+impl LayoutAdapter {
+    pub(crate) fn new(
+        in_channels: usize,
+        out_channels: usize,
+        resampler: Option<StreamingResampler>,
+    ) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            resampler,
+        }
+    }
+
+    /// Adapt interleaved samples and hand them to `on_samples`.
+    pub(crate) fn push(
+        &mut self,
+        samples: &[f32],
+        on_samples: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        let remixed;
+        let samples = if self.in_channels == self.out_channels {
+            samples
+        } else {
+            remixed = downmix::remix(
+                samples,
+                self.in_channels,
+                self.out_channels,
+                DownmixMode::Average,
+            );
+            &remixed
+        };
+
+        match self.resampler.as_mut() {
+            Some(resampler) => resampler.push(samples, on_samples),
+            None => on_samples(samples),
+        }
+    }
+
+    /// Flush the delayed output of the resampler once the layout changes again or the input ends.
+    pub(crate) fn finish(
+        self,
+        on_samples: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        match self.resampler {
+            Some(resampler) => resampler.finish(on_samples).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Relates the timestamps of the packets of the current layout to the frames of the input at the
+/// sample rate at its start.
+///
+/// Timestamps count the frames of the current layout: the timestamps of a chained stream restart
+/// at 0, and those following a change of sample rate advance at the new rate.
+#[derive(Debug)]
+pub(crate) struct LayoutTimeline {
+    original_rate: u32,
+    rate: u32,
+    /// The timestamp at which the current layout starts.
+    start_ts: u64,
+    /// The number of frames before the current layout, at the original sample rate.
+    offset: u64,
+}
+impl LayoutTimeline {
+    pub(crate) fn new(original_rate: u32) -> Self {
+        Self {
+            original_rate,
+            rate: original_rate,
+            start_ts: 0,
+            offset: 0,
+        }
+    }
+
+    /// The sample rate of the current layout.
+    pub(crate) fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// End the current layout at `end_ts`, and start one at `rate` from the timestamp `start_ts`.
+    pub(crate) fn switch(&mut self, end_ts: u64, rate: u32, start_ts: u64) {
+        let frames = end_ts.saturating_sub(self.start_ts) as usize;
+        self.offset += streaming::output_frames(frames, self.rate, self.original_rate) as u64;
+        self.rate = rate;
+        self.start_ts = start_ts;
+    }
+
+    /// The timestamp of the current layout at the frame `ts` of the input, or the start of the
+    /// layout if `ts` comes before it.
+    pub(crate) fn layout_ts(&self, ts: u64) -> u64 {
+        let frames = ts.saturating_sub(self.offset) as usize;
+        self.start_ts + streaming::output_frames(frames, self.original_rate, self.rate) as u64
+    }
+
+    /// The position of the timestamp `ts` of the current layout in the input, in seconds.
+    pub(crate) fn secs(&self, ts: u64) -> f64 {
+        self.offset as f64 / self.original_rate as f64
+            + ts.saturating_sub(self.start_ts) as f64 / self.rate as f64
+    }
+}
@@ -2,26 +2,226 @@
 #[macro_use]
 extern crate log;
 
+mod adpcm;
+mod aiff;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "batch")]
+mod batch;
+mod bext;
+mod chunk;
+mod clipping;
+mod clock;
+mod cue;
+mod decoded;
+#[cfg(feature = "denoise")]
+mod denoise;
+mod downmix;
+mod engine;
 mod error;
-pub use error::AudioConversionError;
+mod fade;
+mod filter;
+mod flac;
+mod format;
+mod g711;
+mod gate;
+mod info;
+mod inspect;
+mod layout;
+mod loudness;
+mod mono;
+#[cfg(feature = "mp3-encoder")]
+mod mp3;
+#[cfg(feature = "opus-encoder")]
+mod opus;
+mod output;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod probe;
+mod progress;
+mod quantize;
+mod raw;
+mod report;
+mod resampler;
+mod resume;
+mod segment;
+mod simd;
+mod source;
+mod streaming;
+mod support;
+mod tags;
+mod tempo;
+mod track;
+mod trim;
+mod vad;
+mod wav64;
+mod weighting;
+#[cfg(feature = "batch")]
+pub use batch::{BatchConverter, BatchResult};
+pub use bext::BextChunk;
+pub use decoded::DecodedAudio;
+#[cfg(feature = "denoise")]
+pub use denoise::DenoiseOptions;
+pub use downmix::DownmixMode;
+pub use engine::ConverterEngine;
+pub use error::{AudioConversionError, ErrorPolicy};
+pub use fade::FadeShape;
+pub use format::SampleFormat;
+pub use g711::PcmEncoding;
+pub use info::AudioInfo;
+pub use inspect::{AudioInspector, ProbeInput};
+pub use output::{OutputFormat, OutputSample};
+pub use progress::Progress;
+pub use quantize::{quantize, soft_clip};
+pub use raw::{RawFormat, RawSpec};
+pub use report::ConversionReport;
+pub use resampler::{ResamplerKind, SincWindow};
+pub use segment::{Segment, SegmentOptions};
+pub use support::{supported_codecs, supported_extensions, InputCodec};
+pub use tags::Tags;
+pub use track::TrackSelector;
+pub use trim::TrimOptions;
+pub use vad::{SpeechSegment, VadOptions};
+pub use wav64::LargeFileFormat;
+pub use weighting::Weighting;
 
-use hound::{WavSpec, WavWriter};
-use rubato::{FftFixedInOut, Resampler};
-use std::fs::File;
+use adpcm::AdpcmWriter;
+use aiff::AiffWriter;
+use chunk::TrailingChunks;
+use clipping::ClipDetector;
+use clock::Stopwatch;
+use engine::ResamplerPool;
+use filter::{Biquad, FirLowpass};
+use flac::FlacWriter;
+use g711::G711Writer;
+use gate::NoiseGate;
+use layout::{LayoutAdapter, LayoutTimeline};
+use loudness::LoudnessTarget;
+use mono::MonoCompatibility;
+#[cfg(feature = "mp3-encoder")]
+use mp3::Mp3Writer;
+#[cfg(feature = "opus-encoder")]
+use opus::OpusWriter;
+use output::OutputWriter;
+use progress::{ProgressFn, ProgressTracker};
+use raw::RawReader;
+use resume::Checkpoint;
+use source::{CountingSource, ReaderSource};
+use streaming::{StreamingResampler, StreamingStage};
+use wav64::Wav64Writer;
+
+use hound::{WavReader, WavSpec, WavWriter};
+use resampler::ReusableResampler;
+use rubato::VecResampler;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, ErrorKind, Read, Seek, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use symphonia::core::{
-    audio::SampleBuffer,
-    codecs::{DecoderOptions, CODEC_TYPE_FLAC, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS},
-    formats::FormatOptions,
-    io::MediaSourceStream,
+    audio::{SampleBuffer, SignalSpec},
+    codecs::{
+        CodecParameters, CodecType, Decoder, DecoderOptions, CODEC_TYPE_PCM_F32LE,
+        CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    },
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, Packet, SeekMode, SeekTo},
+    io::{MediaSource, MediaSourceStream},
     meta::MetadataOptions,
-    probe::Hint,
+    units::TimeBase,
 };
 
+/// The output sample rates accepted by `AudioConverterBuilder::build`, in Hz.
+const OUTPUT_SAMPLE_RATES: std::ops::RangeInclusive<u32> = 1_000..=768_000;
+
+/// The default capacity of the buffer used to write the output WAV file.
+const DEFAULT_OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The interval between the checkpoints of a resumable conversion, in seconds of input.
+const RESUME_SEGMENT_SECS: f64 = 30.0;
+
+/// The length of the input before the checkpoint a resumed conversion decodes again, in seconds,
+/// to bring the resampler and filters back to the state the interruption left them in.
+const RESUME_WARMUP_SECS: f64 = 1.0;
+
+/// The number of samples quantized at once when writing 16-bit WAV output.
+const QUANTIZE_BLOCK_LEN: usize = 8192;
+
+/// The minimum length of a silent run between two regions of sound for a cue marker to be placed
+/// at the start of the second region, in seconds.
+const MIN_CUE_SILENCE_SECS: f32 = 0.5;
+
 #[derive(Debug, Default)]
 pub struct AudioConverterBuilder {
-    input_path: String,
-    output_path: String,
+    input_path: PathBuf,
+    output_path: PathBuf,
     target_sample_rate: u32,
+    track: Option<TrackSelector>,
+    raw_input: Option<RawSpec>,
+    mime_type: Option<String>,
+    start_offset: Option<Duration>,
+    end_offset: Option<Duration>,
+    chunking: Option<(Duration, Duration)>,
+    silence_weighting: Weighting,
+    append: bool,
+    overwrite: bool,
+    compensate_delay: bool,
+    loudness_target: Option<LoudnessTarget>,
+    gain_db: Option<f32>,
+    highpass_hz: Option<f32>,
+    lowpass_hz: Option<f32>,
+    noise_gate: Option<(f32, Duration, Duration)>,
+    #[cfg(feature = "denoise")]
+    denoise: Option<DenoiseOptions>,
+    fade: Option<(Duration, Duration)>,
+    fade_shape: FadeShape,
+    tempo: Option<f32>,
+    rational_resampler: bool,
+    resampler: ResamplerKind,
+    resampler_chunk_size: usize,
+    #[cfg(feature = "parallel")]
+    threads: Option<usize>,
+    passthrough_identical: bool,
+    min_trim_silence_secs: f32,
+    channel_gains_db: Option<Vec<f32>>,
+    verify_output: bool,
+    output_buffer_size: usize,
+    max_memory: Option<usize>,
+    pad_to_secs: Option<f32>,
+    invert_polarity: bool,
+    invert_channels: Option<Vec<bool>>,
+    silence_cue_markers: bool,
+    bwf_metadata: Option<BextChunk>,
+    copy_metadata: bool,
+    antialias_filter: bool,
+    precheck: Option<Precheck>,
+    gapless: bool,
+    decoder_verify: bool,
+    error_policy: ErrorPolicy,
+    resume: bool,
+    target_channels: Option<usize>,
+    downmix: Option<DownmixMode>,
+    channel_matrix: Option<Vec<Vec<f32>>>,
+    bits_per_sample: u16,
+    sample_format: SampleFormat,
+    output_format: OutputFormat,
+    large_file: Option<LargeFileFormat>,
+    encoding: PcmEncoding,
+    soft_clip: bool,
+    trim_trailing_silence: Option<TrimOptions>,
+    trim_leading_silence: Option<TrimOptions>,
+    vad: Option<VadOptions>,
+    progress_callback: Option<ProgressCallback>,
+    cancel_token: Option<Arc<AtomicBool>>,
 }
 impl AudioConverterBuilder {
     /// Create a new audio converter builder.
@@ -31,277 +231,4354 @@ impl AudioConverterBuilder {
     /// * `output_path` - The path to the output WAV file.
     ///
     /// * `target_sample_rate` - The target sample rate for the output WAV file.
-    pub fn new(output_path: impl Into<String>, target_sample_rate: u32) -> Self {
+    pub fn new(output_path: impl AsRef<Path>, target_sample_rate: u32) -> Self {
         Self {
-            output_path: output_path.into(),
+            output_path: output_path.as_ref().to_path_buf(),
             target_sample_rate,
+            compensate_delay: true,
+            output_buffer_size: DEFAULT_OUTPUT_BUFFER_SIZE,
+            resampler_chunk_size: resampler::DEFAULT_CHUNK_SIZE,
+            antialias_filter: true,
+            bits_per_sample: 16,
+            overwrite: true,
             ..Default::default()
         }
     }
 
-    /// Set the input path for the audio converter if the input is an audio file.
+    /// Set the input path for the audio converter if the input is an audio file.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_path` - The path to the input audio file.
+    pub fn with_input_path<P: AsRef<Path>>(mut self, input_path: P) -> Self {
+        self.input_path = input_path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Create a new audio converter builder from a `String`-like output path.
+    #[deprecated(note = "use `AudioConverterBuilder::new`, which takes any `AsRef<Path>`")]
+    pub fn new_with_string(output_path: impl Into<String>, target_sample_rate: u32) -> Self {
+        Self::new(output_path.into(), target_sample_rate)
+    }
+
+    /// Set the input path from a `String`-like path.
+    #[deprecated(note = "use `with_input_path`, which takes any `AsRef<Path>`")]
+    pub fn with_input_path_string<S: Into<String>>(self, input_path: S) -> Self {
+        self.with_input_path(input_path.into())
+    }
+
+    /// Read the input as headerless PCM laid out as `spec` instead of probing its container, e.g.
+    /// the output of `arecord -t raw` or a capture buffer dumped to disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - The sample rate, channel count and sample format of the input.
+    pub fn with_raw_input(mut self, spec: RawSpec) -> Self {
+        self.raw_input = Some(spec);
+        self
+    }
+
+    /// Set the MIME type of the input, e.g. the `Content-Type` of an upload, as a hint of its
+    /// container. Without it, the extension of the input path is the hint.
+    ///
+    /// The container is found from the content of the input either way. The hint only decides
+    /// when the first container marker is preceded by bytes no reader recognizes and the input is
+    /// seekable: the marker may then belong to those bytes, so the search goes on for the hinted
+    /// container first. MIME types no reader claims are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `mime_type` - The MIME type, e.g. `audio/mpeg`. Parameters such as `; codecs=opus` are
+    ///   ignored.
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Convert the track at `index` of the input instead of its default audio track, e.g. the
+    /// second language of a video. Equivalent to `with_track(TrackSelector::Index(index))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the track to convert.
+    pub fn with_track_index(self, index: usize) -> Self {
+        self.with_track(TrackSelector::Index(index))
+    }
+
+    /// Select the audio track(s) of the input to convert, e.g. the commentary track of an OBS
+    /// recording.
+    ///
+    /// Without this option, the default track is converted if it is an audio track and the first
+    /// audio track otherwise, so the audio of MP4, MKV and WebM videos is found automatically.
+    ///
+    /// With `TrackSelector::All`, `convert_audio` writes every audio track to its own WAV file,
+    /// named after the output path: its `{track}` placeholder is replaced by the track index, or
+    /// the index is appended to the file stem if there is none (`out.wav` becomes `out_1.wav`).
+    /// The other conversion methods convert the default audio track.
+    ///
+    /// # Arguments
+    ///
+    /// * `selector` - The track(s) to convert.
+    pub fn with_track(mut self, selector: TrackSelector) -> Self {
+        self.track = Some(selector);
+        self
+    }
+
+    /// Convert only the `[start, end]` window of the input.
+    ///
+    /// For sources that support accurate seeking (e.g. FLAC, WAV) the decoder jumps straight to
+    /// `start`; other sources are decoded from the beginning and the frames before `start` are
+    /// discarded. Decoding stops once `end` is passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The offset from the beginning of the input at which the output starts.
+    ///
+    /// * `end` - The offset at which the output ends, or `None` to convert until the end of the input.
+    pub fn with_time_range(mut self, start: Duration, end: Option<Duration>) -> Self {
+        self.start_offset = Some(start);
+        self.end_offset = end;
+        self
+    }
+
+    /// Split the output into files of a fixed duration, e.g. the 30-second chunks expected by
+    /// Whisper, named `output_000.wav`, `output_001.wav`, ... after the output path. A `{chunk}`
+    /// placeholder in the output path is replaced by the index instead.
+    ///
+    /// Consecutive chunks share `overlap` of audio, so words cut at the end of a chunk are whole
+    /// at the start of the next one; the last chunk may be shorter. `convert_audio_chunks` returns
+    /// the path and position of every chunk. Chunking does not apply to resumable conversions,
+    /// streaming conversions or the conversions writing to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration of each chunk.
+    ///
+    /// * `overlap` - The duration shared by consecutive chunks, shorter than `duration`.
+    pub fn with_chunking(mut self, duration: Duration, overlap: Duration) -> Self {
+        self.chunking = Some((duration, overlap));
+        self
+    }
+
+    /// Trim the silence at the end of the audio, keeping some padding after the last sound.
+    ///
+    /// Trimming is disabled by default, so quiet outros are never cut. Pass `None` to disable it
+    /// again.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The threshold below which audio is silent and the padding to keep.
+    ///   `TrimOptions::default()` uses -40 dBFS and 0.5 seconds.
+    pub fn with_trim_trailing_silence(mut self, options: impl Into<Option<TrimOptions>>) -> Self {
+        self.trim_trailing_silence = options.into();
+        self
+    }
+
+    /// Trim the silence at the start of the audio, keeping some padding before the first sound,
+    /// so voice recordings start immediately.
+    ///
+    /// Trimming is disabled by default. Pass `None` to disable it again.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The threshold below which audio is silent and the padding to keep.
+    ///   `TrimOptions::default()` uses -40 dBFS and 0.5 seconds.
+    pub fn with_trim_leading_silence(mut self, options: impl Into<Option<TrimOptions>>) -> Self {
+        self.trim_leading_silence = options.into();
+        self
+    }
+
+    /// Keep only the speech of the audio, detected by the energy of short frames, and drop the
+    /// silence and noise between the speech segments, e.g. before speech recognition.
+    ///
+    /// Speech is detected on the resampled audio, after the time range and trimming are applied
+    /// and before loudness normalization. Detection needs the whole signal, so it is skipped by
+    /// streaming conversions. Pass `None` to disable it again.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The threshold and timings of the detection, or `None`. Disabled by default.
+    pub fn with_vad(mut self, options: impl Into<Option<VadOptions>>) -> Self {
+        self.vad = options.into();
+        self
+    }
+
+    /// Set the frequency weighting used when detecting the silence to trim.
+    ///
+    /// Silence is detected from the RMS level of 10 ms windows of a weighted copy of the audio,
+    /// so e.g. A-weighting keeps low-frequency rumble from counting as sound. The output itself is
+    /// not filtered.
+    ///
+    /// # Arguments
+    ///
+    /// * `weighting` - The weighting applied to the analysed signal. Defaults to `Weighting::None`.
+    pub fn with_silence_weighting(mut self, weighting: Weighting) -> Self {
+        self.silence_weighting = weighting;
+        self
+    }
+
+    /// Append the converted audio to the output WAV file if it already exists, instead of
+    /// overwriting it.
+    ///
+    /// The spec of the existing file must match the spec of the converted audio, otherwise the
+    /// conversion fails with `AudioConversionError::UnsupportedFormat`. The chunks that follow
+    /// the data of an existing WAV file, such as its tags, cue markers and BWF metadata, are kept
+    /// after the appended audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to append to an existing output file.
+    pub fn with_append(mut self, enabled: bool) -> Self {
+        self.append = enabled;
+        self
+    }
+
+    /// Replace the output file if it already exists. Enabled by default; when disabled, the
+    /// conversion fails with an `AlreadyExists` IO error instead, unless appending to it.
+    ///
+    /// Either way the output is written to a temporary file next to it first, which is renamed
+    /// into place only once it is complete, so a failed conversion leaves any existing file
+    /// untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to replace an existing output file.
+    pub fn with_overwrite(mut self, enabled: bool) -> Self {
+        self.overwrite = enabled;
+        self
+    }
+
+    /// Compensate the delay introduced by the resampler, so the converted audio is time-aligned
+    /// with the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to discard the leading delay frames of the resampled audio. Defaults
+    ///   to `true`.
+    pub fn with_compensate_delay(mut self, enabled: bool) -> Self {
+        self.compensate_delay = enabled;
+        self
+    }
+
+    /// Master the output to a target loudness without exceeding a true-peak ceiling.
+    ///
+    /// The gain needed to reach `target_lufs` is reduced if it would push the true peak above
+    /// `max_true_peak_dbtp`, so the output may land slightly below the loudness target. The final
+    /// gain, loudness and true peak are recorded in the `ConversionReport`.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_lufs` - The integrated loudness of the output in LUFS, e.g. `-16.0`.
+    ///
+    /// * `max_true_peak_dbtp` - The ceiling for the true peak of the output in dBTP, e.g. `-1.0`.
+    pub fn with_master(mut self, target_lufs: f32, max_true_peak_dbtp: f32) -> Self {
+        self.loudness_target = Some(LoudnessTarget {
+            target_lufs,
+            max_true_peak_dbtp: Some(max_true_peak_dbtp),
+        });
+        self
+    }
+
+    /// Normalize the output to a target integrated loudness, measured as specified by EBU R128.
+    ///
+    /// The integrated loudness of the converted audio is measured before the gain is applied; the
+    /// measured loudness, the applied gain and the resulting loudness and true peak are recorded
+    /// in the `ConversionReport`. Normalization needs the whole signal, so it is skipped when
+    /// streaming.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_lufs` - The integrated loudness of the output in LUFS, e.g. `-16.0` for podcasts
+    ///   or `-23.0` for broadcast.
+    ///
+    /// * `true_peak_limit` - The ceiling for the true peak of the output in dBTP, e.g. `-1.0`, or
+    ///   `None` to apply the full gain regardless of the peaks.
+    pub fn with_normalize_loudness(
+        mut self,
+        target_lufs: f32,
+        true_peak_limit: impl Into<Option<f32>>,
+    ) -> Self {
+        self.loudness_target = Some(LoudnessTarget {
+            target_lufs,
+            max_true_peak_dbtp: true_peak_limit.into(),
+        });
+        self
+    }
+
+    /// Apply a fixed gain to the audio before it is quantized, e.g. `20.0` for field recordings
+    /// that are far too quiet. It is applied after loudness normalization.
+    ///
+    /// If the gain would push the peaks above full scale, it is reduced so the loudest sample just
+    /// reaches it, unless soft clipping is enabled. Streaming conversions cannot look ahead, so
+    /// they apply the full gain and clip the peaks, softly if soft clipping is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `gain_db` - The gain in dB, negative to attenuate.
+    pub fn with_gain_db(mut self, gain_db: f32) -> Self {
+        self.gain_db = Some(gain_db);
+        self
+    }
+
+    /// Remove the content below a cutoff frequency, e.g. rumble and handling noise before speech
+    /// recognition, with a second-order Butterworth high-pass filter.
+    ///
+    /// The filter runs on the input samples after channel remixing, before silence trimming and
+    /// resampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff_hz` - The cutoff frequency in Hz, e.g. `80.0`, below the Nyquist frequency of
+    ///   the input.
+    pub fn with_highpass(mut self, cutoff_hz: f32) -> Self {
+        self.highpass_hz = Some(cutoff_hz);
+        self
+    }
+
+    /// Remove the content above a cutoff frequency, e.g. hiss, with a second-order Butterworth
+    /// low-pass filter.
+    ///
+    /// The filter runs on the input samples after channel remixing, before silence trimming and
+    /// resampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff_hz` - The cutoff frequency in Hz, e.g. `7000.0`, below the Nyquist frequency of
+    ///   the input.
+    pub fn with_lowpass(mut self, cutoff_hz: f32) -> Self {
+        self.lowpass_hz = Some(cutoff_hz);
+        self
+    }
+
+    /// Mute the audio while its level stays below a threshold, e.g. the room noise between the
+    /// sentences of a voice memo.
+    ///
+    /// The gate runs on the input samples after the high-pass and low-pass filters and the
+    /// denoiser, before silence trimming and resampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold_db` - The level below which the audio is muted, in dBFS, e.g. `-50.0`.
+    ///
+    /// * `attack` - The time the gate takes to open once the level rises above the threshold.
+    ///
+    /// * `release` - The time the gate takes to close once the level falls below the threshold.
+    pub fn with_noise_gate(
+        mut self,
+        threshold_db: f32,
+        attack: Duration,
+        release: Duration,
+    ) -> Self {
+        self.noise_gate = Some((threshold_db, attack, release));
+        self
+    }
+
+    /// Remove stationary noise, e.g. the hum of a fan or the hiss of a cheap microphone, by
+    /// spectral subtraction of a noise profile estimated from the quietest parts of the audio.
+    ///
+    /// The denoiser runs on the input samples after the high-pass and low-pass filters, before the
+    /// noise gate. It needs the whole signal, so it is skipped by streaming conversions.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The strength and floor of the noise reduction.
+    #[cfg(feature = "denoise")]
+    pub fn with_denoise(mut self, options: DenoiseOptions) -> Self {
+        self.denoise = Some(options);
+        self
+    }
+
+    /// Fade the output in at its start and out at its end, so clips cut from a longer recording
+    /// do not click at their boundaries. The fades are applied after the time range, trimming and
+    /// gain, before the output is padded.
+    ///
+    /// Fades need the end of the audio, so they are skipped by streaming conversions.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade_in` - The duration of the fade in, or zero for none.
+    ///
+    /// * `fade_out` - The duration of the fade out, or zero for none.
+    pub fn with_fade(mut self, fade_in: Duration, fade_out: Duration) -> Self {
+        self.fade = Some((fade_in, fade_out));
+        self
+    }
+
+    /// Set the shape of the fades set by `with_fade`.
+    ///
+    /// # Arguments
+    ///
+    /// * `shape` - The shape of the fades. Defaults to `FadeShape::Linear`.
+    pub fn with_fade_shape(mut self, shape: FadeShape) -> Self {
+        self.fade_shape = shape;
+        self
+    }
+
+    /// Change the speed of the audio without changing its pitch, e.g. to export a podcast at
+    /// 1.5 times its speed. The audio is time-stretched by waveform similarity overlap-add right
+    /// after resampling, so the time range applies to the input and the trimming, fades and
+    /// chunks to the stretched audio.
+    ///
+    /// Time stretching needs the whole signal, so it is skipped by streaming conversions. A tempo
+    /// that is not positive fails the conversion with `AudioConversionError::UnsupportedFormat`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tempo` - The speed of the output relative to the input, e.g. `1.25` for 1.25 times
+    ///   faster or `0.8` for slower. Defaults to `1.0`.
+    pub fn with_tempo(mut self, tempo: f32) -> Self {
+        self.tempo = Some(tempo);
+        self
+    }
+
+    /// Resample non-integer ratios (e.g. 44100 Hz to 48000 Hz) with a sinc resampler driven by the
+    /// exact ratio, which tracks it more precisely over long durations than fixed FFT blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to use the sinc resampler for non-integer ratios.
+    pub fn with_rational_resampler(mut self, enabled: bool) -> Self {
+        self.rational_resampler = enabled;
+        self
+    }
+
+    /// Select the resampler, trading quality against latency and CPU use.
+    ///
+    /// A resampler other than `ResamplerKind::Fft` takes precedence over the rational resampler.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The resampler to use. Defaults to `ResamplerKind::Fft`.
+    pub fn with_resampler(mut self, kind: ResamplerKind) -> Self {
+        self.resampler = kind;
+        self
+    }
+
+    /// Set the number of input frames the resampler processes at once. Smaller chunks lower the
+    /// latency of streaming conversions at the cost of more CPU per frame.
+    ///
+    /// The chunk size does not change the length of the output: whatever the resampler, the
+    /// resampled audio has `round(input_frames * target_rate / input_rate)` frames, as its tail is
+    /// flushed through the resampler and cut to that length.
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - The chunk size in frames. Defaults to `4096`.
+    pub fn with_resampler_chunk_size(mut self, frames: usize) -> Self {
+        self.resampler_chunk_size = frames;
+        self
+    }
+
+    /// Resample long inputs, e.g. multi-hour recordings, in overlapping segments on several
+    /// threads. The segments are crossfaded where they meet, and inputs shorter than a minute are
+    /// resampled on one thread.
+    ///
+    /// Only the in-memory conversion with delay compensation resamples in parallel; streaming and
+    /// resumable conversions resample on one thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - The number of threads. `0` uses one per CPU core. Unset by default, which
+    ///   resamples on one thread.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Copy the PCM data of WAV inputs whose spec already matches the output spec, instead of
+    /// decoding and re-encoding them. This keeps the samples bit-exact and is much faster.
+    ///
+    /// The copy skips all processing. It is not used if a time range, silence trimming, loudness
+    /// normalization, padding, channel gains, polarity inversion, cue markers, BWF metadata or a
+    /// large file format are configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to pass matching WAV inputs through unchanged.
+    pub fn with_passthrough_identical(mut self, enabled: bool) -> Self {
+        self.passthrough_identical = enabled;
+        self
+    }
+
+    /// Set the minimum length of the trailing silence for it to be trimmed.
+    ///
+    /// If the silent run at the end of the audio is shorter than this, the audio is left
+    /// untouched, so clips that simply end loud are not cut. Only applies if trimming is enabled
+    /// with `with_trim_trailing_silence`.
+    ///
+    /// # Arguments
+    ///
+    /// * `secs` - The minimum length of the trailing silence in seconds. Defaults to `0.0`, which
+    ///   trims any trailing silence.
+    pub fn with_min_trim_silence_secs(mut self, secs: f32) -> Self {
+        self.min_trim_silence_secs = secs;
+        self
+    }
+
+    /// Apply a separate gain to each channel, e.g. to balance a recording where one microphone
+    /// was hotter than the other.
+    ///
+    /// The gains are applied after the channels are remixed, so the number of gains must equal the
+    /// number of output channels, otherwise the conversion fails with
+    /// `AudioConversionError::UnsupportedFormat`.
+    ///
+    /// # Arguments
+    ///
+    /// * `gains_db` - The gain of each channel in dB, in channel order.
+    pub fn with_channel_gains_db(mut self, gains_db: Vec<f32>) -> Self {
+        self.channel_gains_db = Some(gains_db);
+        self
+    }
+
+    /// Invert the polarity of all channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to multiply every sample by -1.
+    pub fn with_invert_polarity(mut self, enabled: bool) -> Self {
+        self.invert_polarity = enabled;
+        self
+    }
+
+    /// Invert the polarity of selected channels, e.g. to fix a channel that was wired out of
+    /// phase. The inversion is applied right after decoding, so it takes effect before any
+    /// channels are mixed together.
+    ///
+    /// The number of flags must equal the number of channels, otherwise the conversion fails with
+    /// `AudioConversionError::UnsupportedFormat`. Channels are inverted if either their flag or
+    /// `with_invert_polarity` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Whether to invert each channel, in channel order.
+    pub fn with_invert_channels(mut self, channels: Vec<bool>) -> Self {
+        self.invert_channels = Some(channels);
+        self
+    }
+
+    /// Set the number of channels of the output WAV file, e.g. `1` for speech recognition models
+    /// that expect mono input.
+    ///
+    /// Mono output is folded down according to `with_downmix`, mono input is copied to every
+    /// output channel, and fewer channels than the input keep the first channels, e.g. the front
+    /// left and right of 5.1 audio. More channels than a multichannel input has fail the
+    /// conversion with `AudioConversionError::UnsupportedFormat`. The channels are remixed after
+    /// the channel gains and polarity inversion are applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - The number of output channels. Defaults to the number of input channels.
+    pub fn with_target_channels(mut self, channels: usize) -> Self {
+        self.target_channels = Some(channels);
+        self
+    }
+
+    /// Fold the input down to mono using the given mode. Unless `with_target_channels` sets
+    /// another number of channels, this makes the output mono.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How the channels are folded down. Defaults to `DownmixMode::Average`.
+    pub fn with_downmix(mut self, mode: DownmixMode) -> Self {
+        self.downmix = Some(mode);
+        self
+    }
+
+    /// Mix the channels through a matrix of gains, e.g. `vec![vec![0.71, 1.0]]` for a mono mix
+    /// with the left channel attenuated by 3 dB, or `vec![vec![1.0], vec![1.0]]` to duplicate mono
+    /// to stereo. The matrix overrides `with_target_channels` and `with_downmix`.
+    ///
+    /// A matrix whose rows do not have one gain per input channel fails the conversion with
+    /// `AudioConversionError::UnsupportedFormat`.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - One row per output channel, holding the linear gain of each input channel in
+    ///   that output channel.
+    pub fn with_channel_matrix(mut self, matrix: Vec<Vec<f32>>) -> Self {
+        self.channel_matrix = Some(matrix);
+        self
+    }
+
+    /// Set the number of bits per sample of the output WAV file, e.g. `24` for mastering tools.
+    ///
+    /// Integer samples may have 8, 16, 24 or 32 bits and floating point samples 32 bits; any
+    /// other combination fails the conversion with `AudioConversionError::UnsupportedFormat`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The number of bits per sample. Defaults to `16`.
+    pub fn with_bits_per_sample(mut self, bits: u16) -> Self {
+        self.bits_per_sample = bits;
+        self
+    }
+
+    /// Set the sample format of the output WAV file.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The sample format. Defaults to `SampleFormat::Int`.
+    pub fn with_sample_format(mut self, format: SampleFormat) -> Self {
+        self.sample_format = format;
+        self
+    }
+
+    /// Compand the samples of the output WAV file with G.711 μ-law or A-law after resampling,
+    /// e.g. for telephony systems expecting 8 kHz G.711 WAV files.
+    ///
+    /// Companded samples are 8 bits wide, so the bits per sample and sample format are ignored.
+    /// The encoding applies to WAV output only, and companded WAV files cannot be appended to,
+    /// resumed, or written as RF64 or Wave64 files.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoding` - The encoding of the samples. Defaults to `PcmEncoding::Linear`.
+    pub fn with_encoding(mut self, encoding: PcmEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Set the format of the output file. WAV by default; raw PCM writes the bare samples, whose
+    /// format then takes the place of the bits per sample and sample format of the WAV output.
+    /// IMA ADPCM WAV output always has 4 bits per sample. AIFF output takes integer samples. FLAC
+    /// output is lossless and takes 8, 16 or 24-bit integer samples. Ogg Opus output, with
+    /// the `opus-encoder` feature, takes mono or stereo audio at 8, 12, 16, 24 or 48 kHz. MP3
+    /// output, with the `mp3-encoder` feature, takes mono or stereo audio at the MPEG sample
+    /// rates from 8 to 48 kHz, e.g. 16, 22.05, 44.1 or 48 kHz.
+    ///
+    /// Raw PCM output has no header to check or store cue markers in, so output verification
+    /// only checks its length, and Ogg Opus and MP3 output are not verified. Cue markers are only
+    /// written to WAV output, AIFF, FLAC, Ogg Opus and MP3 output cannot be appended to, and
+    /// resumable conversions need WAV output.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The output format. Defaults to `OutputFormat::Wav`.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Write WAV output as an RF64 or Wave64 file, whose 64-bit sizes lift the 4 GB limit of
+    /// RIFF WAV files.
+    ///
+    /// Without this option, WAV output whose projected size exceeds 4 GB is written as RF64
+    /// automatically, e.g. hours of multichannel 32-bit float audio. The length is projected from
+    /// the duration the input declares, so streaming an input of unknown length still writes a
+    /// RIFF WAV file. RF64 and Wave64 files cannot be appended to, and resumable conversions
+    /// cannot write them.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The 64-bit WAV variant to always write WAV output as.
+    pub fn with_large_file(mut self, format: LargeFileFormat) -> Self {
+        self.large_file = Some(format);
+        self
+    }
+
+    /// Soft-clip the output, bending peaks above 0.9 smoothly toward full scale instead of
+    /// clipping them hard when they are quantized.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to soft-clip the output samples.
+    pub fn with_soft_clip(mut self, enabled: bool) -> Self {
+        self.soft_clip = enabled;
+        self
+    }
+
+    /// Re-read the output WAV file after it is written and check that its spec and length match
+    /// what was written, to catch truncated or corrupt output.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to verify the output file. Defaults to `false`.
+    pub fn with_verify_output(mut self, enabled: bool) -> Self {
+        self.verify_output = enabled;
+        self
+    }
+
+    /// Set the capacity of the buffer used to write a new output WAV file. A larger buffer
+    /// reduces the number of write syscalls, e.g. on network filesystems.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The buffer capacity in bytes. Defaults to 64 KiB.
+    pub fn with_output_buffer_size(mut self, size: usize) -> Self {
+        self.output_buffer_size = size;
+        self
+    }
+
+    /// Cap the memory held by the decoded and converted samples, e.g. for multi-hour inputs on a
+    /// small server.
+    ///
+    /// If the length of the input shows the conversion to an output file would exceed the cap,
+    /// the input is converted like `convert_audio_streaming` instead, which skips the steps that
+    /// need the whole signal. Conversions to memory or to a writer fail instead, as do inputs of
+    /// unknown length once their decoded samples exceed the cap.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The maximum size of the sample buffers in bytes. Unlimited by default.
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Pad the end of the output with silence so it lasts at least `secs`, e.g. for models that
+    /// require fixed-length inputs.
+    ///
+    /// The padding is added after all other processing. Output that is already longer is left
+    /// untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `secs` - The minimum duration of the output in seconds.
+    pub fn with_pad_to_secs(mut self, secs: f32) -> Self {
+        self.pad_to_secs = Some(secs);
+        self
+    }
+
+    /// Mark the start of each region of sound that follows a silent gap with a cue marker in the
+    /// output WAV file, so a DAW shows the segment boundaries of a long recording without it
+    /// being split.
+    ///
+    /// Silence is detected with the threshold and weighting of the trailing silence trimming, on
+    /// the output samples, and gaps must last at least half a second. Markers are not written
+    /// when appending to an existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to write a `cue ` chunk into the output file. Defaults to `false`.
+    pub fn with_silence_cue_markers(mut self, enabled: bool) -> Self {
+        self.silence_cue_markers = enabled;
+        self
+    }
+
+    /// Write Broadcast Wave Format metadata into a `bext` chunk of the output WAV file, turning it
+    /// into a BWF file for broadcast delivery.
+    ///
+    /// The chunk is only written to RIFF WAV output, and not when appending to an existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bext` - The origination and timecode metadata to write.
+    pub fn with_bwf_metadata(mut self, bext: BextChunk) -> Self {
+        self.bwf_metadata = Some(bext);
+        self
+    }
+
+    /// Copy the title, artist, album and comment tags of the input into the output, as read into
+    /// `AudioInfo::tags` by `AudioInspector::probe`.
+    ///
+    /// WAV output stores them in a `LIST` chunk of type `INFO`, FLAC output in a `VORBIS_COMMENT`
+    /// block, Ogg Opus output in its comment header and MP3 output in ID3v2 and ID3v1 tags. Raw
+    /// PCM, AIFF, RF64 and Wave64 output carry no tags, and tags are not written when appending to
+    /// an existing WAV file or into WAV output written by `convert_audio_to_writer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to copy the tags. Defaults to `false`.
+    pub fn with_copy_metadata(mut self, enabled: bool) -> Self {
+        self.copy_metadata = enabled;
+        self
+    }
+
+    /// Low-pass filter the audio slightly below the output Nyquist frequency before downsampling,
+    /// with a windowed-sinc FIR filter that is longer for larger ratios. This suppresses the
+    /// aliasing that the resampler alone may let through for extreme ratios, e.g. 48000 Hz to
+    /// 8000 Hz. The filter is not applied when upsampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to filter the audio before downsampling. Defaults to `true`.
+    pub fn with_antialias_filter(mut self, enabled: bool) -> Self {
+        self.antialias_filter = enabled;
+        self
+    }
+
+    /// Inspect the properties of the input after probing and before decoding, so expensive
+    /// conversions can be refused early, e.g. inputs longer than ten minutes or with more than
+    /// eight channels.
+    ///
+    /// The error returned by `precheck` aborts the conversion and is returned to the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `precheck` - The callback receiving the properties of the input.
+    pub fn with_precheck(
+        mut self,
+        precheck: impl Fn(&AudioInfo) -> Result<(), AudioConversionError> + Send + Sync + 'static,
+    ) -> Self {
+        self.precheck = Some(Precheck(Arc::new(precheck)));
+        self
+    }
+
+    /// Remove the encoder delay and padding frames of the input, for gapless playback of
+    /// consecutive tracks and accurate trimming. Only sources that declare them (e.g. MP3 with a
+    /// LAME header, AAC in MP4) are affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to enable gapless decoding. Defaults to `false`.
+    pub fn with_gapless(mut self, enabled: bool) -> Self {
+        self.gapless = enabled;
+        self
+    }
+
+    /// Verify the decoded audio against the checksums embedded in the input (e.g. the MD5 of a
+    /// FLAC stream), if the decoder supports it.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to verify the decoded audio. Defaults to `false`.
+    pub fn with_decoder_verify(mut self, enabled: bool) -> Self {
+        self.decoder_verify = enabled;
+        self
+    }
+
+    /// Set how packets the decoder fails to decode are handled. With
+    /// `ErrorPolicy::SkipCorrupt`, a damaged file is converted without its corrupt packets, whose
+    /// frames are counted in `ConversionReport::skipped_frames`.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The error policy. Defaults to `ErrorPolicy::Strict`.
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Make file conversions resumable after a crash, e.g. for very long inputs on flaky storage.
+    ///
+    /// The input is streamed to the output file, and the progress is saved to
+    /// `<output_path>.progress` every 30 seconds of input. If that file is present and the output
+    /// file matches it, the conversion seeks past the converted input and appends to the output;
+    /// otherwise it starts afresh. The progress file is removed once the conversion completes.
+    ///
+    /// Like `convert_streaming`, this mode does not trim the trailing silence, normalize the
+    /// loudness or write cue markers. A resumed conversion decodes the second of input before the
+    /// checkpoint again to restore the state of the resampler and filters, so it produces the same
+    /// output as an uninterrupted one, up to rounding.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to make `convert_audio` resumable. Defaults to `false`.
+    pub fn with_resume(mut self, enabled: bool) -> Self {
+        self.resume = enabled;
+        self
+    }
+
+    /// Report the progress of the conversion to a callback, e.g. to render a progress bar.
+    ///
+    /// The callback is called after each decoded packet and each block of output written. The
+    /// percentage is only estimated if the container declares the duration of the input. Raw PCM
+    /// and passthrough copies do not report progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback receiving the progress.
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl Fn(Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress_callback = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Abort the conversion once `token` is set, e.g. when the user cancels an upload.
+    ///
+    /// The token is checked before each decoded packet and each resampled chunk, and the
+    /// conversion then fails with `AudioConversionError::Cancelled`. A partially written output
+    /// file is removed, unless the conversion appends to an existing file or is resumable, in
+    /// which case it is kept so the conversion can continue later.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The flag to set from another thread to cancel the conversion.
+    pub fn with_cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Build the audio converter, checking the settings that do not depend on the input.
+    ///
+    /// Fails with `AudioConversionError::UnsupportedFormat` if the output sample rate is outside
+    /// of 1 kHz to 768 kHz or the options conflict, e.g. 32-bit integer FLAC output, and with
+    /// `AudioConversionError::IoError` if the input file or the directory of the output file does
+    /// not exist.
+    pub fn build(self) -> Result<AudioConverter, AudioConversionError> {
+        let converter = AudioConverter {
+            input_path: self.input_path,
+            output_path: self.output_path,
+            target_sample_rate: self.target_sample_rate,
+            track: self.track,
+            raw_input: self.raw_input,
+            mime_type: self.mime_type,
+            start_offset: self.start_offset,
+            end_offset: self.end_offset,
+            chunking: self.chunking,
+            silence_weighting: self.silence_weighting,
+            append: self.append,
+            overwrite: self.overwrite,
+            compensate_delay: self.compensate_delay,
+            loudness_target: self.loudness_target,
+            gain_db: self.gain_db,
+            highpass_hz: self.highpass_hz,
+            lowpass_hz: self.lowpass_hz,
+            noise_gate: self.noise_gate,
+            #[cfg(feature = "denoise")]
+            denoise: self.denoise,
+            fade: self.fade,
+            fade_shape: self.fade_shape,
+            tempo: self.tempo,
+            rational_resampler: self.rational_resampler,
+            resampler: self.resampler,
+            resampler_chunk_size: self.resampler_chunk_size,
+            #[cfg(feature = "parallel")]
+            threads: self.threads,
+            passthrough_identical: self.passthrough_identical,
+            min_trim_silence_secs: self.min_trim_silence_secs,
+            channel_gains_db: self.channel_gains_db,
+            verify_output: self.verify_output,
+            output_buffer_size: self.output_buffer_size,
+            max_memory: self.max_memory,
+            pad_to_secs: self.pad_to_secs,
+            invert_polarity: self.invert_polarity,
+            invert_channels: self.invert_channels,
+            silence_cue_markers: self.silence_cue_markers,
+            bwf_metadata: self.bwf_metadata,
+            copy_metadata: self.copy_metadata,
+            antialias_filter: self.antialias_filter,
+            precheck: self.precheck,
+            gapless: self.gapless,
+            decoder_verify: self.decoder_verify,
+            error_policy: self.error_policy,
+            resume: self.resume,
+            target_channels: self.target_channels,
+            downmix: self.downmix,
+            channel_matrix: self.channel_matrix,
+            bits_per_sample: self.bits_per_sample,
+            sample_format: self.sample_format,
+            output_format: self.output_format,
+            large_file: self.large_file,
+            encoding: self.encoding,
+            soft_clip: self.soft_clip,
+            trim_trailing_silence: self.trim_trailing_silence,
+            trim_leading_silence: self.trim_leading_silence,
+            vad: self.vad,
+            progress_callback: self.progress_callback,
+            cancel_token: self.cancel_token,
+            resampler_pool: None,
+        };
+        converter.check_settings()?;
+
+        Ok(converter)
+    }
+}
+
+type PrecheckFn = dyn Fn(&AudioInfo) -> Result<(), AudioConversionError> + Send + Sync;
+
+/// A callback inspecting the properties of the input before it is decoded.
+#[derive(Clone)]
+struct Precheck(Arc<PrecheckFn>);
+impl std::fmt::Debug for Precheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Precheck")
+    }
+}
+
+/// A callback receiving the progress of the conversion.
+#[derive(Clone)]
+struct ProgressCallback(Arc<ProgressFn>);
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Interleaved audio at the target sample rate, ready to be written.
+#[derive(Debug)]
+struct ConvertedAudio {
+    samples: Vec<f32>,
+    channels: usize,
+    tags: Tags,
+}
+
+/// The probed input, ready to be decoded.
+struct OpenedInput {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track: InputTrack,
+    tags: Tags,
+    /// The packets read while probing the layout of the track, decoded before the next ones.
+    pending_packets: VecDeque<Packet>,
+}
+
+/// The track of the input that is decoded.
+#[derive(Debug)]
+struct InputTrack {
+    id: u32,
+    time_base: Option<TimeBase>,
+    channels: usize,
+    sample_rate: u32,
+    codec: CodecType,
+    bits_per_sample: Option<u32>,
+    /// The length of the track in frames, if it is declared.
+    n_frames: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioConverter {
+    input_path: PathBuf,
+    output_path: PathBuf,
+    target_sample_rate: u32,
+    track: Option<TrackSelector>,
+    raw_input: Option<RawSpec>,
+    mime_type: Option<String>,
+    start_offset: Option<Duration>,
+    end_offset: Option<Duration>,
+    chunking: Option<(Duration, Duration)>,
+    silence_weighting: Weighting,
+    append: bool,
+    overwrite: bool,
+    compensate_delay: bool,
+    loudness_target: Option<LoudnessTarget>,
+    gain_db: Option<f32>,
+    highpass_hz: Option<f32>,
+    lowpass_hz: Option<f32>,
+    noise_gate: Option<(f32, Duration, Duration)>,
+    #[cfg(feature = "denoise")]
+    denoise: Option<DenoiseOptions>,
+    fade: Option<(Duration, Duration)>,
+    fade_shape: FadeShape,
+    tempo: Option<f32>,
+    rational_resampler: bool,
+    resampler: ResamplerKind,
+    resampler_chunk_size: usize,
+    #[cfg(feature = "parallel")]
+    threads: Option<usize>,
+    passthrough_identical: bool,
+    min_trim_silence_secs: f32,
+    channel_gains_db: Option<Vec<f32>>,
+    verify_output: bool,
+    output_buffer_size: usize,
+    max_memory: Option<usize>,
+    pad_to_secs: Option<f32>,
+    invert_polarity: bool,
+    invert_channels: Option<Vec<bool>>,
+    silence_cue_markers: bool,
+    bwf_metadata: Option<BextChunk>,
+    copy_metadata: bool,
+    antialias_filter: bool,
+    precheck: Option<Precheck>,
+    gapless: bool,
+    decoder_verify: bool,
+    error_policy: ErrorPolicy,
+    resume: bool,
+    target_channels: Option<usize>,
+    downmix: Option<DownmixMode>,
+    channel_matrix: Option<Vec<Vec<f32>>>,
+    bits_per_sample: u16,
+    sample_format: SampleFormat,
+    output_format: OutputFormat,
+    large_file: Option<LargeFileFormat>,
+    encoding: PcmEncoding,
+    soft_clip: bool,
+    trim_trailing_silence: Option<TrimOptions>,
+    trim_leading_silence: Option<TrimOptions>,
+    vad: Option<VadOptions>,
+    progress_callback: Option<ProgressCallback>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    /// The idle resamplers shared by the conversions of a `ConverterEngine`.
+    resampler_pool: Option<Arc<ResamplerPool>>,
+}
+impl AudioConverter {
+    pub fn convert_audio(&self) -> Result<ConversionReport, AudioConversionError> {
+        if self.track == Some(TrackSelector::All) && self.raw_input.is_none() {
+            let mut reports = self.convert_audio_tracks()?;
+            return Ok(reports.remove(0).1);
+        }
+
+        if self.resume {
+            return self.convert_audio_resumable();
+        }
+
+        if self.passthrough_identical && self.raw_input.is_none() {
+            if let Ok(reader) = WavReader::open(&self.input_path) {
+                if let Some(report) = self.passthrough(reader)? {
+                    return Ok(report);
+                }
+            }
+        }
+
+        let file = File::open(&self.input_path)?;
+        self.convert_audio_from_source(file)
+    }
+
+    /// Convert the selected audio tracks of the input file, each to its own WAV file, and return
+    /// the output path and report of every track.
+    ///
+    /// Only `TrackSelector::All` selects several tracks; otherwise this converts a single track to
+    /// the output path like `convert_audio`.
+    pub fn convert_audio_tracks(
+        &self,
+    ) -> Result<Vec<(PathBuf, ConversionReport)>, AudioConversionError> {
+        if self.track != Some(TrackSelector::All) || self.raw_input.is_some() {
+            return Ok(vec![(self.output_path.clone(), self.convert_audio()?)]);
+        }
+
+        let file = File::open(&self.input_path)?;
+        let media_source = MediaSourceStream::new(Box::new(file), Default::default());
+        let probed = probe::probe(
+            media_source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+            self.format_hint().as_deref(),
+        )?;
+        let indices = probe::audio_track_indices(&*probed.format);
+        if indices.is_empty() {
+            return Err(AudioConversionError::ProbeError(
+                "No audio track found".to_string(),
+            ));
+        }
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Converting {} audio tracks", indices.len());
+
+        let mut reports = Vec::with_capacity(indices.len());
+        for index in indices {
+            let mut converter = self.clone();
+            converter.track = Some(TrackSelector::Index(index));
+            converter.output_path = track::track_output_path(&self.output_path, index);
+
+            let report = converter.convert_audio()?;
+            reports.push((converter.output_path, report));
+        }
+
+        Ok(reports)
+    }
+
+    /// Split the converted audio at its silent gaps and write each region of sound to its own
+    /// file, e.g. one file per sentence for speech recognition. Returns the path, start and end of
+    /// every segment, in order; the result is empty if the audio is silent throughout.
+    ///
+    /// Silence is detected on the output samples with the weighting set by
+    /// `with_silence_weighting`, after the time range and trimming are applied. The `{segment}`
+    /// placeholder of the output path is replaced by the segment index, or the index is appended
+    /// to the file stem, e.g. `out_0.wav`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The threshold, minimum gap and padding of the segments.
+    pub fn convert_audio_segments(
+        &self,
+        options: SegmentOptions,
+    ) -> Result<Vec<Segment>, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let mut report = ConversionReport::default();
+        let converted = self.process_audio(Box::new(file), &progress, &mut report)?;
+
+        let sample_rate = self.target_sample_rate;
+        let ranges = segment::find_segments(
+            &self.silence_analysis_samples(&converted.samples, converted.channels, sample_rate),
+            converted.channels,
+            options.threshold(),
+            (options.min_gap_secs.max(0.0) * sample_rate as f32) as usize,
+            (options.padding_secs.max(0.0) * sample_rate as f32) as usize,
+        );
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Writing {} segments", ranges.len());
+
+        let segments = self.write_parts(&converted, ranges, segment::segment_output_path)?;
+        progress.written((converted.samples.len() / converted.channels) as u64);
+
+        Ok(segments)
+    }
+
+    /// Convert the input file and write each of its channels to its own mono file, e.g. the
+    /// interviewer and interviewee recorded on the left and right channels, and return the paths
+    /// of the files, in channel order.
+    ///
+    /// The files are named after the output path, with `{channel}` replaced by the channel label
+    /// or the label appended to the file stem, e.g. `interview_L.wav` and `interview_R.wav` for
+    /// stereo audio. The channels are those of the converted audio, after any remixing.
+    pub fn convert_audio_channels(&self) -> Result<Vec<PathBuf>, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let mut report = ConversionReport::default();
+        let converted = self.process_audio(Box::new(file), &progress, &mut report)?;
+
+        let channels = converted.channels;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Writing {} channels to separate files", channels);
+
+        let mut paths = Vec::with_capacity(channels);
+        for channel in 0..channels {
+            let mut converter = self.clone();
+            converter.output_path =
+                downmix::channel_output_path(&self.output_path, channel, channels);
+            converter.write_wav(&ConvertedAudio {
+                samples: converted
+                    .samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .copied()
+                    .collect(),
+                channels: 1,
+                tags: converted.tags.clone(),
+            })?;
+            paths.push(converter.output_path);
+        }
+        progress.written((converted.samples.len() / channels) as u64);
+
+        Ok(paths)
+    }
+
+    /// Detect the segments of speech of the input file, with the options set by `with_vad` or the
+    /// default ones, without writing the output. The segments include their padding and are
+    /// positioned in the converted audio, after the time range and trimming are applied.
+    pub fn detect_speech(&self) -> Result<Vec<SpeechSegment>, AudioConversionError> {
+        let mut converter = self.clone();
+        converter.vad = None;
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let mut report = ConversionReport::default();
+        let converted = converter.process_audio(Box::new(file), &progress, &mut report)?;
+
+        let sample_rate = self.target_sample_rate;
+        let offset = |frame: usize| Duration::from_secs_f64(frame as f64 / sample_rate as f64);
+        let speech = vad::detect_speech(
+            &converted.samples,
+            converted.channels,
+            sample_rate,
+            &self.vad.unwrap_or_default(),
+        );
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Detected {} speech segments", speech.len());
+
+        Ok(speech
+            .into_iter()
+            .map(|segment| SpeechSegment {
+                start: offset(segment.start),
+                end: offset(segment.end),
+            })
+            .collect())
+    }
+
+    /// Convert the input file to chunks of the duration set by `with_chunking`, and return the
+    /// path, start and end of every chunk, in order.
+    ///
+    /// Without chunking, the whole audio is written to the output path as a single chunk.
+    pub fn convert_audio_chunks(&self) -> Result<Vec<Segment>, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let mut report = ConversionReport::default();
+        let converted = self.process_audio(Box::new(file), &progress, &mut report)?;
+        let chunks = self.write_converted(&converted)?;
+        progress.written((converted.samples.len() / converted.channels) as u64);
+
+        Ok(chunks)
+    }
+
+    /// Convert the audio file held in `bytes`.
+    ///
+    /// The decoder reads from a copy of `bytes`. Use `convert_audio_from_owned_bytes` to hand over
+    /// a buffer the caller no longer needs instead of copying it.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the input audio file.
+    pub fn convert_audio_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<ConversionReport, AudioConversionError> {
+        if let Some(report) = self.passthrough_bytes(bytes)? {
+            return Ok(report);
+        }
+
+        self.convert_audio_from_source(std::io::Cursor::new(bytes.to_vec()))
+    }
+
+    /// Convert the audio file held in `bytes` without copying it, e.g. a large upload.
+    ///
+    /// Any shareable buffer is accepted, e.g. a `Vec<u8>`, an `Arc<[u8]>`, a `Box<[u8]>` or a
+    /// `bytes::Bytes`, and it is dropped once the conversion ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the input audio file.
+    pub fn convert_audio_from_owned_bytes<B: AsRef<[u8]> + Send + Sync + 'static>(
+        &self,
+        bytes: B,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        if let Some(report) = self.passthrough_bytes(bytes.as_ref())? {
+            return Ok(report);
+        }
+
+        self.convert_audio_from_source(std::io::Cursor::new(bytes))
+    }
+
+    /// Convert the audio read from an arbitrary media source, e.g. a decrypting reader or a zip
+    /// entry, without buffering it into memory first.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source of the input audio.
+    pub fn convert_audio_from_source<S: MediaSource + 'static>(
+        &self,
+        source: S,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        self.convert_audio_internal(Box::new(source))
+    }
+
+    /// Convert the audio read from any seekable reader, e.g. a network stream, a file wrapped in a
+    /// decryption layer or a memory-mapped buffer, without copying it into memory first.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader of the input audio.
+    pub fn convert_audio_from_reader<R: Read + Seek + Send + Sync + 'static>(
+        &self,
+        reader: R,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let source = ReaderSource::new(reader)?;
+        self.convert_audio_from_source(source)
+    }
+
+    /// Convert headerless PCM, e.g. from a hardware capture, bypassing probing entirely.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The interleaved samples.
+    ///
+    /// * `input_rate` - The sample rate of the samples in Hz.
+    ///
+    /// * `channels` - The number of interleaved channels.
+    ///
+    /// * `sample_format` - The format of each sample.
+    pub fn convert_raw_pcm(
+        &self,
+        bytes: &[u8],
+        input_rate: u32,
+        channels: usize,
+        sample_format: RawFormat,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+
+        if input_rate == 0 || channels == 0 {
+            let err_msg = format!(
+                "Invalid raw PCM parameters: {} Hz, {} channels",
+                input_rate, channels
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        #[cfg(feature = "logging")]
+        info!(
+            target: "stdout",
+            "Converting raw PCM: {:?}, {} Hz, {} channels",
+            sample_format, input_rate, channels
+        );
+
+        self.precheck(&AudioInfo {
+            codec: None,
+            container: None,
+            channels,
+            sample_rate: input_rate,
+            duration_secs: Some(
+                (bytes.len() / sample_format.bytes_per_sample() / channels) as f64
+                    / input_rate as f64,
+            ),
+            bits_per_sample: Some(sample_format.bytes_per_sample() as u16 * 8),
+            is_lossy: false,
+            metadata: Vec::new(),
+            tags: Tags::default(),
+        })?;
+
+        let mut samples = raw::decode_raw(bytes, sample_format);
+
+        // Keep whole frames inside the requested window
+        let frames = samples.len() / channels;
+        let start = self
+            .start_offset
+            .map_or(0, |offset| {
+                self.offset_to_frames(offset, input_rate) as usize
+            })
+            .min(frames);
+        let end = self
+            .end_offset
+            .map_or(frames, |offset| {
+                self.offset_to_frames(offset, input_rate) as usize
+            })
+            .clamp(start, frames);
+        samples.truncate(end * channels);
+        samples.drain(..start * channels);
+
+        let mut report = ConversionReport {
+            source_bit_depth: Some(sample_format.bytes_per_sample() as u16 * 8),
+            ..Default::default()
+        };
+        let converted = self.process_decoded(samples, channels, input_rate, &mut report)?;
+        self.write_converted(&converted)?;
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Convert the input file and return the bytes of the WAV file instead of writing it to disk.
+    ///
+    /// The output path of the builder is not used and may be empty.
+    pub fn convert_audio_to_vec(&self) -> Result<Vec<u8>, AudioConversionError> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        self.convert_audio_to_writer(&mut buffer)?;
+
+        Ok(buffer.into_inner())
+    }
+
+    /// Convert the audio file held in `bytes` and return the bytes of the output file, without
+    /// touching the file system, e.g. in a browser on `wasm32-unknown-unknown`.
+    ///
+    /// The input and output paths of the builder are not used and may be empty. The input is read
+    /// in place, so any shareable buffer is accepted without copying it, e.g. a `Vec<u8>`, an
+    /// `Arc<[u8]>` or a `bytes::Bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the input audio file.
+    pub fn convert_bytes_to_vec<B: AsRef<[u8]> + Send + Sync + 'static>(
+        &self,
+        bytes: B,
+    ) -> Result<Vec<u8>, AudioConversionError> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        self.convert_audio_from_source_to_writer(std::io::Cursor::new(bytes), &mut buffer)?;
+
+        Ok(buffer.into_inner())
+    }
+
+    /// Convert the input file and write the WAV file to `writer` instead of the output path.
+    ///
+    /// The output path of the builder is not used and may be empty. Appending, output
+    /// verification and cue markers only apply to output files, so they are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination of the WAV file, e.g. a socket buffer or an in-memory cursor.
+    pub fn convert_audio_to_writer<W: Write + Seek>(
+        &self,
+        writer: W,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+        self.convert_audio_from_source_to_writer(file, writer)
+    }
+
+    /// Convert the audio read from a media source and write the WAV file to `writer`, with
+    /// neither the input nor the output path involved, e.g. to pipe audio through memory.
+    ///
+    /// Appending, output verification and cue markers only apply to output files, so they are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source of the input audio.
+    ///
+    /// * `writer` - The destination of the WAV file.
+    pub fn convert_audio_from_source_to_writer<S: MediaSource + 'static, W: Write + Seek>(
+        &self,
+        source: S,
+        writer: W,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+
+        let mut report = ConversionReport::default();
+        let progress = self.progress_tracker();
+        let converted = self.process_audio(Box::new(source), &progress, &mut report)?;
+
+        let spec = self.output_spec(converted.channels as u16)?;
+        let mut wav_writer = self.output_writer(
+            writer,
+            spec,
+            Some(converted.samples.len() as u64),
+            &converted.tags,
+        )?;
+        wav_writer.write(&converted.samples)?;
+        wav_writer.finalize()?;
+        progress.written((converted.samples.len() / converted.channels) as u64);
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Convert the input file and return the interleaved samples at the target sample rate instead
+    /// of writing a file, e.g. `Vec<i16>` for whisper.cpp or `Vec<f32>` for an ML pipeline.
+    ///
+    /// The output path of the builder is not used and may be empty. `i16` samples are quantized
+    /// like those of 16-bit WAV output.
+    pub fn convert_to_samples<T: OutputSample>(&self) -> Result<Vec<T>, AudioConversionError> {
+        let decoded = self.decode_to_f32()?;
+
+        Ok(decoded
+            .into_interleaved()
+            .into_iter()
+            .map(T::from_f32)
+            .collect())
+    }
+
+    /// Convert the input file and return the samples at the target sample rate in memory, with
+    /// interleaved and per-channel views, instead of writing a WAV file only to read it back.
+    ///
+    /// The output path of the builder is not used and may be empty.
+    pub fn decode_to_f32(&self) -> Result<DecodedAudio, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+
+        let mut report = ConversionReport::default();
+        let progress = self.progress_tracker();
+        let converted = self.process_audio(Box::new(file), &progress, &mut report)?;
+        progress.written((converted.samples.len() / converted.channels) as u64);
+
+        Ok(DecodedAudio::new(
+            converted.samples,
+            converted.channels,
+            self.target_sample_rate,
+        ))
+    }
+
+    /// Check that the input is decodable end-to-end without writing the output WAV file.
+    ///
+    /// The complete decode and resample pipeline runs, and the returned report is the same a real
+    /// conversion would produce. Check `ConversionReport::truncated` to find out whether the input
+    /// ended prematurely.
+    pub fn validate(&self) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+        let file = File::open(&self.input_path)?;
+
+        let mut report = ConversionReport::default();
+        self.process_audio(Box::new(file), &self.progress_tracker(), &mut report)?;
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Convert the input block by block, handing each block of output samples to `on_block`
+    /// instead of writing the output WAV file. The whole signal is never held in memory, which
+    /// suits feeding a live consumer such as a socket or a model.
+    ///
+    /// The blocks hold interleaved samples at the target sample rate. Trimming the trailing
+    /// silence and loudness normalization need the whole signal, so both are skipped in this
+    /// mode; otherwise the concatenated blocks match the samples of a buffered conversion. An
+    /// error returned by `on_block` aborts the conversion.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_block` - The callback receiving each block of interleaved output samples.
+    pub fn convert_streaming(
+        &self,
+        mut on_block: impl FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let input = self.open_input(Box::new(file), &progress)?;
+        self.stream_audio(input, &progress, &mut on_block)
+    }
+
+    /// Convert the input file to the output WAV file chunk by chunk, so memory use stays bounded
+    /// regardless of the length of the input, e.g. for hour-long podcasts.
+    ///
+    /// Each decoded packet is resampled and written before the next one is decoded. Trimming the
+    /// trailing silence, loudness normalization and cue markers need the whole signal, so they
+    /// are skipped in this mode; otherwise the output matches that of `convert_audio`.
+    pub fn convert_audio_streaming(&self) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let input = self.open_input(Box::new(file), &progress)?;
+        self.stream_to_output(input, &progress, started)
+    }
+
+    /// Convert the opened input to the output file chunk by chunk.
+    fn stream_to_output(
+        &self,
+        input: OpenedInput,
+        progress: &ProgressTracker,
+        started: Stopwatch,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let output_channels = self.output_channels(input.track.channels)?;
+        let spec = self.output_spec(output_channels as u16)?;
+        let appending = self.append && self.output_path.exists();
+        let projected_samples = self.projected_samples(&input.track, output_channels);
+        let tags = input.tags.clone();
+
+        // The partial output of a failed or cancelled conversion is removed with the temporary
+        // file, while appending leaves what was written in place
+        let mut report = self.write_atomically(|converter| {
+            let (mut wav_writer, trailing_chunks) =
+                converter.create_output_writer(spec, projected_samples, &tags)?;
+
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Streaming converted audio to WAV file: {}", converter.output_path.display());
+
+            let report = converter.stream_audio(input, progress, &mut |converted| {
+                wav_writer.write(converted)
+            })?;
+
+            let written_samples = wav_writer.len();
+            wav_writer.finalize()?;
+            trailing_chunks.restore(&converter.output_path)?;
+            converter.append_wav_chunks(spec, projected_samples, appending, &tags)?;
+
+            if converter.verify_output {
+                converter.verify_written(spec, written_samples)?;
+            }
+            Ok(report)
+        })?;
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Decode and convert the opened input packet by packet, handing each block of output
+    /// samples to `on_block`.
+    fn stream_audio(
+        &self,
+        mut input: OpenedInput,
+        progress: &ProgressTracker,
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+        let channels = input.track.channels;
+        let original_sample_rate = input.track.sample_rate;
+
+        #[cfg(feature = "logging")]
+        if self.loudness_target.is_some() {
+            warn!(target: "stdout", "Loudness normalization is not supported when streaming, skipping it");
+        }
+
+        #[cfg(feature = "logging")]
+        if self.fade.is_some() {
+            warn!(target: "stdout", "Fades are not supported when streaming, skipping them");
+        }
+
+        #[cfg(feature = "logging")]
+        if self.tempo.is_some() {
+            warn!(target: "stdout", "Tempo changes are not supported when streaming, skipping them");
+        }
+
+        #[cfg(feature = "logging")]
+        if self.denoise_enabled() {
+            warn!(target: "stdout", "Denoising is not supported when streaming, skipping it");
+        }
+
+        let mut report = ConversionReport::default();
+        if original_sample_rate != self.target_sample_rate {
+            report.resampler = Some(self.resampler_name(original_sample_rate));
+        }
+        let polarity = self.channel_polarity(channels)?;
+        let gain = self.streaming_gain(&mut report);
+        let output_channels = self.output_channels(channels)?;
+        let channel_gains = self.channel_gains(output_channels)?;
+        let mut filters = self.biquad_filters(output_channels, original_sample_rate)?;
+        let mut gate = self.noise_gate(output_channels, original_sample_rate);
+        let mut clip_detector = ClipDetector::new(channels);
+        let mut mono_compatibility = MonoCompatibility::new(channels);
+        let mut stage = self.streaming_stage(output_channels, original_sample_rate)?;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Streaming converted audio");
+
+        let mut peak = 0.0_f32;
+        let on_block = &mut |converted: &[f32]| {
+            let converted = &self.soft_clipped(converted);
+            on_block(converted)?;
+            progress.written((converted.len() / output_channels) as u64);
+            peak = converted
+                .iter()
+                .fold(peak, |peak, sample| peak.max(sample.abs()));
+            Ok(())
+        };
+
+        let mut input_frames = 0;
+        let mut block = Vec::new();
+        self.process_audio_samples(&mut input, 0, progress, &mut report, &mut |samples| {
+            clip_detector.feed(samples);
+            input_frames += samples.len() / channels;
+
+            block.clear();
+            block.extend_from_slice(samples);
+            if let Some(polarity) = &polarity {
+                apply_channel_gains(&mut block, polarity);
+            }
+            mono_compatibility.feed(&block);
+            if self.needs_remix(channels, output_channels) {
+                block = self.remix(&block, channels, output_channels);
+            }
+            if let Some(channel_gains) = &channel_gains {
+                apply_channel_gains(&mut block, channel_gains);
+            }
+            for filter in filters.iter_mut() {
+                filter.process(&mut block);
+            }
+            if let Some(gate) = &mut gate {
+                gate.process(&mut block);
+            }
+            if let Some(gain) = gain {
+                block.iter_mut().for_each(|sample| *sample *= gain);
+            }
+
+            stage.push(&block, on_block)
+        })?;
+
+        let (mut output_frames, resampler) = stage.finish(on_block)?;
+        if let Some(resampler) = resampler {
+            self.release_resampler(output_channels, original_sample_rate, resampler);
+        }
+
+        let padding_frames = self.padding_frames(output_frames);
+        if padding_frames > 0 {
+            on_block(&vec![0.0; padding_frames * output_channels])?;
+            output_frames += padding_frames;
+        }
+
+        self.record_clipping(clip_detector, &mut report);
+        report.mono_compatibility_db = mono_compatibility.finish();
+        report.input_duration_secs = input_frames as f64 / original_sample_rate as f64;
+        report.output_duration_secs = output_frames as f64 / self.target_sample_rate as f64;
+        self.record_layout(&mut report, channels, original_sample_rate, output_channels);
+        report.samples_written = (output_frames * output_channels) as u64;
+        report.output_peak_dbfs = peak_dbfs(peak);
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Convert the input file in segments, saving the progress after each one so an interrupted
+    /// conversion resumes where it stopped.
+    fn convert_audio_resumable(&self) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+        let file = File::open(&self.input_path)?;
+        let progress = self.progress_tracker();
+        let mut input = self.open_input(Box::new(file), &progress)?;
+        let channels = input.track.channels;
+        let original_sample_rate = input.track.sample_rate;
+        let output_channels = self.output_channels(channels)?;
+        let spec = self.output_spec(output_channels as u16)?;
+        if self
+            .large_file_format(spec, self.projected_samples(&input.track, output_channels))
+            .is_some()
+        {
+            let err_msg = "Resumable conversions cannot write RF64 or Wave64 output".to_string();
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+        let progress_path = Checkpoint::path(&self.output_path);
+
+        // Resume only if the output file is exactly as the last checkpoint left it
+        let checkpoint = Checkpoint::read(&progress_path).filter(|checkpoint| {
+            WavReader::open(&self.output_path).is_ok_and(|reader| {
+                reader.spec() == spec && reader.len() == checkpoint.output_samples
+            })
+        });
+        let (mut wav_writer, checkpoint_frames) = match checkpoint {
+            Some(checkpoint) => {
+                #[cfg(feature = "logging")]
+                info!(
+                    target: "stdout",
+                    "Resuming the conversion after {} input frames",
+                    checkpoint.input_frames
+                );
+
+                (
+                    WavWriter::append(&self.output_path)?,
+                    checkpoint.input_frames,
+                )
+            }
+            None => {
+                self.check_overwrite()?;
+                let file = File::create(&self.output_path)?;
+                let buf_writer = BufWriter::with_capacity(self.output_buffer_size, file);
+                (WavWriter::new(buf_writer, spec)?, 0)
+            }
+        };
+        let written_frames = wav_writer.len() as u64 / output_channels as u64;
+        if checkpoint_frames > 0 {
+            // Count the frames written before the interruption
+            progress.written(written_frames);
+        }
+
+        // Restart a little before the checkpoint, on a whole period of the rate ratio where the
+        // output lines up exactly with that of the whole input, and drop the output up to the end
+        // of the output file
+        let divisor = streaming::gcd(original_sample_rate, self.target_sample_rate) as u64;
+        let input_period = original_sample_rate as u64 / divisor;
+        let output_period = self.target_sample_rate as u64 / divisor;
+        let warmup_frames = (RESUME_WARMUP_SECS * original_sample_rate as f64) as u64;
+        let skip_frames =
+            checkpoint_frames.saturating_sub(warmup_frames) / input_period * input_period;
+        let mut replayed_frames = (checkpoint_frames - skip_frames) as usize;
+        let mut discarded_samples = (written_frames
+            .saturating_sub(skip_frames / input_period * output_period)
+            * output_channels as u64) as usize;
+
+        let mut report = ConversionReport::default();
+        if original_sample_rate != self.target_sample_rate {
+            report.resampler = Some(self.resampler_name(original_sample_rate));
+        }
+        let polarity = self.channel_polarity(channels)?;
+        let channel_gains = self.channel_gains(output_channels)?;
+        let gain = self.streaming_gain(&mut report);
+        let mut filters = self.biquad_filters(output_channels, original_sample_rate)?;
+        let mut gate = self.noise_gate(output_channels, original_sample_rate);
+        let mut clip_detector = ClipDetector::new(channels);
+        let mut mono_compatibility = MonoCompatibility::new(channels);
+        let mut stage = self.streaming_stage(output_channels, original_sample_rate)?;
+
+        let peak = Cell::new(0.0_f32);
+        let segment_frames = (RESUME_SEGMENT_SECS * original_sample_rate as f64) as usize;
+        let mut segment_pos = (skip_frames % segment_frames as u64) as usize;
+        let mut input_frames = skip_frames;
+        let mut block = Vec::new();
+        let mut write = |wav_writer: &mut WavWriter<BufWriter<File>>, converted: &[f32]| {
+            // Drop the output of the replayed input that is already in the output file
+            let discarded = discarded_samples.min(converted.len());
+            discarded_samples -= discarded;
+            write_tracked(
+                wav_writer,
+                &self.soft_clipped(&converted[discarded..]),
+                &progress,
+                &peak,
+            )
+        };
+        self.process_audio_samples(
+            &mut input,
+            skip_frames,
+            &progress,
+            &mut report,
+            &mut |samples| {
+                // The replayed input was already analysed before the interruption
+                let replayed = replayed_frames.min(samples.len() / channels);
+                replayed_frames -= replayed;
+                clip_detector.feed(&samples[replayed * channels..]);
+
+                block.clear();
+                block.extend_from_slice(samples);
+                if let Some(polarity) = &polarity {
+                    apply_channel_gains(&mut block, polarity);
+                }
+                mono_compatibility.feed(&block[replayed * channels..]);
+                if self.needs_remix(channels, output_channels) {
+                    block = self.remix(&block, channels, output_channels);
+                }
+                if let Some(channel_gains) = &channel_gains {
+                    apply_channel_gains(&mut block, channel_gains);
+                }
+                for filter in filters.iter_mut() {
+                    filter.process(&mut block);
+                }
+                if let Some(gate) = &mut gate {
+                    gate.process(&mut block);
+                }
+                if let Some(gain) = gain {
+                    block.iter_mut().for_each(|sample| *sample *= gain);
+                }
+
+                // Save the progress at the segment boundaries
+                let mut rest = &block[..];
+                while !rest.is_empty() {
+                    let frames = (rest.len() / output_channels).min(segment_frames - segment_pos);
+                    stage.push(&rest[..frames * output_channels], &mut |converted| {
+                        write(&mut wav_writer, converted)
+                    })?;
+                    rest = &rest[frames * output_channels..];
+                    segment_pos += frames;
+                    input_frames += frames as u64;
+
+                    // The replayed input is already covered by the saved progress
+                    if segment_pos == segment_frames {
+                        segment_pos = 0;
+                        if input_frames > checkpoint_frames {
+                            // The header must be up to date before the progress is saved
+                            wav_writer.flush()?;
+                            Checkpoint {
+                                input_frames,
+                                output_samples: wav_writer.len(),
+                            }
+                            .write(&progress_path)?;
+                        }
+                    }
+                }
+
+                Ok(())
+            },
+        )?;
+
+        let (_, resampler) = stage.finish(&mut |converted| write(&mut wav_writer, converted))?;
+        if let Some(resampler) = resampler {
+            self.release_resampler(output_channels, original_sample_rate, resampler);
+        }
+
+        let mut output_frames = wav_writer.len() as usize / output_channels;
+        let padding_frames = self.padding_frames(output_frames);
+        if padding_frames > 0 {
+            write_tracked(
+                &mut wav_writer,
+                &vec![0.0; padding_frames * output_channels],
+                &progress,
+                &peak,
+            )?;
+            output_frames += padding_frames;
+        }
+
+        let written_samples = wav_writer.len();
+        wav_writer.finalize()?;
+        resume::truncate_to_riff(&self.output_path)?;
+        // The chunks are only written once the conversion completes, so a resumed output has none
+        self.append_wav_chunks(spec, None, false, &input.tags)?;
+        // Inputs shorter than one segment never save a checkpoint
+        match std::fs::remove_file(&progress_path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        if self.verify_output {
+            self.verify_wav(spec, written_samples as u64)?;
+        }
+
+        self.record_clipping(clip_detector, &mut report);
+        report.mono_compatibility_db = mono_compatibility.finish();
+        report.input_duration_secs = input_frames as f64 / original_sample_rate as f64;
+        report.output_duration_secs = output_frames as f64 / self.target_sample_rate as f64;
+        self.record_layout(&mut report, channels, original_sample_rate, output_channels);
+        report.samples_written = written_samples as u64;
+        report.output_peak_dbfs = peak_dbfs(peak.get());
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    fn convert_audio_internal(
+        &self,
+        source: Box<dyn MediaSource>,
+    ) -> Result<ConversionReport, AudioConversionError> {
+        let started = Stopwatch::start();
+        let mut report = ConversionReport::default();
+        let progress = self.progress_tracker();
+        let input = self.open_input(source, &progress)?;
+        if self.exceeds_max_memory(&input.track)? {
+            #[cfg(feature = "logging")]
+            warn!(target: "stdout", "The conversion would hold more than {} bytes of samples, streaming it", self.max_memory.unwrap_or_default());
+
+            return self.stream_to_output(input, &progress, started);
+        }
+
+        let converted = self.process_opened(input, &progress, &mut report)?;
+        self.write_converted(&converted)?;
+        progress.written((converted.samples.len() / converted.channels) as u64);
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+
+    /// Decode the input and bring it to the target sample rate.
+    fn process_audio(
+        &self,
+        source: Box<dyn MediaSource>,
+        progress: &ProgressTracker,
+        report: &mut ConversionReport,
+    ) -> Result<ConvertedAudio, AudioConversionError> {
+        let input = self.open_input(source, progress)?;
+        if self.exceeds_max_memory(&input.track)? {
+            let err_msg = format!(
+                "Converting the input would hold more than {} bytes of samples",
+                self.max_memory.unwrap_or_default()
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        self.process_opened(input, progress, report)
+    }
+
+    /// Decode the opened input and bring it to the target sample rate.
+    fn process_opened(
+        &self,
+        mut input: OpenedInput,
+        progress: &ProgressTracker,
+        report: &mut ConversionReport,
+    ) -> Result<ConvertedAudio, AudioConversionError> {
+        // Collect all samples, failing before the buffer outgrows the memory cap
+        let max_samples = self
+            .max_memory
+            .map_or(usize::MAX, |bytes| bytes / mem::size_of::<f32>());
+        let mut all_samples = Vec::new();
+        self.process_audio_samples(&mut input, 0, progress, report, &mut |samples| {
+            if all_samples.len() + samples.len() > max_samples {
+                let err_msg = format!(
+                    "The decoded samples exceed the memory cap of {} bytes",
+                    self.max_memory.unwrap_or_default()
+                );
+
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+
+            all_samples.extend_from_slice(samples);
+            Ok(())
+        })?;
+
+        let mut converted = self.process_decoded(
+            all_samples,
+            input.track.channels,
+            input.track.sample_rate,
+            report,
+        )?;
+        converted.tags = input.tags;
+
+        Ok(converted)
+    }
+
+    /// Check the settings that do not depend on the input.
+    fn check_settings(&self) -> Result<(), AudioConversionError> {
+        let ragged_matrix = self.channel_matrix.as_ref().is_some_and(|matrix| {
+            matrix.is_empty() || matrix.iter().any(|row| row.len() != matrix[0].len())
+        });
+        let err_msg = if !OUTPUT_SAMPLE_RATES.contains(&self.target_sample_rate) {
+            Some(format!(
+                "The output sample rate of {} Hz is outside of {} Hz to {} Hz",
+                self.target_sample_rate,
+                OUTPUT_SAMPLE_RATES.start(),
+                OUTPUT_SAMPLE_RATES.end()
+            ))
+        } else if self.target_channels == Some(0) {
+            Some("The output needs at least one channel".to_string())
+        } else if ragged_matrix {
+            Some("The rows of the channel matrix must have the same number of gains".to_string())
+        } else if let Some(tempo) = self
+            .tempo
+            .filter(|tempo| !(*tempo > 0.0 && tempo.is_finite()))
+        {
+            Some(format!("Invalid tempo: {}", tempo))
+        } else if let Some((duration, overlap)) = self
+            .chunking
+            .filter(|(duration, overlap)| duration.is_zero() || overlap >= duration)
+        {
+            Some(format!(
+                "Chunks of {:?} cannot overlap by {:?}",
+                duration, overlap
+            ))
+        } else if self.resume
+            && (self.output_format != OutputFormat::Wav || self.encoding != PcmEncoding::Linear)
+        {
+            Some("Resumable conversions need linear PCM WAV output".to_string())
+        } else {
+            None
+        };
+        if let Some(err_msg) = err_msg {
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        let missing_output_dir = self
+            .output_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty() && !dir.is_dir());
+        let err_msg = if !self.input_path.as_os_str().is_empty() && !self.input_path.is_file() {
+            Some(format!(
+                "The input file {} does not exist",
+                self.input_path.display()
+            ))
+        } else {
+            missing_output_dir
+                .map(|dir| format!("The output directory {} does not exist", dir.display()))
+        };
+        if let Some(err_msg) = err_msg {
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::IoError(std::io::Error::new(
+                ErrorKind::NotFound,
+                err_msg,
+            )));
+        }
+
+        // The channels are only known once the input is probed, so the output spec is checked
+        // for mono audio, which the other layouts share the constraints of
+        self.output_spec(1).map(|_| ())
+    }
+
+    /// The progress tracker of a new conversion.
+    fn progress_tracker(&self) -> ProgressTracker {
+        ProgressTracker::new(
+            self.progress_callback
+                .as_ref()
+                .map(|ProgressCallback(callback)| callback.clone()),
+        )
+    }
+
+    /// The extension hinting at the container of the input: that of its MIME type if one is set,
+    /// or else that of the input path.
+    fn format_hint(&self) -> Option<String> {
+        match &self.mime_type {
+            Some(mime_type) => probe::mime_extension(mime_type).map(str::to_string),
+            None => self
+                .input_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(str::to_string),
+        }
+    }
+
+    /// Probe the input and create a decoder for its selected audio track. The bytes read from
+    /// `source` are counted by `progress`.
+    fn open_input(
+        &self,
+        source: Box<dyn MediaSource>,
+        progress: &ProgressTracker,
+    ) -> Result<OpenedInput, AudioConversionError> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Probing audio");
+
+        let source = CountingSource::new(source, progress.bytes_read());
+        let media_source = MediaSourceStream::new(Box::new(source), Default::default());
+
+        let format_opts = FormatOptions {
+            enable_gapless: self.gapless,
+            ..Default::default()
+        };
+        let metadata_opts: MetadataOptions = Default::default();
+
+        // Probe the media source, unless its layout is given
+        let (format, info, pending_packets): (Box<dyn FormatReader>, AudioInfo, _) =
+            match self.raw_input {
+                Some(spec) => {
+                    let reader = RawReader::new(media_source, spec)?;
+                    let info = reader.audio_info();
+                    (Box::new(reader), info, VecDeque::new())
+                }
+                None => {
+                    let mut probed = probe::probe(
+                        media_source,
+                        &format_opts,
+                        &metadata_opts,
+                        self.format_hint().as_deref(),
+                    )?;
+                    let info = probed.audio_info(self.track.as_ref())?;
+                    (probed.format, info, probed.pending_packets)
+                }
+            };
+
+        #[cfg(feature = "logging")]
+        {
+            use symphonia::core::codecs::{
+                CODEC_TYPE_AAC, CODEC_TYPE_ALAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_OPUS,
+                CODEC_TYPE_VORBIS,
+            };
+
+            // Iterate through the tracks and find audio tracks.
+            for track in format.tracks() {
+                let codec = track.codec_params.codec;
+                match codec {
+                    CODEC_TYPE_VORBIS => {
+                        info!(target: "stdout", "Codec of input audio: Vorbis");
+                    }
+                    CODEC_TYPE_OPUS => info!(target: "stdout", "Codec of input audio: Opus"),
+                    CODEC_TYPE_FLAC => info!(target: "stdout", "Codec of input audio: FLAC"),
+                    CODEC_TYPE_MP3 => info!(target: "stdout", "Codec of input audio: MP3"),
+                    CODEC_TYPE_AAC => info!(target: "stdout", "Codec of input audio: AAC"),
+                    CODEC_TYPE_ALAC => info!(target: "stdout", "Codec of input audio: ALAC"),
+                    _ => info!(target: "stdout", "Codec of input audio: Other ({:?})", codec),
+                }
+
+                // Print additional codec parameters.
+                if let Some(channels) = track.codec_params.channels {
+                    info!(target: "stdout", "Channels of input audio: {}", channels.count());
+                }
+                if let Some(sample_rate) = track.codec_params.sample_rate {
+                    info!(target: "stdout", "Sample rate of input audio: {} Hz", sample_rate);
+                }
+            }
+        }
+
+        let track = probe::audio_track(&*format, self.track.as_ref())?;
+        let mut track_info = track.codec_params.clone();
+        track_info.channels = probe::channels(&track_info);
+        let channels = info.channels;
+        let original_sample_rate = info.sample_rate;
+
+        self.precheck(&info)?;
+
+        let decoder = self.make_decoder(&track_info)?;
+        let input_track = InputTrack {
+            id: track.id,
+            time_base: track_info.time_base,
+            channels,
+            sample_rate: original_sample_rate,
+            codec: track_info.codec,
+            bits_per_sample: track_info.bits_per_sample,
+            n_frames: track_info
+                .n_frames
+                .map(|n_frames| ts_to_frames(n_frames, track_info.time_base, original_sample_rate)),
+        };
+
+        #[cfg(feature = "logging")]
+        {
+            debug!(target: "stdout", "channels: {}", channels);
+            debug!(target: "stdout", "original_sample_rate: {}", original_sample_rate);
+        }
+
+        Ok(OpenedInput {
+            format,
+            decoder,
+            track: input_track,
+            tags: info.tags,
+            pending_packets,
+        })
+    }
+
+    /// Create a decoder for a track with the codec parameters `params`.
+    fn make_decoder(
+        &self,
+        params: &CodecParameters,
+    ) -> Result<Box<dyn Decoder>, AudioConversionError> {
+        let decoder_opts = DecoderOptions {
+            verify: self.decoder_verify,
+        };
+
+        symphonia::default::get_codecs()
+            .make(params, &decoder_opts)
+            .map_err(|e| match e {
+                SymphoniaError::Unsupported(_) => AudioConversionError::UnsupportedCodec(
+                    codec_name(params.codec)
+                        .map_or_else(|| format!("{}", params.codec), |name| name.to_string()),
+                ),
+                e => AudioConversionError::from(e),
+            })
+    }
+
+    /// Run the precheck callback, if any, on the properties of the input.
+    fn precheck(&self, info: &AudioInfo) -> Result<(), AudioConversionError> {
+        match &self.precheck {
+            Some(Precheck(precheck)) => precheck(info).inspect_err(|_e| {
+                #[cfg(feature = "logging")]
+                info!(target: "stdout", "The input was rejected by the precheck: {}", _e);
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Process decoded interleaved samples into audio at the target sample rate.
+    fn process_decoded(
+        &self,
+        mut all_samples: Vec<f32>,
+        channels: usize,
+        original_sample_rate: u32,
+        report: &mut ConversionReport,
+    ) -> Result<ConvertedAudio, AudioConversionError> {
+        let mut clip_detector = ClipDetector::new(channels);
+        clip_detector.feed(&all_samples);
+        self.record_clipping(clip_detector, report);
+
+        if let Some(polarity) = self.channel_polarity(channels)? {
+            apply_channel_gains(&mut all_samples, &polarity);
+        }
+
+        let mut mono_compatibility = MonoCompatibility::new(channels);
+        mono_compatibility.feed(&all_samples);
+        report.mono_compatibility_db = mono_compatibility.finish();
+
+        report.input_duration_secs =
+            (all_samples.len() / channels) as f64 / original_sample_rate as f64;
+
+        let output_channels = self.output_channels(channels)?;
+        if self.needs_remix(channels, output_channels) {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Remixing {} channels to {}", channels, output_channels);
+
+            all_samples = self.remix(&all_samples, channels, output_channels);
+        }
+        if let Some(channel_gains) = self.channel_gains(output_channels)? {
+            apply_channel_gains(&mut all_samples, &channel_gains);
+        }
+        self.record_layout(report, channels, original_sample_rate, output_channels);
+        let channels = output_channels;
+
+        for mut filter in self.biquad_filters(channels, original_sample_rate)? {
+            filter.process(&mut all_samples);
+        }
+
+        #[cfg(feature = "denoise")]
+        if let Some(options) = &self.denoise {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Removing the noise");
+
+            denoise::denoise(&mut all_samples, channels, options);
+        }
+
+        if let Some(mut gate) = self.noise_gate(channels, original_sample_rate) {
+            gate.process(&mut all_samples);
+        }
+
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "num of samples before trimming: {}", all_samples.len());
+
+        let untrimmed_frames = all_samples.len() / channels;
+
+        let all_samples = match self.trim_trailing_silence {
+            Some(options) => {
+                self.trim_ending_silence(&all_samples, channels, original_sample_rate, options)?
+            }
+            None => all_samples,
+        };
+        let all_samples = match self.trim_leading_silence {
+            Some(options) => {
+                self.trim_leading_silence(&all_samples, channels, original_sample_rate, options)
+            }
+            None => all_samples,
+        };
+        let trimmed_frames = untrimmed_frames - all_samples.len() / channels;
+        report.trimmed_ms = trimmed_frames as u64 * 1000 / original_sample_rate as u64;
+
+        let mut samples = if original_sample_rate == self.target_sample_rate {
+            // No resampling needed
+            all_samples
+        } else {
+            let all_samples = match self.antialias_filter(channels, original_sample_rate) {
+                Some(mut filter) => {
+                    #[cfg(feature = "logging")]
+                    info!(target: "stdout", "Applying the anti-alias filter");
+
+                    let mut filtered = filter.process(&all_samples);
+                    filtered.append(&mut filter.flush());
+                    filtered
+                }
+                None => all_samples,
+            };
+            self.resample(&all_samples, channels, original_sample_rate, report)?
+        };
+
+        if let Some(tempo) = self.tempo.filter(|tempo| *tempo != 1.0) {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Changing the tempo by {}x", tempo);
+
+            samples = tempo::stretch(&samples, channels, self.target_sample_rate, tempo);
+        }
+
+        if let Some(options) = &self.vad {
+            let speech = vad::detect_speech(&samples, channels, self.target_sample_rate, options);
+
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Keeping {} speech segments", speech.len());
+
+            samples = vad::keep_speech(&samples, channels, &speech);
+        }
+
+        if let Some(target) = self.loudness_target {
+            self.normalize_loudness(&mut samples, channels, target, report);
+        }
+
+        if let Some(gain_db) = self.gain_db {
+            self.apply_gain(&mut samples, gain_db, report);
+        }
+
+        if let Some((fade_in, fade_out)) = self.fade {
+            let to_frames = |duration: Duration| {
+                (duration.as_secs_f64() * self.target_sample_rate as f64) as usize
+            };
+            fade::apply_fades(
+                &mut samples,
+                channels,
+                to_frames(fade_in),
+                to_frames(fade_out),
+                self.fade_shape,
+            );
+        }
+
+        if self.soft_clip {
+            samples
+                .iter_mut()
+                .for_each(|sample| *sample = soft_clip(*sample));
+        }
+
+        let padding_frames = self.padding_frames(samples.len() / channels);
+        if padding_frames > 0 {
+            #[cfg(feature = "logging")]
+            debug!(target: "stdout", "padding the output with {} silent frames", padding_frames);
+
+            samples.resize(samples.len() + padding_frames * channels, 0.0);
+        }
+
+        report.output_duration_secs =
+            (samples.len() / channels) as f64 / self.target_sample_rate as f64;
+        report.samples_written = samples.len() as u64;
+        report.output_peak_dbfs = peak_dbfs(
+            samples
+                .iter()
+                .fold(0.0_f32, |peak, sample| peak.max(sample.abs())),
+        );
+
+        Ok(ConvertedAudio {
+            samples,
+            channels,
+            tags: Tags::default(),
+        })
+    }
+
+    /// The high-pass and low-pass filters set by `with_highpass` and `with_lowpass`, for audio
+    /// with `channels` channels at `sample_rate`.
+    fn biquad_filters(
+        &self,
+        channels: usize,
+        sample_rate: u32,
+    ) -> Result<Vec<Biquad>, AudioConversionError> {
+        let mut filters = Vec::new();
+        for (cutoff_hz, create) in [
+            (
+                self.highpass_hz,
+                Biquad::highpass as fn(usize, f64, u32) -> Biquad,
+            ),
+            (self.lowpass_hz, Biquad::lowpass),
+        ] {
+            let Some(cutoff_hz) = cutoff_hz else {
+                continue;
+            };
+            if !(cutoff_hz > 0.0 && cutoff_hz < sample_rate as f32 / 2.0) {
+                let err_msg = format!(
+                    "The filter cutoff of {} Hz must be between 0 Hz and the Nyquist frequency of {} Hz",
+                    cutoff_hz,
+                    sample_rate / 2
+                );
+
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+            filters.push(create(channels, cutoff_hz as f64, sample_rate));
+        }
+
+        Ok(filters)
+    }
+
+    /// Whether the denoiser is enabled, which is never the case without the `denoise` feature.
+    fn denoise_enabled(&self) -> bool {
+        #[cfg(feature = "denoise")]
+        let enabled = self.denoise.is_some();
+        #[cfg(not(feature = "denoise"))]
+        let enabled = false;
+        enabled
+    }
+
+    /// The noise gate set by `with_noise_gate`, for audio with `channels` channels at
+    /// `sample_rate`.
+    fn noise_gate(&self, channels: usize, sample_rate: u32) -> Option<NoiseGate> {
+        self.noise_gate.map(|(threshold_db, attack, release)| {
+            NoiseGate::new(
+                channels,
+                sample_rate,
+                threshold_db,
+                attack.as_secs_f32(),
+                release.as_secs_f32(),
+            )
+        })
+    }
+
+    /// The anti-alias filter to apply before downsampling from `original_sample_rate`, or `None`
+    /// if it is disabled or not needed.
+    fn antialias_filter(&self, channels: usize, original_sample_rate: u32) -> Option<FirLowpass> {
+        (self.antialias_filter && original_sample_rate > self.target_sample_rate).then(|| {
+            FirLowpass::anti_alias(channels, original_sample_rate, self.target_sample_rate)
+        })
+    }
+
+    /// The samples soft-clipped if soft clipping is enabled, otherwise the samples as is.
+    fn soft_clipped<'a>(&self, samples: &'a [f32]) -> Cow<'a, [f32]> {
+        if self.soft_clip {
+            Cow::Owned(samples.iter().map(|sample| soft_clip(*sample)).collect())
+        } else {
+            Cow::Borrowed(samples)
+        }
+    }
+
+    /// The number of silent frames to append to output of `frames` frames to reach the padded
+    /// duration.
+    fn padding_frames(&self, frames: usize) -> usize {
+        self.pad_to_secs.map_or(0, |secs| {
+            let target_frames = (secs * self.target_sample_rate as f32).round() as usize;
+            target_frames.saturating_sub(frames)
+        })
+    }
+
+    /// The number of input frames between the start and end offsets, if the input declares its
+    /// length.
+    fn selected_frames(&self, track: &InputTrack) -> Option<u64> {
+        let mut frames = track.n_frames?;
+        if let Some(offset) = self.end_offset {
+            frames = frames.min(self.offset_to_frames(offset, track.sample_rate));
+        }
+        if let Some(offset) = self.start_offset {
+            frames = frames.saturating_sub(self.offset_to_frames(offset, track.sample_rate));
+        }
+
+        Some(frames)
+    }
+
+    /// The number of samples a streaming conversion of `track` writes, if the input declares its
+    /// length.
+    fn projected_samples(&self, track: &InputTrack, output_channels: usize) -> Option<u64> {
+        let frames = self.selected_frames(track)?;
+        let frames =
+            streaming::output_frames(frames as usize, track.sample_rate, self.target_sample_rate);
+        Some((frames + self.padding_frames(frames)) as u64 * output_channels as u64)
+    }
+
+    /// Whether converting the track in memory would hold more bytes of samples than the memory
+    /// cap, counting the decoded samples and the converted ones. Tracks of unknown length never
+    /// exceed it here.
+    fn exceeds_max_memory(&self, track: &InputTrack) -> Result<bool, AudioConversionError> {
+        let Some(max_memory) = self.max_memory else {
+            return Ok(false);
+        };
+        let (Some(frames), Some(converted)) = (
+            self.selected_frames(track),
+            self.projected_samples(track, self.output_channels(track.channels)?),
+        ) else {
+            return Ok(false);
+        };
+
+        let samples = frames * track.channels as u64 + converted;
+        Ok(samples.saturating_mul(mem::size_of::<f32>() as u64) > max_memory as u64)
+    }
+
+    /// The 64-bit WAV variant to write WAV output of `samples` samples as, if it is forced by
+    /// `with_large_file` or the output would not fit in a RIFF WAV file.
+    fn large_file_format(&self, spec: WavSpec, samples: Option<u64>) -> Option<LargeFileFormat> {
+        if self.output_format != OutputFormat::Wav || self.encoding != PcmEncoding::Linear {
+            return None;
+        }
+        if self.large_file.is_some() {
+            return self.large_file;
+        }
+
+        let data_len = samples? * (spec.bits_per_sample / 8) as u64;
+        (data_len > wav64::MAX_RIFF_DATA_LEN).then_some(LargeFileFormat::Rf64)
+    }
+
+    /// Record the sample rates and channel counts of the input and output in `report`.
+    fn record_layout(
+        &self,
+        report: &mut ConversionReport,
+        input_channels: usize,
+        input_sample_rate: u32,
+        output_channels: usize,
+    ) {
+        report.input_sample_rate = input_sample_rate;
+        report.output_sample_rate = self.target_sample_rate;
+        report.input_channels = input_channels;
+        report.output_channels = output_channels;
+    }
+
+    /// Record the clipping found by `clip_detector` in the report.
+    fn record_clipping(&self, clip_detector: ClipDetector, report: &mut ConversionReport) {
+        (report.clipped_sample_count, report.clip_runs) = clip_detector.finish();
+
+        #[cfg(feature = "logging")]
+        if report.clip_runs > 0 {
+            warn!(
+                target: "stdout",
+                "The input is clipped: {} samples in {} runs at full scale",
+                report.clipped_sample_count, report.clip_runs
+            );
+        }
+    }
+
+    /// The number of output channels for input with `input_channels` channels.
+    fn output_channels(&self, input_channels: usize) -> Result<usize, AudioConversionError> {
+        if let Some(matrix) = &self.channel_matrix {
+            if matrix.is_empty() || matrix.iter().any(|row| row.len() != input_channels) {
+                let err_msg = format!(
+                    "Expected a channel matrix with one gain per input channel ({}) in each row",
+                    input_channels
+                );
+
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+
+            return Ok(matrix.len());
+        }
+
+        let output_channels = match (self.target_channels, self.downmix) {
+            (Some(channels), _) => channels,
+            (None, Some(_)) => 1,
+            (None, None) => return Ok(input_channels),
+        };
+
+        if output_channels == 0 || (output_channels > input_channels && input_channels > 1) {
+            let err_msg = format!(
+                "Cannot remix {} channels to {} channels",
+                input_channels, output_channels
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        Ok(output_channels)
+    }
+
+    /// Whether the audio goes through `remix`, which a channel matrix requires even when it keeps
+    /// the number of channels.
+    fn needs_remix(&self, input_channels: usize, output_channels: usize) -> bool {
+        output_channels != input_channels || self.channel_matrix.is_some()
+    }
+
+    /// Remix the interleaved `samples` from `input_channels` to `output_channels` channels.
+    fn remix(&self, samples: &[f32], input_channels: usize, output_channels: usize) -> Vec<f32> {
+        if let Some(matrix) = &self.channel_matrix {
+            return downmix::apply_matrix(samples, input_channels, matrix);
+        }
+
+        downmix::remix(
+            samples,
+            input_channels,
+            output_channels,
+            self.downmix.unwrap_or_default(),
+        )
+    }
+
+    /// The linear per-channel gains, checked against the number of output channels.
+    fn channel_gains(
+        &self,
+        output_channels: usize,
+    ) -> Result<Option<Vec<f32>>, AudioConversionError> {
+        let Some(gains_db) = &self.channel_gains_db else {
+            return Ok(None);
+        };
+        if gains_db.len() != output_channels {
+            let err_msg = format!(
+                "Expected {} channel gains, got {}",
+                output_channels,
+                gains_db.len()
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        Ok(Some(
+            gains_db
+                .iter()
+                .map(|gain_db| 10f32.powf(gain_db / 20.0))
+                .collect(),
+        ))
+    }
+
+    /// The sign of each input channel, `-1.0` for the channels whose polarity is inverted, checked
+    /// against the number of channels.
+    fn channel_polarity(&self, channels: usize) -> Result<Option<Vec<f32>>, AudioConversionError> {
+        let invert_channels = self.invert_channels.as_deref();
+        if let Some(invert_channels) = invert_channels.filter(|invert| invert.len() != channels) {
+            let err_msg = format!(
+                "Expected {} channel polarity flags, got {}",
+                channels,
+                invert_channels.len()
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        if !self.invert_polarity && invert_channels.is_none() {
+            return Ok(None);
+        }
+
+        let signs = (0..channels)
+            .map(|ch| {
+                let inverted =
+                    self.invert_polarity || invert_channels.is_some_and(|invert| invert[ch]);
+                if inverted {
+                    -1.0
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+
+        Ok(Some(signs))
+    }
+
+    /// Resample interleaved samples from `original_sample_rate` to the target sample rate.
+    fn resample(
+        &self,
+        all_samples: &[f32],
+        channels: usize,
+        original_sample_rate: u32,
+        report: &mut ConversionReport,
+    ) -> Result<Vec<f32>, AudioConversionError> {
+        #[cfg(feature = "logging")]
+        info!(
+            target: "stdout",
+            "Resampling from {}Hz to {}Hz",
+            original_sample_rate, self.target_sample_rate
+        );
+
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "num of samples after trimming: {}", all_samples.len());
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Resampling audio");
+
+        // Prepare samples for resampler (separate channels)
+        let input_channels = simd::deinterleave(all_samples, channels);
+
+        let output_frames = streaming::output_frames(
+            input_channels[0].len(),
+            original_sample_rate,
+            self.target_sample_rate,
+        );
+
+        report.resampler = Some(self.resampler_name(original_sample_rate));
+        #[cfg(feature = "parallel")]
+        let parallel_output =
+            self.resample_parallel(&input_channels, original_sample_rate, output_frames)?;
+        #[cfg(not(feature = "parallel"))]
+        let parallel_output = None;
+
+        let output_buffer = match parallel_output {
+            Some(output_buffer) => output_buffer,
+            None => {
+                let mut resampler = self.take_resampler(channels, original_sample_rate)?;
+                let output_buffer =
+                    self.run_resampler(resampler.as_mut(), &input_channels, output_frames)?;
+                self.release_resampler(channels, original_sample_rate, resampler);
+                output_buffer
+            }
+        };
+
+        // Interleave the resampled channels
+        Ok(simd::interleave(&output_buffer))
+    }
+
+    /// The resampler used from `original_sample_rate`. Non-integer ratios use the sinc resampler
+    /// instead of the FFT one if the rational resampler is enabled.
+    fn resampler_kind(&self, original_sample_rate: u32) -> ResamplerKind {
+        let is_integer_ratio = self.target_sample_rate.is_multiple_of(original_sample_rate)
+            || original_sample_rate.is_multiple_of(self.target_sample_rate);
+        if self.resampler == ResamplerKind::Fft && self.rational_resampler && !is_integer_ratio {
+            ResamplerKind::RATIONAL
+        } else {
+            self.resampler
+        }
+    }
+
+    /// The name of the resampler used from `original_sample_rate`, as recorded in the report.
+    fn resampler_name(&self, original_sample_rate: u32) -> &'static str {
+        self.resampler_kind(original_sample_rate).name()
+    }
+
+    /// Create the streaming resampling stage from `original_sample_rate` to the target sample
+    /// rate.
+    fn streaming_stage(
+        &self,
+        channels: usize,
+        original_sample_rate: u32,
+    ) -> Result<StreamingStage, AudioConversionError> {
+        let resampler = if original_sample_rate == self.target_sample_rate {
+            None
+        } else {
+            let resampler = self.take_resampler(channels, original_sample_rate)?;
+            Some(StreamingResampler::new(
+                resampler,
+                channels,
+                original_sample_rate,
+                self.target_sample_rate,
+                self.compensate_delay,
+            ))
+        };
+
+        Ok(StreamingStage::new(
+            channels,
+            self.antialias_filter(channels, original_sample_rate),
+            resampler,
+        ))
+    }
+
+    /// Take an idle resampler from `original_sample_rate` to the target sample rate from the pool
+    /// of the `ConverterEngine` running the conversion, or create one.
+    fn take_resampler(
+        &self,
+        channels: usize,
+        original_sample_rate: u32,
+    ) -> Result<Box<dyn ReusableResampler>, AudioConversionError> {
+        let key = (original_sample_rate, self.target_sample_rate, channels);
+        match self.resampler_pool.as_ref().and_then(|pool| pool.take(key)) {
+            Some(resampler) => Ok(resampler),
+            None => self.create_resampler(channels, original_sample_rate),
+        }
+    }
+
+    /// Hand the resampler of a finished conversion back to the pool of the `ConverterEngine`
+    /// running it, if any.
+    fn release_resampler(
+        &self,
+        channels: usize,
+        original_sample_rate: u32,
+        resampler: Box<dyn ReusableResampler>,
+    ) {
+        if let Some(pool) = &self.resampler_pool {
+            pool.put(
+                (original_sample_rate, self.target_sample_rate, channels),
+                resampler,
+            );
+        }
+    }
+
+    /// Create the resampler from `original_sample_rate` to the target sample rate.
+    fn create_resampler(
+        &self,
+        channels: usize,
+        original_sample_rate: u32,
+    ) -> Result<Box<dyn ReusableResampler>, AudioConversionError> {
+        self.resampler_kind(original_sample_rate).create(
+            channels,
+            original_sample_rate,
+            self.target_sample_rate,
+            self.resampler_chunk_size,
+        )
+    }
+
+    /// Feed the separated input channels through `resampler` chunk by chunk and return
+    /// `output_frames` frames per channel.
+    fn run_resampler(
+        &self,
+        resampler: &mut dyn VecResampler<f32>,
+        input_channels: &[Vec<f32>],
+        output_frames: usize,
+    ) -> Result<Vec<Vec<f32>>, AudioConversionError> {
+        let channels = input_channels.len();
+        let mut output_buffer = vec![Vec::new(); channels];
+
+        // The resampler delays its output by a fixed number of frames. When compensating, those
+        // leading frames are discarded and the resampler is flushed to recover the tail.
+        let delay = if self.compensate_delay {
+            resampler.output_delay()
+        } else {
+            0
+        };
+        let input_frames = input_channels[0].len();
+
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "resampler delay: {} frames", delay);
+
+        // Process the audio in chunks
+        let mut pos = 0;
+        while output_buffer[0].len() < delay + output_frames {
+            self.check_cancelled()?;
+
+            let chunk_size = resampler.input_frames_next();
+            let end = (pos + chunk_size).min(input_frames);
+            let mut resampled_chunk = if pos >= input_frames {
+                // Flush the delayed output once the input is exhausted
+                resampler.process_partial(None, None)?
+            } else {
+                let chunk: Vec<Vec<f32>> = input_channels
+                    .iter()
+                    .map(|channel| channel[pos..end].to_vec())
+                    .collect();
+                if end - pos == chunk_size {
+                    resampler.process(&chunk, None)?
+                } else {
+                    resampler.process_partial(Some(&chunk), None)?
+                }
+            };
+            for ch in 0..channels {
+                output_buffer[ch].append(&mut resampled_chunk[ch]);
+            }
+            pos = end;
+        }
+
+        for channel in output_buffer.iter_mut() {
+            channel.drain(..delay);
+            channel.truncate(output_frames);
+        }
+
+        Ok(output_buffer)
+    }
+
+    /// Apply the gain that brings the audio to the loudness target.
+    fn normalize_loudness(
+        &self,
+        samples: &mut [f32],
+        channels: usize,
+        target: LoudnessTarget,
+        report: &mut ConversionReport,
+    ) {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Normalizing loudness");
+
+        let Some(loudness) =
+            loudness::integrated_loudness(samples, channels, self.target_sample_rate)
+        else {
+            #[cfg(feature = "logging")]
+            warn!(target: "stdout", "The audio is too short or too quiet to measure its loudness, skipping normalization");
+
+            return;
+        };
+        let true_peak = loudness::true_peak_dbtp(samples, channels);
+        let gain_db = loudness::normalization_gain_db(target, loudness, true_peak);
+
+        #[cfg(feature = "logging")]
+        debug!(
+            target: "stdout",
+            "loudness: {:.2} LUFS, true peak: {:.2} dBTP, gain: {:.2} dB",
+            loudness, true_peak, gain_db
+        );
+
+        let gain = 10f32.powf(gain_db as f32 / 20.0);
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+
+        report.measured_loudness_lufs = Some(loudness);
+        report.applied_gain_db = Some(gain_db);
+        report.output_loudness_lufs = Some(loudness + gain_db);
+        report.output_true_peak_dbtp = Some(true_peak + gain_db);
+    }
+
+    /// Apply the fixed gain to the samples, reduced to keep the peaks below full scale unless soft
+    /// clipping is enabled.
+    fn apply_gain(&self, samples: &mut [f32], gain_db: f32, report: &mut ConversionReport) {
+        let peak = samples
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        let mut gain = 10f32.powf(gain_db / 20.0);
+        if !self.soft_clip && peak * gain > 1.0 {
+            gain = 1.0 / peak;
+
+            #[cfg(feature = "logging")]
+            warn!(
+                target: "stdout",
+                "Reducing the gain from {:.2} dB to {:.2} dB to avoid clipping",
+                gain_db,
+                20.0 * gain.log10()
+            );
+        }
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Applying a gain of {:.2} dB", 20.0 * gain.log10());
+
+        samples.iter_mut().for_each(|sample| *sample *= gain);
+        report.fixed_gain_db = Some(20.0 * gain.log10() as f64);
+    }
+
+    /// The linear fixed gain of streaming conversions, applied in full as the peaks are not known
+    /// in advance.
+    fn streaming_gain(&self, report: &mut ConversionReport) -> Option<f32> {
+        report.fixed_gain_db = self.gain_db.map(f64::from);
+        self.gain_db.map(|gain_db| 10f32.powf(gain_db / 20.0))
+    }
+
+    /// Write the converted audio to the output path, or to one file per chunk if chunking is
+    /// enabled, and return the path, start and end of every file written.
+    fn write_converted(
+        &self,
+        converted: &ConvertedAudio,
+    ) -> Result<Vec<Segment>, AudioConversionError> {
+        let frames = converted.samples.len() / converted.channels;
+        let Some((duration, overlap)) = self.chunking else {
+            self.write_wav(converted)?;
+            let end = Duration::from_secs_f64(frames as f64 / self.target_sample_rate as f64);
+            return Ok(vec![Segment {
+                path: self.output_path.clone(),
+                start: Duration::ZERO,
+                end,
+            }]);
+        };
+
+        let chunk_frames = (duration.as_secs_f64() * self.target_sample_rate as f64) as usize;
+        let overlap_frames = (overlap.as_secs_f64() * self.target_sample_rate as f64) as usize;
+        if chunk_frames == 0 || overlap_frames >= chunk_frames {
+            let err_msg = format!("Chunks of {:?} cannot overlap by {:?}", duration, overlap);
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        let ranges = segment::chunk_ranges(frames, chunk_frames, chunk_frames - overlap_frames);
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Writing {} chunks of {:?}", ranges.len(), duration);
+
+        self.write_parts(converted, ranges, segment::chunk_output_path)
+    }
+
+    /// Write each range of frames of the converted audio to its own file, at the path given by
+    /// `output_path` for the output path and the index of the range.
+    fn write_parts(
+        &self,
+        converted: &ConvertedAudio,
+        ranges: Vec<std::ops::Range<usize>>,
+        output_path: fn(&Path, usize) -> PathBuf,
+    ) -> Result<Vec<Segment>, AudioConversionError> {
+        let channels = converted.channels;
+        let offset =
+            |frame: usize| Duration::from_secs_f64(frame as f64 / self.target_sample_rate as f64);
+        let mut parts = Vec::with_capacity(ranges.len());
+        for (index, range) in ranges.into_iter().enumerate() {
+            let mut converter = self.clone();
+            converter.output_path = output_path(&self.output_path, index);
+            converter.write_wav(&ConvertedAudio {
+                samples: converted.samples[range.start * channels..range.end * channels].to_vec(),
+                channels,
+                tags: converted.tags.clone(),
+            })?;
+
+            parts.push(Segment {
+                path: converter.output_path,
+                start: offset(range.start),
+                end: offset(range.end),
+            });
+        }
+
+        Ok(parts)
+    }
+
+    /// Write the converted audio to the output WAV file.
+    fn write_wav(&self, converted: &ConvertedAudio) -> Result<(), AudioConversionError> {
+        self.write_atomically(|converter| converter.write_output_file(converted))
+    }
+
+    /// Write the converted audio to the output path, with the tags, cue markers and BWF metadata.
+    fn write_output_file(&self, converted: &ConvertedAudio) -> Result<(), AudioConversionError> {
+        // Set up WAV writer
+        let spec = self.output_spec(converted.channels as u16)?;
+        let appending = self.append && self.output_path.exists();
+        let samples = Some(converted.samples.len() as u64);
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "generated wav spec: {:?}", spec);
+
+        // Create WAV writer
+        let (mut wav_writer, trailing_chunks) =
+            self.create_output_writer(spec, samples, &converted.tags)?;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Writing {} audio samples to WAV file: {}", converted.samples.len(), self.output_path.display());
+
+        wav_writer.write(&converted.samples)?;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Finalizing WAV file");
+
+        let written_samples = wav_writer.len();
+        wav_writer.finalize()?;
+        trailing_chunks.restore(&self.output_path)?;
+
+        if self.silence_cue_markers {
+            if self.output_format != OutputFormat::Wav {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "Cue markers are only written to WAV output");
+            } else if self.large_file_format(spec, samples).is_some() {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "Cue markers are not written to RF64 or Wave64 output");
+            } else if appending {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "Cue markers are not written when appending to an existing WAV file");
+            } else {
+                let positions = self.silence_cue_positions(converted);
+
+                #[cfg(feature = "logging")]
+                info!(target: "stdout", "Writing {} cue markers", positions.len());
+
+                cue::append_cue_chunk(&self.output_path, &positions)?;
+            }
+        }
+        self.append_wav_chunks(spec, samples, appending, &converted.tags)?;
+
+        if self.verify_output {
+            self.verify_written(spec, written_samples)?;
+        }
+
+        Ok(())
+    }
+
+    /// Append the `LIST` chunk of the copied tags and the `bext` chunk set by `with_bwf_metadata`
+    /// to the finalized output file of `samples` samples.
+    fn append_wav_chunks(
+        &self,
+        spec: WavSpec,
+        samples: Option<u64>,
+        appending: bool,
+        tags: &Tags,
+    ) -> Result<(), AudioConversionError> {
+        let tags = Some(tags).filter(|tags| self.copy_metadata && !tags.is_empty());
+        if tags.is_none() && self.bwf_metadata.is_none() {
+            return Ok(());
+        }
+
+        if self.output_format != OutputFormat::Wav
+            || self.large_file_format(spec, samples).is_some()
+        {
+            // FLAC, Ogg Opus and MP3 output store the tags in their own headers
+            #[cfg(feature = "logging")]
+            if tags.is_some()
+                && matches!(
+                    self.output_format,
+                    OutputFormat::Wav
+                        | OutputFormat::RawPcm { .. }
+                        | OutputFormat::WavAdpcm { .. }
+                        | OutputFormat::Aiff
+                )
+            {
+                warn!(target: "stdout", "Tags are only copied into RIFF WAV, FLAC, Ogg Opus and MP3 output");
+            }
+            #[cfg(feature = "logging")]
+            if self.bwf_metadata.is_some() {
+                warn!(target: "stdout", "BWF metadata is only written to RIFF WAV output");
+            }
+            return Ok(());
+        }
+        if appending {
+            #[cfg(feature = "logging")]
+            warn!(target: "stdout", "Tags and BWF metadata are not written when appending to an existing WAV file");
+
+            return Ok(());
+        }
+
+        if let Some(tags) = tags {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Copying the tags of the input: {:?}", tags);
+
+            tags::append_info_chunk(&self.output_path, tags)?;
+        }
+        if let Some(bext) = &self.bwf_metadata {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Writing the BWF bext chunk");
+
+            bext::append_bext_chunk(&self.output_path, bext)?;
+        }
+
+        Ok(())
+    }
+
+    /// The frames at which a region of sound starts after the beginning of the audio or a silent
+    /// gap of at least `MIN_CUE_SILENCE_SECS`.
+    fn silence_cue_positions(&self, converted: &ConvertedAudio) -> Vec<u32> {
+        let channels = converted.channels;
+        let analysed_samples =
+            self.silence_analysis_samples(&converted.samples, channels, self.target_sample_rate);
+
+        let levels = trim::rms_envelope(&analysed_samples, channels, self.target_sample_rate);
+
+        let threshold = self.trim_trailing_silence.unwrap_or_default().threshold();
+        let min_silent_frames = (MIN_CUE_SILENCE_SECS * self.target_sample_rate as f32) as usize;
+        let mut positions = Vec::new();
+        let mut in_sound = false;
+        let mut silent_frames = 0;
+        for (frame, level) in levels.into_iter().enumerate() {
+            if level >= threshold {
+                if !in_sound {
+                    positions.push(frame as u32);
+                    in_sound = true;
+                }
+                silent_frames = 0;
+            } else if in_sound {
+                silent_frames += 1;
+                if silent_frames >= min_silent_frames {
+                    in_sound = false;
+                }
+            }
+        }
+
+        positions
+    }
+
+    /// The interleaved `samples` whose level is compared to the silence threshold, weighted as set
+    /// by `with_silence_weighting`.
+    fn silence_analysis_samples<'a>(
+        &self,
+        samples: &'a [f32],
+        channels: usize,
+        sample_rate: u32,
+    ) -> Cow<'a, [f32]> {
+        match self.silence_weighting {
+            Weighting::None => Cow::Borrowed(samples),
+            Weighting::AWeighting => {
+                Cow::Owned(weighting::a_weighted(samples, channels, sample_rate))
+            }
+        }
+    }
+
+    /// Check that the output file has the expected spec and number of samples.
+    fn verify_written(&self, spec: WavSpec, samples: u64) -> Result<(), AudioConversionError> {
+        let format = match self.output_format {
+            OutputFormat::Wav if self.encoding != PcmEncoding::Linear => {
+                return self.verify_decodable(spec, samples, "G.711 WAV")
+            }
+            OutputFormat::Wav => return self.verify_wav(spec, samples),
+            OutputFormat::RawPcm { format } => format,
+            OutputFormat::WavAdpcm { block_align } => {
+                // symphonia ignores the fact chunk and also decodes the padding of the last block
+                let block_samples = adpcm::frames_per_block(block_align, spec.channels) as u64
+                    * spec.channels as u64;
+                let padded_samples = samples.div_ceil(block_samples) * block_samples;
+                return self.verify_decodable(spec, padded_samples, "IMA ADPCM WAV");
+            }
+            OutputFormat::Aiff => return self.verify_aiff(spec, samples),
+            OutputFormat::Flac { .. } => return self.verify_decodable(spec, samples, "FLAC"),
+            #[cfg(feature = "opus-encoder")]
+            OutputFormat::OggOpus { .. } => {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "Ogg Opus output is not verified");
+
+                return Ok(());
+            }
+            #[cfg(feature = "mp3-encoder")]
+            OutputFormat::Mp3 { .. } => {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "MP3 output is not verified");
+
+                return Ok(());
+            }
+        };
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Verifying raw PCM file: {}", self.output_path.display());
+
+        let expected = samples * format.bytes_per_sample() as u64;
+        let len = std::fs::metadata(&self.output_path)
+            .map_err(|e| {
+                AudioConversionError::VerificationFailed(format!(
+                    "cannot read back {}: {}",
+                    self.output_path.display(),
+                    e
+                ))
+            })?
+            .len();
+        if len == expected {
+            return Ok(());
+        }
+
+        let err_msg = format!(
+            "{} contains {} bytes, expected {}",
+            self.output_path.display(),
+            len,
+            expected
+        );
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::VerificationFailed(err_msg))
+    }
+
+    /// Check that the header of the output AIFF file declares the expected spec and number of
+    /// samples.
+    fn verify_aiff(&self, spec: WavSpec, samples: u64) -> Result<(), AudioConversionError> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Verifying AIFF file: {}", self.output_path.display());
+
+        let (channels, frames, bits_per_sample, sample_rate) = File::open(&self.output_path)
+            .and_then(aiff::read_comm)
+            .map_err(|e| {
+                AudioConversionError::VerificationFailed(format!(
+                    "cannot read back {}: {}",
+                    self.output_path.display(),
+                    e
+                ))
+            })?;
+
+        let err_msg = if (channels, sample_rate, bits_per_sample)
+            != (spec.channels, spec.sample_rate, spec.bits_per_sample)
+        {
+            format!(
+                "{} has {} channels at {} Hz with {} bits per sample, expected {:?}",
+                self.output_path.display(),
+                channels,
+                sample_rate,
+                bits_per_sample,
+                spec
+            )
+        } else if frames as u64 * channels as u64 != samples {
+            format!(
+                "{} contains {} samples, expected {}",
+                self.output_path.display(),
+                frames as u64 * channels as u64,
+                samples
+            )
+        } else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::VerificationFailed(err_msg))
+    }
+
+    /// Check that the output file, which hound cannot read, decodes to the expected layout and
+    /// number of samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - The expected spec.
+    /// * `samples` - The expected number of samples.
+    /// * `kind` - The name of the output format, for the log.
+    fn verify_decodable(
+        &self,
+        spec: WavSpec,
+        samples: u64,
+        _kind: &str,
+    ) -> Result<(), AudioConversionError> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Verifying {} file: {}", _kind, self.output_path.display());
+
+        let cannot_read = |e: &dyn std::fmt::Display| {
+            AudioConversionError::VerificationFailed(format!(
+                "cannot read back {}: {}",
+                self.output_path.display(),
+                e
+            ))
+        };
+        let file = File::open(&self.output_path).map_err(|e| cannot_read(&e))?;
+        let media_source = MediaSourceStream::new(Box::new(file), Default::default());
+        let probed = probe::probe(
+            media_source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+            None,
+        )
+        .map_err(|e| cannot_read(&e))?;
+        let params = probed
+            .format
+            .default_track()
+            .map(|track| track.codec_params.clone())
+            .ok_or_else(|| cannot_read(&"no audio track"))?;
+
+        let layout = (
+            params.channels.map_or(0, |channels| channels.count()),
+            params.sample_rate.unwrap_or_default(),
+            // G.711 tracks do not declare their sample width
+            params
+                .bits_per_sample
+                .unwrap_or(spec.bits_per_sample as u32),
+        );
+        let expected_layout = (
+            spec.channels as usize,
+            spec.sample_rate,
+            spec.bits_per_sample as u32,
+        );
+        let frames = params.n_frames.unwrap_or_default();
+        let err_msg = if layout != expected_layout {
+            format!(
+                "{} has {} channels at {} Hz with {} bits per sample, expected {:?}",
+                self.output_path.display(),
+                layout.0,
+                layout.1,
+                layout.2,
+                spec
+            )
+        } else if frames * spec.channels as u64 != samples {
+            format!(
+                "{} contains {} samples, expected {}",
+                self.output_path.display(),
+                frames * spec.channels as u64,
+                samples
+            )
+        } else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::VerificationFailed(err_msg))
+    }
+
+    /// Check that the output WAV file has the expected spec and number of samples.
+    fn verify_wav(&self, spec: WavSpec, samples: u64) -> Result<(), AudioConversionError> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Verifying WAV file: {}", self.output_path.display());
+
+        let cannot_read = |e: &dyn std::fmt::Display| {
+            AudioConversionError::VerificationFailed(format!(
+                "cannot read back {}: {}",
+                self.output_path.display(),
+                e
+            ))
+        };
+        // hound cannot read RF64 and Wave64 files, so their header is parsed here
+        let header = File::open(&self.output_path)
+            .and_then(wav64::read_header)
+            .map_err(|e| cannot_read(&e))?;
+        let (written_spec, written_samples) = match header {
+            Some((_, written_spec, written_samples)) => (written_spec, written_samples),
+            None => {
+                let reader = WavReader::open(&self.output_path).map_err(|e| cannot_read(&e))?;
+                (reader.spec(), reader.len() as u64)
+            }
+        };
+
+        let err_msg = if written_spec != spec {
+            format!(
+                "{} has spec {:?}, expected {:?}",
+                self.output_path.display(),
+                written_spec,
+                spec
+            )
+        } else if written_samples != samples {
+            format!(
+                "{} contains {} samples, expected {}",
+                self.output_path.display(),
+                written_samples,
+                samples
+            )
+        } else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::VerificationFailed(err_msg))
+    }
+
+    /// The spec of the output WAV file for audio with the given number of channels.
+    fn output_spec(&self, channels: u16) -> Result<WavSpec, AudioConversionError> {
+        if self.encoding != PcmEncoding::Linear {
+            let err_msg = if self.output_format != OutputFormat::Wav {
+                format!("{:?} encoding needs WAV output", self.encoding)
+            } else if self.large_file.is_some() {
+                format!(
+                    "{:?} encoding cannot be written to RF64 or Wave64 files",
+                    self.encoding
+                )
+            } else {
+                // Companded samples are always 8 bits wide
+                return Ok(WavSpec {
+                    channels,
+                    sample_rate: self.target_sample_rate,
+                    bits_per_sample: 8,
+                    sample_format: hound::SampleFormat::Int,
+                });
+            };
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        if let OutputFormat::WavAdpcm { block_align } = self.output_format {
+            if !adpcm::is_valid_block_align(block_align, channels) {
+                let err_msg = format!(
+                    "A block align of {} bytes does not hold whole IMA ADPCM blocks of {} channels",
+                    block_align, channels
+                );
+
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+
+            // IMA ADPCM codes are 4 bits wide
+            return Ok(WavSpec {
+                channels,
+                sample_rate: self.target_sample_rate,
+                bits_per_sample: 4,
+                sample_format: hound::SampleFormat::Int,
+            });
+        }
+
+        let supported = match (self.output_format, self.sample_format) {
+            (OutputFormat::Flac { .. }, SampleFormat::Int) => {
+                matches!(self.bits_per_sample, 8 | 16 | 24)
+            }
+            (OutputFormat::Flac { .. } | OutputFormat::Aiff, SampleFormat::Float) => false,
+            (_, SampleFormat::Int) => matches!(self.bits_per_sample, 8 | 16 | 24 | 32),
+            (_, SampleFormat::Float) => self.bits_per_sample == 32,
+        };
+        if !supported {
+            let err_msg = format!(
+                "{}-bit {:?} samples are not supported",
+                self.bits_per_sample, self.sample_format
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
+
+        #[cfg(feature = "opus-encoder")]
+        if let OutputFormat::OggOpus { .. } = self.output_format {
+            if !opus::is_supported(channels as usize, self.target_sample_rate) {
+                let err_msg = format!(
+                    "Opus cannot encode {} channels at {} Hz",
+                    channels, self.target_sample_rate
+                );
+
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+        }
+
+        #[cfg(feature = "mp3-encoder")]
+        if let OutputFormat::Mp3 { bitrate_kbps, .. } = self.output_format {
+            let err_msg = if !mp3::is_supported(channels as usize, self.target_sample_rate) {
+                Some(format!(
+                    "MP3 cannot encode {} channels at {} Hz",
+                    channels, self.target_sample_rate
+                ))
+            } else if mp3::bitrate(bitrate_kbps).is_none() {
+                Some(format!("{} kbps is not an MP3 bitrate", bitrate_kbps))
+            } else {
+                None
+            };
+            if let Some(err_msg) = err_msg {
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
+            }
+        }
+
+        Ok(WavSpec {
+            channels,
+            sample_rate: self.target_sample_rate,
+            bits_per_sample: self.bits_per_sample,
+            sample_format: self.sample_format.into(),
+        })
+    }
+
+    /// Copy the WAV file held in `bytes` unchanged if passthrough is enabled and it already has
+    /// the target format.
+    fn passthrough_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Option<ConversionReport>, AudioConversionError> {
+        if !self.passthrough_identical || self.raw_input.is_some() {
+            return Ok(None);
+        }
+
+        match WavReader::new(std::io::Cursor::new(bytes)) {
+            Ok(reader) => self.passthrough(reader),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Copy the PCM data of a WAV input whose spec already matches the output spec, without
+    /// decoding and re-quantizing it.
+    ///
+    /// Returns `None` if the input spec differs from the output spec or the conversion is
+    /// configured to alter the samples.
+    fn passthrough<R: Read>(
+        &self,
+        mut reader: WavReader<R>,
+    ) -> Result<Option<ConversionReport>, AudioConversionError> {
+        let spec = reader.spec();
+        if spec != self.output_spec(spec.channels)?
+            || self.start_offset.is_some()
+            || self.end_offset.is_some()
+            || self.chunking.is_some()
+            || self.vad.is_some()
+            || self.gain_db.is_some()
+            || self.highpass_hz.is_some()
+            || self.lowpass_hz.is_some()
+            || self.noise_gate.is_some()
+            || self.denoise_enabled()
+            || self.fade.is_some()
+            || self.tempo.is_some()
+            || self.trim_trailing_silence.is_some()
+            || self.trim_leading_silence.is_some()
+            || self.loudness_target.is_some()
+            || self.pad_to_secs.is_some()
+            || self.channel_gains_db.is_some()
+            || self.invert_polarity
+            || self.soft_clip
+            || self.invert_channels.is_some()
+            || self.silence_cue_markers
+            || self.bwf_metadata.is_some()
+            || self.copy_metadata
+            || self.output_format != OutputFormat::Wav
+            || self.large_file.is_some()
+            || self.encoding != PcmEncoding::Linear
+            || self.needs_remix(
+                spec.channels as usize,
+                self.output_channels(spec.channels as usize)?,
+            )
+        {
+            return Ok(None);
+        }
+
+        self.precheck(&AudioInfo {
+            codec: codec_name(pcm_codec(spec)),
+            container: Some("wave"),
+            channels: spec.channels as usize,
+            sample_rate: spec.sample_rate,
+            duration_secs: Some(reader.duration() as f64 / spec.sample_rate as f64),
+            bits_per_sample: Some(spec.bits_per_sample),
+            is_lossy: false,
+            metadata: Vec::new(),
+            tags: Tags::default(),
+        })?;
+
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "The input already matches the output spec, copying its PCM data");
+
+        let started = Stopwatch::start();
+        let (written_samples, peak) = self.write_atomically(|converter| {
+            let (mut wav_writer, trailing_chunks) = converter.create_wav_writer(spec)?;
+            let mut peak = 0.0_f32;
+            match spec.sample_format {
+                hound::SampleFormat::Int => {
+                    let full_scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                    for sample in reader.samples::<i32>() {
+                        let sample = sample
+                            .map_err(|e| AudioConversionError::DecoderError(e.to_string()))?;
+                        wav_writer.write_sample(sample)?;
+                        peak = peak.max((sample as f32 / full_scale).abs());
+                    }
+                }
+                hound::SampleFormat::Float => {
+                    for sample in reader.samples::<f32>() {
+                        let sample = sample
+                            .map_err(|e| AudioConversionError::DecoderError(e.to_string()))?;
+                        wav_writer.write_sample(sample)?;
+                        peak = peak.max(sample.abs());
+                    }
+                }
+            }
+            let written_samples = wav_writer.len();
+            wav_writer.finalize()?;
+            trailing_chunks.restore(&converter.output_path)?;
+
+            if converter.verify_output {
+                converter.verify_wav(spec, written_samples as u64)?;
+            }
+            Ok((written_samples, peak))
+        })?;
+
+        let duration_secs = reader.duration() as f64 / spec.sample_rate as f64;
+        Ok(Some(ConversionReport {
+            source_bit_depth: Some(spec.bits_per_sample),
+            input_duration_secs: duration_secs,
+            output_duration_secs: duration_secs,
+            input_codec: codec_name(pcm_codec(spec)),
+            input_sample_rate: spec.sample_rate,
+            output_sample_rate: spec.sample_rate,
+            input_channels: spec.channels as usize,
+            output_channels: spec.channels as usize,
+            samples_written: written_samples as u64,
+            output_peak_dbfs: peak_dbfs(peak),
+            elapsed: started.elapsed(),
+            ..Default::default()
+        }))
+    }
+
+    /// Run `write` with the output path replaced by a temporary file next to it, then rename the
+    /// temporary file over the output file once `write` succeeded. A failed conversion thus never
+    /// leaves a half-written output nor destroys the existing file.
+    ///
+    /// Appending to an existing output file writes to it in place.
+    fn write_atomically<T>(
+        &self,
+        write: impl FnOnce(&Self) -> Result<T, AudioConversionError>,
+    ) -> Result<T, AudioConversionError> {
+        if self.append && self.output_path.exists() {
+            return write(self);
+        }
+        self.check_overwrite()?;
+
+        let mut converter = self.clone();
+        converter.output_path =
+            resume::with_suffix(&self.output_path, &format!(".{}.tmp", std::process::id()));
+        match write(&converter) {
+            Ok(value) => {
+                std::fs::rename(&converter.output_path, &self.output_path)?;
+                Ok(value)
+            }
+            Err(e) => {
+                // Removing the temporary file is best effort, the error is reported either way
+                let _ = std::fs::remove_file(&converter.output_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Fail if the output file exists and overwriting it is disabled by `with_overwrite`.
+    fn check_overwrite(&self) -> Result<(), AudioConversionError> {
+        if self.overwrite || !self.output_path.exists() {
+            return Ok(());
+        }
+
+        let err_msg = format!(
+            "{} already exists and overwriting it is disabled",
+            self.output_path.display()
+        );
+
+        #[cfg(feature = "logging")]
+        error!(target: "stdout", "{}", err_msg);
+
+        Err(AudioConversionError::IoError(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            err_msg,
+        )))
+    }
+
+    /// Create the writer of the output file in the output format, appending to it if requested.
+    /// The chunks detached from the end of an appended WAV file are returned with it, to be
+    /// restored once it is finalized.
     ///
     /// # Arguments
     ///
-    /// * `input_path` - The path to the input audio file.
-    pub fn with_input_path<S: Into<String>>(mut self, input_path: S) -> Self {
-        self.input_path = input_path.into();
-        self
-    }
+    /// * `spec` - The spec of the output samples.
+    /// * `samples` - The projected number of output samples, if known.
+    /// * `tags` - The tags of the input.
+    fn create_output_writer(
+        &self,
+        spec: WavSpec,
+        samples: Option<u64>,
+        tags: &Tags,
+    ) -> Result<(OutputWriter<BufWriter<File>>, TrailingChunks), AudioConversionError> {
+        let format = match self.output_format {
+            OutputFormat::Wav
+                if self.encoding == PcmEncoding::Linear
+                    && self.large_file_format(spec, samples).is_none() =>
+            {
+                let (wav_writer, trailing_chunks) = self.create_wav_writer(spec)?;
+                return Ok((OutputWriter::Wav(wav_writer), trailing_chunks));
+            }
+            OutputFormat::RawPcm { format } => format,
+            _ => {
+                if self.append && self.output_path.exists() {
+                    let err_msg = format!(
+                        "Cannot append to {}: only linear PCM RIFF WAV and raw PCM output can be appended to",
+                        self.output_path.display()
+                    );
 
-    /// Build the audio converter.
-    pub fn build(self) -> AudioConverter {
-        AudioConverter {
-            input_path: self.input_path,
-            output_path: self.output_path,
-            target_sample_rate: self.target_sample_rate,
-        }
-    }
-}
+                    #[cfg(feature = "logging")]
+                    error!(target: "stdout", "{}", err_msg);
 
-#[derive(Debug)]
-pub struct AudioConverter {
-    input_path: String,
-    output_path: String,
-    target_sample_rate: u32,
-}
-impl AudioConverter {
-    pub fn convert_audio(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let file = File::open(&self.input_path)?;
-        let media_source = MediaSourceStream::new(Box::new(file), Default::default());
-        self.convert_audio_internal(media_source)
-    }
+                    return Err(AudioConversionError::UnsupportedFormat(err_msg));
+                }
+
+                let file = File::create(&self.output_path)?;
+                let buf_writer = BufWriter::with_capacity(self.output_buffer_size, file);
+                let output_writer = self.output_writer(buf_writer, spec, samples, tags)?;
+                return Ok((output_writer, TrailingChunks::default()));
+            }
+        };
+
+        let file = if self.append && self.output_path.exists() {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Appending to existing raw PCM file: {}", self.output_path.display());
+
+            File::options().append(true).open(&self.output_path)?
+        } else {
+            File::create(&self.output_path)?
+        };
+        let samples = (file.metadata()?.len() / format.bytes_per_sample() as u64) as u32;
 
-    pub fn convert_audio_from_bytes(&self, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        let buffer = std::io::Cursor::new(bytes.to_vec());
-        let media_source = MediaSourceStream::new(Box::new(buffer), Default::default());
-        self.convert_audio_internal(media_source)
+        let raw_writer = OutputWriter::Raw {
+            writer: BufWriter::with_capacity(self.output_buffer_size, file),
+            format,
+            samples,
+        };
+        Ok((raw_writer, TrailingChunks::default()))
     }
 
-    fn convert_audio_internal(
+    /// Create a writer of the output format writing to `writer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination of the output.
+    /// * `spec` - The spec of the output samples.
+    /// * `samples` - The projected number of output samples, if known.
+    /// * `tags` - The tags of the input, stored in the headers of FLAC, Ogg Opus and MP3 output
+    ///   if they are copied.
+    fn output_writer<W: Write + Seek>(
         &self,
-        media_source: MediaSourceStream,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        #[cfg(feature = "logging")]
-        info!(target: "stdout", "Probing audio");
+        writer: W,
+        spec: WavSpec,
+        samples: Option<u64>,
+        tags: &Tags,
+    ) -> Result<OutputWriter<W>, AudioConversionError> {
+        let no_tags = Tags::default();
+        let tags = if self.copy_metadata { tags } else { &no_tags };
 
-        let mut hint = Hint::new();
-        hint.with_extension("oga");
+        Ok(match self.output_format {
+            OutputFormat::Wav if self.encoding != PcmEncoding::Linear => OutputWriter::G711(
+                G711Writer::new(writer, self.encoding, spec.channels, spec.sample_rate)?,
+            ),
+            OutputFormat::Wav => match self.large_file_format(spec, samples) {
+                Some(format) => {
+                    #[cfg(feature = "logging")]
+                    info!(target: "stdout", "Writing a {:?} file", format);
 
-        let format_opts: FormatOptions = Default::default();
-        let metadata_opts: MetadataOptions = Default::default();
-        let decoder_opts: DecoderOptions = Default::default();
+                    OutputWriter::Wav64(Wav64Writer::new(writer, spec, format)?)
+                }
+                None => OutputWriter::Wav(WavWriter::new(writer, spec)?),
+            },
+            OutputFormat::RawPcm { format } => OutputWriter::Raw {
+                writer,
+                format,
+                samples: 0,
+            },
+            OutputFormat::WavAdpcm { block_align } => OutputWriter::Adpcm(AdpcmWriter::new(
+                writer,
+                spec.channels as usize,
+                spec.sample_rate,
+                block_align,
+            )?),
+            OutputFormat::Aiff => OutputWriter::Aiff(AiffWriter::new(
+                writer,
+                spec.channels,
+                spec.sample_rate,
+                spec.bits_per_sample,
+            )?),
+            OutputFormat::Flac { compression_level } => OutputWriter::Flac(FlacWriter::new(
+                writer,
+                spec.channels as usize,
+                spec.sample_rate,
+                spec.bits_per_sample,
+                compression_level,
+                tags,
+            )?),
+            #[cfg(feature = "opus-encoder")]
+            OutputFormat::OggOpus { bitrate, vbr } => OutputWriter::OggOpus(OpusWriter::new(
+                writer,
+                spec.channels as usize,
+                spec.sample_rate,
+                bitrate,
+                vbr,
+                tags,
+            )?),
+            #[cfg(feature = "mp3-encoder")]
+            OutputFormat::Mp3 {
+                bitrate_kbps,
+                quality,
+            } => OutputWriter::Mp3(Mp3Writer::new(
+                writer,
+                spec.channels as usize,
+                spec.sample_rate,
+                bitrate_kbps,
+                quality,
+                tags,
+            )?),
+        })
+    }
 
-        // Probe the media source
-        let probed = symphonia::default::get_probe().format(
-            &hint,
-            media_source,
-            &format_opts,
-            &metadata_opts,
-        )?;
-        let mut format = probed.format;
+    /// Create the writer of the output WAV file, appending to it if requested. The chunks that
+    /// followed the data of an appended file are detached from it and returned with the writer,
+    /// to be restored once it is finalized.
+    fn create_wav_writer(
+        &self,
+        spec: WavSpec,
+    ) -> Result<(WavWriter<BufWriter<File>>, TrailingChunks), AudioConversionError> {
+        if self.append && self.output_path.exists() {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "Appending to existing WAV file: {}", self.output_path.display());
 
-        #[cfg(feature = "logging")]
-        {
-            // Iterate through the tracks and find audio tracks.
-            for track in format.tracks() {
-                let codec = track.codec_params.codec;
-                match codec {
-                    CODEC_TYPE_VORBIS => {
-                        info!(target: "stdout", "Codec of input audio: Vorbis");
-                    }
-                    CODEC_TYPE_OPUS => info!(target: "stdout", "Codec of input audio: Opus"),
-                    CODEC_TYPE_FLAC => info!(target: "stdout", "Codec of input audio: FLAC"),
-                    _ => info!(target: "stdout", "Codec of input audio: Other ({:?})", codec),
-                }
+            let wav_writer = WavWriter::append(&self.output_path)?;
+            if wav_writer.spec() != spec {
+                let err_msg = format!(
+                    "Cannot append to {}: its spec {:?} does not match the output spec {:?}",
+                    self.output_path.display(),
+                    wav_writer.spec(),
+                    spec
+                );
 
-                // Print additional codec parameters.
-                if let Some(channels) = track.codec_params.channels {
-                    info!(target: "stdout", "Channels of input audio: {}", channels.count());
-                }
-                if let Some(sample_rate) = track.codec_params.sample_rate {
-                    info!(target: "stdout", "Sample rate of input audio: {} Hz", sample_rate);
-                }
+                #[cfg(feature = "logging")]
+                error!(target: "stdout", "{}", err_msg);
+
+                return Err(AudioConversionError::UnsupportedFormat(err_msg));
             }
+            let trailing_chunks = TrailingChunks::detach(&self.output_path)?;
+            Ok((wav_writer, trailing_chunks))
+        } else {
+            let file = File::create(&self.output_path)?;
+            let buf_writer = BufWriter::with_capacity(self.output_buffer_size, file);
+            Ok((WavWriter::new(buf_writer, spec)?, TrailingChunks::default()))
         }
+    }
 
-        let track = format.default_track().unwrap();
-        let mut decoder =
-            symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
-
-        // Get audio info
-        let track_info = track.codec_params.clone();
-        let channels = track_info.channels.unwrap().count();
-        let original_sample_rate = track_info.sample_rate.unwrap();
+    /// Decode the input track and hand the interleaved samples of each packet that fall inside the
+    /// requested window to `on_samples`. The first `skip_frames` frames of the window, counted at
+    /// the sample rate of the input like the window itself, are skipped.
+    fn process_audio_samples(
+        &self,
+        input: &mut OpenedInput,
+        skip_frames: u64,
+        progress: &ProgressTracker,
+        report: &mut ConversionReport,
+        on_samples: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        let OpenedInput {
+            format,
+            decoder,
+            track: input_track,
+            pending_packets,
+            ..
+        } = input;
+        let mut track_id = input_track.id;
+        let channels = input_track.channels;
+        let original_sample_rate = input_track.sample_rate;
 
         #[cfg(feature = "logging")]
-        {
-            debug!(target: "stdout", "channels: {}", channels);
-            debug!(target: "stdout", "original_sample_rate: {}", original_sample_rate);
-        }
+        info!(target: "stdout", "Processing audio samples");
 
-        // Set up WAV writer
-        let spec = WavSpec {
-            channels: channels as u16,
-            sample_rate: self.target_sample_rate,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
+        report.input_codec = codec_name(input_track.codec);
+        report.source_bit_depth = input_track.bits_per_sample.map(|bits| bits as u16);
+        report.is_lossy = support::is_lossy(input_track.codec);
 
         #[cfg(feature = "logging")]
-        info!(target: "stdout", "generated wav spec: {:?}", spec);
-
-        // Create WAV writer
-        let mut wav_writer = WavWriter::create(&self.output_path, spec)?;
+        debug!(
+            target: "stdout",
+            "channels: {}, original_sample_rate: {}",
+            channels, original_sample_rate
+        );
 
-        if original_sample_rate == self.target_sample_rate {
-            // No resampling needed
-            let all_samples = self.process_audio_samples(
-                &mut *format,
-                &mut *decoder,
-                channels,
-                original_sample_rate,
-            )?;
+        // The window is counted in frames of the input, the timestamps of each packet are converted
+        // from the time base of its stream
+        let window_start_ts = self.start_offset.map_or(0, |offset| {
+            self.offset_to_frames(offset, original_sample_rate)
+        });
+        let start_ts = window_start_ts + skip_frames;
+        let end_ts = self
+            .end_offset
+            .map(|offset| self.offset_to_frames(offset, original_sample_rate));
 
-            #[cfg(feature = "logging")]
-            info!(target: "stdout", "Writing {} audio samples to WAV file: {}", all_samples.len(), &self.output_path);
+        progress.set_total_frames(input_track.n_frames.map(|n_frames| {
+            end_ts
+                .map_or(n_frames, |end_ts| end_ts.min(n_frames))
+                .saturating_sub(window_start_ts)
+        }));
+        progress.decoded(skip_frames);
 
-            for sample in all_samples {
-                wav_writer.write_sample((sample * 32768.0_f32) as i16)?;
-            }
-        } else {
-            #[cfg(feature = "logging")]
-            info!(
-                target: "stdout",
-                "Resampling from {}Hz to {}Hz",
-                original_sample_rate, self.target_sample_rate
+        let mut seeked = start_ts > 0
+            && self.seek_to_start(
+                &mut **format,
+                &mut **decoder,
+                track_id,
+                frames_to_ts(start_ts, input_track.time_base, original_sample_rate),
             );
+        if seeked {
+            pending_packets.clear();
+        }
 
-            // Collect all samples
-            let all_samples = self.process_audio_samples(
-                &mut *format,
-                &mut *decoder,
-                channels,
-                original_sample_rate,
-            )?;
-
-            #[cfg(feature = "logging")]
-            debug!(target: "stdout", "num of samples after trimming: {}", all_samples.len());
+        let mut sample_buf: Option<(SignalSpec, SampleBuffer<f32>)> = None;
+        let mut skipped_packets = 0;
 
-            #[cfg(feature = "logging")]
-            info!(target: "stdout", "Resampling audio");
+        // The layout of the decoded packets, the adapter to the layout at the start of the input
+        // if it changed, the window in the timestamps of the layout and the timestamp following
+        // the last decoded packet.
+        let mut layout_channels = channels;
+        let (mut stream_time_base, mut stream_rate) = (input_track.time_base, original_sample_rate);
+        let mut layout_adapter: Option<LayoutAdapter> = None;
+        let mut timeline = LayoutTimeline::new(original_sample_rate);
+        let (mut layout_start_ts, mut layout_end_ts) = (start_ts, end_ts);
+        let mut next_ts = 0;
 
-            // Prepare samples for resampler (separate channels)
-            let mut input_channels: Vec<Vec<f32>> = vec![Vec::new(); channels];
-            for (i, sample) in all_samples.iter().enumerate() {
-                input_channels[i % channels].push(*sample);
-            }
+        // The timestamp the input should be decoded up to, if the track declares its length.
+        // Readers report the end of a truncated file, e.g. a WAV file whose data chunk is cut
+        // short, like the end of a complete one, so the two are told apart by this length.
+        let mut expected_end_ts = input_track
+            .n_frames
+            .map(|n_frames| end_ts.map_or(n_frames, |end_ts| end_ts.min(n_frames)));
 
-            // Create resampler
-            let mut resampler = FftFixedInOut::<f32>::new(
-                original_sample_rate as usize,
-                self.target_sample_rate as usize,
-                4096,
-                channels,
-            )?;
+        loop {
+            self.check_cancelled()?;
 
-            // Process the audio in chunks
-            let chunk_size = resampler.input_frames_next();
-            let mut output_buffer = vec![Vec::new(); channels];
+            let packet = match pending_packets
+                .pop_front()
+                .map_or_else(|| format.next_packet(), Ok)
+            {
+                Ok(packet) => packet,
+                // The end of the input is signalled with an unexpected EOF.
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    if expected_end_ts.is_some_and(|expected_end_ts| next_ts < expected_end_ts) {
+                        #[cfg(feature = "logging")]
+                        warn!(
+                            target: "stdout",
+                            "The input ended at frame {} of the {} frames it declares",
+                            next_ts, input_track.n_frames.unwrap_or_default()
+                        );
 
-            // Process full chunks
-            let mut pos = 0;
-            while pos + chunk_size <= input_channels[0].len() {
-                let mut chunk = vec![Vec::new(); channels];
-                for ch in 0..channels {
-                    chunk[ch] = input_channels[ch][pos..pos + chunk_size].to_vec();
+                        report.truncated = true;
+                    }
+                    break;
                 }
+                // A chained input, e.g. an Icecast dump of Ogg streams, continues with a new
+                // stream whose headers the reader has just read.
+                Err(SymphoniaError::ResetRequired) => {
+                    // The declared length only covers the first stream
+                    expected_end_ts = None;
 
-                if let Ok(mut resampled_chunk) = resampler.process(&chunk, None) {
-                    for ch in 0..channels {
-                        output_buffer[ch].append(&mut resampled_chunk[ch]);
+                    if let Some(adapter) = layout_adapter.take() {
+                        adapter.finish(on_samples)?;
                     }
+
+                    let track = probe::audio_track(&**format, self.track.as_ref())?;
+                    let mut params = track.codec_params.clone();
+                    params.channels = probe::channels(&params);
+                    *decoder = self.make_decoder(&params)?;
+                    track_id = track.id;
+                    layout_channels = params
+                        .channels
+                        .map_or(channels, |channels| channels.count());
+                    // The timestamps of the new stream restart at 0
+                    let rate = params.sample_rate.unwrap_or(original_sample_rate);
+                    (stream_time_base, stream_rate) = (params.time_base, rate);
+                    timeline.switch(next_ts, rate, 0);
+                    layout_start_ts = timeline.layout_ts(start_ts);
+                    layout_end_ts = end_ts.map(|end_ts| timeline.layout_ts(end_ts));
+                    next_ts = 0;
+                    sample_buf = None;
+
+                    #[cfg(feature = "logging")]
+                    info!(
+                        target: "stdout",
+                        "Continuing with the chained stream at {:.3} s: {} channels at {} Hz",
+                        timeline.secs(0), layout_channels, rate
+                    );
+
+                    layout_adapter =
+                        self.layout_adapter(layout_channels, rate, input_track, timeline.secs(0))?;
+                    continue;
+                }
+                Err(_e) => {
+                    #[cfg(feature = "logging")]
+                    warn!(target: "stdout", "Decoding stopped before the end of the input: {}", _e);
+
+                    report.truncated = true;
+                    break;
                 }
-                pos += chunk_size;
+            };
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+            let packet_ts = ts_to_frames(packet.ts(), stream_time_base, stream_rate);
+
+            // Stop once the packet starts past the end of the requested window.
+            if layout_end_ts.is_some_and(|end_ts| packet_ts >= end_ts) {
+                break;
             }
 
-            // Process remaining samples if any
-            if pos < input_channels[0].len() {
-                let mut final_chunk = vec![Vec::new(); channels];
-                for ch in 0..channels {
-                    final_chunk[ch] = input_channels[ch][pos..].to_vec();
-                    // Pad with zeros if necessary
-                    final_chunk[ch].resize(chunk_size, 0.0);
+            let mut decoded = decoder.decode(&packet).map(|_| ());
+            if let Err(SymphoniaError::DecodeError(_)) = decoded {
+                if let Some(new_decoder) = self.redecode_with_new_layout(
+                    &**decoder,
+                    &packet,
+                    layout_channels,
+                    timeline.rate(),
+                ) {
+                    *decoder = new_decoder;
+                    decoded = Ok(());
+                }
+            }
+            let decoded = match decoded {
+                Ok(()) => decoder.last_decoded(),
+                Err(SymphoniaError::DecodeError(e)) => {
+                    let ErrorPolicy::SkipCorrupt { max_skipped } = self.error_policy else {
+                        return Err(SymphoniaError::DecodeError(e).into());
+                    };
+                    if skipped_packets == max_skipped {
+                        let err_msg = format!(
+                            "More than {} corrupt packets, the last one at frame {}: {}",
+                            max_skipped, packet_ts, e
+                        );
+
+                        #[cfg(feature = "logging")]
+                        error!(target: "stdout", "{}", err_msg);
+
+                        return Err(AudioConversionError::DecoderError(err_msg));
+                    }
+
+                    #[cfg(feature = "logging")]
+                    warn!(target: "stdout", "Skipping the corrupt packet at frame {}: {}", packet_ts, e);
+
+                    skipped_packets += 1;
+                    report.skipped_frames +=
+                        ts_to_frames(packet.dur(), stream_time_base, stream_rate);
+                    decoder.reset();
+                    continue;
                 }
+                Err(e) => return Err(e.into()),
+            };
+
+            // Adapt to a layout changing between packets, e.g. in DVB captures switching between
+            // stereo and 5.1
+            let spec = *decoded.spec();
+            if spec.channels.count() != layout_channels || spec.rate != timeline.rate() {
+                // Readers such as the MP3 one count the timestamps at the rate of each layout, so
+                // a seek past a change lands at the wrong position. Decode from the beginning
+                // instead.
+                if seeked && next_ts == 0 {
+                    #[cfg(feature = "logging")]
+                    warn!(target: "stdout", "The layout changes before the start of the requested window, decoding from the beginning");
+
+                    seeked = false;
+                    *decoder = self.make_decoder(decoder.codec_params())?;
+                    if !self.seek_to_start(&mut **format, &mut **decoder, track_id, 0) {
+                        let err_msg = format!(
+                            "The layout changes before the start of the requested window, at {:.3} s or earlier, and the input cannot be seeked back to its beginning",
+                            start_ts as f64 / original_sample_rate as f64
+                        );
+
+                        #[cfg(feature = "logging")]
+                        error!(target: "stdout", "{}", err_msg);
 
-                if let Ok(resampled_chunk) = resampler.process(&final_chunk, None) {
-                    let remaining_samples = (input_channels[0].len() - pos)
-                        * self.target_sample_rate as usize
-                        / original_sample_rate as usize;
-                    for ch in 0..channels {
-                        output_buffer[ch].extend(&resampled_chunk[ch][..remaining_samples]);
+                        return Err(AudioConversionError::UnsupportedFormat(err_msg));
                     }
+                    continue;
                 }
+
+                #[cfg(feature = "logging")]
+                warn!(
+                    target: "stdout",
+                    "The layout changes at {:.3} s from {} channels at {} Hz to {} channels at {} Hz",
+                    timeline.secs(packet_ts), layout_channels, timeline.rate(),
+                    spec.channels.count(), spec.rate
+                );
+
+                if let Some(adapter) = layout_adapter.take() {
+                    adapter.finish(on_samples)?;
+                }
+                timeline.switch(packet_ts, spec.rate, packet_ts);
+                expected_end_ts = None;
+                layout_start_ts = timeline.layout_ts(start_ts);
+                layout_end_ts = end_ts.map(|end_ts| timeline.layout_ts(end_ts));
+                layout_channels = spec.channels.count();
+                layout_adapter = self.layout_adapter(
+                    layout_channels,
+                    spec.rate,
+                    input_track,
+                    timeline.secs(packet_ts),
+                )?;
             }
 
-            #[cfg(feature = "logging")]
-            info!(target: "stdout", "Writing resampled audio to WAV file: {}", &self.output_path);
+            let required_capacity = decoded.capacity() * layout_channels;
+            let sample_buf = match &mut sample_buf {
+                Some((buf_spec, sample_buf))
+                    if *buf_spec == spec && sample_buf.capacity() >= required_capacity =>
+                {
+                    sample_buf
+                }
+                _ => {
+                    let new_buf = SampleBuffer::new(decoded.capacity() as u64, spec);
+                    &mut sample_buf.insert((spec, new_buf)).1
+                }
+            };
+            sample_buf.copy_interleaved_ref(decoded);
+
+            report.decoded_packets += 1;
 
-            // Write resampled data
-            for i in 0..output_buffer[0].len() {
-                for item in output_buffer.iter().take(channels) {
-                    let sample = (item[i] * 32768.0) as i16;
-                    wav_writer.write_sample(sample)?;
+            // Keep only the frames of the packet that fall inside the requested window
+            let samples = sample_buf.samples();
+            let frames = (samples.len() / layout_channels) as u64;
+            next_ts = packet_ts + frames;
+            let first = layout_start_ts.saturating_sub(packet_ts).min(frames);
+            let last = layout_end_ts.map_or(frames, |end_ts| (end_ts - packet_ts).min(frames));
+            if first < last {
+                let samples =
+                    &samples[first as usize * layout_channels..last as usize * layout_channels];
+                match layout_adapter.as_mut() {
+                    Some(adapter) => adapter.push(samples, on_samples)?,
+                    None => on_samples(samples)?,
                 }
+                progress.decoded(last - first);
             }
         }
 
-        #[cfg(feature = "logging")]
-        info!(target: "stdout", "Finalizing WAV file");
+        if let Some(adapter) = layout_adapter {
+            adapter.finish(on_samples)?;
+        }
 
-        wav_writer.finalize()?;
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "num of decoded packets: {}", report.decoded_packets);
 
         Ok(())
     }
 
-    fn process_audio_samples(
+    /// A new decoder that decoded `packet` after `decoder` rejected it, if the packet changes the
+    /// layout from `channels` channels at `sample_rate`.
+    ///
+    /// Decoders such as the MP3 one reject the packets whose layout differs from that of the
+    /// previous ones, while a new decoder takes them.
+    fn redecode_with_new_layout(
         &self,
-        format: &mut dyn symphonia::core::formats::FormatReader,
-        decoder: &mut dyn symphonia::core::codecs::Decoder,
+        decoder: &dyn Decoder,
+        packet: &Packet,
         channels: usize,
-        original_sample_rate: u32,
-    ) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
-        #[cfg(feature = "logging")]
-        info!(target: "stdout", "Processing audio samples");
+        sample_rate: u32,
+    ) -> Option<Box<dyn Decoder>> {
+        let mut new_decoder = self.make_decoder(decoder.codec_params()).ok()?;
+        let spec = *new_decoder.decode(packet).ok()?.spec();
+
+        (spec.channels.count() != channels || spec.rate != sample_rate).then_some(new_decoder)
+    }
+
+    /// The adapter bringing audio of `channels` channels at `sample_rate` to the layout at the
+    /// start of the input, or `None` if the layouts match.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - The number of channels of the audio from `offset_secs` on.
+    ///
+    /// * `sample_rate` - The sample rate of the audio from `offset_secs` on.
+    ///
+    /// * `track` - The decoded track, with the layout at the start of the input.
+    ///
+    /// * `offset_secs` - The position where the layout changes, in seconds.
+    fn layout_adapter(
+        &self,
+        channels: usize,
+        sample_rate: u32,
+        track: &InputTrack,
+        offset_secs: f64,
+    ) -> Result<Option<LayoutAdapter>, AudioConversionError> {
+        if channels == track.channels && sample_rate == track.sample_rate {
+            return Ok(None);
+        }
+        if channels == 0 || sample_rate == 0 || (channels > 1 && channels < track.channels) {
+            let err_msg = format!(
+                "The layout changes at {:.3} s to {} channels at {} Hz, which cannot be converted to the {} channels at {} Hz of the start of the input",
+                offset_secs, channels, sample_rate, track.channels, track.sample_rate
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        }
 
         #[cfg(feature = "logging")]
-        debug!(
+        info!(
             target: "stdout",
-            "channels: {}, original_sample_rate: {}",
-            channels, original_sample_rate
+            "Converting the audio from {:.3} s on to {} channels at {} Hz",
+            offset_secs, track.channels, track.sample_rate
         );
 
-        let mut all_samples = Vec::new();
-        let mut sample_buf: Option<SampleBuffer<f32>> = None;
+        let resampler = if sample_rate == track.sample_rate {
+            None
+        } else {
+            let resampler = self.resampler.create(
+                track.channels,
+                sample_rate,
+                track.sample_rate,
+                self.resampler_chunk_size,
+            )?;
+            Some(StreamingResampler::new(
+                resampler,
+                track.channels,
+                sample_rate,
+                track.sample_rate,
+                true,
+            ))
+        };
 
-        while let Ok(packet) = format.next_packet() {
-            let decoded = decoder.decode(&packet)?;
-            if sample_buf.is_none() {
-                sample_buf = Some(SampleBuffer::new(
-                    decoded.capacity() as u64,
-                    *decoded.spec(),
-                ));
-            }
-            let sample_buf = sample_buf.as_mut().unwrap();
-            sample_buf.copy_interleaved_ref(decoded);
+        Ok(Some(LayoutAdapter::new(
+            channels,
+            track.channels,
+            resampler,
+        )))
+    }
+
+    /// Fail with `AudioConversionError::Cancelled` if the cancel token is set.
+    fn check_cancelled(&self) -> Result<(), AudioConversionError> {
+        if self
+            .cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+        {
+            #[cfg(feature = "logging")]
+            info!(target: "stdout", "The conversion was cancelled");
 
-            all_samples.extend(sample_buf.samples().iter().copied());
+            return Err(AudioConversionError::Cancelled);
         }
 
-        #[cfg(feature = "logging")]
-        debug!(target: "stdout", "num of samples before trimming: {}", all_samples.len());
+        Ok(())
+    }
+
+    /// Seek the format reader to `start_ts` so the frames before it are not decoded, and return
+    /// whether it was seeked.
+    ///
+    /// If the source cannot be seeked accurately, the reader is left at the beginning and the
+    /// frames before `start_ts` are decoded and discarded instead.
+    fn seek_to_start(
+        &self,
+        format: &mut dyn FormatReader,
+        decoder: &mut dyn Decoder,
+        track_id: u32,
+        start_ts: u64,
+    ) -> bool {
+        match format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: start_ts,
+                track_id,
+            },
+        ) {
+            Ok(seeked_to) => {
+                #[cfg(feature = "logging")]
+                debug!(
+                    target: "stdout",
+                    "seeked to ts {} (required ts: {})",
+                    seeked_to.actual_ts, seeked_to.required_ts
+                );
+
+                #[cfg(not(feature = "logging"))]
+                let _ = seeked_to;
+
+                // The decoder must be reset after a seek.
+                decoder.reset();
+                true
+            }
+            Err(_e) => {
+                #[cfg(feature = "logging")]
+                info!(target: "stdout", "Seeking is not supported by the input ({}), decoding from the beginning", _e);
+
+                false
+            }
+        }
+    }
 
-        self.trim_ending_silence(&all_samples, channels, original_sample_rate)
+    /// Convert an offset from the beginning of the input to a number of frames at `sample_rate`.
+    fn offset_to_frames(&self, offset: Duration, sample_rate: u32) -> u64 {
+        (offset.as_secs_f64() * sample_rate as f64) as u64
     }
 
     fn trim_ending_silence(
@@ -309,21 +4586,14 @@ impl AudioConverter {
         samples: &[f32],
         channels: usize,
         sample_rate: u32,
-    ) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        options: TrimOptions,
+    ) -> Result<Vec<f32>, AudioConversionError> {
         #[cfg(feature = "logging")]
         info!(target: "stdout", "Trimming ending silence");
-        // -20 dB ≈ 0.1
-        // -30 dB ≈ 0.0316
-        // -40 dB ≈ 0.01
-        // -50 dB ≈ 0.0032
-        // -60 dB ≈ 0.001
-        let threshold = 0.01;
+        let threshold = options.threshold();
 
         #[cfg(feature = "logging")]
-        debug!(target: "stdout", "threshold for trimming: {}. Possible values: 0.1(-20dB), 0.0316(-30dB), 0.01(-40dB), 0.0032(-50dB), 0.001(-60dB)", threshold);
-
-        // Look for the last non-silent sample
-        let mut last_non_silent_index = 0;
+        debug!(target: "stdout", "threshold for trimming: {} ({} dB)", threshold, options.threshold_db);
 
         #[cfg(feature = "logging")]
         debug!(
@@ -334,7 +4604,7 @@ impl AudioConverter {
             sample_rate
         );
 
-        if samples.len() % channels != 0 {
+        if !samples.len().is_multiple_of(channels) {
             let err_msg = format!(
                 "The number of samples is not divisible by the number of channels. samples.len(): {}, channels: {}",
                 samples.len(),
@@ -344,38 +4614,172 @@ impl AudioConverter {
             #[cfg(feature = "logging")]
             error!(target: "stdout", "{}", err_msg);
 
-            return Err(AudioConversionError::InvalidSampleCount(err_msg).into());
+            return Err(AudioConversionError::InvalidSampleCount(err_msg));
         }
 
-        // First pass: find the last non-silent sample
-        let num_samples = samples.len() / channels;
-        for i in (0..num_samples).rev().step_by(channels) {
-            let mut silent = true;
-            for ch in 0..channels {
-                if !self.is_silent(samples[i + ch], threshold) {
-                    silent = false;
-                    last_non_silent_index = i * channels;
+        let analysed_samples = self.silence_analysis_samples(samples, channels, sample_rate);
 
-                    #[cfg(feature = "logging")]
-                    debug!(target: "stdout", "last_non_silent_index: {}", last_non_silent_index);
+        // Find the last non-silent frame
+        let num_frames = samples.len() / channels;
+        let levels = trim::rms_envelope(&analysed_samples, channels, sample_rate);
+        let last_non_silent_frame = levels.iter().rposition(|level| *level >= threshold);
 
-                    break;
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "last_non_silent_frame: {:?}", last_non_silent_frame);
+
+        // Only trim if the trailing silence is long enough
+        let end_of_sound = last_non_silent_frame.map_or(0, |frame| frame + 1);
+        let silent_frames = num_frames - end_of_sound;
+        let min_silent_frames = (self.min_trim_silence_secs * sample_rate as f32) as usize;
+        if silent_frames < min_silent_frames {
+            #[cfg(feature = "logging")]
+            debug!(
+                target: "stdout",
+                "trailing silence of {} frames is shorter than {} frames, not trimming",
+                silent_frames, min_silent_frames
+            );
+
+            return Ok(samples.to_vec());
+        }
+
+        // Keep the padding after the last non-silent frame
+        let buffer_frames = options.padding_frames(sample_rate);
+        let trim_index = ((end_of_sound + buffer_frames) * channels).min(samples.len());
+
+        Ok(samples[..trim_index].to_vec())
+    }
+
+    /// Drop the silence before the first non-silent frame, keeping the padding of `options`.
+    fn trim_leading_silence(
+        &self,
+        samples: &[f32],
+        channels: usize,
+        sample_rate: u32,
+        options: TrimOptions,
+    ) -> Vec<f32> {
+        #[cfg(feature = "logging")]
+        info!(target: "stdout", "Trimming leading silence");
+
+        let analysed_samples = self.silence_analysis_samples(samples, channels, sample_rate);
+
+        let threshold = options.threshold();
+        let levels = trim::rms_envelope(&analysed_samples, channels, sample_rate);
+        let first_non_silent_frame = levels.iter().position(|level| *level >= threshold);
+
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "first_non_silent_frame: {:?}", first_non_silent_frame);
+
+        // Leave audio that is silent throughout to the trailing silence trimming
+        let start = match first_non_silent_frame {
+            Some(frame) => frame.saturating_sub(options.padding_frames(sample_rate)),
+            None => 0,
+        };
+
+        samples[start * channels..].to_vec()
+    }
+}
+
+/// Quantize the samples to the sample format of `wav_writer` and write them to it.
+fn write_samples<W: Write + Seek>(
+    wav_writer: &mut WavWriter<W>,
+    samples: &[f32],
+) -> Result<(), AudioConversionError> {
+    let spec = wav_writer.spec();
+    match spec.sample_format {
+        // The most common output, quantized in blocks instead of sample by sample
+        hound::SampleFormat::Int if spec.bits_per_sample == 16 => {
+            let mut quantized = Vec::with_capacity(samples.len().min(QUANTIZE_BLOCK_LEN));
+            for block in samples.chunks(QUANTIZE_BLOCK_LEN) {
+                quantized.clear();
+                simd::quantize_i16(block, &mut quantized);
+                let mut sample_writer = wav_writer.get_i16_writer(block.len() as u32);
+                for sample in &quantized {
+                    sample_writer.write_sample(*sample);
                 }
+                sample_writer.flush()?;
             }
-            if !silent {
-                break;
+        }
+        hound::SampleFormat::Int => {
+            for sample in samples.iter() {
+                wav_writer.write_sample(quantize::quantize(*sample, spec.bits_per_sample))?;
             }
         }
+        hound::SampleFormat::Float => {
+            for sample in samples.iter() {
+                wav_writer.write_sample(*sample)?;
+            }
+        }
+    }
+    Ok(())
+}
 
-        // Add a small buffer (e.g., 0.5 seconds) after the last non-silent sample
-        let buffer_duration_secs = 0.5;
-        let buffer_samples = (buffer_duration_secs * sample_rate as f32) as usize * channels;
-        let trim_index = (last_non_silent_index + channels - 1 + buffer_samples).min(samples.len());
+/// Write the samples like `write_samples`, count the frames written in `progress` and raise
+/// `peak` to the largest absolute sample written.
+fn write_tracked<W: Write + Seek>(
+    wav_writer: &mut WavWriter<W>,
+    samples: &[f32],
+    progress: &ProgressTracker,
+    peak: &Cell<f32>,
+) -> Result<(), AudioConversionError> {
+    write_samples(wav_writer, samples)?;
+    progress.written((samples.len() / wav_writer.spec().channels as usize) as u64);
+    peak.set(
+        samples
+            .iter()
+            .fold(peak.get(), |peak, sample| peak.max(sample.abs())),
+    );
+    Ok(())
+}
 
-        Ok(samples[..trim_index].to_vec())
+/// The sample peak `peak` in dBFS, or `None` if it is silent.
+fn peak_dbfs(peak: f32) -> Option<f64> {
+    (peak > 0.0).then(|| 20.0 * (peak as f64).log10())
+}
+
+/// The codec of PCM samples stored with the given spec.
+fn pcm_codec(spec: WavSpec) -> CodecType {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => CODEC_TYPE_PCM_F32LE,
+        (_, 8) => CODEC_TYPE_PCM_U8,
+        (_, 24) => CODEC_TYPE_PCM_S24LE,
+        (_, 32) => CODEC_TYPE_PCM_S32LE,
+        _ => CODEC_TYPE_PCM_S16LE,
+    }
+}
+
+/// The short name of `codec` if a decoder is registered for it.
+/// Convert the timestamp `ts` of a stream in `time_base` to a number of frames at `sample_rate`,
+/// rounded down. Timestamps count frames if the stream declares no time base.
+fn ts_to_frames(ts: u64, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(TimeBase { numer, denom }) => {
+            (ts as u128 * numer as u128 * sample_rate as u128 / denom as u128) as u64
+        }
+        None => ts,
     }
+}
+
+/// Convert a number of frames at `sample_rate` to a timestamp in `time_base`, rounded down.
+fn frames_to_ts(frames: u64, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(TimeBase { numer, denom }) => {
+            (frames as u128 * denom as u128 / (numer as u128 * sample_rate as u128)) as u64
+        }
+        None => frames,
+    }
+}
+
+pub(crate) fn codec_name(codec: CodecType) -> Option<&'static str> {
+    symphonia::default::get_codecs()
+        .get_codec(codec)
+        .map(|descriptor| descriptor.short_name)
+}
 
-    fn is_silent(&self, sample: f32, threshold: f32) -> bool {
-        sample.abs() < threshold
+/// Apply the linear `channel_gains` to the interleaved samples in place.
+fn apply_channel_gains(samples: &mut [f32], channel_gains: &[f32]) {
+    for frame in samples.chunks_mut(channel_gains.len()) {
+        for (sample, gain) in frame.iter_mut().zip(channel_gains.iter()) {
+            *sample *= gain;
+        }
     }
 }
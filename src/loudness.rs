@@ -0,0 +1,222 @@
+use std::f64::consts::PI;
+
+/// Loudness normalization settings.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoudnessTarget {
+    /// The integrated loudness of the output, in LUFS.
+    pub(crate) target_lufs: f32,
+    /// The ceiling for the true peak of the output, in dBTP.
+    pub(crate) max_true_peak_dbtp: Option<f32>,
+}
+
+/// The gain that brings audio of the given loudness and true peak to `target`.
+///
+/// If reaching the target loudness would push the true peak over the ceiling, the gain is reduced
+/// so the peak lands exactly on the ceiling.
+pub(crate) fn normalization_gain_db(
+    target: LoudnessTarget,
+    loudness_lufs: f64,
+    true_peak_dbtp: f64,
+) -> f64 {
+    let gain_db = target.target_lufs as f64 - loudness_lufs;
+    match target.max_true_peak_dbtp {
+        Some(ceiling) if true_peak_dbtp + gain_db > ceiling as f64 => {
+            ceiling as f64 - true_peak_dbtp
+        }
+        _ => gain_db,
+    }
+}
+
+/// Measure the integrated loudness of interleaved samples, in LUFS, as specified by ITU-R BS.1770.
+///
+/// Returns `None` if the audio is shorter than one gating block or entirely below the absolute
+/// gate.
+pub(crate) fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f64> {
+    let block_len = (0.4 * sample_rate as f64) as usize;
+    let step = block_len / 4;
+    let frames = samples.len() / channels;
+    if block_len == 0 || frames < block_len {
+        return None;
+    }
+
+    // K-weight every channel and keep the squared signal
+    let mut squared = vec![Vec::with_capacity(frames); channels];
+    let mut filters = vec![KWeighting::new(sample_rate as f64); channels];
+    for frame in samples.chunks_exact(channels) {
+        for (ch, sample) in frame.iter().enumerate() {
+            let y = filters[ch].process(*sample as f64);
+            squared[ch].push(y * y);
+        }
+    }
+
+    // Mean square of each channel in 400 ms blocks overlapping by 75%
+    let weights = channel_weights(channels);
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    while start + block_len <= frames {
+        let block: Vec<f64> = squared
+            .iter()
+            .map(|channel| channel[start..start + block_len].iter().sum::<f64>() / block_len as f64)
+            .collect();
+        blocks.push(block);
+        start += step;
+    }
+
+    let loudness = |block: &[f64]| {
+        -0.691
+            + 10.0
+                * block
+                    .iter()
+                    .zip(weights.iter())
+                    .map(|(z, g)| z * g)
+                    .sum::<f64>()
+                    .log10()
+    };
+    let gated_loudness = |blocks: &[&Vec<f64>]| {
+        let mean: Vec<f64> = (0..channels)
+            .map(|ch| blocks.iter().map(|block| block[ch]).sum::<f64>() / blocks.len() as f64)
+            .collect();
+        loudness(&mean)
+    };
+
+    // Absolute gate at -70 LUFS, then relative gate 10 LU below the absolutely gated loudness
+    let above_absolute: Vec<&Vec<f64>> = blocks.iter().filter(|b| loudness(b) > -70.0).collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+    let relative_gate = gated_loudness(&above_absolute) - 10.0;
+    let above_relative: Vec<&Vec<f64>> = above_absolute
+        .into_iter()
+        .filter(|b| loudness(b) > relative_gate)
+        .collect();
+    if above_relative.is_empty() {
+        return None;
+    }
+
+    Some(gated_loudness(&above_relative))
+}
+
+/// Estimate the true peak of interleaved samples in dBTP by 4x oversampling.
+pub(crate) fn true_peak_dbtp(samples: &[f32], channels: usize) -> f64 {
+    const OVERSAMPLING: usize = 4;
+    const HALF_TAPS: isize = 6;
+
+    let frames = samples.len() / channels;
+    let mut peak = 0.0f64;
+    for ch in 0..channels {
+        let sample_at = |n: isize| {
+            if n < 0 || n as usize >= frames {
+                0.0
+            } else {
+                samples[n as usize * channels + ch] as f64
+            }
+        };
+
+        for n in 0..frames as isize {
+            peak = peak.max(sample_at(n).abs());
+
+            // Interpolate between this sample and the next with a Hann-windowed sinc
+            for phase in 1..OVERSAMPLING {
+                let t = phase as f64 / OVERSAMPLING as f64;
+                let mut value = 0.0;
+                for k in (1 - HALF_TAPS)..=HALF_TAPS {
+                    let x = t - k as f64;
+                    let window = 0.5 + 0.5 * (PI * x / (HALF_TAPS as f64 + 1.0)).cos();
+                    value += sample_at(n + k) * sinc(x) * window;
+                }
+                peak = peak.max(value.abs());
+            }
+        }
+    }
+
+    20.0 * peak.log10()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The BS.1770 weight of each channel. Surround channels of 5.0/5.1 layouts count 1.41 and the LFE
+/// channel is excluded.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+/// The BS.1770 K-weighting filter: a high shelf modelling the head followed by a high pass.
+#[derive(Debug, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        // High shelf
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // High pass
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self { shelf, highpass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.highpass.process(self.shelf.process(x))
+    }
+}
+
+/// A biquad section in direct form I.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
@@ -1,36 +1,1351 @@
-use clap::Parser;
-use std::process;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+#[cfg(any(feature = "batch", feature = "watch"))]
+use std::collections::HashMap;
+use std::{
+    io::{self, Cursor, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The exit codes documented in the help.
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  any other error, or some files of a batch failed
+  2  invalid arguments
+  3  input file not found
+  4  unsupported format or codec
+  5  decode error";
+
+/// The config file read when `--config` is not given, if it exists.
+const DEFAULT_CONFIG: &str = "wavup.toml";
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-struct Args {
+#[command(author, version, about, long_about = None, after_help = EXIT_CODES)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// TOML file whose keys, named like the options, e.g. `sample_rate = 16000`, replace their
+    /// defaults. `wavup.toml` in the current directory is read if it exists
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print errors to stderr as JSON objects, one per line, and no log unless `--verbose` is given
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Print errors only, without progress bars or results
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print the log of the conversion instead of progress bars; repeat for more detail
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// The flags of `convert`, also accepted without the subcommand
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert an audio file, the default without a subcommand
+    Convert(ConvertArgs),
+    /// Print the properties of an audio file without converting it
+    Info(InfoArgs),
+    /// Convert every matching file of a directory tree
+    #[cfg(feature = "batch")]
+    Batch(BatchArgs),
+    /// Write each audio track of the input, or each segment of sound between silent gaps, to its
+    /// own file
+    Split(SplitArgs),
+    /// Convert the audio files added to a directory until interrupted
+    #[cfg(feature = "watch")]
+    Watch(WatchArgs),
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input audio file path, or `-` to read the audio from stdin
+    #[arg(short, long, required = true)]
+    input: Option<PathBuf>,
+
+    /// Output file path, or `-` to write the audio to stdout
+    #[arg(short, long, default_value = "output.wav")]
+    out_file: PathBuf,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct InfoArgs {
+    /// Audio file path
+    file: PathBuf,
+
+    /// Print the properties as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+#[cfg(feature = "batch")]
+#[derive(Args)]
+struct BatchArgs {
+    /// Directory of the input files
+    #[arg(long)]
+    input_dir: PathBuf,
+
+    /// Pattern of the files converted from the input directory, relative to it
+    #[arg(long, default_value = "**/*")]
+    glob: String,
+
+    /// Directory the converted files are written to, keeping their path relative to the input
+    /// directory
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Name of each converted file, where `{stem}` is replaced by the name of the input file
+    /// without its extension, `{rate}` by the output sample rate and `{ext}` by the extension of
+    /// the output format
+    #[arg(long, default_value = "{stem}.{ext}")]
+    output_template: String,
+
+    /// Number of files converted at the same time, one per CPU core by default
+    #[arg(short, long, default_value_t = 0)]
+    jobs: usize,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct SplitArgs {
     /// Input audio file path
     #[arg(short, long)]
-    input: String,
+    input: PathBuf,
 
-    /// Output WAV file path
+    /// Output file path, where `{track}`, `{segment}` when splitting by silence or `{channel}`
+    /// when splitting by channel, is replaced by the index or channel label. Without it, they are
+    /// appended to the file name, e.g. `output_1.wav` or `output_L.wav`
     #[arg(short, long, default_value = "output.wav")]
-    out_file: String,
+    out_file: PathBuf,
+
+    /// Split the audio at its silent gaps instead of by track, e.g. into sentences
+    #[arg(long)]
+    by_silence: bool,
+
+    /// Write each channel to its own mono file instead of splitting by track, e.g. the
+    /// interviewer and interviewee recorded on separate channels
+    #[arg(long, conflicts_with = "by_silence")]
+    by_channel: bool,
+
+    /// Level below which audio is considered silent when splitting by silence, in dBFS
+    #[arg(long, default_value_t = -40.0, allow_hyphen_values = true)]
+    silence_threshold_db: f32,
+
+    /// Minimum length of the silent gaps the audio is split at, in seconds
+    #[arg(long, default_value_t = 0.5)]
+    min_gap_secs: f32,
+
+    /// Length of silence kept before and after each segment, in seconds
+    #[arg(long, default_value_t = 0.1)]
+    segment_padding_secs: f32,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[cfg(feature = "watch")]
+#[derive(Args)]
+struct WatchArgs {
+    /// Directory watched for new audio files, including its subdirectories
+    dir: PathBuf,
+
+    /// Directory the converted files are written to, keeping their path relative to the watched
+    /// directory. Files added to it are not converted
+    #[arg(long)]
+    output_dir: PathBuf,
+
+    /// Name of each converted file, where `{stem}` is replaced by the name of the input file
+    /// without its extension, `{rate}` by the output sample rate and `{ext}` by the extension of
+    /// the output format
+    #[arg(long, default_value = "{stem}.{ext}")]
+    output_template: String,
+
+    /// Seconds a new file must stay unchanged before it is converted, so that files still being
+    /// copied are not read
+    #[arg(long, default_value_t = 1.0)]
+    settle_secs: f64,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// The output settings shared by the subcommands that convert audio.
+#[derive(Args)]
+struct OutputArgs {
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = Format::Wav)]
+    format: Format,
 
     /// Output sample rate in Hz
     #[arg(short = 'r', long, default_value_t = 44100)]
     sample_rate: u32,
+
+    /// Number of output channels, those of the input by default
+    #[arg(long)]
+    channels: Option<usize>,
+
+    /// Mix the channels through a matrix of linear gains, one row per output channel separated by
+    /// `;`, each with one gain per input channel separated by `,`, e.g. `0.71,1` for a mono mix
+    /// with the left channel 3 dB quieter or `1;1` to duplicate mono to stereo
+    #[arg(long, value_parser = parse_channel_matrix, conflicts_with = "channels")]
+    channel_matrix: Option<ChannelMatrix>,
+
+    /// Number of bits per sample of the output
+    #[arg(long, default_value_t = 16)]
+    bits_per_sample: u16,
+
+    /// Offset of the input at which the output starts, in seconds or as `[hh:]mm:ss`, e.g.
+    /// `1:15:30`. The input is seeked to it when its format allows
+    #[arg(long, value_parser = parse_time)]
+    start: Option<Duration>,
+
+    /// Length of the audio converted from the start, in seconds or as `[hh:]mm:ss`; the rest of
+    /// the input is not decoded
+    #[arg(long, value_parser = parse_time)]
+    duration: Option<Duration>,
+
+    /// Split the output into files of this duration, in seconds or as `[hh:]mm:ss`, named
+    /// `output_000.wav`, `output_001.wav`, ... or with `{chunk}` in the output path replaced
+    #[arg(long, value_parser = parse_time)]
+    chunk: Option<Duration>,
+
+    /// Duration of audio shared by consecutive chunks, in seconds or as `[hh:]mm:ss`
+    #[arg(long, value_parser = parse_time, default_value = "0", requires = "chunk")]
+    chunk_overlap: Duration,
+
+    /// Remove the content below this frequency in Hz, e.g. rumble, with a high-pass filter
+    #[arg(long)]
+    highpass_hz: Option<f32>,
+
+    /// Remove the content above this frequency in Hz, e.g. hiss, with a low-pass filter
+    #[arg(long)]
+    lowpass_hz: Option<f32>,
+
+    /// Mute the audio while its level stays below this level in dBFS, e.g. the room noise between
+    /// sentences
+    #[arg(long, allow_hyphen_values = true)]
+    noise_gate_db: Option<f32>,
+
+    /// Time the noise gate takes to open, in seconds or as `[hh:]mm:ss`
+    #[arg(long, value_parser = parse_time, default_value = "0.005", requires = "noise_gate_db")]
+    gate_attack: Duration,
+
+    /// Time the noise gate takes to close, in seconds or as `[hh:]mm:ss`
+    #[arg(long, value_parser = parse_time, default_value = "0.2", requires = "noise_gate_db")]
+    gate_release: Duration,
+
+    /// Remove stationary noise, e.g. hum or hiss, by spectral subtraction
+    #[cfg(feature = "denoise")]
+    #[arg(long)]
+    denoise: bool,
+
+    /// Multiple of the noise profile removed by `--denoise`; higher removes more noise but adds
+    /// artifacts
+    #[cfg(feature = "denoise")]
+    #[arg(long, default_value_t = 1.5, requires = "denoise")]
+    denoise_strength: f32,
+
+    /// Gain applied to the audio in dB, reduced if needed to keep the peaks below full scale
+    #[arg(long, allow_hyphen_values = true)]
+    gain_db: Option<f32>,
+
+    /// Duration of the fade in at the start of the output, in seconds or as `[hh:]mm:ss`
+    #[arg(long, value_parser = parse_time)]
+    fade_in: Option<Duration>,
+
+    /// Duration of the fade out at the end of the output, in seconds or as `[hh:]mm:ss`
+    #[arg(long, value_parser = parse_time)]
+    fade_out: Option<Duration>,
+
+    /// Shape of the fades
+    #[arg(long, value_enum, default_value_t = FadeShape::Linear)]
+    fade_shape: FadeShape,
+
+    /// Speed of the output relative to the input, e.g. `1.5`, changed without shifting the pitch
+    #[arg(long)]
+    tempo: Option<f32>,
+
+    /// Soft-clip peaks instead of clipping them hard
+    #[arg(long)]
+    soft_clip: bool,
+
+    /// Trim the silence at the end of the audio
+    #[arg(long)]
+    trim_trailing_silence: bool,
+
+    /// Trim the silence at the start of the audio
+    #[arg(long)]
+    trim_leading_silence: bool,
+
+    /// Level below which audio is considered silent when trimming, in dBFS
+    #[arg(long, default_value_t = -40.0, allow_hyphen_values = true)]
+    trim_threshold_db: f32,
+
+    /// Length of silence kept next to the sound when trimming, in seconds
+    #[arg(long, default_value_t = 0.5)]
+    trim_padding_secs: f32,
+
+    /// Keep only the speech of the audio, dropping the silence and noise between the words
+    #[arg(long)]
+    vad: bool,
+
+    /// Level above which audio is considered speech by `--vad`, in dBFS
+    #[arg(long, default_value_t = -45.0, allow_hyphen_values = true, requires = "vad")]
+    vad_threshold_db: f32,
+
+    /// Skip up to this many corrupt packets of the input instead of failing at the first one
+    #[arg(long, value_name = "MAX_PACKETS")]
+    skip_corrupt: Option<usize>,
+
+    /// Stream the conversion when its samples would take more than this many MiB of memory
+    #[arg(long = "max-memory", value_name = "MIB")]
+    max_memory_mib: Option<usize>,
+
+    /// Resample inputs longer than a minute on this many threads, 0 for one per CPU core
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Replace existing output files, the default
+    #[arg(long, overrides_with = "no_clobber")]
+    overwrite: bool,
+
+    /// Fail instead of replacing existing output files
+    #[arg(long, overrides_with = "overwrite")]
+    no_clobber: bool,
+}
+impl OutputArgs {
+    /// A builder writing to `output_path` with these settings.
+    fn builder(&self, output_path: &Path) -> wavup::AudioConverterBuilder {
+        let trim_options = wavup::TrimOptions {
+            threshold_db: self.trim_threshold_db,
+            padding_secs: self.trim_padding_secs,
+        };
+
+        let mut builder = wavup::AudioConverterBuilder::new(output_path, self.sample_rate)
+            .with_output_format(self.format.into())
+            .with_bits_per_sample(self.bits_per_sample)
+            .with_soft_clip(self.soft_clip)
+            // `--overwrite` wins over a `no_clobber` set in the config file
+            .with_overwrite(self.overwrite || !self.no_clobber);
+        if let Some(channels) = self.channels {
+            builder = builder.with_target_channels(channels);
+        }
+        if let Some(ChannelMatrix(matrix)) = &self.channel_matrix {
+            builder = builder.with_channel_matrix(matrix.clone());
+        }
+        if let Some(max_skipped) = self.skip_corrupt {
+            builder = builder.with_error_policy(wavup::ErrorPolicy::SkipCorrupt { max_skipped });
+        }
+        if let Some(mib) = self.max_memory_mib {
+            builder = builder.with_max_memory(mib.saturating_mul(1024 * 1024));
+        }
+        #[cfg(feature = "parallel")]
+        if let Some(threads) = self.threads {
+            builder = builder.with_threads(threads);
+        }
+        if let Some(cutoff_hz) = self.highpass_hz {
+            builder = builder.with_highpass(cutoff_hz);
+        }
+        if let Some(cutoff_hz) = self.lowpass_hz {
+            builder = builder.with_lowpass(cutoff_hz);
+        }
+        if let Some(threshold_db) = self.noise_gate_db {
+            builder = builder.with_noise_gate(threshold_db, self.gate_attack, self.gate_release);
+        }
+        #[cfg(feature = "denoise")]
+        if self.denoise {
+            builder = builder.with_denoise(wavup::DenoiseOptions {
+                strength: self.denoise_strength,
+                ..Default::default()
+            });
+        }
+        if let Some(gain_db) = self.gain_db {
+            builder = builder.with_gain_db(gain_db);
+        }
+        if self.vad {
+            builder = builder.with_vad(wavup::VadOptions {
+                threshold_db: self.vad_threshold_db,
+                ..Default::default()
+            });
+        }
+        if self.fade_in.is_some() || self.fade_out.is_some() {
+            builder = builder
+                .with_fade(
+                    self.fade_in.unwrap_or_default(),
+                    self.fade_out.unwrap_or_default(),
+                )
+                .with_fade_shape(self.fade_shape.into());
+        }
+        if let Some(tempo) = self.tempo {
+            builder = builder.with_tempo(tempo);
+        }
+        if let Some(chunk) = self.chunk {
+            builder = builder.with_chunking(chunk, self.chunk_overlap);
+        }
+        if self.start.is_some() || self.duration.is_some() {
+            let start = self.start.unwrap_or_default();
+            builder =
+                builder.with_time_range(start, self.duration.map(|duration| start + duration));
+        }
+        if self.trim_trailing_silence {
+            builder = builder.with_trim_trailing_silence(trim_options);
+        }
+        if self.trim_leading_silence {
+            builder = builder.with_trim_leading_silence(trim_options);
+        }
+        builder
+    }
+}
+
+/// The rows of gains given to `--channel-matrix`.
+#[derive(Clone, Debug)]
+struct ChannelMatrix(Vec<Vec<f32>>);
+
+/// Parse a channel matrix given as rows separated by `;` of gains separated by `,`, e.g. `1;1`.
+fn parse_channel_matrix(value: &str) -> Result<ChannelMatrix, String> {
+    value
+        .split(';')
+        .map(|row| {
+            row.split(',')
+                .map(|gain| {
+                    gain.trim()
+                        .parse()
+                        .map_err(|_| format!("invalid gain `{}`", gain.trim()))
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()
+        .map(ChannelMatrix)
+}
+
+/// Parse a time offset given in seconds, e.g. `90.5`, or as `[hh:]mm:ss`, e.g. `1:30.5`.
+fn parse_time(value: &str) -> Result<Duration, String> {
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return Err("expected seconds or [hh:]mm:ss".to_string());
+    }
+    let mut secs = 0.0;
+    for part in parts {
+        let part: f64 = part
+            .parse()
+            .map_err(|_| "expected seconds or [hh:]mm:ss".to_string())?;
+        secs = secs * 60.0 + part;
+    }
+    Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
+/// The format of the output file.
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Wav,
+    Aiff,
+    Flac,
+}
+impl From<Format> for wavup::OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Wav => Self::Wav,
+            Format::Aiff => Self::Aiff,
+            Format::Flac => Self::Flac {
+                compression_level: 5,
+            },
+        }
+    }
+}
+
+/// The shape of the fades.
+#[derive(Clone, Copy, ValueEnum)]
+enum FadeShape {
+    Linear,
+    Cosine,
+}
+impl From<FadeShape> for wavup::FadeShape {
+    fn from(shape: FadeShape) -> Self {
+        match shape {
+            FadeShape::Linear => Self::Linear,
+            FadeShape::Cosine => Self::Cosine,
+        }
+    }
+}
+
+/// The category of an error, which selects the exit code.
+#[derive(Clone, Copy)]
+enum ErrorKind {
+    Other,
+    InvalidArguments,
+    NotFound,
+    UnsupportedFormat,
+    Decode,
+}
+impl ErrorKind {
+    /// The category of a conversion error.
+    fn of(error: &wavup::AudioConversionError) -> Self {
+        use wavup::AudioConversionError::*;
+        match error {
+            IoError(e) if e.kind() == io::ErrorKind::NotFound => Self::NotFound,
+            ProbeError(_) | UnsupportedCodec(_) | UnsupportedFormat(_) => Self::UnsupportedFormat,
+            DecoderError(_) => Self::Decode,
+            _ => Self::Other,
+        }
+    }
+
+    /// The name of the category in JSON errors.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::InvalidArguments => "invalid_arguments",
+            Self::NotFound => "not_found",
+            Self::UnsupportedFormat => "unsupported_format",
+            Self::Decode => "decode_error",
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::InvalidArguments => 2,
+            Self::NotFound => 3,
+            Self::UnsupportedFormat => 4,
+            Self::Decode => 5,
+        }
+    }
+}
+
+/// Reports the progress, results and errors of the subcommands.
+#[derive(Clone, Copy)]
+struct Reporter {
+    /// Whether errors are printed as JSON objects.
+    json_errors: bool,
+    /// Whether only errors are printed.
+    quiet: bool,
+    /// Whether progress bars are drawn.
+    progress: bool,
+}
+impl Reporter {
+    /// The line reporting the error of the operation on the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - What failed, e.g. `"converting"`.
+    ///
+    /// * `file` - The file the operation failed on.
+    ///
+    /// * `error` - The error.
+    fn error_line(self, action: &str, file: &Path, error: &wavup::AudioConversionError) -> String {
+        let kind = ErrorKind::of(error);
+        if self.json_errors {
+            format!(
+                "{{\"error\":{},\"exit_code\":{},\"action\":{},\"file\":{},\"message\":{}}}",
+                json_string(kind.name()),
+                kind.exit_code(),
+                json_string(action),
+                json_string(&file.to_string_lossy()),
+                json_string(&error.to_string())
+            )
+        } else {
+            format!("Error {} {}: {}", action, file.display(), error)
+        }
+    }
+
+    /// Print the error to stderr and exit with the code of its category.
+    fn exit(self, action: &str, file: &Path, error: &wavup::AudioConversionError) -> ! {
+        eprintln!("{}", self.error_line(action, file, error));
+        process::exit(ErrorKind::of(error).exit_code());
+    }
+
+    /// Print a result to stdout, unless quiet.
+    fn result(self, message: &str) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    /// A progress bar labeled `label`, if progress bars are drawn.
+    fn progress_bar(self, label: impl Into<String>) -> Option<Arc<ProgressBar>> {
+        self.progress.then(|| Arc::new(ProgressBar::new(label)))
+    }
+}
+
+/// The width of progress bars, in characters.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// A progress bar redrawn in place on stderr.
+struct ProgressBar {
+    label: String,
+    /// The text drawn last, so the bar is only redrawn when it changes.
+    drawn: Mutex<String>,
+}
+impl ProgressBar {
+    fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            drawn: Mutex::new(String::new()),
+        }
+    }
+
+    /// Draw the bar filled to `fraction`, followed by `detail`.
+    fn set(&self, fraction: f64, detail: &str) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let filled = (fraction * PROGRESS_BAR_WIDTH as f64) as usize;
+        self.draw(format!(
+            "{} [{}{}] {:>3}% {}",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(PROGRESS_BAR_WIDTH - filled),
+            (fraction * 100.0) as u32,
+            detail
+        ));
+    }
+
+    /// Draw the label followed by `detail`, when the total is unknown.
+    fn set_detail(&self, detail: &str) {
+        self.draw(format!("{} {}", self.label, detail));
+    }
+
+    fn draw(&self, text: String) {
+        let mut drawn = self.drawn.lock().unwrap_or_else(|e| e.into_inner());
+        if *drawn != text {
+            eprint!("\r\x1b[2K{}", text);
+            *drawn = text;
+        }
+    }
+
+    /// Erase the bar, e.g. before the result is printed.
+    fn clear(&self) {
+        let mut drawn = self.drawn.lock().unwrap_or_else(|e| e.into_inner());
+        if !drawn.is_empty() {
+            eprint!("\r\x1b[2K");
+            drawn.clear();
+        }
+    }
+
+    /// Print a line above the bar, to stdout or stderr, then draw the bar again.
+    #[cfg(feature = "batch")]
+    fn print_above(&self, line: &str, to_stderr: bool) {
+        let drawn = self.drawn.lock().unwrap_or_else(|e| e.into_inner());
+        eprint!("\r\x1b[2K");
+        if to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        eprint!("{}", drawn);
+    }
+}
+
+/// A progress callback drawing the progress of one file on the bar: the percentage if the duration
+/// of the input is known, the length of audio converted so far otherwise.
+fn file_progress(
+    bar: Arc<ProgressBar>,
+    sample_rate: u32,
+) -> impl Fn(wavup::Progress) + Send + Sync {
+    move |progress| match progress.percent {
+        Some(percent) => bar.set(percent as f64 / 100.0, ""),
+        None => bar.set_detail(&format!(
+            "{:.0} s converted",
+            progress.frames_written as f64 / sample_rate as f64
+        )),
+    }
+}
+
+/// Prints the log records of the library to stderr, keeping stdout for the results.
+#[cfg(feature = "logging")]
+struct StderrLogger;
+#[cfg(feature = "logging")]
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
 }
 
 fn main() {
-    let args = Args::parse();
+    // The flags are looked up by hand so that argument errors honor them too, and the config is
+    // read before the arguments are parsed, as it changes the defaults
+    let json_errors = std::env::args_os().any(|arg| arg == "--json-errors");
+    let (config, required) = config_path();
+    let command = match with_config(Cli::command(), &config, required) {
+        Ok(command) => command,
+        Err(message) => {
+            let message = format!("Error reading {}: {}", config.display(), message);
+            if json_errors {
+                exit_invalid_arguments(&message);
+            }
+            eprintln!("{}", message);
+            process::exit(ErrorKind::InvalidArguments.exit_code());
+        }
+    };
+    let cli = match command
+        .try_get_matches()
+        .and_then(|matches| Cli::from_arg_matches(&matches))
+    {
+        Ok(cli) => cli,
+        Err(e) if json_errors && e.use_stderr() => {
+            exit_invalid_arguments(e.render().to_string().trim_end())
+        }
+        Err(e) => e.exit(),
+    };
+    let reporter = Reporter {
+        json_errors: cli.json_errors,
+        quiet: cli.quiet,
+        progress: !cli.quiet && cli.verbose == 0 && io::stderr().is_terminal(),
+    };
+
+    #[cfg(feature = "logging")]
+    if log::set_logger(&StderrLogger).is_ok() {
+        // Errors are already reported as JSON objects, which log records would break up
+        log::set_max_level(match (cli.quiet, cli.verbose) {
+            (_, 0) if cli.json_errors => log::LevelFilter::Off,
+            (true, _) => log::LevelFilter::Error,
+            (false, 0) => log::LevelFilter::Warn,
+            (false, 1) => log::LevelFilter::Info,
+            (false, 2) => log::LevelFilter::Debug,
+            (false, _) => log::LevelFilter::Trace,
+        });
+    }
+
+    match cli.command {
+        None => convert(&cli.convert, reporter),
+        Some(Command::Convert(args)) => convert(&args, reporter),
+        Some(Command::Info(args)) => print_info(&args.file, args.json, reporter),
+        #[cfg(feature = "batch")]
+        Some(Command::Batch(args)) => convert_directory(&args, reporter),
+        Some(Command::Split(args)) => split_tracks(&args, reporter),
+        #[cfg(feature = "watch")]
+        Some(Command::Watch(args)) => watch_directory(&args, reporter),
+    }
+}
+
+/// Print an argument error as a JSON object and exit.
+fn exit_invalid_arguments(message: &str) -> ! {
+    let kind = ErrorKind::InvalidArguments;
+    eprintln!(
+        "{{\"error\":{},\"exit_code\":{},\"message\":{}}}",
+        json_string(kind.name()),
+        kind.exit_code(),
+        json_string(message)
+    );
+    process::exit(kind.exit_code());
+}
+
+/// The path of the config file given by `--config`, which must exist, or the default config
+/// file, which is skipped if it does not exist.
+fn config_path() -> (PathBuf, bool) {
+    let mut args = std::env::args_os();
+    while let Some(arg) = args.next() {
+        // Without a value, the error is reported when the arguments are parsed
+        if arg == "--config" {
+            if let Some(path) = args.next() {
+                return (PathBuf::from(path), true);
+            }
+        } else if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return (PathBuf::from(path), true);
+        }
+    }
+    (PathBuf::from(DEFAULT_CONFIG), false)
+}
+
+/// The command line with the defaults of its options replaced by the values of the config file.
+///
+/// # Arguments
+///
+/// * `command` - The command line, whose options are looked up in every subcommand.
+///
+/// * `path` - The path of the TOML config file.
+///
+/// * `required` - Whether a missing config file is an error.
+fn with_config(
+    command: clap::Command,
+    path: &Path,
+    required: bool,
+) -> Result<clap::Command, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => return Ok(command),
+        Err(e) => return Err(e.to_string()),
+    };
+    let config: toml::Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| e.message().to_string())?;
+
+    let mut defaults = Vec::new();
+    for (key, value) in config {
+        let id = key.replace('-', "_");
+        if !has_option(&command, &id) {
+            return Err(format!("unknown option `{}`", key));
+        }
+        let value = match value {
+            toml::Value::String(value) => value,
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                value.to_string()
+            }
+            _ => return Err(format!("`{}` must be a string, a number or a boolean", key)),
+        };
+        defaults.push((id, value));
+    }
+    Ok(with_defaults(command, &defaults))
+}
+
+/// Whether the command or one of its subcommands has an option named `id`.
+fn has_option(command: &clap::Command, id: &str) -> bool {
+    command
+        .get_arguments()
+        .any(|arg| arg.get_id() == id && arg.get_long().is_some())
+        || command
+            .get_subcommands()
+            .any(|subcommand| has_option(subcommand, id))
+}
+
+/// The command with the defaults of its options and those of its subcommands replaced. Options
+/// with a default are no longer required.
+fn with_defaults(mut command: clap::Command, defaults: &[(String, String)]) -> clap::Command {
+    for (id, value) in defaults {
+        if command
+            .get_arguments()
+            .any(|arg| arg.get_id() == id.as_str())
+        {
+            command = command.mut_arg(id, |arg| arg.default_value(value.clone()).required(false));
+        }
+    }
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, |subcommand| with_defaults(subcommand, defaults));
+    }
+    command
+}
+
+/// Convert the input file, or stdin, to the output file, or stdout.
+fn convert(args: &ConvertArgs, reporter: Reporter) {
+    // Always set, the option only lets the flattened flags be absent when a subcommand is used
+    let input = args.input.clone().unwrap_or_default();
+    let mut builder = args.output.builder(&args.out_file);
+    // Stdin is read by `convert_piped`
+    if input != Path::new("-") {
+        builder = builder.with_input_path(&input);
+    }
+    let bar = reporter.progress_bar(input.display().to_string());
+    if let Some(bar) = &bar {
+        builder =
+            builder.with_progress_callback(file_progress(bar.clone(), args.output.sample_rate));
+    }
+    let converter = match builder.build() {
+        Ok(converter) => converter,
+        Err(e) => reporter.exit("converting", &input, &e),
+    };
+
+    let to_stdout = args.out_file == Path::new("-");
+    let result = match (input == Path::new("-"), to_stdout) {
+        (false, false) if args.output.chunk.is_some() => converter
+            .convert_audio_chunks()
+            .map(|chunks| chunks.into_iter().map(|chunk| chunk.path).collect()),
+        (false, false) => converter
+            .convert_audio()
+            .map(|_| vec![args.out_file.clone()]),
+        (from_stdin, _) => {
+            convert_piped(&converter, from_stdin, to_stdout).map(|_| vec![args.out_file.clone()])
+        }
+    };
+    if let Some(bar) = &bar {
+        bar.clear();
+    }
+    let output_paths = match result {
+        Ok(output_paths) => output_paths,
+        Err(e) => reporter.exit("converting", &input, &e),
+    };
+
+    // Keep stdout clean for the audio when it is piped
+    if !to_stdout {
+        for output_path in output_paths {
+            reporter.result(&format!(
+                "Successfully converted audio to {} (sample rate: {} Hz)",
+                output_path.display(),
+                args.output.sample_rate
+            ));
+        }
+    }
+}
+
+/// Convert every audio track of the input, or every segment of sound between silent gaps, to its
+/// own output file.
+fn split_tracks(args: &SplitArgs, reporter: Reporter) {
+    let mut builder = args
+        .output
+        .builder(&args.out_file)
+        .with_input_path(&args.input);
+    if !args.by_silence && !args.by_channel {
+        builder = builder.with_track(wavup::TrackSelector::All);
+    }
+    let bar = reporter.progress_bar(args.input.display().to_string());
+    if let Some(bar) = &bar {
+        builder =
+            builder.with_progress_callback(file_progress(bar.clone(), args.output.sample_rate));
+    }
+    let converter = match builder.build() {
+        Ok(converter) => converter,
+        Err(e) => reporter.exit("converting", &args.input, &e),
+    };
+
+    if args.by_silence {
+        let result = converter.convert_audio_segments(wavup::SegmentOptions {
+            threshold_db: args.silence_threshold_db,
+            min_gap_secs: args.min_gap_secs,
+            padding_secs: args.segment_padding_secs,
+        });
+        if let Some(bar) = &bar {
+            bar.clear();
+        }
+        match result {
+            Ok(segments) => {
+                for segment in segments {
+                    reporter.result(&format!(
+                        "Converted {:.3} s - {:.3} s to {}",
+                        segment.start.as_secs_f64(),
+                        segment.end.as_secs_f64(),
+                        segment.path.display()
+                    ));
+                }
+            }
+            Err(e) => reporter.exit("converting", &args.input, &e),
+        }
+        return;
+    }
+
+    if args.by_channel {
+        let result = converter.convert_audio_channels();
+        if let Some(bar) = &bar {
+            bar.clear();
+        }
+        match result {
+            Ok(paths) => {
+                for path in paths {
+                    reporter.result(&format!("Converted a channel to {}", path.display()));
+                }
+            }
+            Err(e) => reporter.exit("converting", &args.input, &e),
+        }
+        return;
+    }
+
+    let result = converter.convert_audio_tracks();
+    if let Some(bar) = &bar {
+        bar.clear();
+    }
+    match result {
+        Ok(tracks) => {
+            for (path, _) in tracks {
+                reporter.result(&format!("Converted a track to {}", path.display()));
+            }
+        }
+        Err(e) => reporter.exit("converting", &args.input, &e),
+    }
+}
+
+/// Convert the audio read from stdin or the input file, and write it to stdout or the output
+/// file.
+///
+/// Stdin is read to the end first, since probing the input needs to seek. The output is buffered
+/// in memory when written to stdout, as the header holding the length of the audio is only
+/// written last.
+fn convert_piped(
+    converter: &wavup::AudioConverter,
+    from_stdin: bool,
+    to_stdout: bool,
+) -> Result<wavup::ConversionReport, wavup::AudioConversionError> {
+    if !to_stdout {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        return converter.convert_audio_from_owned_bytes(bytes);
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    let report = if from_stdin {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        converter.convert_audio_from_source_to_writer(Cursor::new(bytes), &mut output)?
+    } else {
+        converter.convert_audio_to_writer(&mut output)?
+    };
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(output.get_ref())?;
+    stdout.flush()?;
+    Ok(report)
+}
 
-    let converter = wavup::AudioConverterBuilder::new(&args.out_file, args.sample_rate)
-        .with_input_path(args.input)
-        .build();
+/// Convert every file of the input directory matching the glob pattern, naming the outputs with
+/// the output template.
+#[cfg(feature = "batch")]
+fn convert_directory(args: &BatchArgs, reporter: Reporter) {
+    let converter = match args.output.builder(Path::new("")).build() {
+        Ok(converter) => converter,
+        Err(e) => reporter.exit("converting", &args.input_dir, &e),
+    };
+    let found = match wavup::BatchConverter::new(converter.clone()).with_directory(
+        &args.input_dir,
+        &args.glob,
+        &args.output_dir,
+    ) {
+        Ok(batch) => batch,
+        Err(e) => reporter.exit("listing", &args.input_dir, &e),
+    };
+    let jobs: Vec<(PathBuf, PathBuf)> = found
+        .jobs()
+        .iter()
+        .map(|(input, output)| {
+            let name = output_file_name(&args.output_template, input, &args.output);
+            (input.clone(), output.with_file_name(name))
+        })
+        .collect();
 
-    if let Err(e) = converter.convert_audio() {
-        eprintln!("Error converting audio: {}", e);
+    // Files progress and complete on several threads, so the progress is shared
+    let total = jobs.len();
+    let progress = Arc::new(Mutex::new(BatchProgress::default()));
+    let bar = reporter.progress_bar("Batch");
+    let mut batch = wavup::BatchConverter::new(converter)
+        .with_jobs(jobs)
+        .with_threads(args.jobs);
+    if let Some(bar) = bar.clone() {
+        let progress = progress.clone();
+        batch = batch.with_progress_callback(move |path, file_progress| {
+            let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+            let fraction = file_progress
+                .percent
+                .map_or(0.0, |percent| percent as f64 / 100.0);
+            progress.running.insert(path.to_path_buf(), fraction);
+            progress.draw(&bar, total);
+        });
+    }
+    let batch_bar = bar.clone();
+    let results = batch
+        .with_completion_callback(move |result| {
+            let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+            progress.running.remove(&result.input_path);
+            progress.completed += 1;
+            let (line, to_stderr) = match &result.result {
+                Ok(_) if reporter.quiet => return,
+                Ok(_) => (
+                    format!(
+                        "[{}/{}] Converted {} to {}",
+                        progress.completed,
+                        total,
+                        result.input_path.display(),
+                        result.output_path.display()
+                    ),
+                    false,
+                ),
+                Err(e) if reporter.json_errors => (
+                    reporter.error_line("converting", &result.input_path, e),
+                    true,
+                ),
+                Err(e) => (
+                    format!(
+                        "[{}/{}] Error converting {}: {}",
+                        progress.completed,
+                        total,
+                        result.input_path.display(),
+                        e
+                    ),
+                    true,
+                ),
+            };
+            match &batch_bar {
+                Some(bar) => {
+                    progress.draw(bar, total);
+                    bar.print_above(&line, to_stderr);
+                }
+                None if to_stderr => eprintln!("{}", line),
+                None => println!("{}", line),
+            }
+        })
+        .convert();
+    if let Some(bar) = &bar {
+        bar.clear();
+    }
+
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.result.as_ref().err().map(|e| (result, e)))
+        .collect();
+    reporter.result(&format!(
+        "Converted {} of {} files",
+        results.len() - failed.len(),
+        results.len()
+    ));
+    if !failed.is_empty() {
+        // Each failure was already reported as a JSON object
+        if reporter.json_errors {
+            process::exit(1);
+        }
+        eprintln!(
+            "Failed to convert {} of {} files:",
+            failed.len(),
+            results.len()
+        );
+        for (result, e) in failed {
+            eprintln!("  {}: {}", result.input_path.display(), e);
+        }
         process::exit(1);
     }
+}
+
+/// The name of the output file of `input` given by the output template.
+#[cfg(any(feature = "batch", feature = "watch"))]
+fn output_file_name(template: &str, input: &Path, output: &OutputArgs) -> String {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    template
+        .replace("{stem}", &stem)
+        .replace("{rate}", &output.sample_rate.to_string())
+        .replace(
+            "{ext}",
+            wavup::OutputFormat::from(output.format).extension(),
+        )
+}
+
+/// The progress of a batch, shared by the threads converting its files.
+#[cfg(feature = "batch")]
+#[derive(Default)]
+struct BatchProgress {
+    /// The fraction converted of each file being converted.
+    running: HashMap<PathBuf, f64>,
+    /// The number of files converted or failed.
+    completed: usize,
+}
+#[cfg(feature = "batch")]
+impl BatchProgress {
+    /// Draw the overall progress of the batch of `total` files, counting the files being converted
+    /// by the fraction converted so far.
+    fn draw(&self, bar: &ProgressBar, total: usize) {
+        let converted = self.completed as f64 + self.running.values().sum::<f64>();
+        bar.set(
+            converted / total.max(1) as f64,
+            &format!(
+                "{}/{} files, {} running",
+                self.completed,
+                total,
+                self.running.len()
+            ),
+        );
+    }
+}
+
+/// Convert each audio file created in or moved to the watched directory, once it has stopped
+/// changing, until the process is interrupted. A failed conversion is reported and watching
+/// goes on.
+#[cfg(feature = "watch")]
+fn watch_directory(args: &WatchArgs, reporter: Reporter) {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::{
+        sync::mpsc::{self, RecvTimeoutError},
+        time::{Duration, Instant},
+    };
+
+    let watch_error = |e: notify::Error| wavup::AudioConversionError::IoError(io::Error::other(e));
+    if let Err(e) = std::fs::create_dir_all(&args.output_dir) {
+        reporter.exit("creating", &args.output_dir, &e.into());
+    }
+    // Events hold absolute paths, so both directories are compared in that form
+    let (dir, output_dir) = match (args.dir.canonicalize(), args.output_dir.canonicalize()) {
+        (Ok(dir), Ok(output_dir)) => (dir, output_dir),
+        (Err(e), _) => reporter.exit("watching", &args.dir, &e.into()),
+        (_, Err(e)) => reporter.exit("watching", &args.output_dir, &e.into()),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(e) => reporter.exit("watching", &args.dir, &watch_error(e)),
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
+        reporter.exit("watching", &args.dir, &watch_error(e));
+    }
+    reporter.result(&format!(
+        "Watching {} for new audio files, press Ctrl-C to stop",
+        args.dir.display()
+    ));
+
+    let is_audio = |path: &Path| {
+        path.extension().is_some_and(|extension| {
+            wavup::supported_extensions()
+                .iter()
+                .any(|supported| extension.eq_ignore_ascii_case(supported))
+        })
+    };
+    let settle = Duration::from_secs_f64(args.settle_secs.max(0.0));
+    // The time of the last change of each file waiting to be converted
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if is_audio(&path) && !path.starts_with(&output_dir) {
+                            pending.insert(path, Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!(
+                "{}",
+                reporter.error_line("watching", &args.dir, &watch_error(e))
+            ),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for input in settled {
+            pending.remove(&input);
+            // Removed or renamed again before it settled
+            if !input.is_file() {
+                continue;
+            }
+
+            let relative = input.strip_prefix(&dir).unwrap_or(&input);
+            let output = output_dir.join(relative).with_file_name(output_file_name(
+                &args.output_template,
+                &input,
+                &args.output,
+            ));
+            let result = match output.parent() {
+                Some(parent) => std::fs::create_dir_all(parent),
+                None => Ok(()),
+            }
+            .map_err(wavup::AudioConversionError::from)
+            .and_then(|_| {
+                args.output
+                    .builder(&output)
+                    .with_input_path(&input)
+                    .build()?
+                    .convert_audio()
+            });
+            match result {
+                Ok(_) => reporter.result(&format!(
+                    "Converted {} to {}",
+                    relative.display(),
+                    output.display()
+                )),
+                Err(e) => eprintln!("{}", reporter.error_line("converting", &input, &e)),
+            }
+        }
+    }
+}
+
+/// Print the properties of the audio file probed by the library, as text or as a JSON object.
+fn print_info(file: &Path, json: bool, reporter: Reporter) {
+    let info = match wavup::AudioInspector::probe(file) {
+        Ok(info) => info,
+        Err(e) => reporter.exit("probing", file, &e),
+    };
+
+    if json {
+        println!("{}", info_json(file, &info));
+        return;
+    }
 
+    let unknown = || "unknown".to_string();
+    println!("File:        {}", file.display());
+    println!("Codec:       {}", info.codec.unwrap_or("unknown"));
+    println!("Container:   {}", info.container.unwrap_or("unknown"));
     println!(
-        "Successfully converted audio to {} (sample rate: {} Hz)",
-        args.out_file, args.sample_rate
+        "Duration:    {}",
+        info.duration_secs
+            .map_or_else(unknown, |secs| format!("{:.3} s", secs))
     );
+    println!("Sample rate: {} Hz", info.sample_rate);
+    println!("Channels:    {}", info.channels);
+    println!(
+        "Bit depth:   {}",
+        info.bits_per_sample
+            .map_or_else(unknown, |bits| bits.to_string())
+    );
+    println!("Lossy:       {}", if info.is_lossy { "yes" } else { "no" });
+    for (key, value) in &info.metadata {
+        println!("Tag:         {} = {}", key, value);
+    }
+}
+
+/// The properties of the audio file as a single-line JSON object.
+fn info_json(file: &Path, info: &wavup::AudioInfo) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    let tags: Vec<String> = [
+        ("title", &info.tags.title),
+        ("artist", &info.tags.artist),
+        ("album", &info.tags.album),
+        ("comment", &info.tags.comment),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        value
+            .as_deref()
+            .map(|value| format!("\"{}\":{}", name, json_string(value)))
+    })
+    .collect();
+    let metadata: Vec<String> = info
+        .metadata
+        .iter()
+        .map(|(key, value)| format!("[{},{}]", json_string(key), json_string(value)))
+        .collect();
+
+    format!(
+        "{{\"file\":{},\"codec\":{},\"container\":{},\"duration_secs\":{},\"sample_rate\":{},\"channels\":{},\"bits_per_sample\":{},\"is_lossy\":{},\"tags\":{{{}}},\"metadata\":[{}]}}",
+        json_string(&file.to_string_lossy()),
+        optional(info.codec.map(json_string)),
+        optional(info.container.map(json_string)),
+        optional(info.duration_secs.map(|secs| secs.to_string())),
+        info.sample_rate,
+        info.channels,
+        optional(info.bits_per_sample.map(|bits| bits.to_string())),
+        info.is_lossy,
+        tags.join(","),
+        metadata.join(","),
+    )
+}
+
+/// The string as a quoted JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
 }
@@ -0,0 +1,47 @@
+/// The level reported when the channels cancel each other out completely, in dB.
+const MIN_LEVEL_DB: f64 = -100.0;
+
+/// Measures how well interleaved multichannel audio folds down to mono, by comparing the level of
+/// the mono sum with the average level of the channels.
+///
+/// Correlated channels keep their level when summed, so the result is close to 0 dB. Channels that
+/// are out of phase cancel each other out and give a large negative value.
+#[derive(Debug)]
+pub(crate) struct MonoCompatibility {
+    channels: usize,
+    mono_energy: f64,
+    channel_energy: f64,
+}
+impl MonoCompatibility {
+    pub(crate) fn new(channels: usize) -> Self {
+        Self {
+            channels,
+            mono_energy: 0.0,
+            channel_energy: 0.0,
+        }
+    }
+
+    /// Accumulate the next block of interleaved samples.
+    pub(crate) fn feed(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            let mono =
+                frame.iter().map(|sample| *sample as f64).sum::<f64>() / self.channels as f64;
+            self.mono_energy += mono * mono;
+            self.channel_energy += frame
+                .iter()
+                .map(|sample| (*sample as f64).powi(2))
+                .sum::<f64>()
+                / self.channels as f64;
+        }
+    }
+
+    /// The level of the mono sum relative to the average channel level in dB, or `None` for mono
+    /// or silent audio.
+    pub(crate) fn finish(self) -> Option<f64> {
+        if self.channels < 2 || self.channel_energy == 0.0 {
+            return None;
+        }
+
+        Some((10.0 * (self.mono_energy / self.channel_energy).log10()).max(MIN_LEVEL_DB))
+    }
+}
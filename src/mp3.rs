@@ -0,0 +1,189 @@
+use crate::{AudioConversionError, Tags};
+use mp3lame_encoder::{
+    max_required_buffer_size, Bitrate, Builder, Encoder, FlushGap, Id3Tag, InterleavedPcm, MonoPcm,
+    Quality,
+};
+use std::{
+    io::{Seek, SeekFrom, Write},
+    num::NonZeroU32,
+};
+
+/// Whether LAME can encode audio with the given number of channels and sample rate, i.e. a
+/// sample rate of MPEG-1, MPEG-2 or MPEG-2.5 Layer III.
+pub(crate) fn is_supported(channels: usize, sample_rate: u32) -> bool {
+    matches!(channels, 1 | 2)
+        && matches!(
+            sample_rate,
+            8000 | 11025 | 12000 | 16000 | 22050 | 24000 | 32000 | 44100 | 48000
+        )
+}
+
+/// The constant bitrate LAME encodes at, or `None` if it is not a bitrate of MPEG Layer III.
+pub(crate) fn bitrate(bitrate_kbps: u16) -> Option<Bitrate> {
+    Some(match bitrate_kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => return None,
+    })
+}
+
+/// The LAME quality of the algorithm, from `0`, the best, to `9`, the fastest.
+fn quality(quality: u8) -> Quality {
+    match quality {
+        0 => Quality::Best,
+        1 => Quality::SecondBest,
+        2 => Quality::NearBest,
+        3 => Quality::VeryNice,
+        4 => Quality::Nice,
+        5 => Quality::Good,
+        6 => Quality::Decent,
+        7 => Quality::Ok,
+        8 => Quality::SecondWorst,
+        _ => Quality::Worst,
+    }
+}
+
+/// Encodes the samples to a constant bitrate MP3 stream with LAME.
+///
+/// The tags are written to an ID3v2 tag at the start. `finalize` flushes the encoder and
+/// overwrites the first frame with the LAME tag, which records the encoder delay and padding so
+/// players can decode the audio gaplessly.
+pub(crate) struct Mp3Writer<W: Write + Seek> {
+    writer: W,
+    encoder: Encoder,
+    channels: usize,
+    /// The position of the LAME tag frame, right after the ID3v2 tag.
+    lame_tag_pos: u64,
+    /// The encoded frames not written yet.
+    buffer: Vec<u8>,
+    /// The number of samples written.
+    samples: u32,
+}
+impl<W: Write + Seek> Mp3Writer<W> {
+    /// Set up the encoder. The ID3v2 tag is written with the first encoded frames.
+    pub(crate) fn new(
+        mut writer: W,
+        channels: usize,
+        sample_rate: u32,
+        bitrate_kbps: u16,
+        quality_level: u8,
+        tags: &Tags,
+    ) -> Result<Self, AudioConversionError> {
+        let encoder_error = |e: &dyn std::fmt::Display| {
+            AudioConversionError::EncoderError(format!("cannot set up LAME: {}", e))
+        };
+        let bitrate = bitrate(bitrate_kbps).ok_or_else(|| {
+            AudioConversionError::UnsupportedFormat(format!(
+                "{} kbps is not an MP3 bitrate",
+                bitrate_kbps
+            ))
+        })?;
+
+        let mut builder = Builder::new().ok_or_else(|| encoder_error(&"out of memory"))?;
+        builder
+            .set_num_channels(channels as u8)
+            .map_err(|e| encoder_error(&e))?;
+        builder
+            .set_sample_rate(sample_rate)
+            .map_err(|e| encoder_error(&e))?;
+        // LAME would otherwise resample to a lower sample rate for low bitrates
+        builder
+            .set_output_sample_rate(NonZeroU32::new(sample_rate))
+            .map_err(|e| encoder_error(&e))?;
+        builder.set_brate(bitrate).map_err(|e| encoder_error(&e))?;
+        builder
+            .set_quality(quality(quality_level))
+            .map_err(|e| encoder_error(&e))?;
+        builder
+            .set_to_write_vbr_tag(true)
+            .map_err(|e| encoder_error(&e))?;
+        fn field(value: &Option<String>) -> &[u8] {
+            value.as_deref().unwrap_or_default().as_bytes()
+        }
+        builder
+            .set_id3_tag(Id3Tag {
+                title: field(&tags.title),
+                artist: field(&tags.artist),
+                album: field(&tags.album),
+                album_art: &[],
+                year: &[],
+                comment: field(&tags.comment),
+            })
+            .map_err(|e| encoder_error(&format!("{:?}", e)))?;
+        let encoder = builder.build().map_err(|e| encoder_error(&e))?;
+
+        let lame_tag_pos = writer.stream_position()? + encoder.id3v2_tag_size() as u64;
+
+        Ok(Self {
+            writer,
+            encoder,
+            channels,
+            lame_tag_pos,
+            buffer: Vec::new(),
+            samples: 0,
+        })
+    }
+
+    /// Encode the interleaved samples.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<(), AudioConversionError> {
+        self.buffer.clear();
+        self.buffer
+            .reserve(max_required_buffer_size(samples.len() / self.channels));
+        let encoded = if self.channels == 1 {
+            self.encoder
+                .encode_to_vec(MonoPcm(samples), &mut self.buffer)
+        } else {
+            self.encoder
+                .encode_to_vec(InterleavedPcm(samples), &mut self.buffer)
+        };
+        encoded.map_err(|e| AudioConversionError::EncoderError(e.to_string()))?;
+        self.writer.write_all(&self.buffer)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Flush the encoder and write the LAME tag over the first frame.
+    pub(crate) fn finalize(mut self) -> Result<(), AudioConversionError> {
+        self.buffer.clear();
+        self.buffer.reserve(max_required_buffer_size(0));
+        self.encoder
+            .flush_to_vec::<FlushGap>(&mut self.buffer)
+            .map_err(|e| AudioConversionError::EncoderError(e.to_string()))?;
+        self.writer.write_all(&self.buffer)?;
+
+        self.buffer.clear();
+        self.buffer.reserve(self.encoder.lame_tag_size());
+        if self
+            .encoder
+            .lame_tag_encode_to_vec(&mut self.buffer)
+            .is_some()
+        {
+            let end = self.writer.stream_position()?;
+            self.writer.seek(SeekFrom::Start(self.lame_tag_pos))?;
+            self.writer.write_all(&self.buffer)?;
+            self.writer.seek(SeekFrom::Start(end))?;
+        }
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{AudioConversionError, Tags};
+use audiopus::{coder::Encoder, Application, Bitrate, Channels, SampleRate};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::io::Write;
+
+/// The sample rate Ogg Opus granule positions and the pre-skip are counted in.
+const GRANULE_RATE: u32 = 48000;
+
+/// The number of Opus frames per second, i.e. 20 ms frames.
+const FRAMES_PER_SEC: u32 = 50;
+
+/// The largest Opus packet, as recommended by libopus.
+const MAX_PACKET_BYTES: usize = 4000;
+
+/// The serial number of the only logical stream of the file.
+const STREAM_SERIAL: u32 = 1;
+
+/// Whether Opus can encode audio with the given number of channels and sample rate.
+pub(crate) fn is_supported(channels: usize, sample_rate: u32) -> bool {
+    matches!(channels, 1 | 2) && matches!(sample_rate, 8000 | 12000 | 16000 | 24000 | 48000)
+}
+
+/// Encodes the samples with Opus and muxes the packets into an Ogg stream, as laid out by
+/// RFC 7845.
+///
+/// Samples are buffered until a 20 ms frame is complete. `finalize` pads the last frame with
+/// silence, flushes the encoder delay, and sets the final granule position so players trim the
+/// padding.
+pub(crate) struct OpusWriter<W: Write> {
+    packet_writer: PacketWriter<W>,
+    encoder: Encoder,
+    channels: usize,
+    /// The number of interleaved samples of a frame.
+    frame_len: usize,
+    /// The number of 48 kHz granules per input frame.
+    granule_scale: u64,
+    /// The number of granules the decoder discards at the start, i.e. the encoder delay.
+    pre_skip: u64,
+    /// The interleaved samples of the incomplete frame.
+    pending: Vec<f32>,
+    /// The number of samples written, excluding padding.
+    samples: u32,
+    /// The number of granules encoded so far.
+    encoded: u64,
+    /// The last encoded packet and its granule position, held back so the end of the stream can
+    /// be marked on it.
+    held: Option<(Vec<u8>, u64)>,
+}
+impl<W: Write> OpusWriter<W> {
+    /// Write the identification and comment headers and return the writer of the audio packets.
+    pub(crate) fn new(
+        writer: W,
+        channels: usize,
+        sample_rate: u32,
+        bitrate: u32,
+        vbr: bool,
+        tags: &Tags,
+    ) -> Result<Self, AudioConversionError> {
+        let mut encoder = Encoder::new(
+            SampleRate::try_from(sample_rate as i32)?,
+            Channels::try_from(channels as i32)?,
+            Application::Audio,
+        )?;
+        encoder.set_bitrate(Bitrate::BitsPerSecond(bitrate.min(i32::MAX as u32) as i32))?;
+        encoder.set_vbr(vbr)?;
+
+        let granule_scale = (GRANULE_RATE / sample_rate) as u64;
+        let pre_skip = encoder.lookahead()? as u64 * granule_scale;
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes());
+        // No output gain, and the channel mapping of mono and stereo streams
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+
+        let mut comment = b"OpusTags".to_vec();
+        comment
+            .extend_from_slice(&tags.vorbis_comment(concat!("wavup ", env!("CARGO_PKG_VERSION"))));
+
+        // Both headers are on pages of their own
+        let mut packet_writer = PacketWriter::new(writer);
+        packet_writer.write_packet(
+            head.into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+        packet_writer.write_packet(
+            comment.into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        Ok(Self {
+            packet_writer,
+            encoder,
+            channels,
+            frame_len: (sample_rate / FRAMES_PER_SEC) as usize * channels,
+            granule_scale,
+            pre_skip,
+            pending: Vec::new(),
+            samples: 0,
+            encoded: 0,
+            held: None,
+        })
+    }
+
+    /// Encode every completed frame of the interleaved samples.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<(), AudioConversionError> {
+        self.pending.extend_from_slice(samples);
+        self.samples += samples.len() as u32;
+
+        if self.pending.len() >= self.frame_len {
+            let complete = self.pending.len() / self.frame_len * self.frame_len;
+            let pending = std::mem::take(&mut self.pending);
+            for frame in pending[..complete].chunks_exact(self.frame_len) {
+                self.encode_frame(frame)?;
+            }
+            self.pending = pending[complete..].to_vec();
+        }
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u32 {
+        self.samples
+    }
+
+    /// Encode the remaining samples and end the stream.
+    pub(crate) fn finalize(mut self) -> Result<(), AudioConversionError> {
+        let end =
+            self.pre_skip + (self.samples as usize / self.channels) as u64 * self.granule_scale;
+
+        // Feed silence until the encoder delay has passed the end of the audio
+        while self.encoded < end || !self.pending.is_empty() {
+            let mut frame = std::mem::take(&mut self.pending);
+            frame.resize(self.frame_len, 0.0);
+            self.encode_frame(&frame)?;
+        }
+
+        if let Some((packet, _)) = self.held.take() {
+            self.packet_writer.write_packet(
+                packet.into_boxed_slice(),
+                STREAM_SERIAL,
+                PacketWriteEndInfo::EndStream,
+                end,
+            )?;
+        }
+        self.packet_writer.into_inner().flush()?;
+
+        Ok(())
+    }
+
+    /// Encode one frame of interleaved samples and write the packet held back before it.
+    fn encode_frame(&mut self, frame: &[f32]) -> Result<(), AudioConversionError> {
+        let mut packet = vec![0u8; MAX_PACKET_BYTES];
+        let len = self.encoder.encode_float(frame, &mut packet)?;
+        packet.truncate(len);
+        self.encoded += (frame.len() / self.channels) as u64 * self.granule_scale;
+
+        if let Some((previous, granule)) = self.held.replace((packet, self.encoded)) {
+            self.packet_writer.write_packet(
+                previous.into_boxed_slice(),
+                STREAM_SERIAL,
+                PacketWriteEndInfo::NormalPacket,
+                granule,
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+#[cfg(feature = "mp3-encoder")]
+use crate::mp3::Mp3Writer;
+#[cfg(feature = "opus-encoder")]
+use crate::opus::OpusWriter;
+use crate::{
+    adpcm::AdpcmWriter, aiff::AiffWriter, flac::FlacWriter, g711::G711Writer, quantize::quantize,
+    raw, wav64::Wav64Writer, write_samples, AudioConversionError, RawFormat,
+};
+use hound::WavWriter;
+use std::io::{Seek, Write};
+
+/// The format of the output file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A WAV file with the sample format set by `with_bits_per_sample` and `with_sample_format`.
+    #[default]
+    Wav,
+    /// Headerless interleaved PCM, e.g. the 16-bit little-endian samples whisper.cpp reads. The
+    /// sample rate and channel count are not recorded anywhere, so the reader must know them.
+    RawPcm {
+        /// The format of each sample.
+        format: RawFormat,
+    },
+    /// A WAV file of IMA ADPCM blocks, 4 bits per sample, e.g. for embedded playback chips.
+    WavAdpcm {
+        /// The size of each block in bytes: a 4-byte header per channel followed by a multiple
+        /// of 4 bytes per channel, e.g. `256` for mono or `512` for stereo audio at 8 kHz to
+        /// 22 kHz, and `1024` for mono or `2048` for stereo audio at 44.1 or 48 kHz.
+        block_align: u16,
+    },
+    /// An AIFF file of big-endian integer samples, with the bits per sample set by
+    /// `with_bits_per_sample`, e.g. for Mac audio tools.
+    Aiff,
+    /// A losslessly compressed FLAC file with the bits per sample set by `with_bits_per_sample`.
+    Flac {
+        /// The compression level from `0`, the fastest, to `8`, the smallest output. Higher levels
+        /// are treated as `8`.
+        compression_level: u8,
+    },
+    /// Opus audio in an Ogg container, small enough for voice archival. Only available with the
+    /// `opus-encoder` feature.
+    #[cfg(feature = "opus-encoder")]
+    OggOpus {
+        /// The target bitrate in bits per second, e.g. `24_000` for speech.
+        bitrate: u32,
+        /// Whether the bitrate may vary with the complexity of the audio. Constant bitrate
+        /// otherwise.
+        vbr: bool,
+    },
+    /// Constant bitrate MP3, for players and services that take nothing else. Only available with
+    /// the `mp3-encoder` feature.
+    #[cfg(feature = "mp3-encoder")]
+    Mp3 {
+        /// The bitrate in kbit/s, one of the MPEG Layer III bitrates from `8` to `320`, e.g.
+        /// `128` for music or `64` for speech.
+        bitrate_kbps: u16,
+        /// The quality of the LAME encoder from `0`, the best and slowest, to `9`, the fastest.
+        /// Higher values are treated as `9`.
+        quality: u8,
+    },
+}
+
+impl OutputFormat {
+    /// The conventional extension of files in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav | Self::WavAdpcm { .. } => "wav",
+            Self::RawPcm { .. } => "raw",
+            Self::Aiff => "aiff",
+            Self::Flac { .. } => "flac",
+            #[cfg(feature = "opus-encoder")]
+            Self::OggOpus { .. } => "opus",
+            #[cfg(feature = "mp3-encoder")]
+            Self::Mp3 { .. } => "mp3",
+        }
+    }
+}
+
+/// A sample type returned by `AudioConverter::convert_to_samples`.
+pub trait OutputSample: Sized {
+    /// Convert a sample in `[-1.0, 1.0]` to this type.
+    fn from_f32(sample: f32) -> Self;
+}
+impl OutputSample for i16 {
+    fn from_f32(sample: f32) -> Self {
+        quantize(sample, 16) as i16
+    }
+}
+impl OutputSample for f32 {
+    fn from_f32(sample: f32) -> Self {
+        sample
+    }
+}
+
+/// The writer of the output, in the format selected by `OutputFormat`.
+pub(crate) enum OutputWriter<W: Write + Seek> {
+    Wav(WavWriter<W>),
+    Wav64(Wav64Writer<W>),
+    G711(G711Writer<W>),
+    Adpcm(AdpcmWriter<W>),
+    Raw {
+        writer: W,
+        format: RawFormat,
+        /// The number of samples in the output, including those already present when appending.
+        samples: u32,
+    },
+    Aiff(AiffWriter<W>),
+    Flac(FlacWriter<W>),
+    #[cfg(feature = "opus-encoder")]
+    OggOpus(OpusWriter<W>),
+    #[cfg(feature = "mp3-encoder")]
+    Mp3(Mp3Writer<W>),
+}
+impl<W: Write + Seek> OutputWriter<W> {
+    /// Quantize the samples to the output format and write them.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<(), AudioConversionError> {
+        match self {
+            Self::Wav(wav_writer) => write_samples(wav_writer, samples),
+            Self::Wav64(wav64_writer) => Ok(wav64_writer.write(samples)?),
+            Self::G711(g711_writer) => Ok(g711_writer.write(samples)?),
+            Self::Adpcm(adpcm_writer) => Ok(adpcm_writer.write(samples)?),
+            Self::Raw {
+                writer,
+                format,
+                samples: written,
+            } => {
+                writer.write_all(&raw::encode_raw(samples, *format))?;
+                *written += samples.len() as u32;
+                Ok(())
+            }
+            Self::Aiff(aiff_writer) => Ok(aiff_writer.write(samples)?),
+            Self::Flac(flac_writer) => Ok(flac_writer.write(samples)?),
+            #[cfg(feature = "opus-encoder")]
+            Self::OggOpus(opus_writer) => opus_writer.write(samples),
+            #[cfg(feature = "mp3-encoder")]
+            Self::Mp3(mp3_writer) => mp3_writer.write(samples),
+        }
+    }
+
+    /// The number of samples in the output.
+    pub(crate) fn len(&self) -> u64 {
+        match self {
+            Self::Wav(wav_writer) => wav_writer.len() as u64,
+            Self::Wav64(wav64_writer) => wav64_writer.len(),
+            Self::G711(g711_writer) => g711_writer.len() as u64,
+            Self::Adpcm(adpcm_writer) => adpcm_writer.len() as u64,
+            Self::Raw { samples, .. } => *samples as u64,
+            Self::Aiff(aiff_writer) => aiff_writer.len() as u64,
+            Self::Flac(flac_writer) => flac_writer.len() as u64,
+            #[cfg(feature = "opus-encoder")]
+            Self::OggOpus(opus_writer) => opus_writer.len() as u64,
+            #[cfg(feature = "mp3-encoder")]
+            Self::Mp3(mp3_writer) => mp3_writer.len() as u64,
+        }
+    }
+
+    /// Write the pending samples and complete the header or the end of the stream.
+    pub(crate) fn finalize(self) -> Result<(), AudioConversionError> {
+        match self {
+            Self::Wav(wav_writer) => wav_writer.finalize()?,
+            Self::Wav64(wav64_writer) => wav64_writer.finalize()?,
+            Self::G711(g711_writer) => g711_writer.finalize()?,
+            Self::Adpcm(adpcm_writer) => adpcm_writer.finalize()?,
+            Self::Raw { mut writer, .. } => writer.flush()?,
+            Self::Aiff(aiff_writer) => aiff_writer.finalize()?,
+            Self::Flac(flac_writer) => flac_writer.finalize()?,
+            #[cfg(feature = "opus-encoder")]
+            Self::OggOpus(opus_writer) => opus_writer.finalize()?,
+            #[cfg(feature = "mp3-encoder")]
+            Self::Mp3(mp3_writer) => mp3_writer.finalize()?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{streaming, AudioConversionError, AudioConverter};
+use rayon::prelude::*;
+
+/// The minimum length of the segments resampled in parallel, in seconds of input. Shorter inputs
+/// are resampled in one piece.
+const MIN_SEGMENT_SECS: usize = 30;
+
+impl AudioConverter {
+    /// Resample the separated input channels in overlapping segments on the threads set by
+    /// `with_threads`, and return `output_frames` frames per channel, or `None` if the input is
+    /// resampled in one piece.
+    ///
+    /// Each segment is extended by a margin on both sides, so the start-up of its resampler falls
+    /// outside the part that is kept. The segments start on whole periods of the rate ratio,
+    /// where the output of the segment lines up exactly with that of the whole input, and
+    /// neighbours are crossfaded over the middle of their overlap.
+    pub(crate) fn resample_parallel(
+        &self,
+        input_channels: &[Vec<f32>],
+        original_sample_rate: u32,
+        output_frames: usize,
+    ) -> Result<Option<Vec<Vec<f32>>>, AudioConversionError> {
+        let Some(threads) = self.threads else {
+            return Ok(None);
+        };
+        // Without delay compensation the output of each segment would be shifted by the delay
+        if !self.compensate_delay {
+            return Ok(None);
+        }
+
+        let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool,
+            Err(_e) => {
+                #[cfg(feature = "logging")]
+                warn!(target: "stdout", "Failed to start the thread pool, resampling on one thread: {}", _e);
+
+                return Ok(None);
+            }
+        };
+
+        let channels = input_channels.len();
+        let input_frames = input_channels[0].len();
+        let min_segment_frames = MIN_SEGMENT_SECS * original_sample_rate as usize;
+        let segments = pool
+            .current_num_threads()
+            .min(input_frames / min_segment_frames);
+        if segments < 2 {
+            return Ok(None);
+        }
+
+        let divisor = streaming::gcd(original_sample_rate, self.target_sample_rate);
+        let input_period = (original_sample_rate / divisor) as usize;
+        let output_period = (self.target_sample_rate / divisor) as usize;
+        let align = |frames: usize| frames.div_ceil(input_period) * input_period;
+        let to_output = |frames: usize| frames / input_period * output_period;
+
+        let segment_frames = align(input_frames.div_ceil(segments));
+        let margin = align(2 * self.resampler_chunk_size);
+        let half_crossfade = to_output(margin) / 4;
+
+        #[cfg(feature = "logging")]
+        info!(
+            target: "stdout",
+            "Resampling {} segments of {} frames on {} threads",
+            input_frames.div_ceil(segment_frames), segment_frames, pool.current_num_threads()
+        );
+
+        let starts: Vec<usize> = (0..input_frames).step_by(segment_frames).collect();
+        let resampled: Vec<Result<Vec<Vec<f32>>, AudioConversionError>> = pool.install(|| {
+            starts
+                .par_iter()
+                .map(|start| {
+                    let window_start = start.saturating_sub(margin);
+                    let window_end = (start + segment_frames + margin).min(input_frames);
+                    let window: Vec<Vec<f32>> = input_channels
+                        .iter()
+                        .map(|channel| channel[window_start..window_end].to_vec())
+                        .collect();
+                    let frames = streaming::output_frames(
+                        window_end - window_start,
+                        original_sample_rate,
+                        self.target_sample_rate,
+                    );
+
+                    let mut resampler = self.take_resampler(channels, original_sample_rate)?;
+                    let segment = self.run_resampler(resampler.as_mut(), &window, frames)?;
+                    self.release_resampler(channels, original_sample_rate, resampler);
+                    Ok(segment)
+                })
+                .collect()
+        });
+
+        // Overlap-add the segments, fading each one in over the end of the previous one
+        let boundaries: Vec<usize> = starts
+            .iter()
+            .map(|start| to_output(*start))
+            .chain([output_frames])
+            .collect();
+        let mut output_buffer = vec![vec![0.0; output_frames]; channels];
+        for (index, segment) in resampled.into_iter().enumerate() {
+            let segment = segment?;
+            let offset = to_output(starts[index].saturating_sub(margin));
+            let (start, end) = (boundaries[index], boundaries[index + 1]);
+            let first = start.saturating_sub(half_crossfade);
+            let last = (end + half_crossfade)
+                .min(output_frames)
+                .min(offset + segment[0].len());
+
+            for frame in first..last {
+                let mut gain = 1.0;
+                if index > 0 {
+                    gain *= crossfade_gain(frame + half_crossfade - start, half_crossfade);
+                }
+                if index + 2 < boundaries.len() {
+                    gain *= crossfade_gain(end + half_crossfade - 1 - frame, half_crossfade);
+                }
+                for (output, segment) in output_buffer.iter_mut().zip(&segment) {
+                    output[frame] += segment[frame - offset] * gain;
+                }
+            }
+        }
+
+        Ok(Some(output_buffer))
+    }
+}
+
+/// The gain of a linear crossfade of `2 * half_len` frames, `position` frames into the fade in.
+fn crossfade_gain(position: usize, half_len: usize) -> f32 {
+    ((position as f32 + 0.5) / (2 * half_len) as f32).min(1.0)
+}
@@ -0,0 +1,348 @@
+use crate::{support, AudioConversionError, AudioInfo, Tags, TrackSelector};
+use std::{
+    collections::VecDeque,
+    io::{Seek, SeekFrom},
+};
+#[cfg(feature = "aac")]
+use symphonia::default::formats::AdtsReader;
+#[cfg(feature = "isomp4")]
+use symphonia::default::formats::IsoMp4Reader;
+#[cfg(any(feature = "mp3", feature = "all-codecs"))]
+use symphonia::default::formats::MpaReader;
+#[cfg(feature = "all-codecs")]
+use symphonia::default::formats::{AiffReader, CafReader};
+use symphonia::{
+    core::{
+        audio::Channels,
+        codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL},
+        errors::Error as SymphoniaError,
+        formats::{FormatOptions, FormatReader, Packet, Track},
+        io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered},
+        meta::{MetadataOptions, MetadataRevision},
+        probe::{Descriptor, Instantiate, QueryDescriptor},
+    },
+    default::formats::{FlacReader, MkvReader, OggReader, WavReader},
+};
+
+/// The function creating the reader of a container, as found by the probe.
+type FormatInstantiate =
+    fn(MediaSourceStream, &FormatOptions) -> symphonia::core::errors::Result<Box<dyn FormatReader>>;
+
+/// The maximum number of packets decoded to find the layout of a track that does not declare it.
+const MAX_LAYOUT_PACKETS: usize = 16;
+
+/// The probed input, with the details the symphonia probe does not keep.
+pub(crate) struct ProbedInput {
+    pub(crate) format: Box<dyn FormatReader>,
+    /// The short name of the container, e.g. `"ogg"`.
+    pub(crate) container: Option<&'static str>,
+    /// The metadata found before the container, e.g. an ID3v2 tag in front of MP3 frames.
+    pub(crate) metadata: Vec<MetadataRevision>,
+    /// The packets of the audio track read while looking for its layout, still to be decoded.
+    pub(crate) pending_packets: VecDeque<Packet>,
+}
+impl ProbedInput {
+    /// The properties of the audio track picked by `selector`, or of the default audio track if
+    /// `selector` is `None`.
+    pub(crate) fn audio_info(
+        &mut self,
+        selector: Option<&TrackSelector>,
+    ) -> Result<AudioInfo, AudioConversionError> {
+        let track = audio_track(&*self.format, selector)?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let (channels, sample_rate) = match (channels(&params), params.sample_rate) {
+            (Some(channels), Some(sample_rate)) => (channels.count(), sample_rate),
+            _ => self.decoded_layout(track_id, &params)?,
+        };
+
+        let mut info = AudioInfo {
+            codec: crate::codec_name(params.codec),
+            container: self.container,
+            channels,
+            sample_rate,
+            duration_secs: params.n_frames.map(|n_frames| match params.time_base {
+                Some(time_base) => {
+                    let time = time_base.calc_time(n_frames);
+                    time.seconds as f64 + time.frac
+                }
+                None => n_frames as f64 / sample_rate as f64,
+            }),
+            bits_per_sample: params.bits_per_sample.map(|bits| bits as u16),
+            is_lossy: support::is_lossy(params.codec),
+            metadata: Vec::new(),
+            tags: Tags::default(),
+        };
+
+        let container_metadata = self.format.metadata().current().cloned();
+        for revision in self.metadata.iter().chain(container_metadata.as_ref()) {
+            info.metadata.extend(
+                revision
+                    .tags()
+                    .iter()
+                    .map(|tag| (tag.key.clone(), tag.value.to_string())),
+            );
+            info.tags.add_revision(revision);
+        }
+
+        Ok(info)
+    }
+
+    /// The number of channels and sample rate of the track, taken from its first packet that
+    /// decodes, for streams that only declare them in the packets, e.g. some ADTS streams. The
+    /// packets read are queued in `pending_packets`.
+    fn decoded_layout(
+        &mut self,
+        track_id: u32,
+        params: &CodecParameters,
+    ) -> Result<(usize, u32), AudioConversionError> {
+        #[cfg(feature = "logging")]
+        debug!(target: "stdout", "The codec parameters lack the layout, decoding the first packet");
+
+        let mut decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .map_err(AudioConversionError::from)?;
+        while self.pending_packets.len() < MAX_LAYOUT_PACKETS {
+            let Ok(packet) = self.format.next_packet() else {
+                break;
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let spec = decoder.decode(&packet).map(|decoded| *decoded.spec());
+            self.pending_packets.push_back(packet);
+            if let Ok(spec) = spec {
+                return Ok((spec.channels.count(), spec.rate));
+            }
+        }
+
+        Err(AudioConversionError::ProbeError(
+            "The number of channels or the sample rate is unknown".to_string(),
+        ))
+    }
+}
+
+/// The audio track picked by `selector`. Without a selector, or with `TrackSelector::All`, it is
+/// the default track if it is an audio track and the first audio track otherwise, e.g. when the
+/// default track of a video is its video track.
+pub(crate) fn audio_track<'a>(
+    format: &'a dyn FormatReader,
+    selector: Option<&TrackSelector>,
+) -> Result<&'a Track, AudioConversionError> {
+    match selector {
+        Some(TrackSelector::Index(index)) => {
+            let track = format.tracks().get(*index).ok_or_else(|| {
+                AudioConversionError::ProbeError(format!(
+                    "No track at index {}, the input has {} tracks",
+                    index,
+                    format.tracks().len()
+                ))
+            })?;
+            if !is_audio(track) {
+                return Err(AudioConversionError::ProbeError(format!(
+                    "The track at index {} is not an audio track",
+                    index
+                )));
+            }
+            Ok(track)
+        }
+        Some(TrackSelector::Language(language)) => format
+            .tracks()
+            .iter()
+            .filter(|track| is_audio(track))
+            .find(|track| {
+                track
+                    .language
+                    .as_ref()
+                    .is_some_and(|track_language| track_language.eq_ignore_ascii_case(language))
+            })
+            .ok_or_else(|| {
+                AudioConversionError::ProbeError(format!(
+                    "No audio track with language {}",
+                    language
+                ))
+            }),
+        None | Some(TrackSelector::All) => format
+            .default_track()
+            .filter(|track| is_audio(track))
+            .or_else(|| format.tracks().iter().find(|track| is_audio(track)))
+            .ok_or_else(|| AudioConversionError::ProbeError("No audio track found".to_string())),
+    }
+}
+
+/// The indices of the audio tracks of the input.
+pub(crate) fn audio_track_indices(format: &dyn FormatReader) -> Vec<usize> {
+    format
+        .tracks()
+        .iter()
+        .enumerate()
+        .filter(|(_, track)| is_audio(track))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Whether `track` is an audio track. Demuxers leave the codec of the tracks they cannot decode,
+/// such as video tracks, unset. The sample rate may only be known once a packet is decoded.
+fn is_audio(track: &Track) -> bool {
+    track.codec_params.codec != CODEC_TYPE_NULL
+}
+
+/// The channels of a track, taken from its channel layout if the demuxer only sets that, as the
+/// Matroska demuxer does.
+pub(crate) fn channels(params: &CodecParameters) -> Option<Channels> {
+    params
+        .channels
+        .or_else(|| params.channel_layout.map(|layout| layout.into_channels()))
+}
+
+/// Probe `media_source` like the default symphonia probe, additionally recording which container
+/// was found.
+///
+/// The first container marker found is trusted if it is at the start of the stream or right after
+/// its metadata. A marker found further in, past bytes no reader recognizes, may as well be part of
+/// that junk, so if `hint` names the extension of another container and the source is seekable,
+/// the search goes on for that container and only falls back to the first marker if it finds none.
+pub(crate) fn probe(
+    mut media_source: MediaSourceStream,
+    format_opts: &FormatOptions,
+    metadata_opts: &MetadataOptions,
+    hint: Option<&str>,
+) -> Result<ProbedInput, AudioConversionError> {
+    let probe = symphonia::default::get_probe();
+    let mut metadata = Vec::new();
+    let hint = hint.filter(|_| media_source.is_seekable());
+    // The position and reader of the first marker passed over for the hint, and the number of
+    // metadata revisions read before it
+    let mut fallback: Option<(u64, FormatInstantiate, usize)> = None;
+
+    loop {
+        let scan_start = media_source.pos();
+        let instantiate = match probe.next(&mut media_source) {
+            Ok(Instantiate::Format(instantiate)) => instantiate,
+            Ok(Instantiate::Metadata(instantiate)) => {
+                let mut reader = instantiate(metadata_opts);
+                metadata.push(reader.read_all(&mut media_source).map_err(probe_error)?);
+                continue;
+            }
+            Err(err) => {
+                let (pos, instantiate, metadata_len) = fallback.ok_or_else(|| probe_error(err))?;
+                media_source
+                    .seek(SeekFrom::Start(pos))
+                    .map_err(AudioConversionError::IoError)?;
+                metadata.truncate(metadata_len);
+                return open_format(media_source, instantiate, format_opts, metadata);
+            }
+        };
+
+        if let Some(hint) = hint {
+            let pos = media_source.pos();
+            let hinted = marker_descriptor(&mut media_source)?.is_some_and(|descriptor| {
+                descriptor
+                    .extensions
+                    .iter()
+                    .any(|extension| extension.eq_ignore_ascii_case(hint))
+            });
+            if !hinted && (fallback.is_some() || pos > scan_start) {
+                fallback.get_or_insert((pos, instantiate, metadata.len()));
+                // Go on searching from the byte after the start of the marker
+                media_source
+                    .read_byte()
+                    .map_err(AudioConversionError::IoError)?;
+                continue;
+            }
+        }
+
+        return open_format(media_source, instantiate, format_opts, metadata);
+    }
+}
+
+/// The descriptor of the container whose marker starts at the position of `media_source`, which
+/// is left unchanged.
+fn marker_descriptor(
+    media_source: &mut MediaSourceStream,
+) -> Result<Option<&'static Descriptor>, AudioConversionError> {
+    let mut marker = [0u8; 16];
+    media_source
+        .read_buf_exact(&mut marker)
+        .map_err(AudioConversionError::IoError)?;
+    media_source.seek_buffered_rev(marker.len());
+    Ok(descriptor(&marker))
+}
+
+/// Create the reader of the container whose marker starts at the position of `media_source`.
+fn open_format(
+    mut media_source: MediaSourceStream,
+    instantiate: FormatInstantiate,
+    format_opts: &FormatOptions,
+    metadata: Vec<MetadataRevision>,
+) -> Result<ProbedInput, AudioConversionError> {
+    let container = marker_descriptor(&mut media_source)?.map(|descriptor| descriptor.short_name);
+    let format = instantiate(media_source, format_opts).map_err(probe_error)?;
+
+    Ok(ProbedInput {
+        format,
+        container,
+        metadata,
+        pending_packets: VecDeque::new(),
+    })
+}
+
+/// The extension hint of a MIME type, e.g. `"mp3"` for `audio/mpeg`, or `None` if no reader
+/// claims it. Parameters such as `; codecs=opus` are ignored.
+pub(crate) fn mime_extension(mime_type: &str) -> Option<&'static str> {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    // MPEG audio is nearly always MP3, and the MIME type symphonia does not list for MP4 audio
+    if essence.eq_ignore_ascii_case("audio/mpeg") {
+        return Some("mp3");
+    }
+    if essence.eq_ignore_ascii_case("audio/mp4") || essence.eq_ignore_ascii_case("audio/x-m4a") {
+        return Some("m4a");
+    }
+    descriptors()
+        .find(|descriptor| {
+            descriptor
+                .mime_types
+                .iter()
+                .any(|mime_type| mime_type.eq_ignore_ascii_case(essence))
+        })
+        .and_then(|descriptor| descriptor.extensions.first().copied())
+}
+
+/// The descriptors of the container readers compiled in.
+fn descriptors() -> impl Iterator<Item = &'static Descriptor> {
+    let descriptors: Vec<&'static [Descriptor]> = vec![
+        #[cfg(feature = "aac")]
+        AdtsReader::query(),
+        #[cfg(feature = "all-codecs")]
+        CafReader::query(),
+        FlacReader::query(),
+        #[cfg(feature = "isomp4")]
+        IsoMp4Reader::query(),
+        #[cfg(any(feature = "mp3", feature = "all-codecs"))]
+        MpaReader::query(),
+        #[cfg(feature = "all-codecs")]
+        AiffReader::query(),
+        WavReader::query(),
+        OggReader::query(),
+        MkvReader::query(),
+    ];
+    descriptors.into_iter().flatten()
+}
+
+/// The descriptor of the container whose marker starts `context`.
+fn descriptor(context: &[u8]) -> Option<&'static Descriptor> {
+    descriptors().find(|descriptor| {
+        descriptor
+            .markers
+            .iter()
+            .any(|marker| context.starts_with(marker))
+    })
+}
+
+fn probe_error(err: SymphoniaError) -> AudioConversionError {
+    match err {
+        SymphoniaError::IoError(e) => AudioConversionError::IoError(e),
+        e => AudioConversionError::ProbeError(e.to_string()),
+    }
+}
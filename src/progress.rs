@@ -0,0 +1,84 @@
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// The progress of a conversion, handed to the progress callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The number of bytes read from the input so far.
+    pub bytes_read: u64,
+    /// The number of input frames decoded so far, counted from the start of the requested window.
+    pub frames_decoded: u64,
+    /// The number of frames written to the output so far.
+    pub frames_written: u64,
+    /// The estimated percentage of the input decoded, if the container declares its duration.
+    pub percent: Option<f32>,
+}
+
+pub(crate) type ProgressFn = dyn Fn(Progress) + Send + Sync;
+
+/// Counts the progress of a conversion and reports it to the callback, if any.
+pub(crate) struct ProgressTracker {
+    callback: Option<Arc<ProgressFn>>,
+    bytes_read: Arc<AtomicU64>,
+    total_frames: Cell<Option<u64>>,
+    frames_decoded: Cell<u64>,
+    frames_written: Cell<u64>,
+}
+impl ProgressTracker {
+    pub(crate) fn new(callback: Option<Arc<ProgressFn>>) -> Self {
+        Self {
+            callback,
+            bytes_read: Arc::new(AtomicU64::new(0)),
+            total_frames: Cell::new(None),
+            frames_decoded: Cell::new(0),
+            frames_written: Cell::new(0),
+        }
+    }
+
+    /// The counter of the bytes read from the input, shared with the input source.
+    pub(crate) fn bytes_read(&self) -> Arc<AtomicU64> {
+        self.bytes_read.clone()
+    }
+
+    /// Set the number of frames in the requested window of the input, if it is known.
+    pub(crate) fn set_total_frames(&self, total_frames: Option<u64>) {
+        self.total_frames.set(total_frames);
+    }
+
+    /// Count `frames` more decoded input frames and report the progress.
+    pub(crate) fn decoded(&self, frames: u64) {
+        self.frames_decoded.set(self.frames_decoded.get() + frames);
+        self.report();
+    }
+
+    /// Count `frames` more output frames written and report the progress.
+    pub(crate) fn written(&self, frames: u64) {
+        self.frames_written.set(self.frames_written.get() + frames);
+        self.report();
+    }
+
+    fn report(&self) {
+        let Some(callback) = &self.callback else {
+            return;
+        };
+
+        let frames_decoded = self.frames_decoded.get();
+        let percent = self
+            .total_frames
+            .get()
+            .filter(|total_frames| *total_frames > 0)
+            .map(|total_frames| (frames_decoded as f32 / total_frames as f32 * 100.0).min(100.0));
+
+        callback(Progress {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            frames_decoded,
+            frames_written: self.frames_written.get(),
+            percent,
+        });
+    }
+}
@@ -0,0 +1,37 @@
+/// The level above which `soft_clip` starts compressing samples.
+const SOFT_CLIP_KNEE: f32 = 0.9;
+
+/// Quantize a sample in `[-1.0, 1.0]` to a signed integer of `bits_per_sample` bits.
+///
+/// The sample is scaled by `2^(bits_per_sample - 1)` and rounded to the nearest integer. Samples
+/// at or beyond full scale saturate at the largest or smallest representable value instead of
+/// wrapping around, e.g. `1.0` becomes `32767` at 16 bits.
+///
+/// # Arguments
+///
+/// * `sample` - The sample to quantize.
+///
+/// * `bits_per_sample` - The bit depth of the integer samples, from 8 to 32.
+pub fn quantize(sample: f32, bits_per_sample: u16) -> i32 {
+    let scale = (1_i64 << (bits_per_sample - 1)) as f64;
+    (sample as f64 * scale).round().clamp(-scale, scale - 1.0) as i32
+}
+
+/// Compress the peaks of a sample smoothly into `[-1.0, 1.0]`.
+///
+/// Samples below the knee at 0.9 pass through unchanged; louder samples are bent with a `tanh`
+/// curve that approaches full scale, which avoids the harsh harmonics of hard digital clipping.
+///
+/// # Arguments
+///
+/// * `sample` - The sample to compress.
+pub fn soft_clip(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= SOFT_CLIP_KNEE {
+        return sample;
+    }
+
+    let headroom = 1.0 - SOFT_CLIP_KNEE;
+    let compressed = SOFT_CLIP_KNEE + headroom * ((magnitude - SOFT_CLIP_KNEE) / headroom).tanh();
+    compressed.copysign(sample)
+}
@@ -0,0 +1,256 @@
+use crate::{quantize::quantize, simd, AudioConversionError, AudioInfo, Tags};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use symphonia::core::{
+    audio::Channels,
+    codecs::{
+        CodecParameters, CodecType, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S16LE,
+        CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    },
+    errors::{unsupported_error, Error as SymphoniaError, Result as SymphoniaResult},
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::{MediaSource, MediaSourceStream},
+    meta::{Metadata, MetadataLog},
+    units::TimeBase,
+};
+
+/// The number of frames in each packet read from raw PCM input.
+const RAW_PACKET_FRAMES: u64 = 4096;
+
+/// The sample format of headerless PCM input or output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// Unsigned 8-bit samples.
+    U8,
+    /// Signed 16-bit little-endian samples.
+    S16LE,
+    /// Signed 24-bit little-endian samples, packed in 3 bytes.
+    S24LE,
+    /// Signed 32-bit little-endian samples.
+    S32LE,
+    /// 32-bit little-endian floating point samples.
+    F32LE,
+}
+impl RawFormat {
+    /// The number of bytes of one sample.
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::S16LE => 2,
+            Self::S24LE => 3,
+            Self::S32LE | Self::F32LE => 4,
+        }
+    }
+
+    /// The symphonia codec decoding samples in this format.
+    fn codec(self) -> CodecType {
+        match self {
+            Self::U8 => CODEC_TYPE_PCM_U8,
+            Self::S16LE => CODEC_TYPE_PCM_S16LE,
+            Self::S24LE => CODEC_TYPE_PCM_S24LE,
+            Self::S32LE => CODEC_TYPE_PCM_S32LE,
+            Self::F32LE => CODEC_TYPE_PCM_F32LE,
+        }
+    }
+}
+
+/// The layout of headerless PCM input, which cannot be probed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawSpec {
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// The number of interleaved channels.
+    pub channels: usize,
+    /// The format of each sample.
+    pub format: RawFormat,
+}
+
+/// Reads headerless PCM as a single track, in packets of `RAW_PACKET_FRAMES` frames, so it is
+/// decoded by the PCM decoder like the audio of any container.
+pub(crate) struct RawReader {
+    source: MediaSourceStream,
+    tracks: Vec<Track>,
+    metadata: MetadataLog,
+    frame_bytes: usize,
+    next_ts: u64,
+}
+impl RawReader {
+    pub(crate) fn new(
+        source: MediaSourceStream,
+        spec: RawSpec,
+    ) -> Result<Self, AudioConversionError> {
+        let channels = u32::try_from(spec.channels)
+            .ok()
+            .filter(|channels| (1..=Channels::all().bits().count_ones()).contains(channels))
+            .and_then(|channels| Channels::from_bits(u32::MAX >> (32 - channels)));
+        let (Some(channels), true) = (channels, spec.sample_rate > 0) else {
+            let err_msg = format!(
+                "Invalid raw PCM parameters: {} Hz, {} channels",
+                spec.sample_rate, spec.channels
+            );
+
+            #[cfg(feature = "logging")]
+            error!(target: "stdout", "{}", err_msg);
+
+            return Err(AudioConversionError::UnsupportedFormat(err_msg));
+        };
+
+        let frame_bytes = spec.format.bytes_per_sample() * spec.channels;
+        let bits_per_sample = spec.format.bytes_per_sample() as u32 * 8;
+
+        let mut codec_params = CodecParameters::new();
+        codec_params
+            .for_codec(spec.format.codec())
+            .with_sample_rate(spec.sample_rate)
+            .with_time_base(TimeBase::new(1, spec.sample_rate))
+            .with_bits_per_sample(bits_per_sample)
+            .with_bits_per_coded_sample(bits_per_sample)
+            .with_channels(channels)
+            .with_max_frames_per_packet(RAW_PACKET_FRAMES);
+        if let Some(byte_len) = source.byte_len() {
+            codec_params.with_n_frames(byte_len / frame_bytes as u64);
+        }
+
+        Ok(Self {
+            source,
+            tracks: vec![Track::new(0, codec_params)],
+            metadata: MetadataLog::default(),
+            frame_bytes,
+            next_ts: 0,
+        })
+    }
+
+    /// The properties of the input, as declared by its spec.
+    pub(crate) fn audio_info(&self) -> AudioInfo {
+        let params = &self.tracks[0].codec_params;
+        let sample_rate = params.sample_rate.unwrap_or_default();
+
+        AudioInfo {
+            codec: crate::codec_name(params.codec),
+            container: None,
+            channels: params.channels.map_or(0, |channels| channels.count()),
+            sample_rate,
+            duration_secs: params
+                .n_frames
+                .map(|n_frames| n_frames as f64 / sample_rate as f64),
+            bits_per_sample: params.bits_per_sample.map(|bits| bits as u16),
+            is_lossy: false,
+            metadata: Vec::new(),
+            tags: Tags::default(),
+        }
+    }
+}
+impl FormatReader for RawReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> SymphoniaResult<Self> {
+        unsupported_error("raw: the layout of raw PCM cannot be probed")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &[]
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> SymphoniaResult<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+        let required_ts = match to {
+            SeekTo::Time { time, .. } => params.time_base.unwrap().calc_timestamp(time),
+            SeekTo::TimeStamp { ts, .. } => ts,
+        };
+        let actual_ts = params
+            .n_frames
+            .map_or(required_ts, |n_frames| required_ts.min(n_frames));
+
+        self.source
+            .seek(SeekFrom::Start(actual_ts * self.frame_bytes as u64))?;
+        self.next_ts = actual_ts;
+
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts,
+            actual_ts,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> SymphoniaResult<Packet> {
+        let mut buf = vec![0u8; RAW_PACKET_FRAMES as usize * self.frame_bytes];
+        let mut len = 0;
+        while len < buf.len() {
+            match self.source.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(SymphoniaError::IoError(e)),
+            }
+        }
+
+        // A trailing incomplete frame is ignored
+        let frames = (len / self.frame_bytes) as u64;
+        if frames == 0 {
+            return Err(SymphoniaError::IoError(ErrorKind::UnexpectedEof.into()));
+        }
+        buf.truncate(frames as usize * self.frame_bytes);
+
+        let packet = Packet::new_from_boxed_slice(0, self.next_ts, frames, buf.into_boxed_slice());
+        self.next_ts += frames;
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.source
+    }
+}
+
+/// Interpret `bytes` as interleaved samples in `format` and convert them to f32. A trailing
+/// incomplete sample is ignored.
+pub(crate) fn decode_raw(bytes: &[u8], format: RawFormat) -> Vec<f32> {
+    let chunks = bytes.chunks_exact(format.bytes_per_sample());
+    match format {
+        RawFormat::U8 => chunks.map(|b| (b[0] as f32 - 128.0) / 128.0).collect(),
+        RawFormat::S16LE => chunks
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        RawFormat::S24LE => chunks
+            // Place the 24 bits in the upper bytes so the sign is kept
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0)
+            .collect(),
+        RawFormat::S32LE => chunks
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        RawFormat::F32LE => chunks
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    }
+}
+
+/// Encode the samples as interleaved samples in `format`, the inverse of `decode_raw`. Integer
+/// samples are quantized like those of WAV output.
+pub(crate) fn encode_raw(samples: &[f32], format: RawFormat) -> Vec<u8> {
+    if format == RawFormat::S16LE {
+        let mut quantized = Vec::new();
+        simd::quantize_i16(samples, &mut quantized);
+        return quantized
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+    }
+
+    let mut bytes = Vec::with_capacity(samples.len() * format.bytes_per_sample());
+    for sample in samples.iter() {
+        match format {
+            RawFormat::U8 => bytes.push((quantize(*sample, 8) + 128) as u8),
+            RawFormat::S16LE => {
+                bytes.extend_from_slice(&(quantize(*sample, 16) as i16).to_le_bytes())
+            }
+            RawFormat::S24LE => bytes.extend_from_slice(&quantize(*sample, 24).to_le_bytes()[..3]),
+            RawFormat::S32LE => bytes.extend_from_slice(&quantize(*sample, 32).to_le_bytes()),
+            RawFormat::F32LE => bytes.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+    bytes
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+/// Statistics collected while converting an audio file.
+#[derive(Debug, Clone, Default)]
+pub struct ConversionReport {
+    /// The number of packets decoded from the input. Seeking to the start of a time range skips
+    /// the packets before it.
+    pub decoded_packets: u64,
+    /// The number of input samples that belong to a run of clipped samples.
+    pub clipped_sample_count: usize,
+    /// The number of runs of consecutive clipped samples found in the input.
+    pub clip_runs: usize,
+    /// The duration of the decoded input in seconds, computed from the number of decoded frames.
+    pub input_duration_secs: f64,
+    /// The duration of the output in seconds, computed from the number of written frames.
+    pub output_duration_secs: f64,
+    /// The resampler used to reach the target sample rate, or `None` if no resampling was needed.
+    pub resampler: Option<&'static str>,
+    /// The integrated loudness of the audio before loudness normalization, in LUFS.
+    pub measured_loudness_lufs: Option<f64>,
+    /// The gain applied by loudness normalization, in dB.
+    pub applied_gain_db: Option<f64>,
+    /// The gain applied by `with_gain_db`, in dB. Lower than requested if it was reduced to keep
+    /// the peaks below full scale.
+    pub fixed_gain_db: Option<f64>,
+    /// The integrated loudness of the output after loudness normalization, in LUFS.
+    pub output_loudness_lufs: Option<f64>,
+    /// The true peak of the output after loudness normalization, in dBTP.
+    pub output_true_peak_dbtp: Option<f64>,
+    /// The bits per sample of the source, if the container or codec declares it. Lossy codecs
+    /// usually do not.
+    pub source_bit_depth: Option<u16>,
+    /// Whether the source codec is lossy (e.g. MP3, Vorbis, AAC), in which case the WAV output is
+    /// no more faithful than the source.
+    pub is_lossy: bool,
+    /// The level of the mono fold-down relative to the average channel level of the input, in dB.
+    /// Values close to 0 dB fold down cleanly, large negative values indicate phase cancellation.
+    /// `None` for mono input.
+    pub mono_compatibility_db: Option<f64>,
+    /// The short name of the codec of the input, e.g. `"mp3"`, if it is known.
+    pub input_codec: Option<&'static str>,
+    /// The sample rate of the input in Hz.
+    pub input_sample_rate: u32,
+    /// The sample rate of the output in Hz.
+    pub output_sample_rate: u32,
+    /// The number of channels of the input.
+    pub input_channels: usize,
+    /// The number of channels of the output.
+    pub output_channels: usize,
+    /// The number of samples in the output, counting every channel.
+    pub samples_written: u64,
+    /// The sample peak of the output in dBFS, or `None` if the output is silent. A resumed
+    /// conversion only measures the samples converted after resuming.
+    pub output_peak_dbfs: Option<f64>,
+    /// The length of the leading and trailing silence trimmed from the input, in milliseconds.
+    pub trimmed_ms: u64,
+    /// The wall-clock time the conversion took.
+    pub elapsed: Duration,
+    /// Whether decoding stopped before the end of the input because it is truncated or corrupt.
+    pub truncated: bool,
+    /// The number of frames of the corrupt packets skipped under `ErrorPolicy::SkipCorrupt`.
+    pub skipped_frames: u64,
+}
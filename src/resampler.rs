@@ -0,0 +1,131 @@
+use crate::AudioConversionError;
+use rubato::{
+    FastFixedIn, FftFixedInOut, PolynomialDegree, Resampler, SincFixedIn,
+    SincInterpolationParameters, SincInterpolationType, VecResampler, WindowFunction,
+};
+
+/// The default number of input frames the resampler processes at once.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// The resampler used to reach the target sample rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResamplerKind {
+    /// A synchronous FFT resampler. Fast and accurate, but it only approximates non-integer
+    /// ratios through fixed blocks.
+    #[default]
+    Fft,
+    /// An asynchronous sinc interpolation resampler driven by the exact ratio. The highest
+    /// quality, at a higher CPU cost that grows with `sinc_len`.
+    SincFixedIn {
+        /// The length of the sinc interpolation filter, e.g. `256`. Longer filters have a
+        /// steeper cutoff and more latency.
+        sinc_len: usize,
+        /// The window applied to the sinc filter.
+        window: SincWindow,
+    },
+    /// An asynchronous resampler interpolating with cubic polynomials. The cheapest option, for
+    /// real-time use where CPU matters more than quality.
+    Fast,
+}
+impl ResamplerKind {
+    /// The sinc resampler with the settings used by `with_rational_resampler`.
+    pub(crate) const RATIONAL: Self = Self::SincFixedIn {
+        sinc_len: 256,
+        window: SincWindow::BlackmanHarris2,
+    };
+
+    /// The name of the resampler, as recorded in the report.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Fft => "FftFixedInOut",
+            Self::SincFixedIn { .. } => "SincFixedIn",
+            Self::Fast => "FastFixedIn",
+        }
+    }
+
+    /// Create the resampler from `input_rate` to `output_rate`, processing `chunk_size` input
+    /// frames at once.
+    pub(crate) fn create(
+        &self,
+        channels: usize,
+        input_rate: u32,
+        output_rate: u32,
+        chunk_size: usize,
+    ) -> Result<Box<dyn ReusableResampler>, AudioConversionError> {
+        if chunk_size == 0 {
+            return Err(AudioConversionError::UnsupportedFormat(
+                "The resampler chunk size must be at least 1 frame".to_string(),
+            ));
+        }
+
+        let ratio = output_rate as f64 / input_rate as f64;
+        match *self {
+            Self::Fft => Ok(Box::new(FftFixedInOut::<f32>::new(
+                input_rate as usize,
+                output_rate as usize,
+                chunk_size,
+                channels,
+            )?)),
+            Self::SincFixedIn { sinc_len, window } => {
+                let parameters = SincInterpolationParameters {
+                    sinc_len,
+                    f_cutoff: 0.95,
+                    oversampling_factor: 256,
+                    interpolation: SincInterpolationType::Cubic,
+                    window: window.into(),
+                };
+                Ok(Box::new(SincFixedIn::<f32>::new(
+                    ratio, 1.0, parameters, chunk_size, channels,
+                )?))
+            }
+            Self::Fast => Ok(Box::new(FastFixedIn::<f32>::new(
+                ratio,
+                1.0,
+                PolynomialDegree::Cubic,
+                chunk_size,
+                channels,
+            )?)),
+        }
+    }
+}
+
+/// A resampler whose state can be cleared, so it can convert another input.
+pub(crate) trait ReusableResampler: VecResampler<f32> {
+    /// Discard the buffered frames and restore the initial state.
+    fn reset(&mut self);
+}
+impl<R: Resampler<f32>> ReusableResampler for R {
+    fn reset(&mut self) {
+        Resampler::reset(self)
+    }
+}
+
+/// The window applied to the filter of the sinc resampler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SincWindow {
+    /// Intermediate rolloff and intermediate attenuation.
+    Blackman,
+    /// Slower rolloff but better attenuation than `Blackman`.
+    Blackman2,
+    /// Slow rolloff but good attenuation.
+    BlackmanHarris,
+    /// Slower rolloff but better attenuation than `BlackmanHarris`.
+    #[default]
+    BlackmanHarris2,
+    /// Fast rolloff but not very high attenuation.
+    Hann,
+    /// Slower rolloff and higher attenuation than `Hann`.
+    Hann2,
+}
+impl From<SincWindow> for WindowFunction {
+    fn from(window: SincWindow) -> Self {
+        match window {
+            SincWindow::Blackman => WindowFunction::Blackman,
+            SincWindow::Blackman2 => WindowFunction::Blackman2,
+            SincWindow::BlackmanHarris => WindowFunction::BlackmanHarris,
+            SincWindow::BlackmanHarris2 => WindowFunction::BlackmanHarris2,
+            SincWindow::Hann => WindowFunction::Hann,
+            SincWindow::Hann2 => WindowFunction::Hann2,
+        }
+    }
+}
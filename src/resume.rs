@@ -0,0 +1,67 @@
+use std::{
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// The progress of a resumable conversion, saved next to the output file after each segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Checkpoint {
+    /// The number of input frames converted, counted from the start of the requested window.
+    pub(crate) input_frames: u64,
+    /// The number of samples in the output file once those frames are converted.
+    pub(crate) output_samples: u32,
+}
+impl Checkpoint {
+    /// The path of the progress file of the output file at `output_path`.
+    pub(crate) fn path(output_path: &Path) -> PathBuf {
+        with_suffix(output_path, ".progress")
+    }
+
+    /// Read the checkpoint saved at `path`, or `None` if there is none or it is malformed.
+    pub(crate) fn read(path: impl AsRef<Path>) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        let mut fields = contents.split_whitespace();
+        let input_frames = fields.next()?.parse().ok()?;
+        let output_samples = fields.next()?.parse().ok()?;
+
+        Some(Self {
+            input_frames,
+            output_samples,
+        })
+    }
+
+    /// Save the checkpoint at `path`. The file is replaced atomically, so an interruption never
+    /// leaves a partially written checkpoint behind.
+    pub(crate) fn write(&self, path: &Path) -> io::Result<()> {
+        let tmp_path = with_suffix(path, ".tmp");
+        fs::write(
+            &tmp_path,
+            format!("{} {}\n", self.input_frames, self.output_samples),
+        )?;
+        fs::rename(tmp_path, path)
+    }
+}
+
+/// Append `suffix` to the file name of `path`, keeping non-UTF-8 paths intact.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Cut the WAV file at `path` after the end of its RIFF chunk, dropping data left over from an
+/// interrupted conversion.
+pub(crate) fn truncate_to_riff(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header)?;
+    let riff_len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+
+    if file.metadata()?.len() > riff_len + 8 {
+        file.set_len(riff_len + 8)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::track::numbered_output_path;
+
+/// The placeholder of the output path replaced by the segment index when splitting by silence.
+const SEGMENT_PLACEHOLDER: &str = "{segment}";
+
+/// The placeholder of the output path replaced by the chunk index when chunking.
+const CHUNK_PLACEHOLDER: &str = "{chunk}";
+
+/// How the audio is split at its silent gaps by `AudioConverter::convert_audio_segments`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentOptions {
+    /// The level below which a sample is considered silent, in dBFS.
+    pub threshold_db: f32,
+    /// The minimum length of a silent gap between two segments, in seconds. Shorter pauses, e.g.
+    /// between words, do not split the audio.
+    pub min_gap_secs: f32,
+    /// The length of the silence kept before and after the sound of each segment, in seconds.
+    pub padding_secs: f32,
+}
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -40.0,
+            min_gap_secs: 0.5,
+            padding_secs: 0.1,
+        }
+    }
+}
+impl SegmentOptions {
+    /// The linear amplitude of the threshold.
+    pub(crate) fn threshold(&self) -> f32 {
+        10f32.powf(self.threshold_db / 20.0)
+    }
+}
+
+/// A part of the audio written to its own file by `AudioConverter::convert_audio_segments` or
+/// `AudioConverter::convert_audio_chunks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// The path of the output file of the segment.
+    pub path: PathBuf,
+    /// The offset of the start of the segment in the converted audio.
+    pub start: Duration,
+    /// The offset of the end of the segment in the converted audio.
+    pub end: Duration,
+}
+
+/// The frames of each region of sound of the interleaved samples, extended by the padding.
+///
+/// A region ends when the samples stay below `threshold` for at least `min_gap_frames` frames.
+/// The padding of a region never overlaps the previous one.
+pub(crate) fn find_segments(
+    samples: &[f32],
+    channels: usize,
+    threshold: f32,
+    min_gap_frames: usize,
+    padding_frames: usize,
+) -> Vec<Range<usize>> {
+    let frames = samples.len() / channels;
+    let mut sounds: Vec<Range<usize>> = Vec::new();
+    for (frame, samples) in samples.chunks_exact(channels).enumerate() {
+        if samples.iter().all(|sample| sample.abs() < threshold) {
+            continue;
+        }
+        match sounds.last_mut() {
+            Some(sound) if frame - sound.end < min_gap_frames => sound.end = frame + 1,
+            _ => sounds.push(frame..frame + 1),
+        }
+    }
+
+    let mut previous_end = 0;
+    sounds
+        .into_iter()
+        .map(|sound| {
+            let start = sound.start.saturating_sub(padding_frames).max(previous_end);
+            let end = (sound.end + padding_frames).min(frames);
+            previous_end = end;
+            start..end
+        })
+        .collect()
+}
+
+/// The output path of the segment at `index` when splitting by silence.
+///
+/// The `{segment}` placeholder of `output_path` is replaced by the index, e.g. `utt-{segment}.wav`
+/// becomes `utt-0.wav`. Without a placeholder, the index is appended to the file stem, e.g.
+/// `out.wav` becomes `out_0.wav`.
+pub(crate) fn segment_output_path(output_path: &Path, index: usize) -> PathBuf {
+    numbered_output_path(output_path, SEGMENT_PLACEHOLDER, &index.to_string())
+}
+
+/// The frames of each chunk of `chunk_frames` frames, starting every `step_frames` frames, of
+/// audio of `frames` frames. The last chunk may be shorter.
+pub(crate) fn chunk_ranges(
+    frames: usize,
+    chunk_frames: usize,
+    step_frames: usize,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < frames {
+        let end = (start + chunk_frames).min(frames);
+        ranges.push(start..end);
+        if end == frames {
+            break;
+        }
+        start += step_frames;
+    }
+    ranges
+}
+
+/// The output path of the chunk at `index` when chunking.
+///
+/// The `{chunk}` placeholder of `output_path` is replaced by the index padded to three digits,
+/// e.g. `part-{chunk}.wav` becomes `part-000.wav`. Without a placeholder, the index is appended
+/// to the file stem, e.g. `output.wav` becomes `output_000.wav`.
+pub(crate) fn chunk_output_path(output_path: &Path, index: usize) -> PathBuf {
+    numbered_output_path(output_path, CHUNK_PLACEHOLDER, &format!("{:03}", index))
+}
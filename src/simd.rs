@@ -0,0 +1,205 @@
+use crate::quantize::quantize;
+
+/// Quantize the samples to 16-bit integers like `quantize(sample, 16)` and append them to
+/// `output`.
+///
+/// With the `simd` feature, blocks of 8 samples are converted with SSE2 on x86_64 and NEON on
+/// aarch64, rounding half away from zero and saturating exactly like the scalar path.
+pub(crate) fn quantize_i16(samples: &[f32], output: &mut Vec<i16>) {
+    output.reserve(samples.len());
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let samples = {
+        let vectorized = samples.len() / 8 * 8;
+        // SAFETY: the capacity of `output` was reserved above, and SSE2 and NEON are part of the
+        // baseline of their architectures
+        unsafe { arch::quantize_i16(&samples[..vectorized], output) };
+        &samples[vectorized..]
+    };
+
+    output.extend(samples.iter().map(|sample| quantize(*sample, 16) as i16));
+}
+
+/// Split interleaved samples into one buffer per channel.
+///
+/// With the `simd` feature, stereo audio is split 4 frames at a time.
+pub(crate) fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    let frames = samples.len() / channels;
+    let mut output: Vec<Vec<f32>> = (0..channels).map(|_| Vec::with_capacity(frames)).collect();
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    if let [left, right] = output.as_mut_slice() {
+        let vectorized = frames / 4 * 4;
+        // SAFETY: both buffers have a capacity of `frames` frames
+        unsafe { arch::deinterleave_stereo(&samples[..vectorized * 2], left, right) };
+        for frame in samples[vectorized * 2..frames * 2].chunks_exact(2) {
+            left.push(frame[0]);
+            right.push(frame[1]);
+        }
+        return output;
+    }
+
+    for frame in samples[..frames * channels].chunks_exact(channels) {
+        for (channel, sample) in output.iter_mut().zip(frame) {
+            channel.push(*sample);
+        }
+    }
+    output
+}
+
+/// Interleave the samples of each channel, stopping at the end of the shortest channel.
+///
+/// With the `simd` feature, stereo audio is interleaved 4 frames at a time.
+pub(crate) fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+    let mut output = Vec::with_capacity(frames * channels.len());
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    if let [left, right] = channels {
+        let vectorized = frames / 4 * 4;
+        // SAFETY: the capacity of `output` holds every frame
+        unsafe { arch::interleave_stereo(&left[..vectorized], &right[..vectorized], &mut output) };
+        for (left, right) in left[vectorized..frames]
+            .iter()
+            .zip(&right[vectorized..frames])
+        {
+            output.extend([*left, *right]);
+        }
+        return output;
+    }
+
+    for frame in 0..frames {
+        output.extend(channels.iter().map(|channel| channel[frame]));
+    }
+    output
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod arch {
+    use std::arch::x86_64::*;
+
+    /// Quantize `samples`, whose length is a multiple of 8, into the reserved capacity of
+    /// `output`.
+    pub(super) unsafe fn quantize_i16(samples: &[f32], output: &mut Vec<i16>) {
+        for block in samples.chunks_exact(8) {
+            let low = round_i32(_mm_loadu_ps(block.as_ptr()));
+            let high = round_i32(_mm_loadu_ps(block.as_ptr().add(4)));
+            let len = output.len();
+            _mm_storeu_si128(
+                output.as_mut_ptr().add(len) as *mut __m128i,
+                _mm_packs_epi32(low, high),
+            );
+            output.set_len(len + 8);
+        }
+    }
+
+    /// Scale the samples to 16 bits, clamp them and round them half away from zero. NaN becomes
+    /// zero.
+    #[inline(always)]
+    unsafe fn round_i32(samples: __m128) -> __m128i {
+        let scaled = _mm_mul_ps(samples, _mm_set1_ps(32768.0));
+        let scaled = _mm_and_ps(scaled, _mm_cmpord_ps(scaled, scaled));
+        let clamped = _mm_max_ps(
+            _mm_min_ps(scaled, _mm_set1_ps(32767.0)),
+            _mm_set1_ps(-32768.0),
+        );
+
+        // Truncate, then step away from zero where the remainder reaches a half
+        let truncated = _mm_cvttps_epi32(clamped);
+        let remainder = _mm_sub_ps(clamped, _mm_cvtepi32_ps(truncated));
+        let up = _mm_castps_si128(_mm_cmpge_ps(remainder, _mm_set1_ps(0.5)));
+        let down = _mm_castps_si128(_mm_cmple_ps(remainder, _mm_set1_ps(-0.5)));
+        _mm_add_epi32(_mm_sub_epi32(truncated, up), down)
+    }
+
+    /// Split the stereo frames of `samples`, whose length is a multiple of 8, into the reserved
+    /// capacity of `left` and `right`.
+    pub(super) unsafe fn deinterleave_stereo(
+        samples: &[f32],
+        left: &mut Vec<f32>,
+        right: &mut Vec<f32>,
+    ) {
+        for block in samples.chunks_exact(8) {
+            let first = _mm_loadu_ps(block.as_ptr());
+            let second = _mm_loadu_ps(block.as_ptr().add(4));
+            let len = left.len();
+            _mm_storeu_ps(
+                left.as_mut_ptr().add(len),
+                _mm_shuffle_ps::<0b10_00_10_00>(first, second),
+            );
+            _mm_storeu_ps(
+                right.as_mut_ptr().add(len),
+                _mm_shuffle_ps::<0b11_01_11_01>(first, second),
+            );
+            left.set_len(len + 4);
+            right.set_len(len + 4);
+        }
+    }
+
+    /// Interleave `left` and `right`, whose length is a multiple of 4, into the reserved
+    /// capacity of `output`.
+    pub(super) unsafe fn interleave_stereo(left: &[f32], right: &[f32], output: &mut Vec<f32>) {
+        for (left, right) in left.chunks_exact(4).zip(right.chunks_exact(4)) {
+            let left = _mm_loadu_ps(left.as_ptr());
+            let right = _mm_loadu_ps(right.as_ptr());
+            let len = output.len();
+            _mm_storeu_ps(output.as_mut_ptr().add(len), _mm_unpacklo_ps(left, right));
+            _mm_storeu_ps(
+                output.as_mut_ptr().add(len + 4),
+                _mm_unpackhi_ps(left, right),
+            );
+            output.set_len(len + 8);
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod arch {
+    use std::arch::aarch64::*;
+
+    /// Quantize `samples`, whose length is a multiple of 8, into the reserved capacity of
+    /// `output`.
+    pub(super) unsafe fn quantize_i16(samples: &[f32], output: &mut Vec<i16>) {
+        for block in samples.chunks_exact(8) {
+            // Rounding half away from zero saturates, and NaN becomes zero
+            let low = vcvtaq_s32_f32(vmulq_n_f32(vld1q_f32(block.as_ptr()), 32768.0));
+            let high = vcvtaq_s32_f32(vmulq_n_f32(vld1q_f32(block.as_ptr().add(4)), 32768.0));
+            let len = output.len();
+            vst1q_s16(
+                output.as_mut_ptr().add(len),
+                vcombine_s16(vqmovn_s32(low), vqmovn_s32(high)),
+            );
+            output.set_len(len + 8);
+        }
+    }
+
+    /// Split the stereo frames of `samples`, whose length is a multiple of 8, into the reserved
+    /// capacity of `left` and `right`.
+    pub(super) unsafe fn deinterleave_stereo(
+        samples: &[f32],
+        left: &mut Vec<f32>,
+        right: &mut Vec<f32>,
+    ) {
+        for block in samples.chunks_exact(8) {
+            let frames = vld2q_f32(block.as_ptr());
+            let len = left.len();
+            vst1q_f32(left.as_mut_ptr().add(len), frames.0);
+            vst1q_f32(right.as_mut_ptr().add(len), frames.1);
+            left.set_len(len + 4);
+            right.set_len(len + 4);
+        }
+    }
+
+    /// Interleave `left` and `right`, whose length is a multiple of 4, into the reserved
+    /// capacity of `output`.
+    pub(super) unsafe fn interleave_stereo(left: &[f32], right: &[f32], output: &mut Vec<f32>) {
+        for (left, right) in left.chunks_exact(4).zip(right.chunks_exact(4)) {
+            let len = output.len();
+            vst2q_f32(
+                output.as_mut_ptr().add(len),
+                float32x4x2_t(vld1q_f32(left.as_ptr()), vld1q_f32(right.as_ptr())),
+            );
+            output.set_len(len + 8);
+        }
+    }
+}
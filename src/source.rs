@@ -0,0 +1,86 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use symphonia::core::io::MediaSource;
+
+/// Adapts any seekable reader into a media source.
+pub(crate) struct ReaderSource<R> {
+    reader: R,
+    byte_len: Option<u64>,
+}
+impl<R: Read + Seek> ReaderSource<R> {
+    /// Wrap `reader`, measuring its length by seeking to its end.
+    pub(crate) fn new(mut reader: R) -> io::Result<Self> {
+        let position = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(position))?;
+
+        Ok(Self {
+            reader,
+            byte_len: Some(end),
+        })
+    }
+}
+impl<R: Read> Read for ReaderSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+impl<R: Seek> Seek for ReaderSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
+impl<R: Read + Seek + Send + Sync> MediaSource for ReaderSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.byte_len
+    }
+}
+
+/// Counts the bytes read from a media source, as the furthest position read so far, so data
+/// read again after seeking back is not counted twice.
+pub(crate) struct CountingSource {
+    inner: Box<dyn MediaSource>,
+    position: u64,
+    bytes_read: Arc<AtomicU64>,
+}
+impl CountingSource {
+    pub(crate) fn new(inner: Box<dyn MediaSource>, bytes_read: Arc<AtomicU64>) -> Self {
+        Self {
+            inner,
+            position: 0,
+            bytes_read,
+        }
+    }
+}
+impl Read for CountingSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        self.bytes_read.fetch_max(self.position, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+impl Seek for CountingSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
+impl MediaSource for CountingSource {
+    fn is_seekable(&self) -> bool {
+        self.inner.is_seekable()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.inner.byte_len()
+    }
+}
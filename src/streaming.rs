@@ -0,0 +1,208 @@
+use crate::{filter::FirLowpass, resampler::ReusableResampler, AudioConversionError};
+
+/// The stages of a streaming conversion that bring the audio to the target sample rate: the
+/// anti-alias filter followed by the resampler, both optional.
+pub(crate) struct StreamingStage {
+    channels: usize,
+    antialias_filter: Option<FirLowpass>,
+    resampler: Option<StreamingResampler>,
+    input_frames: usize,
+}
+impl StreamingStage {
+    pub(crate) fn new(
+        channels: usize,
+        antialias_filter: Option<FirLowpass>,
+        resampler: Option<StreamingResampler>,
+    ) -> Self {
+        Self {
+            channels,
+            antialias_filter,
+            resampler,
+            input_frames: 0,
+        }
+    }
+
+    /// Process interleaved input samples and emit every block of output that is ready.
+    pub(crate) fn push(
+        &mut self,
+        samples: &[f32],
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        self.input_frames += samples.len() / self.channels;
+
+        let filtered;
+        let samples = match self.antialias_filter.as_mut() {
+            Some(antialias_filter) => {
+                filtered = antialias_filter.process(samples);
+                &filtered
+            }
+            None => samples,
+        };
+
+        match self.resampler.as_mut() {
+            Some(resampler) => resampler.push(samples, on_block),
+            None => on_block(samples),
+        }
+    }
+
+    /// Flush the delayed output, and return the number of frames emitted in total and the
+    /// resampler, which may be reset and reused.
+    pub(crate) fn finish(
+        mut self,
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(usize, Option<Box<dyn ReusableResampler>>), AudioConversionError> {
+        if let Some(antialias_filter) = self.antialias_filter.as_mut() {
+            let flushed = antialias_filter.flush();
+            match self.resampler.as_mut() {
+                Some(resampler) => resampler.push(&flushed, on_block)?,
+                None => on_block(&flushed)?,
+            }
+        }
+
+        match self.resampler {
+            Some(resampler) => resampler
+                .finish(on_block)
+                .map(|(frames, resampler)| (frames, Some(resampler))),
+            None => Ok((self.input_frames, None)),
+        }
+    }
+}
+
+/// Resamples interleaved audio incrementally, handing every finished block of output to a
+/// callback instead of collecting the whole signal.
+///
+/// The output matches that of resampling the whole signal at once: the input is fed to the
+/// resampler in the same chunks, the leading delay frames are discarded when compensating, and
+/// the tail is flushed through the resampler.
+pub(crate) struct StreamingResampler {
+    resampler: Box<dyn ReusableResampler>,
+    channels: usize,
+    /// The queued input of each channel, shorter than the next chunk of the resampler.
+    pending: Vec<Vec<f32>>,
+    /// The number of leading output frames still to discard.
+    skip_frames: usize,
+    input_frames: usize,
+    output_frames: usize,
+    input_rate: u32,
+    output_rate: u32,
+}
+impl StreamingResampler {
+    pub(crate) fn new(
+        resampler: Box<dyn ReusableResampler>,
+        channels: usize,
+        input_rate: u32,
+        output_rate: u32,
+        compensate_delay: bool,
+    ) -> Self {
+        let skip_frames = if compensate_delay {
+            resampler.output_delay()
+        } else {
+            0
+        };
+
+        Self {
+            resampler,
+            channels,
+            pending: vec![Vec::new(); channels],
+            skip_frames,
+            input_frames: 0,
+            output_frames: 0,
+            input_rate,
+            output_rate,
+        }
+    }
+
+    /// Queue interleaved input samples and emit every block the resampler can produce from them.
+    pub(crate) fn push(
+        &mut self,
+        samples: &[f32],
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, sample) in self.pending.iter_mut().zip(frame.iter()) {
+                channel.push(*sample);
+            }
+        }
+        self.input_frames += samples.len() / self.channels;
+
+        while self.pending[0].len() >= self.resampler.input_frames_next() {
+            let chunk_size = self.resampler.input_frames_next();
+            let chunk: Vec<Vec<f32>> = self
+                .pending
+                .iter_mut()
+                .map(|channel| channel.drain(..chunk_size).collect())
+                .collect();
+
+            let resampled_chunk = self.resampler.process(&chunk, None)?;
+            self.emit(&resampled_chunk, usize::MAX, on_block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Resample the queued input as a partial chunk and flush the resampler until the output
+    /// covers the whole input, and return the number of frames emitted in total and the
+    /// resampler.
+    pub(crate) fn finish(
+        mut self,
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(usize, Box<dyn ReusableResampler>), AudioConversionError> {
+        let expected_frames = output_frames(self.input_frames, self.input_rate, self.output_rate);
+
+        while self.output_frames < expected_frames {
+            let resampled_chunk = if self.pending[0].is_empty() {
+                // Flush the delayed output once the input is exhausted
+                self.resampler.process_partial(None, None)?
+            } else {
+                let chunk: Vec<Vec<f32>> = self.pending.iter_mut().map(std::mem::take).collect();
+                self.resampler.process_partial(Some(&chunk), None)?
+            };
+            self.emit(&resampled_chunk, expected_frames, on_block)?;
+        }
+
+        Ok((self.output_frames, self.resampler))
+    }
+
+    /// Interleave the resampled chunk, minus the frames still to discard, and hand it to
+    /// `on_block`. No more than `max_frames` frames are emitted in total.
+    fn emit(
+        &mut self,
+        resampled_chunk: &[Vec<f32>],
+        max_frames: usize,
+        on_block: &mut dyn FnMut(&[f32]) -> Result<(), AudioConversionError>,
+    ) -> Result<(), AudioConversionError> {
+        let skip = self.skip_frames.min(resampled_chunk[0].len());
+        self.skip_frames -= skip;
+
+        let frames = (resampled_chunk[0].len() - skip).min(max_frames - self.output_frames);
+        if frames == 0 {
+            return Ok(());
+        }
+
+        let mut block = Vec::with_capacity(frames * self.channels);
+        for i in skip..skip + frames {
+            for channel in resampled_chunk.iter() {
+                block.push(channel[i]);
+            }
+        }
+        self.output_frames += frames;
+
+        on_block(&block)
+    }
+}
+
+/// The number of frames `input_frames` frames at `input_rate` last at `output_rate`, rounded to
+/// the nearest frame so the output duration matches the input.
+pub(crate) fn output_frames(input_frames: usize, input_rate: u32, output_rate: u32) -> usize {
+    let frames =
+        (input_frames as u64 * output_rate as u64 + input_rate as u64 / 2) / input_rate as u64;
+    frames as usize
+}
+
+/// The greatest common divisor of `a` and `b`.
+pub(crate) fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
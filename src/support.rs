@@ -0,0 +1,128 @@
+use symphonia::core::codecs::{
+    CodecType, CODEC_TYPE_AAC, CODEC_TYPE_AC4, CODEC_TYPE_ADPCM_G722, CODEC_TYPE_ADPCM_G726,
+    CODEC_TYPE_ADPCM_G726LE, CODEC_TYPE_ADPCM_IMA_QT, CODEC_TYPE_ADPCM_IMA_WAV,
+    CODEC_TYPE_ADPCM_MS, CODEC_TYPE_ALAC, CODEC_TYPE_ATRAC1, CODEC_TYPE_ATRAC3,
+    CODEC_TYPE_ATRAC3PLUS, CODEC_TYPE_ATRAC9, CODEC_TYPE_DCA, CODEC_TYPE_EAC3, CODEC_TYPE_FLAC,
+    CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3, CODEC_TYPE_MUSEPACK, CODEC_TYPE_OPUS,
+    CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_SPEEX,
+    CODEC_TYPE_VORBIS, CODEC_TYPE_WMA,
+};
+
+/// An audio codec WavUp may be able to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputCodec {
+    Aac,
+    Adpcm,
+    Alac,
+    Flac,
+    Mp1,
+    Mp2,
+    Mp3,
+    Pcm,
+    Vorbis,
+}
+impl InputCodec {
+    const ALL: [InputCodec; 9] = [
+        Self::Aac,
+        Self::Adpcm,
+        Self::Alac,
+        Self::Flac,
+        Self::Mp1,
+        Self::Mp2,
+        Self::Mp3,
+        Self::Pcm,
+        Self::Vorbis,
+    ];
+
+    /// A representative symphonia codec type of the codec.
+    fn codec_type(self) -> CodecType {
+        match self {
+            Self::Aac => CODEC_TYPE_AAC,
+            Self::Adpcm => CODEC_TYPE_ADPCM_IMA_WAV,
+            Self::Alac => CODEC_TYPE_ALAC,
+            Self::Flac => CODEC_TYPE_FLAC,
+            Self::Mp1 => CODEC_TYPE_MP1,
+            Self::Mp2 => CODEC_TYPE_MP2,
+            Self::Mp3 => CODEC_TYPE_MP3,
+            Self::Pcm => CODEC_TYPE_PCM_S16LE,
+            Self::Vorbis => CODEC_TYPE_VORBIS,
+        }
+    }
+}
+
+/// The file extensions of the inputs WavUp can decode with the enabled cargo features.
+pub fn supported_extensions() -> &'static [&'static str] {
+    &[
+        #[cfg(feature = "aac")]
+        "aac",
+        #[cfg(feature = "all-codecs")]
+        "aif",
+        #[cfg(feature = "all-codecs")]
+        "aifc",
+        #[cfg(feature = "all-codecs")]
+        "aiff",
+        #[cfg(feature = "all-codecs")]
+        "caf",
+        "flac",
+        #[cfg(feature = "isomp4")]
+        "m4a",
+        #[cfg(feature = "isomp4")]
+        "m4b",
+        "mka",
+        "mkv",
+        #[cfg(feature = "all-codecs")]
+        "mp1",
+        #[cfg(feature = "all-codecs")]
+        "mp2",
+        #[cfg(feature = "mp3")]
+        "mp3",
+        #[cfg(feature = "isomp4")]
+        "mp4",
+        "oga",
+        "ogg",
+        "wav",
+        "wave",
+        "webm",
+    ]
+}
+
+/// The codecs registered with the decoder.
+pub fn supported_codecs() -> Vec<InputCodec> {
+    let registry = symphonia::default::get_codecs();
+    InputCodec::ALL
+        .into_iter()
+        .filter(|codec| registry.get_codec(codec.codec_type()).is_some())
+        .collect()
+}
+
+/// Whether `codec` discards information when encoding, so its decoded output is only an
+/// approximation of the original signal.
+pub(crate) fn is_lossy(codec: CodecType) -> bool {
+    matches!(
+        codec,
+        CODEC_TYPE_AAC
+            | CODEC_TYPE_AC4
+            | CODEC_TYPE_ADPCM_G722
+            | CODEC_TYPE_ADPCM_G726
+            | CODEC_TYPE_ADPCM_G726LE
+            | CODEC_TYPE_ADPCM_IMA_QT
+            | CODEC_TYPE_ADPCM_IMA_WAV
+            | CODEC_TYPE_ADPCM_MS
+            | CODEC_TYPE_ATRAC1
+            | CODEC_TYPE_ATRAC3
+            | CODEC_TYPE_ATRAC3PLUS
+            | CODEC_TYPE_ATRAC9
+            | CODEC_TYPE_DCA
+            | CODEC_TYPE_EAC3
+            | CODEC_TYPE_MP1
+            | CODEC_TYPE_MP2
+            | CODEC_TYPE_MP3
+            | CODEC_TYPE_MUSEPACK
+            | CODEC_TYPE_OPUS
+            | CODEC_TYPE_PCM_ALAW
+            | CODEC_TYPE_PCM_MULAW
+            | CODEC_TYPE_SPEEX
+            | CODEC_TYPE_VORBIS
+            | CODEC_TYPE_WMA
+    )
+}
@@ -0,0 +1,96 @@
+use crate::chunk;
+use std::{io, path::Path};
+use symphonia::core::meta::{MetadataRevision, StandardTagKey};
+
+/// The descriptive tags of the input, found under whichever name its format uses, e.g. `TIT2` in
+/// an ID3v2 tag or `TITLE` in a Vorbis comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    /// The title of the track.
+    pub title: Option<String>,
+    /// The performing artist.
+    pub artist: Option<String>,
+    /// The album the track is part of.
+    pub album: Option<String>,
+    /// A free comment.
+    pub comment: Option<String>,
+}
+impl Tags {
+    /// Whether none of the tags is set.
+    pub fn is_empty(&self) -> bool {
+        self.fields().is_empty()
+    }
+
+    /// Set the tags that are not set yet from the standard tags of the metadata revision.
+    pub(crate) fn add_revision(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::Comment) => &mut self.comment,
+                _ => continue,
+            };
+            // RIFF INFO values keep their NUL terminator
+            if field.is_none() {
+                *field = Some(tag.value.to_string().trim_end_matches('\0').to_string());
+            }
+        }
+    }
+
+    /// The set tags with their Vorbis comment field names.
+    fn fields(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("TITLE", &self.title),
+            ("ARTIST", &self.artist),
+            ("ALBUM", &self.album),
+            ("COMMENT", &self.comment),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (name, value)))
+        .collect()
+    }
+
+    /// The tags as a Vorbis comment header, as stored in FLAC `VORBIS_COMMENT` blocks and Ogg Opus
+    /// comment headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `vendor` - The name of the encoder.
+    pub(crate) fn vorbis_comment(&self, vendor: &str) -> Vec<u8> {
+        let fields = self.fields();
+        let mut comment = Vec::new();
+        comment.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        comment.extend_from_slice(vendor.as_bytes());
+        comment.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+        for (name, value) in fields {
+            let field = format!("{}={}", name, value);
+            comment.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            comment.extend_from_slice(field.as_bytes());
+        }
+        comment
+    }
+}
+
+/// Append a `LIST` chunk of type `INFO` holding the tags to the finalized WAV file at `path`.
+pub(crate) fn append_info_chunk(path: impl AsRef<Path>, tags: &Tags) -> io::Result<()> {
+    let mut body = b"INFO".to_vec();
+    for (name, value) in tags.fields() {
+        let id = match name {
+            "TITLE" => b"INAM",
+            "ARTIST" => b"IART",
+            "ALBUM" => b"IPRD",
+            _ => b"ICMT",
+        };
+        // The values are NUL-terminated, and each subchunk starts at an even offset
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(value.len() as u32 + 1).to_le_bytes());
+        body.extend_from_slice(value.as_bytes());
+        body.push(0);
+        if value.len() % 2 == 0 {
+            body.push(0);
+        }
+    }
+
+    chunk::append_chunk(path, b"LIST", &body)
+}
@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+
+/// The length of the frames overlapped by the time stretcher, in seconds.
+const FRAME_SECS: f32 = 0.03;
+
+/// How far a frame may be taken from its nominal position to match the previous frame, in
+/// seconds.
+const TOLERANCE_SECS: f32 = 0.0075;
+
+/// The distance between the positions compared by the coarse pass of the search, in samples.
+const COARSE_STEP: usize = 4;
+
+/// Change the tempo of the interleaved samples without changing their pitch, by waveform
+/// similarity overlap-add (WSOLA).
+///
+/// The output is built from Hann-windowed frames overlapped by half their length. Each frame is
+/// taken from around the position of the input matching its place in the output, shifted so its
+/// waveform lines up with that of the previous frame, which avoids the phase jumps of a plain
+/// overlap-add.
+///
+/// # Arguments
+///
+/// * `tempo` - The speed of the output relative to the input, e.g. `1.5` for 1.5 times faster.
+pub(crate) fn stretch(samples: &[f32], channels: usize, sample_rate: u32, tempo: f32) -> Vec<f32> {
+    let frames = samples.len() / channels;
+    let frame_len = ((FRAME_SECS * sample_rate as f32) as usize / 2).max(1) * 2;
+    let hop = frame_len / 2;
+    let tolerance = (TOLERANCE_SECS * sample_rate as f32) as usize;
+    let output_frames = (frames as f64 / tempo as f64).round() as usize;
+
+    // Pad the input so the first frame fades in over silence and the last frames can be read
+    // whole, and search a mono mix of it
+    let mut input = vec![0.0; hop * channels];
+    input.extend_from_slice(&samples[..frames * channels]);
+    input.resize(input.len() + (frame_len + tolerance) * channels, 0.0);
+    let mono: Vec<f32> = input
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    let max_start = mono.len() - frame_len;
+
+    let window: Vec<f32> = (0..frame_len)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / frame_len as f32).cos())
+        .collect();
+
+    let synthesis_frames = (output_frames + hop).div_ceil(hop) + 1;
+    let mut output = vec![0.0; (synthesis_frames * hop + frame_len) * channels];
+    let mut previous_start: Option<usize> = None;
+    for index in 0..synthesis_frames {
+        let nominal = ((index * hop) as f64 * tempo as f64) as usize;
+        let start = match previous_start {
+            Some(previous) => best_match(
+                &mono,
+                (previous + hop).min(max_start),
+                nominal.saturating_sub(tolerance).min(max_start),
+                (nominal + tolerance).min(max_start),
+                hop,
+            ),
+            None => nominal.min(max_start),
+        };
+        previous_start = Some(start);
+
+        let input_frame = &input[start * channels..(start + frame_len) * channels];
+        let output_frame =
+            &mut output[index * hop * channels..(index * hop + frame_len) * channels];
+        for ((output, input), window) in output_frame
+            .chunks_exact_mut(channels)
+            .zip(input_frame.chunks_exact(channels))
+            .zip(window.iter())
+        {
+            for (output, input) in output.iter_mut().zip(input) {
+                *output += input * window;
+            }
+        }
+    }
+
+    output[hop * channels..(hop + output_frames) * channels].to_vec()
+}
+
+/// The start, between `first` and `last`, of the `len` samples of `mono` most similar to those
+/// at `target`, found by a coarse search refined around its best position.
+fn best_match(mono: &[f32], target: usize, first: usize, last: usize, len: usize) -> usize {
+    let reference = &mono[target..target + len];
+    let correlation = |start: usize| -> f32 {
+        reference
+            .iter()
+            .zip(&mono[start..start + len])
+            .map(|(a, b)| a * b)
+            .sum()
+    };
+    let best = |positions: &mut dyn Iterator<Item = usize>| {
+        positions
+            .map(|start| (correlation(start), start))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(first, |(_, start)| start)
+    };
+
+    let coarse = best(&mut (first..=last).step_by(COARSE_STEP));
+    best(
+        &mut (coarse.saturating_sub(COARSE_STEP - 1).max(first)
+            ..=(coarse + COARSE_STEP - 1).min(last)),
+    )
+}
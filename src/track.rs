@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+/// The placeholder of the output path replaced by the track index when converting every track.
+const TRACK_PLACEHOLDER: &str = "{track}";
+
+/// Selects the audio track(s) of the input to convert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackSelector {
+    /// The track at this index, in the order the container lists its tracks.
+    Index(usize),
+    /// The first audio track tagged with this language, e.g. `"eng"`. Compared case-insensitively.
+    Language(String),
+    /// Every audio track, each written to its own WAV file.
+    All,
+}
+
+/// The output path of the track at `index` when converting every track.
+///
+/// The `{track}` placeholder of `output_path` is replaced by the track index, e.g.
+/// `out-{track}.wav` becomes `out-1.wav`. Without a placeholder, the index is appended to the
+/// file stem, e.g. `out.wav` becomes `out_1.wav`.
+pub(crate) fn track_output_path(output_path: &Path, index: usize) -> PathBuf {
+    numbered_output_path(output_path, TRACK_PLACEHOLDER, &index.to_string())
+}
+
+/// The output path with `placeholder` replaced by `number`, or with `_{number}` appended to its
+/// file stem if it has no placeholder.
+pub(crate) fn numbered_output_path(output_path: &Path, placeholder: &str, number: &str) -> PathBuf {
+    if let Some(path) = output_path.to_str() {
+        if path.contains(placeholder) {
+            return PathBuf::from(path.replace(placeholder, number));
+        }
+    }
+
+    let mut file_name = output_path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!("_{}", number));
+    if let Some(extension) = output_path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output_path.with_file_name(file_name)
+}
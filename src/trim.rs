@@ -0,0 +1,69 @@
+/// The length of the window over which the level compared to the silence threshold is measured,
+/// in seconds.
+const RMS_WINDOW_SECS: f32 = 0.01;
+
+/// How silence is detected and how much of it is kept when it is trimmed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimOptions {
+    /// The RMS level below which the audio is considered silent, in dBFS, measured over 10 ms
+    /// windows on the loudest channel.
+    pub threshold_db: f32,
+    /// The length of the silence kept next to the sound, in seconds.
+    pub padding_secs: f32,
+}
+impl Default for TrimOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -40.0,
+            padding_secs: 0.5,
+        }
+    }
+}
+impl TrimOptions {
+    /// The linear amplitude of the threshold.
+    pub(crate) fn threshold(&self) -> f32 {
+        10f32.powf(self.threshold_db / 20.0)
+    }
+
+    /// The number of frames of padding at `sample_rate`.
+    pub(crate) fn padding_frames(&self, sample_rate: u32) -> usize {
+        (self.padding_secs.max(0.0) * sample_rate as f32) as usize
+    }
+}
+
+/// The RMS level of each frame of the interleaved `samples`, measured over the window of
+/// `RMS_WINDOW_SECS` centred on it and taking the loudest channel.
+///
+/// A single click then barely moves the level, unlike when each sample is compared to the
+/// threshold on its own.
+pub(crate) fn rms_envelope(samples: &[f32], channels: usize, sample_rate: u32) -> Vec<f32> {
+    let frames = samples.len() / channels;
+    let half_window = (RMS_WINDOW_SECS * sample_rate as f32 / 2.0) as usize;
+
+    // Slide the window over the frames, keeping the sum of squares of each channel in it
+    let mut sums = vec![0.0f64; channels];
+    let (mut window_start, mut window_end) = (0, 0);
+    let mut envelope = Vec::with_capacity(frames);
+    for frame in 0..frames {
+        while window_end < (frame + half_window + 1).min(frames) {
+            let samples = &samples[window_end * channels..(window_end + 1) * channels];
+            for (sum, sample) in sums.iter_mut().zip(samples) {
+                *sum += (*sample as f64).powi(2);
+            }
+            window_end += 1;
+        }
+        while window_start < frame.saturating_sub(half_window) {
+            let samples = &samples[window_start * channels..(window_start + 1) * channels];
+            for (sum, sample) in sums.iter_mut().zip(samples) {
+                *sum -= (*sample as f64).powi(2);
+            }
+            window_start += 1;
+        }
+
+        let len = (window_end - window_start) as f64;
+        let loudest = sums.iter().fold(0.0f64, |loudest, sum| loudest.max(*sum));
+        envelope.push((loudest.max(0.0) / len).sqrt() as f32);
+    }
+
+    envelope
+}
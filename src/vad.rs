@@ -0,0 +1,96 @@
+use std::{ops::Range, time::Duration};
+
+/// How speech is told apart from silence and noise by the energy-based voice activity detection
+/// of `AudioConverterBuilder::with_vad` and `AudioConverter::detect_speech`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VadOptions {
+    /// The level of the analysis frames above which they are speech, in dBFS of their RMS.
+    pub threshold_db: f32,
+    /// The length of the analysis frames, in seconds.
+    pub frame_secs: f32,
+    /// The minimum length of speech, in seconds. Shorter bursts, e.g. clicks, are dropped.
+    pub min_speech_secs: f32,
+    /// The minimum length of a pause between two speech segments, in seconds. Shorter pauses are
+    /// kept inside the segment.
+    pub min_silence_secs: f32,
+    /// The length of audio kept before and after each speech segment, in seconds.
+    pub padding_secs: f32,
+}
+impl Default for VadOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -45.0,
+            frame_secs: 0.03,
+            min_speech_secs: 0.25,
+            min_silence_secs: 0.3,
+            padding_secs: 0.2,
+        }
+    }
+}
+
+/// A segment of speech detected by `AudioConverter::detect_speech`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeechSegment {
+    /// The offset of the start of the speech in the converted audio.
+    pub start: Duration,
+    /// The offset of the end of the speech in the converted audio.
+    pub end: Duration,
+}
+
+/// The frames of each segment of speech of the interleaved samples, padded as set by `options`.
+pub(crate) fn detect_speech(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    options: &VadOptions,
+) -> Vec<Range<usize>> {
+    let to_frames = |secs: f32| (secs.max(0.0) * sample_rate as f32) as usize;
+    let frames = samples.len() / channels;
+    let window = to_frames(options.frame_secs).max(1);
+    let threshold = 10f32.powf(options.threshold_db / 10.0);
+
+    // Group the analysis windows whose mean power exceeds the threshold, bridging short pauses
+    let mut speech: Vec<Range<usize>> = Vec::new();
+    for (index, window_samples) in samples.chunks(window * channels).enumerate() {
+        let power = window_samples
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f32>()
+            / window_samples.len() as f32;
+        if power < threshold {
+            continue;
+        }
+
+        let start = index * window;
+        let end = (start + window).min(frames);
+        match speech.last_mut() {
+            Some(segment) if start - segment.end < to_frames(options.min_silence_secs) => {
+                segment.end = end
+            }
+            _ => speech.push(start..end),
+        }
+    }
+
+    let min_speech = to_frames(options.min_speech_secs);
+    let padding = to_frames(options.padding_secs);
+    let mut previous_end = 0;
+    speech
+        .into_iter()
+        .filter(|segment| segment.len() >= min_speech)
+        .map(|segment| {
+            let start = segment.start.saturating_sub(padding).max(previous_end);
+            let end = (segment.end + padding).min(frames);
+            previous_end = end;
+            start..end
+        })
+        .collect()
+}
+
+/// The interleaved samples of the speech segments, one after the other.
+pub(crate) fn keep_speech(samples: &[f32], channels: usize, speech: &[Range<usize>]) -> Vec<f32> {
+    speech
+        .iter()
+        .flat_map(|segment| &samples[segment.start * channels..segment.end * channels])
+        .copied()
+        .collect()
+}
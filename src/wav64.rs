@@ -0,0 +1,281 @@
+use crate::raw::{self, RawFormat};
+use hound::{SampleFormat, WavSpec};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The largest data chunk written to a RIFF WAV file, leaving room for the header chunks below the
+/// 4 GB limit of its 32-bit sizes.
+pub(crate) const MAX_RIFF_DATA_LEN: u64 = u32::MAX as u64 - 1024;
+
+/// The largest fmt chunk body read back, far above the 40 bytes of `WAVE_FORMAT_EXTENSIBLE`, so a
+/// corrupt size cannot make `read_header` allocate gigabytes.
+const MAX_FMT_LEN: u64 = 1024;
+
+/// The GUIDs of the Wave64 `riff`, `wave`, `fmt ` and `data` chunks.
+const W64_RIFF: [u8; 16] = *b"riff\x2e\x91\xcf\x11\xa5\xd6\x28\xdb\x04\xc1\x00\x00";
+const W64_WAVE: [u8; 16] = *b"wave\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+const W64_FMT: [u8; 16] = *b"fmt \xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+const W64_DATA: [u8; 16] = *b"data\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+
+/// The WAV variant with 64-bit sizes written when the output does not fit in a RIFF WAV file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LargeFileFormat {
+    /// RF64 (EBU Tech 3306), a RIFF WAV file whose sizes are stored in a `ds64` chunk. Read by
+    /// most current audio tools.
+    #[default]
+    Rf64,
+    /// Sony Wave64, which replaces the RIFF chunk ids with GUIDs and all sizes with 64-bit ones.
+    W64,
+}
+
+/// Writes PCM samples in an RF64 or Wave64 file.
+///
+/// The sizes in the header are written as zero, then set by `finalize` once the length of the
+/// audio is known.
+pub(crate) struct Wav64Writer<W: Write + Seek> {
+    writer: W,
+    format: LargeFileFormat,
+    spec: WavSpec,
+    /// The number of samples written.
+    samples: u64,
+    /// The position of the header in the writer.
+    start: u64,
+}
+impl<W: Write + Seek> Wav64Writer<W> {
+    /// Write the header and return the writer of the samples.
+    pub(crate) fn new(mut writer: W, spec: WavSpec, format: LargeFileFormat) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        let mut wav64_writer = Self {
+            writer,
+            format,
+            spec,
+            samples: 0,
+            start,
+        };
+        let header = wav64_writer.header();
+        wav64_writer.writer.write_all(&header)?;
+
+        Ok(wav64_writer)
+    }
+
+    /// Quantize the interleaved samples to the sample format of the spec and write them.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.writer
+            .write_all(&raw::encode_raw(samples, raw_format(self.spec)))?;
+        self.samples += samples.len() as u64;
+        Ok(())
+    }
+
+    /// The number of samples written.
+    pub(crate) fn len(&self) -> u64 {
+        self.samples
+    }
+
+    /// Pad the data chunk and write the final header.
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        let padding = self.padding();
+        self.writer.write_all(&vec![0; padding as usize])?;
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        let header = self.header();
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// The number of bytes of sample data.
+    fn data_len(&self) -> u64 {
+        self.samples * (self.spec.bits_per_sample / 8) as u64
+    }
+
+    /// The number of zero bytes aligning the end of the data chunk, to 2 bytes in RF64 files and
+    /// 8 bytes in Wave64 files.
+    fn padding(&self) -> u64 {
+        let alignment = match self.format {
+            LargeFileFormat::Rf64 => 2,
+            LargeFileFormat::W64 => 8,
+        };
+        (alignment - self.data_len() % alignment) % alignment
+    }
+
+    /// The chunks before the sample data, with the sizes of the samples written so far.
+    fn header(&self) -> Vec<u8> {
+        let fmt = fmt_chunk(self.spec);
+        let data_len = self.data_len();
+        let mut header = Vec::new();
+
+        match self.format {
+            LargeFileFormat::Rf64 => {
+                let riff_len = 4 + 36 + 8 + fmt.len() as u64 + 8 + data_len + self.padding();
+                let frames = self.samples / self.spec.channels as u64;
+
+                // The 32-bit sizes are set to -1 and replaced by those of the ds64 chunk
+                header.extend_from_slice(b"RF64");
+                header.extend_from_slice(&u32::MAX.to_le_bytes());
+                header.extend_from_slice(b"WAVE");
+                header.extend_from_slice(b"ds64");
+                header.extend_from_slice(&28u32.to_le_bytes());
+                header.extend_from_slice(&riff_len.to_le_bytes());
+                header.extend_from_slice(&data_len.to_le_bytes());
+                header.extend_from_slice(&frames.to_le_bytes());
+                header.extend_from_slice(&0u32.to_le_bytes());
+                header.extend_from_slice(b"fmt ");
+                header.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+                header.extend_from_slice(&fmt);
+                header.extend_from_slice(b"data");
+                header.extend_from_slice(&u32::MAX.to_le_bytes());
+            }
+            LargeFileFormat::W64 => {
+                // Sizes include the 24 bytes of the GUID and the size itself
+                let fmt_len = 24 + fmt.len() as u64;
+                let file_len = 40 + fmt_len + 24 + data_len + self.padding();
+
+                header.extend_from_slice(&W64_RIFF);
+                header.extend_from_slice(&file_len.to_le_bytes());
+                header.extend_from_slice(&W64_WAVE);
+                header.extend_from_slice(&W64_FMT);
+                header.extend_from_slice(&fmt_len.to_le_bytes());
+                header.extend_from_slice(&fmt);
+                header.extend_from_slice(&W64_DATA);
+                header.extend_from_slice(&(24 + data_len).to_le_bytes());
+            }
+        }
+        header
+    }
+}
+
+/// The format, spec and number of samples declared by the header of the RF64 or Wave64 file read
+/// by `reader`, or `None` if it is neither.
+pub(crate) fn read_header<R: Read>(
+    mut reader: R,
+) -> io::Result<Option<(LargeFileFormat, WavSpec, u64)>> {
+    let mut magic = [0u8; 16];
+    reader.read_exact(&mut magic[..4])?;
+    let (format, fmt, data_len) = match &magic[..4] {
+        b"RF64" => {
+            // RF64 and WAVE, then the ds64 chunk header, sizes and table length
+            let mut ds64 = [0u8; 44];
+            reader.read_exact(&mut ds64)?;
+            let data_len = u64::from_le_bytes(ds64[24..32].try_into().unwrap());
+
+            let mut fmt_header = [0u8; 8];
+            reader.read_exact(&mut fmt_header)?;
+            let fmt_len = u32::from_le_bytes(fmt_header[4..].try_into().unwrap());
+            let fmt = read_fmt(&mut reader, fmt_len as u64)?;
+            (LargeFileFormat::Rf64, fmt, data_len)
+        }
+        b"riff" => {
+            reader.read_exact(&mut magic[4..])?;
+            if magic != W64_RIFF {
+                return Ok(None);
+            }
+
+            // The file size and the wave GUID, then the fmt chunk. Wave64 chunk sizes include
+            // the 24-byte GUID and size header
+            let mut riff = [0u8; 48];
+            reader.read_exact(&mut riff)?;
+            let fmt_len = u64::from_le_bytes(riff[40..].try_into().unwrap());
+            let fmt = read_fmt(&mut reader, chunk_body_len(fmt_len)?)?;
+
+            let mut data = [0u8; 24];
+            reader.read_exact(&mut data)?;
+            let data_len = chunk_body_len(u64::from_le_bytes(data[16..].try_into().unwrap()))?;
+            (LargeFileFormat::W64, fmt, data_len)
+        }
+        _ => return Ok(None),
+    };
+
+    if fmt.len() < 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the fmt chunk is too short",
+        ));
+    }
+    let format_tag = match u16::from_le_bytes([fmt[0], fmt[1]]) {
+        // The sub format of WAVE_FORMAT_EXTENSIBLE starts with the format tag
+        0xfffe if fmt.len() >= 26 => u16::from_le_bytes([fmt[24], fmt[25]]),
+        format_tag => format_tag,
+    };
+    let spec = WavSpec {
+        channels: u16::from_le_bytes([fmt[2], fmt[3]]),
+        sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+        bits_per_sample: u16::from_le_bytes([fmt[14], fmt[15]]),
+        sample_format: match format_tag {
+            3 => SampleFormat::Float,
+            _ => SampleFormat::Int,
+        },
+    };
+    let samples = data_len / (spec.bits_per_sample / 8).max(1) as u64;
+
+    Ok(Some((format, spec, samples)))
+}
+
+/// The size of the body of a Wave64 chunk whose declared size, `chunk_len`, includes its header.
+fn chunk_body_len(chunk_len: u64) -> io::Result<u64> {
+    chunk_len.checked_sub(24).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "a Wave64 chunk size of {} is below its header size",
+                chunk_len
+            ),
+        )
+    })
+}
+
+/// Read the body of the fmt chunk, `fmt_len` bytes long.
+fn read_fmt<R: Read>(reader: &mut R, fmt_len: u64) -> io::Result<Vec<u8>> {
+    if fmt_len > MAX_FMT_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the fmt chunk is {} bytes long", fmt_len),
+        ));
+    }
+    let mut fmt = vec![0u8; fmt_len as usize];
+    reader.read_exact(&mut fmt)?;
+    Ok(fmt)
+}
+
+/// The body of the `fmt ` chunk of the spec, using `WAVE_FORMAT_EXTENSIBLE` for more than two
+/// channels or more than 16 bits per sample like hound does.
+fn fmt_chunk(spec: WavSpec) -> Vec<u8> {
+    let format_tag: u16 = match spec.sample_format {
+        SampleFormat::Int => 1,
+        SampleFormat::Float => 3,
+    };
+    let block_align = spec.channels * (spec.bits_per_sample / 8);
+    let extensible = spec.channels > 2 || spec.bits_per_sample > 16;
+
+    let mut fmt = Vec::with_capacity(40);
+    fmt.extend_from_slice(&if extensible { 0xfffe } else { format_tag }.to_le_bytes());
+    fmt.extend_from_slice(&spec.channels.to_le_bytes());
+    fmt.extend_from_slice(&spec.sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(spec.sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&spec.bits_per_sample.to_le_bytes());
+
+    if extensible {
+        // The channel mask assigns the first speaker positions in order
+        let channel_mask = match spec.channels {
+            1..=18 => (1u32 << spec.channels) - 1,
+            _ => 0,
+        };
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&spec.bits_per_sample.to_le_bytes());
+        fmt.extend_from_slice(&channel_mask.to_le_bytes());
+        fmt.extend_from_slice(&(format_tag as u32).to_le_bytes());
+        fmt.extend_from_slice(b"\x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71");
+    }
+    fmt
+}
+
+/// The raw format of samples stored with the spec.
+fn raw_format(spec: WavSpec) -> RawFormat {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, _) => RawFormat::F32LE,
+        (_, 8) => RawFormat::U8,
+        (_, 24) => RawFormat::S24LE,
+        (_, 32) => RawFormat::S32LE,
+        _ => RawFormat::S16LE,
+    }
+}
@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+
+/// Frequency weighting applied to the signal analysed by silence detection.
+///
+/// The weighting only affects the decision of where the audio is trimmed; the output samples are
+/// never filtered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Weighting {
+    /// Compare the raw sample amplitudes against the threshold.
+    #[default]
+    None,
+    /// Apply an A-weighting filter first, so the trim decision tracks perceived loudness and
+    /// low-frequency rumble is not mistaken for content.
+    AWeighting,
+}
+
+// Pole frequencies of the IEC 61672 A-weighting curve, in Hz.
+const A_WEIGHTING_F1: f64 = 20.598_997;
+const A_WEIGHTING_F2: f64 = 107.652_65;
+const A_WEIGHTING_F3: f64 = 737.862_23;
+const A_WEIGHTING_F4: f64 = 12_194.217;
+
+/// Return an A-weighted copy of the interleaved `samples`, normalized to 0 dB at 1 kHz.
+pub(crate) fn a_weighted(samples: &[f32], channels: usize, sample_rate: u32) -> Vec<f32> {
+    let sample_rate = sample_rate as f64;
+
+    // A-weighting is the product of s^4 / ((s + w1)^2 (s + w2) (s + w3) (s + w4)^2), realized here
+    // as a cascade of first-order sections discretized with the bilinear transform.
+    let sections = [
+        FirstOrder::highpass(A_WEIGHTING_F1, sample_rate),
+        FirstOrder::highpass(A_WEIGHTING_F1, sample_rate),
+        FirstOrder::highpass(A_WEIGHTING_F2, sample_rate),
+        FirstOrder::highpass(A_WEIGHTING_F3, sample_rate),
+        FirstOrder::lowpass(A_WEIGHTING_F4, sample_rate),
+        FirstOrder::lowpass(A_WEIGHTING_F4, sample_rate),
+    ];
+    let gain = 1.0
+        / sections
+            .iter()
+            .map(|section| section.magnitude(1000.0, sample_rate))
+            .product::<f64>();
+
+    // Each channel keeps its own filter state.
+    let mut filters = vec![sections; channels];
+    let mut weighted = Vec::with_capacity(samples.len());
+    for frame in samples.chunks(channels) {
+        for (sample, sections) in frame.iter().zip(filters.iter_mut()) {
+            let y = sections
+                .iter_mut()
+                .fold(*sample as f64, |x, section| section.process(x));
+            weighted.push((y * gain) as f32);
+        }
+    }
+
+    weighted
+}
+
+/// A first-order IIR section: `y[n] = b0 * x[n] + b1 * x[n - 1] - a1 * y[n - 1]`.
+#[derive(Debug, Clone, Copy)]
+struct FirstOrder {
+    b0: f64,
+    b1: f64,
+    a1: f64,
+    x1: f64,
+    y1: f64,
+}
+impl FirstOrder {
+    /// Discretize `s / (s + w)` with the pole at `pole_hz`.
+    fn highpass(pole_hz: f64, sample_rate: f64) -> Self {
+        let k = 2.0 * sample_rate;
+        let w = 2.0 * PI * pole_hz;
+        Self {
+            b0: k / (k + w),
+            b1: -k / (k + w),
+            a1: (w - k) / (k + w),
+            x1: 0.0,
+            y1: 0.0,
+        }
+    }
+
+    /// Discretize `w / (s + w)` with the pole at `pole_hz`.
+    fn lowpass(pole_hz: f64, sample_rate: f64) -> Self {
+        let k = 2.0 * sample_rate;
+        let w = 2.0 * PI * pole_hz;
+        Self {
+            b0: w / (k + w),
+            b1: w / (k + w),
+            a1: (w - k) / (k + w),
+            x1: 0.0,
+            y1: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 - self.a1 * self.y1;
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+
+    /// The magnitude response of the section at `freq` Hz.
+    fn magnitude(&self, freq: f64, sample_rate: f64) -> f64 {
+        let omega = 2.0 * PI * freq / sample_rate;
+        let (sin, cos) = omega.sin_cos();
+        let numerator = (self.b0 + self.b1 * cos).hypot(self.b1 * sin);
+        let denominator = (1.0 + self.a1 * cos).hypot(self.a1 * sin);
+        numerator / denominator
+    }
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone};
+use wavup::{AudioConversionError, AudioConverterBuilder, OutputFormat};
+
+const RATE: u32 = 8000;
+
+/// The number of frames of a mono block of 256 bytes: two per byte after the 4-byte header,
+/// plus the one stored in the header.
+const BLOCK_FRAMES: usize = 505;
+
+/// Encode the mono `samples` to IMA ADPCM WAV output with 256-byte blocks and return its path.
+fn encode(name: &str, samples: &[f32]) -> std::path::PathBuf {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_output_format(OutputFormat::WavAdpcm { block_align: 256 })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, 1))
+        .unwrap();
+    output_path
+}
+
+/// Decode the WAV file at `path` with symphonia into 32-bit float samples.
+fn decode(path: &std::path::Path, name: &str) -> Vec<f32> {
+    let decoded_path = tmp_path(name);
+    AudioConverterBuilder::new(&decoded_path, RATE)
+        .with_input_path(path)
+        .with_bits_per_sample(32)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    read_wav(decoded_path).1
+}
+
+#[test]
+fn adpcm_output_decodes_back_to_the_input() {
+    let samples = tone(RATE, 1, 2.0, 440.0);
+    let output_path = encode("adpcm_round_trip.wav", &samples);
+    let decoded = decode(&output_path, "adpcm_round_trip_decoded.wav");
+
+    // The codes follow the decoded signal, so the error stays well below the signal
+    let noise: f32 = decoded
+        .iter()
+        .zip(&samples)
+        .map(|(decoded, sample)| (decoded - sample).powi(2))
+        .sum();
+    let signal: f32 = samples.iter().map(|sample| sample * sample).sum();
+    let snr_db = 10.0 * (signal / noise).log10();
+    assert!(snr_db > 25.0, "SNR of {:.1} dB", snr_db);
+}
+
+#[test]
+fn fact_chunk_counts_the_frames_without_the_padding_of_the_last_block() {
+    // Two blocks, the second one padded with silence
+    let frames = BLOCK_FRAMES + 100;
+    let samples = tone(RATE, 1, frames as f32 / RATE as f32, 440.0);
+    let output_path = encode("adpcm_fact.wav", &samples);
+    let bytes = std::fs::read(&output_path).unwrap();
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 0x11);
+    assert_eq!(u16::from_le_bytes([bytes[32], bytes[33]]), 256);
+    assert_eq!(
+        u16::from_le_bytes([bytes[38], bytes[39]]) as usize,
+        BLOCK_FRAMES
+    );
+    assert_eq!(&bytes[40..44], b"fact");
+    assert_eq!(read_u32(48) as usize, frames);
+    assert_eq!(&bytes[52..56], b"data");
+    assert_eq!(read_u32(56), 2 * 256);
+    assert_eq!(bytes.len(), 60 + 2 * 256);
+
+    // symphonia ignores the fact chunk and decodes the padding, which settles to silence once the
+    // step size has adapted to it
+    let decoded = decode(&output_path, "adpcm_fact_decoded.wav");
+    assert_eq!(decoded.len(), 2 * BLOCK_FRAMES);
+    assert!(decoded[frames + 50..].iter().all(|sample| sample.abs() < 0.01));
+}
+
+#[test]
+fn block_aligns_without_whole_blocks_are_rejected() {
+    // Too short for the header, and not a whole number of 4-byte groups after it
+    for block_align in [4, 254] {
+        let result = AudioConverterBuilder::new(tmp_path("adpcm_invalid.wav"), RATE)
+            .with_output_format(OutputFormat::WavAdpcm { block_align })
+            .with_overwrite(true)
+            .build()
+            .and_then(|converter| {
+                converter.convert_audio_from_bytes(&float_wav(&tone(RATE, 1, 0.1, 440.0), RATE, 1))
+            });
+        assert!(
+            matches!(result, Err(AudioConversionError::UnsupportedFormat(_))),
+            "block align {}: {:?}",
+            block_align,
+            result
+        );
+    }
+}
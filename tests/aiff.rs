@@ -0,0 +1,127 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use std::io::Cursor;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+};
+use wavup::{AudioConverterBuilder, OutputFormat};
+
+/// The size of the `FORM` header, the `COMM` chunk and the `SSND` chunk header.
+const HEADER_LEN: usize = 54;
+
+/// Convert the interleaved `samples` to AIFF output with `bits_per_sample` bits, verifying its
+/// `COMM` chunk, and return the bytes of the file.
+fn encode(name: &str, samples: &[f32], sample_rate: u32, channels: u16, bits: u16) -> Vec<u8> {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, sample_rate)
+        .with_output_format(OutputFormat::Aiff)
+        .with_bits_per_sample(bits)
+        .with_verify_output(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, sample_rate, channels))
+        .unwrap();
+    std::fs::read(output_path).unwrap()
+}
+
+/// Decode the AIFF file `bytes` with symphonia into interleaved samples.
+fn decode(bytes: Vec<u8>) -> Vec<f32> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let mut reader =
+        symphonia::default::formats::AiffReader::try_new(source, &FormatOptions::default())
+            .unwrap();
+    let mut decoder = symphonia::default::get_codecs()
+        .make(
+            &reader.default_track().unwrap().codec_params,
+            &DecoderOptions::default(),
+        )
+        .unwrap();
+
+    let mut samples = Vec::new();
+    while let Ok(packet) = reader.next_packet() {
+        let decoded = decoder.decode(&packet).unwrap();
+        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        buf.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buf.samples());
+    }
+    samples
+}
+
+#[test]
+fn comm_chunk_declares_the_layout_and_the_80_bit_rate() {
+    let samples = tone(44100, 2, 0.5, 440.0);
+    let bytes = encode("aiff_comm.aiff", &samples, 44100, 2, 16);
+
+    assert_eq!(&bytes[..4], b"FORM");
+    assert_eq!(&bytes[8..12], b"AIFF");
+    assert_eq!(&bytes[12..16], b"COMM");
+    assert_eq!(bytes[16..20], 18u32.to_be_bytes());
+    assert_eq!(bytes[20..22], 2u16.to_be_bytes());
+    assert_eq!(bytes[22..26], (samples.len() as u32 / 2).to_be_bytes());
+    assert_eq!(bytes[26..28], 16u16.to_be_bytes());
+    // 44100 is 0xAC44 << 15, stored with the explicit integer bit of the mantissa
+    assert_eq!(bytes[28..38], [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&bytes[38..42], b"SSND");
+    assert_eq!(bytes.len(), HEADER_LEN + 2 * samples.len());
+
+    // Rates of other magnitudes move the exponent
+    let bytes = encode("aiff_comm_8k.aiff", &tone(8000, 1, 0.1, 440.0), 8000, 1, 16);
+    assert_eq!(bytes[28..38], [0x40, 0x0B, 0xFA, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn samples_are_big_endian_at_8_and_24_bits() {
+    let samples = [0.5, -0.5, 0.25, 0.0];
+    let bytes = encode("aiff_8_bit.aiff", &samples, 8000, 1, 8);
+    assert_eq!(bytes[HEADER_LEN..], [0x40, 0xC0, 0x20, 0x00]);
+
+    let bytes = encode("aiff_24_bit.aiff", &samples, 8000, 1, 24);
+    assert_eq!(
+        bytes[HEADER_LEN..],
+        [0x40, 0, 0, 0xC0, 0, 0, 0x20, 0, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn odd_length_sound_data_is_padded() {
+    let bytes = encode("aiff_pad.aiff", &[0.5, -0.5, 0.25], 8000, 1, 8);
+
+    // The pad byte counts toward the form but not the SSND chunk
+    assert_eq!(bytes.len(), HEADER_LEN + 3 + 1);
+    assert_eq!(bytes.last(), Some(&0));
+    assert_eq!(bytes[4..8], (bytes.len() as u32 - 8).to_be_bytes());
+    assert_eq!(bytes[42..46], (8u32 + 3).to_be_bytes());
+}
+
+#[test]
+fn aiff_output_decodes_back_to_the_input() {
+    let samples = tone(48000, 2, 0.5, 440.0);
+    for bits in [8, 16, 24] {
+        let bytes = encode(
+            &format!("aiff_round_trip_{}.aiff", bits),
+            &samples,
+            48000,
+            2,
+            bits,
+        );
+        let decoded = decode(bytes);
+        assert_eq!(decoded.len(), samples.len(), "{} bits", bits);
+
+        // Within the rounding of the quantization
+        let step = 1.0 / (1 << (bits - 1)) as f32;
+        for (decoded, sample) in decoded.iter().zip(&samples) {
+            assert!(
+                (decoded - sample).abs() <= step / 2.0 + 1e-6,
+                "{} bits: {} decoded as {}",
+                bits,
+                sample,
+                decoded
+            );
+        }
+    }
+}
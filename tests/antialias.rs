@@ -0,0 +1,55 @@
+mod common;
+
+use common::{float_wav, rms, tmp_path};
+use std::f64::consts::PI;
+use wavup::AudioConverterBuilder;
+
+const INPUT_RATE: u32 = 48000;
+const OUTPUT_RATE: u32 = 8000;
+
+/// Two seconds of a linear sweep from 100 Hz to 20 kHz at 48 kHz.
+fn sweep() -> Vec<f32> {
+    let len = 2 * INPUT_RATE as usize;
+    (0..len)
+        .map(|i| {
+            // The phase of a sweep rising by 9950 Hz per second, computed in f64 since the phase
+            // grows too large for f32 to keep the sweep clean
+            let t = i as f64 / INPUT_RATE as f64;
+            let cycles = 100.0 * t + 9950.0 / 2.0 * t * t;
+            0.5 * (2.0 * PI * cycles.fract()).sin() as f32
+        })
+        .collect()
+}
+
+/// The level of the output while the sweep is between 4.3 kHz and 6 kHz, just above the output
+/// Nyquist frequency where the resampler alone lets aliasing through, relative to its level while
+/// the sweep is below 3 kHz, in dB.
+fn aliasing_db(antialias: bool) -> f32 {
+    let output_path = tmp_path(&format!("antialias_{}.wav", antialias));
+    AudioConverterBuilder::new(&output_path, OUTPUT_RATE)
+        .with_antialias_filter(antialias)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&sweep(), INPUT_RATE, 1))
+        .unwrap();
+    let (_, samples) = common::read_wav(output_path);
+
+    let at = |secs: f32| (secs * OUTPUT_RATE as f32) as usize;
+    let in_band = rms(&samples[at(0.05)..at(0.25)]);
+    let aliased = rms(&samples[at(0.42)..at(0.6)]);
+    20.0 * (aliased / in_band).log10()
+}
+
+#[test]
+fn antialias_filter_suppresses_the_fold_down() {
+    let filtered = aliasing_db(true);
+    let unfiltered = aliasing_db(false);
+    assert!(filtered < -60.0, "aliasing at {} dB", filtered);
+    assert!(
+        filtered < unfiltered,
+        "{} dB vs {} dB",
+        filtered,
+        unfiltered
+    );
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone, write_fixture};
+use wavup::{AudioConversionError, AudioConverterBuilder, BextChunk};
+
+const RATE: u32 = 16000;
+
+/// The ids of the chunks of the WAV file `bytes`, after checking that they fill its RIFF chunk.
+fn chunk_ids(bytes: &[u8]) -> Vec<[u8; 4]> {
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    assert_eq!(read_u32(4) as usize, bytes.len() - 8);
+
+    let mut ids = Vec::new();
+    let mut offset = 12;
+    while offset < bytes.len() {
+        ids.push(bytes[offset..offset + 4].try_into().unwrap());
+        let len = read_u32(offset + 4) as usize;
+        offset += 8 + len + len % 2;
+    }
+    assert_eq!(offset, bytes.len());
+    ids
+}
+
+#[test]
+fn appending_adds_the_frames_of_the_new_clip() {
+    let output_path = tmp_path("append.wav");
+    let _ = std::fs::remove_file(&output_path);
+
+    for secs in [1.0, 2.0] {
+        AudioConverterBuilder::new(&output_path, RATE)
+            .with_append(true)
+            .build()
+            .unwrap()
+            .convert_audio_from_bytes(&float_wav(&tone(RATE, 2, secs, 440.0), RATE, 2))
+            .unwrap();
+    }
+
+    let (spec, samples) = read_wav(&output_path);
+    assert_eq!(spec.channels, 2);
+    assert_eq!(samples.len() / 2, 3 * RATE as usize);
+}
+
+#[test]
+fn appending_a_clip_with_another_spec_fails() {
+    let output_path = tmp_path("append_mismatch.wav");
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2))
+        .unwrap();
+
+    let result = AudioConverterBuilder::new(&output_path, RATE)
+        .with_append(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&tone(RATE, 1, 1.0, 440.0), RATE, 1));
+    assert!(matches!(
+        result,
+        Err(AudioConversionError::UnsupportedFormat(_))
+    ));
+
+    // The existing file is left untouched
+    let (_, samples) = read_wav(&output_path);
+    assert_eq!(samples.len() / 2, RATE as usize);
+}
+
+#[test]
+fn appending_keeps_the_chunks_after_the_data() {
+    // A tone with a `LIST` chunk of type `INFO` holding its title, placed before the `data` chunk
+    // where the WAV reader looks for it
+    let mut wav = float_wav(&tone(RATE, 1, 1.0, 440.0), RATE, 1);
+    let list = [
+        &b"LIST"[..],
+        &18_u32.to_le_bytes(),
+        b"INFO",
+        b"INAM",
+        &6_u32.to_le_bytes(),
+    ];
+    let mut list = list.concat();
+    list.extend_from_slice(b"Tone\0\0");
+    let data_pos = wav.windows(4).position(|id| id == b"data").unwrap();
+    wav.splice(data_pos..data_pos, list);
+    let riff_len = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    let input_path = write_fixture("append_chunks_input.wav", &wav);
+
+    // The first conversion writes the tags, cue markers and BWF metadata after the data
+    let output_path = tmp_path("append_chunks.wav");
+    let bext = BextChunk {
+        description: "Appended tone".to_string(),
+        ..BextChunk::default()
+    };
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(&input_path)
+        .with_copy_metadata(true)
+        .with_silence_cue_markers(true)
+        .with_bwf_metadata(bext)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    let ids = chunk_ids(&std::fs::read(&output_path).unwrap());
+    assert_eq!(ids, [*b"fmt ", *b"data", *b"cue ", *b"LIST", *b"bext"]);
+
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(&input_path)
+        .with_append(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    // The appended samples extend the data chunk and the other chunks follow it unchanged
+    let bytes = std::fs::read(&output_path).unwrap();
+    assert_eq!(chunk_ids(&bytes), ids);
+    let bext_pos = bytes.windows(4).position(|id| id == b"bext").unwrap();
+    assert_eq!(&bytes[bext_pos + 8..bext_pos + 21], b"Appended tone");
+    let (_, samples) = read_wav(&output_path);
+    assert_eq!(samples.len(), 2 * RATE as usize);
+}
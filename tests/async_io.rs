@@ -0,0 +1,87 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
+use wavup::{AudioConversionError, AudioConverterBuilder};
+
+const RATE: u32 = 44100;
+
+/// An async source that hands out `data` a few bytes at a time, then fails if `fail` is set.
+struct TrickleReader {
+    data: Vec<u8>,
+    position: usize,
+    fail: bool,
+}
+impl AsyncRead for TrickleReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let len = buf
+            .remaining()
+            .min(1000)
+            .min(self.data.len() - self.position);
+        if len == 0 && self.fail {
+            return Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into()));
+        }
+        buf.put_slice(&self.data[self.position..self.position + len]);
+        self.position += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn convert_async(data: Vec<u8>, fail: bool, name: &str) -> Result<(), AudioConversionError> {
+    let converter = AudioConverterBuilder::new(tmp_path(name), 16000)
+        .with_overwrite(true)
+        .build()
+        .unwrap();
+    let reader = TrickleReader {
+        data,
+        position: 0,
+        fail,
+    };
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(converter.convert_audio_async(reader))
+        .map(|_| ())
+}
+
+#[test]
+fn async_conversion_matches_the_bytes_conversion() {
+    let input = float_wav(&tone(RATE, 2, 3.0, 440.0), RATE, 2);
+    let expected_path = tmp_path("async_expected.wav");
+    AudioConverterBuilder::new(&expected_path, 16000)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&input)
+        .unwrap();
+
+    convert_async(input, false, "async_converted.wav").unwrap();
+    assert_eq!(
+        read_wav(tmp_path("async_converted.wav")),
+        read_wav(expected_path)
+    );
+}
+
+#[test]
+fn async_read_errors_are_reported() {
+    let mut input = float_wav(&tone(RATE, 2, 3.0, 440.0), RATE, 2);
+    input.truncate(input.len() / 2);
+
+    match convert_async(input, true, "async_failed.wav") {
+        Err(AudioConversionError::IoError(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset)
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(!tmp_path("async_failed.wav").exists());
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::{flac_in_ogg, float_wav, read_wav, rms, tmp_path, tone, write_fixture};
+use wavup::{AudioConverterBuilder, OutputFormat, SampleFormat};
+
+const RATE: u32 = 16000;
+
+/// `secs` seconds of a tone with `channels` channels at `sample_rate`, encoded to FLAC by wavup.
+fn flac(name: &str, sample_rate: u32, channels: u16, secs: f32) -> Vec<u8> {
+    let path = tmp_path(name);
+    AudioConverterBuilder::new(&path, sample_rate)
+        .with_output_format(OutputFormat::Flac {
+            compression_level: 5,
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(
+            &tone(sample_rate, channels as usize, secs, 440.0),
+            sample_rate,
+            channels,
+        ))
+        .unwrap();
+    std::fs::read(path).unwrap()
+}
+
+/// Convert the file `name` holding `input` to mono 32-bit float samples at `RATE`, and return
+/// whether the conversion reported it truncated with the samples.
+fn convert(name: &str, input: &[u8]) -> (bool, Vec<f32>) {
+    let input_path = write_fixture(name, input);
+    let output_path = tmp_path(&format!("{}.wav", name));
+    let report = AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(input_path)
+        .with_target_channels(1)
+        .with_sample_format(SampleFormat::Float)
+        .with_bits_per_sample(32)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    (report.truncated, read_wav(output_path).1)
+}
+
+#[test]
+fn every_stream_of_a_chained_input_is_converted() {
+    let first = flac("chained_first.flac", RATE, 1, 1.0);
+    let second = flac("chained_second.flac", RATE, 1, 1.5);
+    let chained = [flac_in_ogg(&first, 1), flac_in_ogg(&second, 2)].concat();
+
+    // The length declared by the first stream does not end the conversion or mark it truncated
+    let (truncated, samples) = convert("chained.ogg", &chained);
+    let (_, first) = convert("chained_first.ogg", &flac_in_ogg(&first, 1));
+    let (_, second) = convert("chained_second.ogg", &flac_in_ogg(&second, 2));
+    assert!(!truncated);
+    assert_eq!(samples.len(), 40000);
+    assert_eq!(samples, [first, second].concat());
+}
+
+#[test]
+fn chained_streams_with_another_layout_are_adapted_to_the_first_one() {
+    let first = flac("chained_mono.flac", RATE, 1, 1.0);
+    let second = flac("chained_stereo.flac", 22050, 2, 1.0);
+    let chained = [flac_in_ogg(&first, 1), flac_in_ogg(&second, 2)].concat();
+
+    let (truncated, samples) = convert("chained_layout.ogg", &chained);
+    let (_, first) = convert("chained_mono.ogg", &flac_in_ogg(&first, 1));
+    assert!(!truncated);
+
+    // The first stream is unchanged, and the second one is downmixed and resampled to it, within
+    // the delay of the resampler
+    assert_eq!(samples[..first.len()], first);
+    let second = &samples[first.len()..];
+    assert!(
+        second.len().abs_diff(RATE as usize) < RATE as usize / 100,
+        "{} frames",
+        second.len()
+    );
+    let middle = &second[RATE as usize / 10..second.len() - RATE as usize / 10];
+    assert!(
+        (rms(middle) - 0.5 / 2f32.sqrt()).abs() < 0.01,
+        "RMS of {}",
+        rms(middle)
+    );
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{channel, float_wav, read_wav, rms, tmp_path, tone};
+use wavup::{AudioConversionError, AudioConverterBuilder};
+
+const RATE: u32 = 16000;
+
+#[test]
+fn gains_scale_each_channel() {
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+    let output_path = tmp_path("channel_gains_stereo.wav");
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_channel_gains_db(vec![-6.0, 0.0])
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&input)
+        .unwrap();
+
+    let (spec, samples) = read_wav(&output_path);
+    let input_rms = rms(&tone(RATE, 1, 1.0, 440.0));
+    let left_db = 20.0 * (rms(&channel(&samples, 2, 0)) / input_rms).log10();
+    let right_db = 20.0 * (rms(&channel(&samples, 2, 1)) / input_rms).log10();
+    assert_eq!(spec.channels, 2);
+    assert!((left_db + 6.0).abs() < 0.1, "left at {} dB", left_db);
+    assert!(right_db.abs() < 0.1, "right at {} dB", right_db);
+}
+
+#[test]
+fn gains_match_the_downmixed_channels() {
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+    let convert = |gains_db: Vec<f32>| {
+        AudioConverterBuilder::new(tmp_path("channel_gains_mono.wav"), RATE)
+            .with_target_channels(1)
+            .with_channel_gains_db(gains_db)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio_from_bytes(&input)
+    };
+
+    // One gain per output channel, not per input channel
+    assert!(convert(vec![-6.0]).is_ok());
+    assert!(matches!(
+        convert(vec![-6.0, 0.0]),
+        Err(AudioConversionError::UnsupportedFormat(_))
+    ));
+}
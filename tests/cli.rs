@@ -0,0 +1,389 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{float_wav, int16_wav, read_wav, read_wav_bytes, rms, tmp_path, tone, write_fixture};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+#[test]
+fn cli_converts_a_16_bit_wav() {
+    // One second of a stereo 440 Hz tone at half of full scale, which decodes to S16 buffers
+    let samples: Vec<i16> = (0..16000)
+        .flat_map(|frame| {
+            let sample = (16384.0
+                * (2.0 * std::f32::consts::PI * 440.0 * frame as f32 / 16000.0).sin())
+                as i16;
+            [sample, sample]
+        })
+        .collect();
+    let input_path = write_fixture("cli_s16.wav", &int16_wav(&samples, 16000, 2));
+    let output_path = tmp_path("cli_s16_output.wav");
+    let _ = std::fs::remove_file(&output_path);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_wavup"))
+        .current_dir(env!("CARGO_TARGET_TMPDIR"))
+        .arg("-i")
+        .arg(&input_path)
+        .arg("-o")
+        .arg(&output_path)
+        .args(["-r", "8000", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let (spec, samples) = read_wav(&output_path);
+    assert_eq!(spec.channels, 2);
+    assert_eq!(spec.sample_rate, 8000);
+    assert_eq!(spec.bits_per_sample, 16);
+    assert_eq!(spec.sample_format, hound::SampleFormat::Int);
+    assert_eq!(samples.len(), 2 * 8000);
+    assert!(rms(&samples) > 0.3);
+}
+
+/// An empty directory in the temporary directory, so that the CLI running in it reads no
+/// `wavup.toml` of another test.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = tmp_path(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write one second of a stereo tone at 16 kHz to the file `name` of `dir`.
+fn write_input(dir: &Path, name: &str) {
+    std::fs::write(
+        dir.join(name),
+        float_wav(&tone(16000, 2, 1.0, 440.0), 16000, 2),
+    )
+    .unwrap();
+}
+
+/// Run the CLI in `dir` with the arguments.
+fn wavup(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wavup"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Assert that the CLI exited with `code`, showing its stderr otherwise.
+fn assert_exit_code(output: &Output, code: i32) {
+    assert_eq!(
+        output.status.code(),
+        Some(code),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn exit_codes_follow_the_category_of_the_error() {
+    let dir = test_dir("cli_exit_codes");
+    write_input(&dir, "input.wav");
+    std::fs::write(dir.join("text.wav"), "not audio").unwrap();
+
+    assert_exit_code(&wavup(&dir, &["-i", "input.wav", "-q"]), 0);
+    assert_exit_code(&wavup(&dir, &["-i", "input.wav", "-r", "fast"]), 2);
+    assert_exit_code(&wavup(&dir, &["-i", "missing.wav"]), 3);
+    assert_exit_code(&wavup(&dir, &["-i", "text.wav"]), 4);
+}
+
+#[test]
+fn json_errors_are_printed_as_one_object_per_line() {
+    let dir = test_dir("cli_json_errors");
+
+    let output = wavup(&dir, &["-i", "missing.wav", "--json-errors"]);
+    assert_exit_code(&output, 3);
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        concat!(
+            r#"{"error":"not_found","exit_code":3,"action":"converting","file":"missing.wav","#,
+            r#""message":"IO error: The input file missing.wav does not exist"}"#,
+            "\n"
+        )
+    );
+
+    // Argument errors are reported the same way, without a file
+    let output = wavup(&dir, &["-i", "missing.wav", "-r", "fast", "--json-errors"]);
+    assert_exit_code(&output, 2);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1, "{}", stderr);
+    assert!(
+        stderr.starts_with(r#"{"error":"invalid_arguments","exit_code":2,"message":""#),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("\"file\""), "{}", stderr);
+}
+
+#[test]
+fn flags_override_the_defaults_of_the_config_file() {
+    let dir = test_dir("cli_config");
+    write_input(&dir, "input.wav");
+    std::fs::write(
+        dir.join("wavup.toml"),
+        "sample_rate = 8000\nbits-per-sample = 24\n",
+    )
+    .unwrap();
+
+    // wavup.toml in the current directory replaces the defaults
+    assert_exit_code(
+        &wavup(&dir, &["-i", "input.wav", "-o", "config.wav", "-q"]),
+        0,
+    );
+    let (spec, _) = read_wav(dir.join("config.wav"));
+    assert_eq!(spec.sample_rate, 8000);
+    assert_eq!(spec.bits_per_sample, 24);
+
+    // A flag wins over the config file, which still sets the other defaults
+    let output = wavup(
+        &dir,
+        &["-i", "input.wav", "-o", "flag.wav", "-r", "22050", "-q"],
+    );
+    assert_exit_code(&output, 0);
+    let (spec, _) = read_wav(dir.join("flag.wav"));
+    assert_eq!(spec.sample_rate, 22050);
+    assert_eq!(spec.bits_per_sample, 24);
+
+    // --config reads another file, which must exist and only name known options
+    std::fs::write(dir.join("other.toml"), "sample_rate = 11025\n").unwrap();
+    let output = wavup(
+        &dir,
+        &[
+            "--config",
+            "other.toml",
+            "-i",
+            "input.wav",
+            "-o",
+            "other.wav",
+            "-q",
+        ],
+    );
+    assert_exit_code(&output, 0);
+    assert_eq!(read_wav(dir.join("other.wav")).0.sample_rate, 11025);
+    assert_exit_code(
+        &wavup(&dir, &["--config", "missing.toml", "-i", "input.wav"]),
+        2,
+    );
+    std::fs::write(dir.join("unknown.toml"), "speed = 2\n").unwrap();
+    assert_exit_code(
+        &wavup(&dir, &["--config", "unknown.toml", "-i", "input.wav"]),
+        2,
+    );
+}
+
+#[test]
+fn no_clobber_keeps_an_existing_output() {
+    let dir = test_dir("cli_no_clobber");
+    write_input(&dir, "input.wav");
+    std::fs::write(dir.join("output.wav"), "existing").unwrap();
+
+    let output = wavup(&dir, &["-i", "input.wav", "--no-clobber"]);
+    assert_exit_code(&output, 1);
+    assert_eq!(std::fs::read(dir.join("output.wav")).unwrap(), b"existing");
+
+    // --overwrite wins over a no_clobber default, as does the last of the two flags
+    std::fs::write(dir.join("wavup.toml"), "no_clobber = true\n").unwrap();
+    assert_exit_code(&wavup(&dir, &["-i", "input.wav", "-q"]), 1);
+    assert_exit_code(&wavup(&dir, &["-i", "input.wav", "--overwrite", "-q"]), 0);
+    assert_eq!(read_wav(dir.join("output.wav")).0.sample_rate, 44100);
+    assert_exit_code(
+        &wavup(&dir, &["-i", "input.wav", "--overwrite", "--no-clobber"]),
+        1,
+    );
+}
+
+#[test]
+fn start_and_duration_select_the_converted_window() {
+    let dir = test_dir("cli_time_range");
+    write_input(&dir, "input.wav");
+
+    let output = wavup(
+        &dir,
+        &[
+            "-i",
+            "input.wav",
+            "-r",
+            "16000",
+            "--start",
+            "0.25",
+            "--duration",
+            "0:00.5",
+            "-q",
+        ],
+    );
+    assert_exit_code(&output, 0);
+    let (_, samples) = read_wav(dir.join("output.wav"));
+    assert_eq!(samples.len(), 2 * 8000);
+
+    let output = wavup(&dir, &["-i", "input.wav", "--start", "1:x"]);
+    assert_exit_code(&output, 2);
+}
+
+#[test]
+fn dash_reads_stdin_and_writes_stdout() {
+    let dir = test_dir("cli_pipe");
+    let input = float_wav(&tone(16000, 2, 1.0, 440.0), 16000, 2);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wavup"))
+        .current_dir(&dir)
+        .args(["-i", "-", "-o", "-", "-r", "8000"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_exit_code(&output, 0);
+
+    // Stdout holds only the audio
+    let (spec, samples) = read_wav_bytes(&output.stdout);
+    assert_eq!(spec.sample_rate, 8000);
+    assert_eq!(samples.len(), 2 * 8000);
+    assert!(std::fs::read_dir(&dir).unwrap().next().is_none());
+}
+
+#[test]
+fn info_json_prints_the_properties_as_one_object() {
+    let dir = test_dir("cli_info");
+    write_input(&dir, "input.wav");
+
+    let output = wavup(&dir, &["info", "input.wav", "--json"]);
+    assert_exit_code(&output, 0);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    for field in [
+        "\"file\":\"input.wav\"",
+        "\"codec\":\"pcm_f32le\"",
+        "\"container\":\"wave\"",
+        "\"duration_secs\":1,",
+        "\"sample_rate\":16000,",
+        "\"channels\":2,",
+        "\"bits_per_sample\":32,",
+        "\"is_lossy\":false,",
+        "\"tags\":{}",
+    ] {
+        assert!(stdout.contains(field), "{} not in {}", field, stdout);
+    }
+    assert!(stdout.starts_with('{') && stdout.ends_with("}\n"));
+
+    assert_exit_code(&wavup(&dir, &["info", "missing.wav"]), 3);
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batch_converts_every_file_and_fails_if_one_fails() {
+    let dir = test_dir("cli_batch");
+    std::fs::create_dir_all(dir.join("input/nested")).unwrap();
+    write_input(&dir, "input/a.wav");
+    write_input(&dir, "input/b.wav");
+    write_input(&dir, "input/nested/c.wav");
+
+    let output = wavup(
+        &dir,
+        &[
+            "batch",
+            "--input-dir",
+            "input",
+            "--output-dir",
+            "output",
+            "--output-template",
+            "{stem}_{rate}.{ext}",
+            "-r",
+            "8000",
+            "-j",
+            "2",
+        ],
+    );
+    assert_exit_code(&output, 0);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Converted 3 of 3 files"), "{}", stdout);
+    for path in [
+        "output/a_8000.wav",
+        "output/b_8000.wav",
+        "output/nested/c_8000.wav",
+    ] {
+        assert_eq!(read_wav(dir.join(path)).0.sample_rate, 8000, "{}", path);
+    }
+
+    // A file that is not audio fails the batch, which still converts the others
+    std::fs::write(dir.join("input/text.wav"), "not audio").unwrap();
+    let output = wavup(
+        &dir,
+        &[
+            "batch",
+            "--input-dir",
+            "input",
+            "--output-dir",
+            "output",
+            "-j",
+            "2",
+            "--json-errors",
+        ],
+    );
+    assert_exit_code(&output, 1);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1, "{}", stderr);
+    assert!(
+        stderr.contains("\"error\":\"unsupported_format\""),
+        "{}",
+        stderr
+    );
+    assert!(dir.join("output/nested/c.wav").exists());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_converts_the_files_added_to_the_directory() {
+    use std::time::{Duration, Instant};
+
+    let dir = test_dir("cli_watch");
+    std::fs::create_dir_all(dir.join("input")).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wavup"))
+        .current_dir(&dir)
+        .args([
+            "watch",
+            "input",
+            "--output-dir",
+            "output",
+            "--settle-secs",
+            "0.2",
+            "-r",
+            "8000",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Wait for the watcher to start, then add a file
+    let started = Instant::now();
+    while !dir.join("output").exists() && started.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::thread::sleep(Duration::from_millis(500));
+    write_input(&dir, "input/added.wav");
+
+    let output_path = dir.join("output/added.wav");
+    let converted = loop {
+        let readable = std::fs::metadata(&output_path).is_ok()
+            && hound::WavReader::open(&output_path).is_ok_and(|reader| reader.len() == 2 * 8000);
+        if readable || started.elapsed() > Duration::from_secs(20) {
+            break readable;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(converted, "{} was not written", output_path.display());
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use wavup::{AudioConverterBuilder, ConversionReport};
+
+const RATE: u32 = 16000;
+
+fn convert(samples: &[f32], name: &str) -> ConversionReport {
+    AudioConverterBuilder::new(tmp_path(name), RATE)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, 1))
+        .unwrap()
+}
+
+#[test]
+fn clipped_input_is_reported() {
+    // A 100 Hz tone driven 3.5 dB over full scale and hard clipped flattens every half cycle
+    let clipped: Vec<f32> = tone(RATE, 1, 1.0, 100.0)
+        .iter()
+        .map(|sample| (sample * 3.0).clamp(-1.0, 1.0))
+        .collect();
+    let report = convert(&clipped, "clipping_clipped.wav");
+    assert_eq!(report.clip_runs, 200);
+    assert!(report.clipped_sample_count >= 200 * 3);
+
+    let report = convert(&tone(RATE, 1, 1.0, 100.0), "clipping_clean.wav");
+    assert_eq!(report.clip_runs, 0);
+    assert_eq!(report.clipped_sample_count, 0);
+}
@@ -0,0 +1,278 @@
+//! Fixtures and helpers shared by the integration tests. Fixtures are generated into the
+//! temporary directory Cargo provides to integration tests, so no large files are checked in.
+
+#![allow(dead_code)]
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::{
+    f32::consts::PI,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use symphonia::core::io::MediaSource;
+
+/// A path in the temporary directory of the integration tests.
+pub fn tmp_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+/// A path in the `audio` directory of sample files.
+pub fn sample_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("audio")
+        .join(name)
+}
+
+/// A sine tone of `secs` seconds at `frequency` Hz and half of full scale, the same on every
+/// channel, as interleaved samples.
+pub fn tone(sample_rate: u32, channels: usize, secs: f32, frequency: f32) -> Vec<f32> {
+    let frames = (secs * sample_rate as f32).round() as usize;
+    (0..frames)
+        .flat_map(|frame| {
+            let sample = 0.5 * (2.0 * PI * frequency * frame as f32 / sample_rate as f32).sin();
+            std::iter::repeat_n(sample, channels)
+        })
+        .collect()
+}
+
+/// The bytes of a 32-bit float WAV file holding the interleaved `samples`.
+pub fn float_wav(samples: &[f32], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    bytes.into_inner()
+}
+
+/// The bytes of a 16-bit integer WAV file holding the interleaved `samples`.
+pub fn int16_wav(samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    bytes.into_inner()
+}
+
+/// Write `bytes` to the file `name` of the temporary directory and return its path.
+pub fn write_fixture(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = tmp_path(name);
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+/// The spec and the samples of the WAV file at `path`, scaled to `[-1, 1]`.
+pub fn read_wav(path: impl AsRef<Path>) -> (WavSpec, Vec<f32>) {
+    read_wav_reader(WavReader::open(path).unwrap())
+}
+
+/// The spec and the samples of the WAV file held in `bytes`, scaled to `[-1, 1]`.
+pub fn read_wav_bytes(bytes: &[u8]) -> (WavSpec, Vec<f32>) {
+    read_wav_reader(WavReader::new(Cursor::new(bytes)).unwrap())
+}
+
+fn read_wav_reader<R: std::io::Read>(mut reader: WavReader<R>) -> (WavSpec, Vec<f32>) {
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().map(Result::unwrap).collect(),
+        SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.unwrap() as f32 / scale)
+                .collect()
+        }
+    };
+    (spec, samples)
+}
+
+/// The root mean square of `samples`.
+pub fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// The samples of `channel` out of interleaved samples of `channels` channels.
+pub fn channel(samples: &[f32], channels: usize, channel: usize) -> Vec<f32> {
+    samples
+        .iter()
+        .skip(channel)
+        .step_by(channels)
+        .copied()
+        .collect()
+}
+
+/// A media source over an in-memory buffer that counts the bytes read through it.
+pub struct CountingSource {
+    pub inner: Cursor<Vec<u8>>,
+    pub seekable: bool,
+    pub bytes_read: Arc<AtomicUsize>,
+}
+impl Read for CountingSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read.fetch_add(n, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+impl Seek for CountingSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+impl MediaSource for CountingSource {
+    fn is_seekable(&self) -> bool {
+        self.seekable
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.seekable.then(|| self.inner.get_ref().len() as u64)
+    }
+}
+
+/// The FLAC file `flac` repackaged into a Matroska file, whose timestamps count milliseconds
+/// rather than frames. Each FLAC frame becomes a block group in a cluster of its own.
+pub fn flac_in_mkv(flac: &[u8]) -> Vec<u8> {
+    use symphonia::core::{
+        formats::{FormatOptions, FormatReader},
+        io::MediaSourceStream,
+    };
+
+    /// An EBML element with the given id and body, its size written on eight bytes.
+    fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut size = (body.len() as u64).to_be_bytes();
+        size[0] = 0x01;
+        [id, &size, body].concat()
+    }
+    fn uint(id: &[u8], value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(flac.to_vec())), Default::default());
+    let mut reader =
+        symphonia::default::formats::FlacReader::try_new(source, &FormatOptions::default())
+            .unwrap();
+    let params = reader.default_track().unwrap().codec_params.clone();
+    let sample_rate = params.sample_rate.unwrap() as u64;
+    let ms = |frames: u64| frames * 1000 / sample_rate;
+
+    let mut clusters = Vec::new();
+    while let Ok(packet) = reader.next_packet() {
+        // The duration of the block lets a seek land on the block holding the target
+        let block = [
+            element(&[0xA1], &[&[0x81, 0, 0, 0][..], packet.buf()].concat()),
+            uint(&[0x9B], ms(packet.ts() + packet.dur()) - ms(packet.ts())),
+        ]
+        .concat();
+        let cluster = [uint(&[0xE7], ms(packet.ts())), element(&[0xA0], &block)].concat();
+        clusters.extend(element(&[0x1F, 0x43, 0xB6, 0x75], &cluster));
+    }
+
+    // The codec private data is the stream marker and the STREAMINFO block
+    let audio = [
+        element(&[0xB5], &(sample_rate as f64).to_be_bytes()),
+        uint(&[0x9F], params.channels.unwrap().count() as u64),
+    ]
+    .concat();
+    let track = [
+        uint(&[0xD7], 1),
+        uint(&[0x73, 0xC5], 1),
+        uint(&[0x83], 2),
+        element(&[0x86], b"A_FLAC"),
+        element(&[0x63, 0xA2], &flac[..42]),
+        element(&[0xE1], &audio),
+    ]
+    .concat();
+    let info = [
+        uint(&[0x2A, 0xD7, 0xB1], 1_000_000),
+        element(
+            &[0x44, 0x89],
+            &(ms(params.n_frames.unwrap()) as f64).to_be_bytes(),
+        ),
+    ]
+    .concat();
+    let segment = [
+        element(&[0x15, 0x49, 0xA9, 0x66], &info),
+        element(&[0x16, 0x54, 0xAE, 0x6B], &element(&[0xAE], &track)),
+        clusters,
+    ]
+    .concat();
+    let ebml = [element(&[0x42, 0x82], b"matroska"), uint(&[0x42, 0x85], 2)].concat();
+
+    [
+        element(&[0x1A, 0x45, 0xDF, 0xA3], &ebml),
+        element(&[0x18, 0x53, 0x80, 0x67], &segment),
+    ]
+    .concat()
+}
+
+/// The FLAC file `flac` repackaged into an Ogg FLAC stream with the serial number `serial`. Each
+/// FLAC frame is a packet on a page of its own. Concatenated streams form a chained Ogg file.
+pub fn flac_in_ogg(flac: &[u8], serial: u32) -> Vec<u8> {
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+    use symphonia::core::{
+        formats::{FormatOptions, FormatReader},
+        io::MediaSourceStream,
+    };
+
+    let mut ogg = Vec::new();
+    let mut writer = PacketWriter::new(&mut ogg);
+
+    // The mapping header, the stream marker and the STREAMINFO block, no longer the last one, then
+    // an empty VORBIS_COMMENT block as the only other header packet
+    let mut streaminfo = flac[4..42].to_vec();
+    streaminfo[0] &= 0x7F;
+    let header = [&b"\x7FFLAC\x01\x00\x00\x01fLaC"[..], &streaminfo].concat();
+    writer
+        .write_packet(header.into(), serial, PacketWriteEndInfo::EndPage, 0)
+        .unwrap();
+    let comment = [0x84, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0];
+    writer
+        .write_packet(
+            comment.to_vec().into(),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )
+        .unwrap();
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(flac.to_vec())), Default::default());
+    let mut reader =
+        symphonia::default::formats::FlacReader::try_new(source, &FormatOptions::default())
+            .unwrap();
+    let mut packets = Vec::new();
+    while let Ok(packet) = reader.next_packet() {
+        packets.push((packet.buf().to_vec(), packet.ts() + packet.dur()));
+    }
+    let last = packets.len() - 1;
+    for (index, (frame, end_ts)) in packets.into_iter().enumerate() {
+        let end = if index == last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::EndPage
+        };
+        writer
+            .write_packet(frame.into(), serial, end, end_ts)
+            .unwrap();
+    }
+    drop(writer);
+    ogg
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 16000;
+
+/// The sample offsets of the markers in the `cue ` chunk of the WAV file `bytes`.
+fn cue_positions(bytes: &[u8]) -> Vec<u32> {
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let len = read_u32(offset + 4) as usize;
+        if &bytes[offset..offset + 4] == b"cue " {
+            let count = read_u32(offset + 8) as usize;
+            return (0..count)
+                .map(|point| read_u32(offset + 12 + 24 * point + 20))
+                .collect();
+        }
+        offset += 8 + len + len % 2;
+    }
+    panic!("no cue chunk");
+}
+
+#[test]
+fn markers_sit_at_the_segment_starts() {
+    // Three one-second tones separated by one second of silence, starting at 0 s, 2 s and 4 s
+    let mut samples = Vec::new();
+    for segment in 0..3 {
+        if segment > 0 {
+            samples.extend(vec![0.0; RATE as usize]);
+        }
+        samples.extend(tone(RATE, 1, 1.0, 440.0));
+    }
+    let output_path = tmp_path("cue_markers.wav");
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_silence_cue_markers(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&samples, RATE, 1))
+        .unwrap();
+
+    // The level is measured over 10 ms windows, so a marker may lead its segment by 5 ms
+    let positions = cue_positions(&std::fs::read(output_path).unwrap());
+    assert_eq!(positions.len(), 3, "{:?}", positions);
+    for (position, start) in positions.iter().zip([0, 2 * RATE, 4 * RATE]) {
+        assert!(
+            (start.saturating_sub(RATE / 200)..=start).contains(position),
+            "marker at {}, segment at {}",
+            position,
+            start
+        );
+    }
+}
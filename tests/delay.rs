@@ -0,0 +1,53 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path};
+use wavup::AudioConverterBuilder;
+
+const INPUT_RATE: u32 = 48000;
+const OUTPUT_RATE: u32 = 16000;
+
+/// The lag in output frames, within `±max_lag`, at which the cross-correlation of `input`, taken
+/// at the output rate, and `output` peaks.
+fn peak_lag(input: &[f32], output: &[f32], max_lag: isize) -> isize {
+    let ratio = (INPUT_RATE / OUTPUT_RATE) as usize;
+    let input: Vec<f32> = input.iter().step_by(ratio).copied().collect();
+    (-max_lag..=max_lag)
+        .max_by(|a, b| {
+            let correlation = |lag: isize| -> f32 {
+                (0..input.len())
+                    .filter_map(|i| {
+                        let j = i as isize + lag;
+                        (j >= 0 && (j as usize) < output.len())
+                            .then(|| input[i] * output[j as usize])
+                    })
+                    .sum()
+            };
+            correlation(*a).total_cmp(&correlation(*b))
+        })
+        .unwrap()
+}
+
+/// The output of resampling `input` with delay compensation `enabled`.
+fn resample(input: &[f32], enabled: bool) -> Vec<f32> {
+    let output_path = tmp_path(&format!("delay_{}.wav", enabled));
+    AudioConverterBuilder::new(&output_path, OUTPUT_RATE)
+        .with_compensate_delay(enabled)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(input, INPUT_RATE, 1))
+        .unwrap();
+    read_wav(&output_path).1
+}
+
+#[test]
+fn compensated_impulse_is_not_delayed() {
+    // An impulse half a second into one second of silence
+    let mut input = vec![0.0; INPUT_RATE as usize];
+    input[INPUT_RATE as usize / 2] = 0.9;
+
+    assert_eq!(peak_lag(&input, &resample(&input, true), 200), 0);
+
+    // Without compensation the resampler delay shows
+    assert!(peak_lag(&input, &resample(&input, false), 200) > 0);
+}
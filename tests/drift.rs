@@ -0,0 +1,61 @@
+mod common;
+
+use common::{int16_wav, read_wav, tmp_path};
+use std::f64::consts::PI;
+use wavup::AudioConverterBuilder;
+
+const INPUT_RATE: u32 = 44100;
+const OUTPUT_RATE: u32 = 48000;
+const FREQUENCY: f64 = 997.0;
+const SECS: usize = 20;
+
+/// The delay in output frames of the `FREQUENCY` Hz tone in the second of `output` starting at
+/// `start_secs`, against a tone of phase zero at time zero, within half a period.
+fn tone_delay(output: &[f32], start_secs: usize) -> f64 {
+    let rate = OUTPUT_RATE as usize;
+    let (mut re, mut im) = (0.0, 0.0);
+    for (i, sample) in output[start_secs * rate..(start_secs + 1) * rate]
+        .iter()
+        .enumerate()
+    {
+        let t = (start_secs * rate + i) as f64 / OUTPUT_RATE as f64;
+        re += *sample as f64 * (2.0 * PI * FREQUENCY * t).sin();
+        im += *sample as f64 * (2.0 * PI * FREQUENCY * t).cos();
+    }
+    // sin(2 pi f (t - delay)) is out of phase with sin(2 pi f t) by -2 pi f delay
+    -im.atan2(re) / (2.0 * PI * FREQUENCY) * OUTPUT_RATE as f64
+}
+
+#[test]
+fn rational_resampler_does_not_drift() {
+    let frames = SECS * INPUT_RATE as usize;
+    let input: Vec<i16> = (0..frames)
+        .map(|frame| {
+            let t = frame as f64 / INPUT_RATE as f64;
+            (16384.0 * (2.0 * PI * FREQUENCY * t).sin()).round() as i16
+        })
+        .collect();
+
+    let output_path = tmp_path("drift.wav");
+    let report = AudioConverterBuilder::new(&output_path, OUTPUT_RATE)
+        .with_rational_resampler(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&int16_wav(&input, INPUT_RATE, 1))
+        .unwrap();
+    assert_eq!(report.resampler, Some("SincFixedIn"));
+
+    let (_, output) = read_wav(&output_path);
+    assert_eq!(output.len(), SECS * OUTPUT_RATE as usize);
+
+    // Any error in the ratio makes the delay of the tone grow linearly over the file. Extrapolated
+    // from the first to the last second, it stays under a frame over an hour.
+    let drift = tone_delay(&output, SECS - 1) - tone_delay(&output, 1);
+    let drift_per_hour = drift / (SECS - 2) as f64 * 3600.0;
+    assert!(
+        drift_per_hour.abs() < 1.0,
+        "drift of {} frames per hour",
+        drift_per_hour
+    );
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use common::{channel, float_wav, read_wav, rms, tmp_path, tone, write_fixture};
+use std::time::Duration;
+use wavup::{AudioConverterBuilder, FadeShape, SampleFormat, VadOptions};
+
+const RATE: u32 = 16000;
+
+/// A builder converting to 32-bit float output at the input rate, so the samples are compared
+/// without quantization or resampling.
+fn float_output(name: &str) -> AudioConverterBuilder {
+    AudioConverterBuilder::new(tmp_path(name), RATE)
+        .with_sample_format(SampleFormat::Float)
+        .with_bits_per_sample(32)
+        .with_overwrite(true)
+}
+
+/// Convert the interleaved `samples` with `builder` and return the output samples.
+fn convert(builder: AudioConverterBuilder, name: &str, samples: &[f32], channels: u16) -> Vec<f32> {
+    builder
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, channels))
+        .unwrap();
+    read_wav(tmp_path(name)).1
+}
+
+/// The level of the tone at `frequency` after the filter set up by `filter`, relative to the
+/// input, in dB. The first tenth of a second, where the filter settles, is left out.
+fn filtered_db(
+    name: &str,
+    frequency: f32,
+    filter: impl Fn(AudioConverterBuilder) -> AudioConverterBuilder,
+) -> f32 {
+    let input = tone(RATE, 1, 1.0, frequency);
+    let output = convert(filter(float_output(name)), name, &input, 1);
+    let settled = RATE as usize / 10;
+    20.0 * (rms(&output[settled..]) / rms(&input[settled..])).log10()
+}
+
+/// Silence, then `secs` seconds of a tone, for each of the `(silence, tone)` lengths in seconds.
+fn bursts(lengths: &[(f32, f32)]) -> Vec<f32> {
+    lengths
+        .iter()
+        .flat_map(|(silence, secs)| {
+            let silence = vec![0.0; (silence * RATE as f32) as usize];
+            silence.into_iter().chain(tone(RATE, 1, *secs, 440.0))
+        })
+        .collect()
+}
+
+#[test]
+fn speech_segments_are_bounded_by_the_sound_and_its_padding() {
+    // A burst of 1 s and one of 0.5 s separated by a short pause, a click too short to be speech,
+    // then silence
+    let input = bursts(&[(1.0, 1.0), (0.2, 0.5), (1.0, 0.1), (1.0, 0.0)]);
+    let input_path = write_fixture("dsp_vad.wav", &float_wav(&input, RATE, 1));
+
+    let options = VadOptions::default();
+    let segments = AudioConverterBuilder::new(tmp_path("dsp_vad_output.wav"), RATE)
+        .with_input_path(&input_path)
+        .with_vad(options)
+        .build()
+        .unwrap()
+        .detect_speech()
+        .unwrap();
+    assert_eq!(segments.len(), 1, "{:?}", segments);
+
+    // The segment covers both bursts and the padding, give or take an analysis frame
+    let start = 1.0 - options.padding_secs;
+    let end = 2.7 + options.padding_secs;
+    let segment = segments[0];
+    assert!(
+        (segment.start.as_secs_f32() - start).abs() <= options.frame_secs,
+        "starts at {:?}",
+        segment.start
+    );
+    assert!(
+        (segment.end.as_secs_f32() - end).abs() <= options.frame_secs,
+        "ends at {:?}",
+        segment.end
+    );
+
+    // The output holds only the segment
+    let output = convert(
+        float_output("dsp_vad_speech.wav").with_vad(options),
+        "dsp_vad_speech.wav",
+        &input,
+        1,
+    );
+    let segment_frames = (segment.end - segment.start).as_secs_f64() * RATE as f64;
+    assert_eq!(output.len(), segment_frames.round() as usize);
+}
+
+#[test]
+fn fades_follow_their_envelope() {
+    let input = vec![0.5; RATE as usize];
+    let fade = Duration::from_millis(250);
+    let fade_frames = RATE as usize / 4;
+
+    for shape in [FadeShape::Linear, FadeShape::Cosine] {
+        let name = format!("dsp_fade_{:?}.wav", shape);
+        let output = convert(
+            float_output(&name)
+                .with_fade(fade, fade)
+                .with_fade_shape(shape),
+            &name,
+            &input,
+            1,
+        );
+        assert_eq!(output.len(), input.len());
+
+        let gain = |position: f32| match shape {
+            FadeShape::Linear => position,
+            FadeShape::Cosine => 0.5 - 0.5 * (position * std::f32::consts::PI).cos(),
+        };
+        for frame in 0..fade_frames {
+            let expected = 0.5 * gain(frame as f32 / fade_frames as f32);
+            assert!(
+                (output[frame] - expected).abs() < 1e-6,
+                "{:?} fade in",
+                shape
+            );
+            let from_end = output.len() - 1 - frame;
+            assert!(
+                (output[from_end] - expected).abs() < 1e-6,
+                "{:?} fade out",
+                shape
+            );
+        }
+        assert!(output[fade_frames..input.len() - fade_frames]
+            .iter()
+            .all(|sample| *sample == 0.5));
+    }
+}
+
+#[test]
+fn filters_attenuate_by_3_db_at_the_cutoff() {
+    let lowpass = |builder: AudioConverterBuilder| builder.with_lowpass(1000.0);
+    let highpass = |builder: AudioConverterBuilder| builder.with_highpass(1000.0);
+
+    // The response of a second-order Butterworth filter, with the frequencies warped by the
+    // bilinear transform: 3 dB down at the cutoff and 12 dB per octave beyond it
+    let warped = |frequency: f32| (std::f32::consts::PI * frequency / RATE as f32).tan();
+    let response_db = |ratio: f32| -10.0 * (1.0 + ratio.powi(4)).log10();
+
+    for frequency in [100.0, 1000.0, 4000.0] {
+        let name = format!("dsp_lowpass_{}.wav", frequency);
+        let db = filtered_db(&name, frequency, lowpass);
+        let expected_db = response_db(warped(frequency) / warped(1000.0));
+        assert!(
+            (db - expected_db).abs() < 0.5,
+            "low-pass: {} Hz at {:.2} dB instead of {:.2} dB",
+            frequency,
+            db,
+            expected_db
+        );
+    }
+    for frequency in [250.0, 1000.0, 6000.0] {
+        let name = format!("dsp_highpass_{}.wav", frequency);
+        let db = filtered_db(&name, frequency, highpass);
+        let expected_db = response_db(warped(1000.0) / warped(frequency));
+        assert!(
+            (db - expected_db).abs() < 0.5,
+            "high-pass: {} Hz at {:.2} dB instead of {:.2} dB",
+            frequency,
+            db,
+            expected_db
+        );
+    }
+}
+
+#[test]
+fn tempo_changes_the_length_but_not_the_pitch() {
+    let input = tone(RATE, 1, 2.0, 440.0);
+
+    for tempo in [1.5, 0.8] {
+        let name = format!("dsp_tempo_{}.wav", tempo);
+        let output = convert(float_output(&name).with_tempo(tempo), &name, &input, 1);
+        assert_eq!(
+            output.len(),
+            (input.len() as f32 / tempo).round() as usize,
+            "tempo {}",
+            tempo
+        );
+
+        // Two upward zero crossings per period of the tone, away from the edges
+        let middle = &output[RATE as usize / 10..output.len() - RATE as usize / 10];
+        let crossings = middle
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        let frequency = crossings as f32 * RATE as f32 / middle.len() as f32;
+        assert!(
+            (frequency - 440.0).abs() < 5.0,
+            "tempo {}: {} Hz",
+            tempo,
+            frequency
+        );
+    }
+}
+
+/// One second of stereo audio with a different tone on each channel.
+fn two_tones() -> Vec<f32> {
+    tone(RATE, 1, 1.0, 440.0)
+        .into_iter()
+        .zip(tone(RATE, 1, 1.0, 1000.0))
+        .flat_map(|(left, right)| [left, 0.5 * right])
+        .collect()
+}
+
+#[test]
+fn channel_matrix_mixes_each_output_channel() {
+    let input = two_tones();
+    let left = channel(&input, 2, 0);
+    let right = channel(&input, 2, 1);
+
+    // A mono mix with the left channel attenuated, and swapped stereo channels
+    let output = convert(
+        float_output("dsp_matrix_mono.wav").with_channel_matrix(vec![vec![0.5, 1.0]]),
+        "dsp_matrix_mono.wav",
+        &input,
+        2,
+    );
+    assert_eq!(output.len(), left.len());
+    for ((output, left), right) in output.iter().zip(&left).zip(&right) {
+        assert!((output - (0.5 * left + right)).abs() < 1e-6);
+    }
+
+    let output = convert(
+        float_output("dsp_matrix_swap.wav")
+            .with_channel_matrix(vec![vec![0.0, 1.0], vec![1.0, 0.0]]),
+        "dsp_matrix_swap.wav",
+        &input,
+        2,
+    );
+    assert_eq!(channel(&output, 2, 0), right);
+    assert_eq!(channel(&output, 2, 1), left);
+}
+
+#[test]
+fn each_channel_is_written_to_its_own_file() {
+    let input = two_tones();
+    let input_path = write_fixture("dsp_channels_input.wav", &float_wav(&input, RATE, 2));
+
+    let paths = float_output("dsp_channels.wav")
+        .with_input_path(&input_path)
+        .build()
+        .unwrap()
+        .convert_audio_channels()
+        .unwrap();
+    assert_eq!(
+        paths,
+        [
+            tmp_path("dsp_channels_L.wav"),
+            tmp_path("dsp_channels_R.wav")
+        ]
+    );
+    for (index, path) in paths.iter().enumerate() {
+        let (spec, samples) = read_wav(path);
+        assert_eq!(spec.channels, 1);
+        assert_eq!(samples, channel(&input, 2, index), "{}", path.display());
+    }
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "mp3")]
+
+mod common;
+
+use common::{sample_path, tmp_path};
+use wavup::{AudioConverterBuilder, AudioInspector};
+
+/// The length of the MPEG audio frame whose header starts `bytes`, for layer III.
+fn mp3_frame_len(bytes: &[u8]) -> usize {
+    assert!(
+        bytes[0] == 0xff && bytes[1] & 0xe0 == 0xe0,
+        "not a frame header"
+    );
+    let mpeg1 = (bytes[1] >> 3) & 0x3 == 0x3;
+    let bitrates: [u32; 15] = if mpeg1 {
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ]
+    } else {
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160]
+    };
+    let sample_rate = [44100, 48000, 32000][((bytes[2] >> 2) & 0x3) as usize]
+        >> match (bytes[1] >> 3) & 0x3 {
+            0x3 => 0,
+            0x2 => 1,
+            _ => 2,
+        };
+    let bitrate = bitrates[(bytes[2] >> 4) as usize] * 1000;
+    let padding = ((bytes[2] >> 1) & 0x1) as u32;
+    ((if mpeg1 { 144 } else { 72 }) * bitrate / sample_rate + padding) as usize
+}
+
+/// `mp3` without the Xing/Info frame declaring its number of frames.
+fn strip_info_frame(mp3: &[u8]) -> Vec<u8> {
+    let id3_len = if mp3.starts_with(b"ID3") {
+        10 + mp3[6..10]
+            .iter()
+            .fold(0usize, |len, byte| (len << 7) | *byte as usize)
+    } else {
+        0
+    };
+    let frame_len = mp3_frame_len(&mp3[id3_len..]);
+    let frame = &mp3[id3_len..id3_len + frame_len];
+    assert!(frame.windows(4).any(|tag| tag == b"Xing" || tag == b"Info"));
+    [&mp3[..id3_len], &mp3[id3_len + frame_len..]].concat()
+}
+
+#[test]
+fn vbr_mp3_duration_is_counted_from_the_decoded_frames() {
+    // A VBR MP3 whose frame count is only declared by its Info frame
+    let mp3 = std::fs::read(sample_path("mono_ch_audio.mp3")).unwrap();
+    let declared = AudioInspector::probe(&mp3).unwrap();
+    let known_secs = declared.duration_secs.unwrap();
+
+    // Without it, the duration can only be estimated from the bitrate before decoding
+    let stripped = strip_info_frame(&mp3);
+    let report = AudioConverterBuilder::new(tmp_path("duration_vbr.wav"), 16000)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&stripped)
+        .unwrap();
+
+    let input_frame = 1.0 / declared.sample_rate as f64;
+    assert!(
+        (report.input_duration_secs - known_secs).abs() <= input_frame,
+        "input of {} s instead of {} s",
+        report.input_duration_secs,
+        known_secs
+    );
+    assert!(
+        (report.output_duration_secs - known_secs).abs() <= 1.0 / 16000.0,
+        "output of {} s instead of {} s",
+        report.output_duration_secs,
+        known_secs
+    );
+}
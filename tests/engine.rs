@@ -0,0 +1,71 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone, write_fixture};
+use wavup::{AudioConverterBuilder, ConverterEngine};
+
+#[test]
+fn engine_reuses_resamplers_and_matches_a_fresh_converter() {
+    let inputs = [(44100, 440.0), (44100, 880.0), (22050, 440.0)].map(|(rate, frequency)| {
+        write_fixture(
+            &format!("engine_{}_{}.wav", rate, frequency),
+            &float_wav(&tone(rate, 1, 1.0, frequency), rate, 1),
+        )
+    });
+    let engine = ConverterEngine::new(
+        AudioConverterBuilder::new("unused.wav", 16000)
+            .with_overwrite(true)
+            .build()
+            .unwrap(),
+    );
+
+    for (index, input) in inputs.iter().chain(&inputs).enumerate() {
+        let pooled_path = tmp_path(&format!("engine_pooled_{}.wav", index));
+        engine.convert(input, &pooled_path).unwrap();
+
+        let fresh_path = tmp_path(&format!("engine_fresh_{}.wav", index));
+        AudioConverterBuilder::new(&fresh_path, 16000)
+            .with_input_path(input)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio()
+            .unwrap();
+        assert_eq!(read_wav(&pooled_path).1, read_wav(&fresh_path).1);
+    }
+
+    // One resampler per input rate, reused by the second round of conversions
+    assert_eq!(engine.idle_resamplers(), 2);
+}
+
+#[test]
+fn engine_reuses_the_resamplers_of_streamed_conversions() {
+    let input = write_fixture(
+        "engine_streamed.wav",
+        &float_wav(&tone(44100, 1, 1.0, 440.0), 44100, 1),
+    );
+    // Any input exceeds the limit, so every conversion is streamed
+    let engine = ConverterEngine::new(
+        AudioConverterBuilder::new("unused.wav", 16000)
+            .with_max_memory(1)
+            .with_overwrite(true)
+            .build()
+            .unwrap(),
+    );
+
+    for index in 0..2 {
+        let pooled_path = tmp_path(&format!("engine_streamed_pooled_{}.wav", index));
+        engine.convert(&input, &pooled_path).unwrap();
+        assert_eq!(engine.idle_resamplers(), 1);
+
+        let fresh_path = tmp_path(&format!("engine_streamed_fresh_{}.wav", index));
+        AudioConverterBuilder::new(&fresh_path, 16000)
+            .with_input_path(&input)
+            .with_max_memory(1)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio()
+            .unwrap();
+        assert_eq!(read_wav(&pooled_path).1, read_wav(&fresh_path).1);
+    }
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::{tmp_path, write_fixture};
+use std::{error::Error, io::ErrorKind};
+use wavup::{AudioConversionError, AudioConverterBuilder};
+
+#[test]
+fn io_error_of_a_missing_input_is_chained() {
+    // The input exists when the converter is built, which checks it, and is gone when it is opened
+    let input_path = write_fixture("error_removed.flac", b"");
+    let converter = AudioConverterBuilder::new(tmp_path("error_removed.wav"), 16000)
+        .with_input_path(&input_path)
+        .build()
+        .unwrap();
+    std::fs::remove_file(&input_path).unwrap();
+
+    let err = converter.convert_audio().unwrap_err();
+    assert!(matches!(err, AudioConversionError::IoError(_)));
+
+    let source = err.source().expect("the I/O error is the source");
+    let io_error = source
+        .downcast_ref::<std::io::Error>()
+        .expect("the source is an io::Error");
+    assert_eq!(io_error.kind(), ErrorKind::NotFound);
+}
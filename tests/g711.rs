@@ -0,0 +1,99 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path};
+use wavup::{AudioConverterBuilder, PcmEncoding};
+
+const RATE: u32 = 8000;
+
+/// The size of the RIFF header, the `fmt ` and `fact` chunks and the `data` chunk header.
+const HEADER_LEN: usize = 58;
+
+/// Convert the 16-bit `samples` to mono G.711 WAV output and return the bytes of the file.
+fn encode(name: &str, encoding: PcmEncoding, samples: &[i16]) -> Vec<u8> {
+    let samples: Vec<f32> = samples
+        .iter()
+        .map(|sample| *sample as f32 / 32768.0)
+        .collect();
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_encoding(encoding)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&samples, RATE, 1))
+        .unwrap();
+    std::fs::read(output_path).unwrap()
+}
+
+#[test]
+fn mulaw_codes_match_the_reference_table() {
+    // Silence, full scale, and both sides of the edge between the first two segments
+    let samples = [0, 32767, -32768, 120, 124, -120, -124];
+    let bytes = encode("g711_mulaw_codes.wav", PcmEncoding::MuLaw, &samples);
+    assert_eq!(
+        bytes[HEADER_LEN..HEADER_LEN + samples.len()],
+        [0xFF, 0x80, 0x00, 0xF0, 0xEF, 0x70, 0x6F]
+    );
+}
+
+#[test]
+fn alaw_codes_match_the_reference_table() {
+    // Silence, full scale, and both sides of the edge between the first two segments
+    let samples = [0, 32767, -32768, 248, 256, -256, -264];
+    let bytes = encode("g711_alaw_codes.wav", PcmEncoding::ALaw, &samples);
+    assert_eq!(
+        bytes[HEADER_LEN..HEADER_LEN + samples.len()],
+        [0xD5, 0xAA, 0x2A, 0xDA, 0xC5, 0x5A, 0x45]
+    );
+}
+
+#[test]
+fn g711_output_decodes_back_to_the_input() {
+    // An odd number of samples, so the data chunk is followed by a pad byte
+    let samples: Vec<i16> = (0..RATE as usize + 1)
+        .map(|frame| {
+            let t = frame as f32 / RATE as f32;
+            (12000.0 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()) as i16
+        })
+        .collect();
+
+    for (encoding, format_tag) in [(PcmEncoding::MuLaw, 7), (PcmEncoding::ALaw, 6)] {
+        let name = format!("g711_round_trip_{:?}.wav", encoding);
+        let bytes = encode(&name, encoding, &samples);
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        // The fmt chunk declares the encoding, the fact chunk the number of frames, and the
+        // chunk sizes leave out the pad byte of the data chunk but the RIFF size includes it
+        assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), format_tag);
+        assert_eq!(&bytes[38..42], b"fact");
+        assert_eq!(read_u32(46) as usize, samples.len());
+        assert_eq!(read_u32(54) as usize, samples.len());
+        assert_eq!(bytes.len(), HEADER_LEN + samples.len() + 1);
+        assert_eq!(bytes.last(), Some(&0));
+        assert_eq!(read_u32(4) as usize, bytes.len() - 8);
+
+        // Decoding it with symphonia gives back the input, within the step of the companding
+        // curve, which is at most 1/32 of the magnitude below 8192
+        let decoded_path = tmp_path(&format!("g711_round_trip_{:?}_decoded.wav", encoding));
+        AudioConverterBuilder::new(&decoded_path, RATE)
+            .with_input_path(tmp_path(&name))
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio()
+            .unwrap();
+        let (_, decoded) = read_wav(&decoded_path);
+        assert_eq!(decoded.len(), samples.len());
+        for (decoded, sample) in decoded.iter().zip(&samples) {
+            let error = (decoded * 32768.0 - *sample as f32).abs();
+            assert!(
+                error <= (*sample as f32).abs() / 32.0 + 16.0,
+                "{:?} decoded {} as {}",
+                encoding,
+                sample,
+                decoded * 32768.0
+            );
+        }
+    }
+}
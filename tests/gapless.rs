@@ -0,0 +1,42 @@
+#![cfg(feature = "mp3")]
+
+mod common;
+
+use common::{read_wav, sample_path, tmp_path};
+use wavup::AudioConverterBuilder;
+
+/// The number of frames of the sample MP3, from its Info header: 387 frames of 576 samples.
+const ENCODED_FRAMES: usize = 387 * 576;
+
+/// The encoder delay and padding declared by the LAME tag of the sample MP3.
+const ENCODER_DELAY: usize = 576;
+const ENCODER_PADDING: usize = 896;
+
+/// The samples of the mono sample MP3 decoded at its own sample rate.
+fn decode(gapless: bool) -> Vec<f32> {
+    let output_path = tmp_path(&format!("gapless_{}.wav", gapless));
+    AudioConverterBuilder::new(&output_path, 22050)
+        .with_input_path(sample_path("mono_ch_audio.mp3"))
+        .with_gapless(gapless)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    read_wav(output_path).1
+}
+
+#[test]
+fn gapless_removes_the_encoder_delay_and_padding() {
+    let full = decode(false);
+    let gapless = decode(true);
+    assert_eq!(full.len(), ENCODED_FRAMES);
+    assert_eq!(
+        gapless.len(),
+        ENCODED_FRAMES - ENCODER_DELAY - ENCODER_PADDING
+    );
+
+    // The decoder adds 529 frames of delay on top of the encoder delay
+    let offset = ENCODER_DELAY + 529;
+    assert_eq!(gapless[..], full[offset..offset + gapless.len()]);
+}
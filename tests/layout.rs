@@ -0,0 +1,121 @@
+#![cfg(feature = "mp3")]
+
+mod common;
+
+use common::{flac_in_ogg, float_wav, read_wav, rms, sample_path, tmp_path, tone, write_fixture};
+use std::{path::PathBuf, time::Duration};
+use wavup::{AudioConversionError, AudioConverterBuilder, OutputFormat};
+
+const RATE: u32 = 22050;
+
+/// Ten seconds of mono audio at 22050 Hz followed by thirty seconds of stereo audio at 44100 Hz,
+/// as in a capture whose layout changes mid-stream. MP3 frames decode on their own, so the
+/// second file is appended without its ID3v2 tag.
+fn layout_change() -> PathBuf {
+    let mut mp3 = std::fs::read(sample_path("mono_ch_audio.mp3")).unwrap();
+    let stereo = std::fs::read(sample_path("dual_ch_audio.mp3")).unwrap();
+    assert_eq!(&stereo[..3], b"ID3");
+    let tag_len = stereo[6..10]
+        .iter()
+        .fold(0, |len, byte| len << 7 | *byte as usize);
+    mp3.extend_from_slice(&stereo[10 + tag_len..]);
+    write_fixture("layout_change.mp3", &mp3)
+}
+
+#[test]
+fn audio_after_a_layout_change_is_adapted_to_the_first_layout() {
+    let output_path = tmp_path("layout_full.wav");
+    let report = AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(layout_change())
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    assert!(!report.truncated);
+
+    // Both parts are converted, the stereo one downmixed and resampled
+    let (spec, samples) = read_wav(&output_path);
+    assert_eq!((spec.channels, spec.sample_rate), (1, RATE));
+    let secs = samples.len() as f64 / RATE as f64;
+    assert!((secs - (10.109 + 30.145)).abs() < 0.1, "{} s", secs);
+    let stereo_part = &samples[11 * RATE as usize..];
+    assert!(rms(stereo_part) > 0.01, "RMS of {}", rms(stereo_part));
+}
+
+#[test]
+fn a_window_past_a_layout_change_is_decoded_from_the_beginning() {
+    let input = layout_change();
+    let convert = |name: &str, range: Option<(u64, u64)>| {
+        let output_path = tmp_path(name);
+        let mut builder = AudioConverterBuilder::new(&output_path, RATE)
+            .with_input_path(&input)
+            .with_overwrite(true);
+        if let Some((start, end)) = range {
+            builder =
+                builder.with_time_range(Duration::from_secs(start), Some(Duration::from_secs(end)));
+        }
+        builder.build().unwrap().convert_audio().unwrap();
+        read_wav(output_path).1
+    };
+    let full = convert("layout_window_full.wav", None);
+    let window = convert("layout_window.wav", Some((15, 16)));
+
+    // The MP3 reader counts the timestamps after the change at 44100 Hz, so a seek to the start
+    // of the window would land seconds away from it. The window matches the full conversion, but
+    // for the blocks of the resampler starting at another frame.
+    let expected = &full[15 * RATE as usize..16 * RATE as usize];
+    assert_eq!(window.len(), expected.len());
+    let error: Vec<f32> = window
+        .iter()
+        .zip(expected)
+        .map(|(window, expected)| window - expected)
+        .collect();
+    assert!(
+        rms(&error) < rms(expected) / 100.0,
+        "error of RMS {} for a signal of RMS {}",
+        rms(&error),
+        rms(expected)
+    );
+}
+
+#[test]
+fn layouts_that_cannot_be_adapted_fail_with_their_position() {
+    // Three channels, then stereo, which cannot be upmixed back to them
+    let flac = |name: &str, channels: u16| {
+        let path = tmp_path(name);
+        AudioConverterBuilder::new(&path, 16000)
+            .with_output_format(OutputFormat::Flac {
+                compression_level: 5,
+            })
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio_from_bytes(&float_wav(
+                &tone(16000, channels as usize, 1.0, 440.0),
+                16000,
+                channels,
+            ))
+            .unwrap();
+        std::fs::read(path).unwrap()
+    };
+    let chained = [
+        flac_in_ogg(&flac("layout_3ch.flac", 3), 1),
+        flac_in_ogg(&flac("layout_2ch.flac", 2), 2),
+    ]
+    .concat();
+    let input_path = write_fixture("layout_3ch_2ch.ogg", &chained);
+
+    let result = AudioConverterBuilder::new(tmp_path("layout_3ch_2ch.wav"), 16000)
+        .with_input_path(input_path)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio();
+    match result {
+        Err(AudioConversionError::UnsupportedFormat(message)) => {
+            assert!(message.contains("at 1.000 s"), "{}", message)
+        }
+        result => panic!("{:?}", result),
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 48000;
+
+#[test]
+fn mastering_keeps_the_true_peak_below_the_ceiling() {
+    // A quiet 1 kHz tone at -29 LUFS with a 1 ms burst at -3 dBFS every second: reaching -16 LUFS
+    // needs 13 dB of gain, which would push the bursts 10 dB over full scale
+    let samples: Vec<f32> = tone(RATE, 2, 5.0, 1000.0)
+        .chunks(2)
+        .enumerate()
+        .flat_map(|(frame, samples)| {
+            let gain = if frame % (RATE as usize) < RATE as usize / 1000 {
+                1.4
+            } else {
+                0.06
+            };
+            samples
+                .iter()
+                .map(move |sample| sample * gain)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let output_path = tmp_path("master.wav");
+    let report = AudioConverterBuilder::new(&output_path, RATE)
+        .with_master(-16.0, -1.0)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&samples, RATE, 2))
+        .unwrap();
+
+    // The gain is reduced to respect the ceiling, so the output lands below the loudness target
+    let measured = report.measured_loudness_lufs.unwrap();
+    let applied_gain = report.applied_gain_db.unwrap();
+    assert!(applied_gain < -16.0 - measured - 1.0);
+    assert!(report.output_true_peak_dbtp.unwrap() <= -1.0 + 1e-6);
+    assert!(report.output_loudness_lufs.unwrap() < -16.0);
+    assert!((report.output_loudness_lufs.unwrap() - (measured + applied_gain)).abs() < 0.1);
+
+    // The written samples stay below the ceiling, up to the rounding of the 16-bit output
+    let (_, output) = read_wav(&output_path);
+    let peak = output
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    assert!(
+        20.0 * peak.log10() <= -1.0 + 0.01,
+        "peak of {} dBFS",
+        20.0 * peak.log10()
+    );
+}
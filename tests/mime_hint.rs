@@ -0,0 +1,65 @@
+#![cfg(feature = "mp3")]
+
+mod common;
+
+use common::{sample_path, tmp_path, write_fixture};
+use wavup::{AudioConverterBuilder, AudioInspector};
+
+/// The sample MP3 behind a few bytes of junk that happen to hold an Ogg page marker, as left by a
+/// broken upload or a stream recorder.
+fn junk_prefixed_mp3() -> Vec<u8> {
+    let mut bytes = b"\x00\x01junkOggS\x00\x02not a page\x00".to_vec();
+    bytes.extend_from_slice(&std::fs::read(sample_path("mono_ch_audio.mp3")).unwrap());
+    bytes
+}
+
+#[test]
+fn mime_type_hint_skips_a_foreign_marker_in_leading_junk() {
+    let bytes = junk_prefixed_mp3();
+    let converter = |mime_type: Option<&str>| {
+        let mut builder =
+            AudioConverterBuilder::new(tmp_path("mime_hint.wav"), 16000).with_overwrite(true);
+        if let Some(mime_type) = mime_type {
+            builder = builder.with_mime_type(mime_type);
+        }
+        builder.build().unwrap()
+    };
+
+    // Without a hint, the Ogg marker is taken for the container
+    assert!(converter(None).convert_audio_from_bytes(&bytes).is_err());
+
+    let report = converter(Some("audio/mpeg; charset=binary"))
+        .convert_audio_from_bytes(&bytes)
+        .unwrap();
+    assert_eq!(report.input_codec, Some("mp3"));
+}
+
+#[test]
+fn input_extension_is_the_default_hint() {
+    let input_path = write_fixture("mime_hint_junk.mp3", &junk_prefixed_mp3());
+    let info = AudioInspector::probe(input_path.as_path()).unwrap();
+    assert_eq!(info.container, Some("mp3"));
+
+    let report = AudioConverterBuilder::new(tmp_path("mime_hint_path.wav"), 16000)
+        .with_input_path(&input_path)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    assert!(report.output_duration_secs > 0.0);
+}
+
+#[test]
+fn hint_does_not_override_a_marker_at_the_start() {
+    // The first marker is trusted whatever the hint
+    let report = AudioConverterBuilder::new(tmp_path("mime_hint_start.wav"), 16000)
+        .with_input_path(sample_path("example_vorbris.ogg"))
+        .with_mime_type("audio/mpeg")
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    assert_eq!(report.input_codec, Some("vorbis"));
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 16000;
+
+/// The mono compatibility reported for converting the interleaved `samples`.
+fn mono_compatibility_db(samples: &[f32], channels: u16, name: &str) -> Option<f64> {
+    AudioConverterBuilder::new(tmp_path(name), RATE)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, channels))
+        .unwrap()
+        .mono_compatibility_db
+}
+
+#[test]
+fn anti_phase_stereo_is_flagged() {
+    let in_phase = tone(RATE, 2, 1.0, 440.0);
+    let anti_phase: Vec<f32> = in_phase
+        .chunks(2)
+        .flat_map(|frame| [frame[0], -frame[1]])
+        .collect();
+
+    let in_phase_db = mono_compatibility_db(&in_phase, 2, "mono_in_phase.wav").unwrap();
+    assert!(in_phase_db.abs() < 0.1, "in phase at {} dB", in_phase_db);
+
+    let anti_phase_db = mono_compatibility_db(&anti_phase, 2, "mono_anti_phase.wav").unwrap();
+    assert!(anti_phase_db < -40.0, "anti-phase at {} dB", anti_phase_db);
+
+    let mono = tone(RATE, 1, 1.0, 440.0);
+    assert_eq!(mono_compatibility_db(&mono, 1, "mono_mono.wav"), None);
+}
@@ -0,0 +1,131 @@
+#![cfg(all(feature = "mp3-encoder", feature = "mp3"))]
+
+mod common;
+
+use common::{channel, float_wav, read_wav, rms, tmp_path, tone, write_fixture};
+use wavup::{AudioConversionError, AudioConverterBuilder, AudioInspector, OutputFormat};
+
+/// Encode two seconds of a 440 Hz stereo tone at 44.1 kHz to MP3 and return its path.
+fn encode(name: &str, format: OutputFormat) -> Result<std::path::PathBuf, AudioConversionError> {
+    let input_path = write_fixture(
+        &format!("{}_input.wav", name),
+        &float_wav(&tone(44100, 2, 2.0, 440.0), 44100, 2),
+    );
+    let output_path = tmp_path(&format!("{}.mp3", name));
+    AudioConverterBuilder::new(&output_path, 44100)
+        .with_input_path(input_path)
+        .with_target_channels(2)
+        .with_output_format(format)
+        .with_overwrite(true)
+        .build()?
+        .convert_audio()?;
+    Ok(output_path)
+}
+
+#[test]
+fn mp3_output_decodes_to_the_input() {
+    let mp3_path = encode(
+        "mp3_output",
+        OutputFormat::Mp3 {
+            bitrate_kbps: 128,
+            quality: 2,
+        },
+    )
+    .unwrap();
+
+    // The LAME tag lets the decoder drop the encoder delay and padding
+    let output_path = tmp_path("mp3_output_decoded.wav");
+    AudioConverterBuilder::new(&output_path, 44100)
+        .with_input_path(&mp3_path)
+        .with_target_channels(2)
+        .with_gapless(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    let (spec, samples) = read_wav(output_path);
+    assert_eq!(spec.channels, 2);
+    assert_eq!(samples.len(), 2 * 88200);
+
+    // The encoder loses about half a dB of the tone at 128 kbps
+    let expected = rms(&tone(44100, 1, 2.0, 440.0));
+    for index in 0..2 {
+        let level = rms(&channel(&samples, 2, index));
+        assert!(
+            (level / expected - 1.0).abs() < 0.1,
+            "{} vs {}",
+            level,
+            expected
+        );
+    }
+}
+
+#[test]
+fn mp3_output_rejects_invalid_bitrates() {
+    let err = encode(
+        "mp3_output_bitrate",
+        OutputFormat::Mp3 {
+            bitrate_kbps: 100,
+            quality: 2,
+        },
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&err, AudioConversionError::UnsupportedFormat(msg) if msg.contains("100 kbps")),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn mp3_output_stores_the_tags_before_the_lame_tag() {
+    // A WAV input with a `LIST` chunk of type `INFO` holding the title and artist, placed before
+    // the `data` chunk where the WAV reader looks for it
+    let mut wav = float_wav(&tone(44100, 1, 1.0, 440.0), 44100, 1);
+    let mut list = b"LIST\0\0\0\0INFO".to_vec();
+    for (id, value) in [(b"INAM", &b"Tone\0\0"[..]), (b"IART", &b"wavup\0"[..])] {
+        list.extend_from_slice(id);
+        list.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        list.extend_from_slice(value);
+    }
+    let list_len = (list.len() - 8) as u32;
+    list[4..8].copy_from_slice(&list_len.to_le_bytes());
+    let data_pos = wav.windows(4).position(|id| id == b"data").unwrap();
+    wav.splice(data_pos..data_pos, list);
+    let riff_len = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    let input_path = write_fixture("mp3_output_tags_input.wav", &wav);
+
+    let mp3_path = tmp_path("mp3_output_tags.mp3");
+    AudioConverterBuilder::new(&mp3_path, 44100)
+        .with_input_path(input_path)
+        .with_target_channels(1)
+        .with_output_format(OutputFormat::Mp3 {
+            bitrate_kbps: 64,
+            quality: 5,
+        })
+        .with_copy_metadata(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    assert_eq!(&std::fs::read(&mp3_path).unwrap()[..3], b"ID3");
+
+    let tags = AudioInspector::probe(mp3_path.as_path()).unwrap().tags;
+    assert_eq!(tags.title.as_deref(), Some("Tone"));
+    assert_eq!(tags.artist.as_deref(), Some("wavup"));
+
+    // The decoder only finds the LAME tag right after the ID3v2 tag
+    let output_path = tmp_path("mp3_output_tags_decoded.wav");
+    AudioConverterBuilder::new(&output_path, 44100)
+        .with_input_path(&mp3_path)
+        .with_gapless(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    assert_eq!(read_wav(output_path).1.len(), 44100);
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone, write_fixture};
+use wavup::AudioConverterBuilder;
+
+#[test]
+fn buffer_size_does_not_change_the_output() {
+    let input_path = write_fixture(
+        "output_buffer.wav",
+        &float_wav(&tone(44100, 2, 2.0, 440.0), 44100, 2),
+    );
+    let convert = |size: usize| {
+        let output_path = tmp_path(&format!("output_buffer_{}.wav", size));
+        AudioConverterBuilder::new(&output_path, 16000)
+            .with_input_path(&input_path)
+            .with_output_buffer_size(size)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio()
+            .unwrap();
+        std::fs::read(output_path).unwrap()
+    };
+
+    let tiny = convert(1);
+    let large = convert(4 << 20);
+    assert_eq!(tiny.len(), 44 + 2 * 16000 * 4);
+    assert_eq!(tiny, large);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{int16_wav, read_wav, tmp_path, tone};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 16000;
+
+/// The spec and samples of converting `secs` of a stereo 16-bit tone padded to five seconds.
+fn padded(secs: f32, name: &str) -> (hound::WavSpec, Vec<f32>) {
+    let samples: Vec<i16> = tone(RATE, 2, secs, 440.0)
+        .iter()
+        .map(|sample| (sample * i16::MAX as f32) as i16)
+        .collect();
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_pad_to_secs(5.0)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&int16_wav(&samples, RATE, 2))
+        .unwrap();
+    read_wav(output_path)
+}
+
+#[test]
+fn short_clip_is_padded_with_silence() {
+    let (spec, samples) = padded(2.0, "pad_short.wav");
+    assert_eq!(spec.channels, 2);
+    assert_eq!(spec.bits_per_sample, 16);
+    assert_eq!(samples.len(), 5 * RATE as usize * 2);
+
+    // The clip is kept and followed by three seconds of silence in both channels
+    let (clip, tail) = samples.split_at(2 * RATE as usize * 2);
+    assert!(clip[clip.len() - 200..].iter().any(|sample| *sample != 0.0));
+    assert!(tail.iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn long_clip_is_left_untouched() {
+    let (_, samples) = padded(6.0, "pad_long.wav");
+    assert_eq!(samples.len(), 6 * RATE as usize * 2);
+}
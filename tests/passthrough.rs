@@ -0,0 +1,59 @@
+mod common;
+
+use common::{int16_wav, tmp_path, write_fixture};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 44100;
+
+/// The contents of the `data` chunk of the WAV file `bytes`.
+fn data_chunk(bytes: &[u8]) -> &[u8] {
+    let mut offset = 12;
+    loop {
+        let id = &bytes[offset..offset + 4];
+        let len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        if id == b"data" {
+            return &bytes[offset + 8..offset + 8 + len];
+        }
+        offset += 8 + len + len % 2;
+    }
+}
+
+#[test]
+fn matching_wav_is_passed_through_byte_for_byte() {
+    // Two seconds of stereo noise over the whole 16-bit range, full-scale negative samples
+    // included
+    let mut state = 0x2545_f491u32;
+    let samples: Vec<i16> = (0..2 * 2 * RATE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as i16
+        })
+        .chain([i16::MIN, i16::MAX])
+        .collect();
+    let input = int16_wav(&samples, RATE, 2);
+    let input_path = write_fixture("passthrough.wav", &input);
+
+    let convert = |passthrough: bool| {
+        let output_path = tmp_path(&format!("passthrough_{}.wav", passthrough));
+        let report = AudioConverterBuilder::new(&output_path, RATE)
+            .with_input_path(&input_path)
+            .with_passthrough_identical(passthrough)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio()
+            .unwrap();
+        (report, std::fs::read(output_path).unwrap())
+    };
+
+    let (report, output) = convert(true);
+    assert_eq!(report.decoded_packets, 0);
+    assert_eq!(data_chunk(&output), data_chunk(&input));
+
+    // The decoded path reaches the same samples, but only by decoding them
+    let (report, output) = convert(false);
+    assert!(report.decoded_packets > 0);
+    assert_eq!(data_chunk(&output), data_chunk(&input));
+}
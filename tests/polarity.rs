@@ -0,0 +1,40 @@
+mod common;
+
+use common::{float_wav, read_wav, rms, tmp_path, tone};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 16000;
+
+/// The RMS level of the mono downmix of a stereo tone whose right channel is out of phase, with
+/// the polarity of the channels flagged by `invert` inverted.
+fn downmix_rms(invert: Option<Vec<bool>>, name: &str) -> f32 {
+    let samples: Vec<f32> = tone(RATE, 2, 1.0, 440.0)
+        .chunks(2)
+        .flat_map(|frame| [frame[0], -frame[1]])
+        .collect();
+    let output_path = tmp_path(name);
+    let mut builder = AudioConverterBuilder::new(&output_path, RATE)
+        .with_target_channels(1)
+        .with_overwrite(true);
+    if let Some(invert) = invert {
+        builder = builder.with_invert_channels(invert);
+    }
+    builder
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&samples, RATE, 2))
+        .unwrap();
+    let (_, samples) = read_wav(output_path);
+    rms(&samples)
+}
+
+#[test]
+fn inverting_the_out_of_phase_channel_fixes_the_downmix() {
+    // Left as is, the channels cancel each other out
+    assert!(downmix_rms(None, "polarity_cancelled.wav") < 1e-6);
+
+    // Inverted before the fold-down, they add up to the level of the tone
+    let fixed = downmix_rms(Some(vec![false, true]), "polarity_fixed.wav");
+    let expected = rms(&tone(RATE, 1, 1.0, 440.0));
+    assert!((fixed - expected).abs() < 1e-3, "{} vs {}", fixed, expected);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone, CountingSource};
+use std::{
+    io::Cursor,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use wavup::{AudioConversionError, AudioConverterBuilder};
+
+const RATE: u32 = 16000;
+
+/// Convert ten seconds of 8-channel audio, refusing inputs with more than `max_channels`
+/// channels, and return the result and the number of bytes read from the input.
+fn convert(max_channels: usize) -> (Result<(), AudioConversionError>, usize) {
+    let input = float_wav(&tone(RATE, 8, 10.0, 440.0), RATE, 8);
+    let bytes_read = Arc::new(AtomicUsize::new(0));
+    let source = CountingSource {
+        inner: Cursor::new(input),
+        seekable: true,
+        bytes_read: bytes_read.clone(),
+    };
+
+    let result = AudioConverterBuilder::new(tmp_path("precheck.wav"), RATE)
+        .with_precheck(move |info| {
+            if info.channels > max_channels {
+                return Err(AudioConversionError::UnsupportedFormat(format!(
+                    "{} channels",
+                    info.channels
+                )));
+            }
+            Ok(())
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_source(source)
+        .map(|_| ());
+    (result, bytes_read.load(Ordering::Relaxed))
+}
+
+#[test]
+fn precheck_rejects_before_decoding() {
+    // The 5 MB input is rejected after reading little more than its header
+    let (result, bytes_read) = convert(2);
+    match result {
+        Err(AudioConversionError::UnsupportedFormat(reason)) => assert_eq!(reason, "8 channels"),
+        other => panic!("{:?}", other),
+    }
+    assert!(bytes_read < 100_000, "read {} bytes", bytes_read);
+
+    // Accepted, the whole input is decoded
+    let (result, bytes_read) = convert(8);
+    result.unwrap();
+    assert!(bytes_read > 5_000_000, "read {} bytes", bytes_read);
+}
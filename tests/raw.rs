@@ -0,0 +1,71 @@
+mod common;
+
+use common::{float_wav, int16_wav, read_wav, tmp_path, tone};
+use wavup::{AudioConverterBuilder, RawFormat};
+
+const RATE: u32 = 16000;
+
+/// The samples of converting `raw` to 8 kHz, and of converting the same samples in the WAV
+/// file `reference` to 8 kHz.
+fn convert(raw: &[u8], format: RawFormat, reference: &[u8], name: &str) -> (Vec<f32>, Vec<f32>) {
+    let converter = |output: &str| {
+        AudioConverterBuilder::new(tmp_path(output), 8000)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+    };
+
+    converter(&format!("{}_raw.wav", name))
+        .convert_raw_pcm(raw, RATE, 2, format)
+        .unwrap();
+    converter(&format!("{}_reference.wav", name))
+        .convert_audio_from_bytes(reference)
+        .unwrap();
+
+    let (raw_spec, raw_samples) = read_wav(tmp_path(&format!("{}_raw.wav", name)));
+    let (reference_spec, reference_samples) =
+        read_wav(tmp_path(&format!("{}_reference.wav", name)));
+    assert_eq!(raw_spec, reference_spec);
+    assert_eq!(raw_spec.sample_rate, 8000);
+    assert_eq!(raw_spec.channels, 2);
+    (raw_samples, reference_samples)
+}
+
+#[test]
+fn s16le_matches_the_wav_conversion() {
+    let samples: Vec<i16> = tone(RATE, 2, 1.0, 440.0)
+        .iter()
+        .map(|sample| (sample * i16::MAX as f32) as i16)
+        .collect();
+    let raw: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+
+    let (converted, reference) = convert(
+        &raw,
+        RawFormat::S16LE,
+        &int16_wav(&samples, RATE, 2),
+        "raw_s16le",
+    );
+    assert_eq!(converted.len(), 16000);
+    assert_eq!(converted, reference);
+}
+
+#[test]
+fn f32le_matches_the_wav_conversion() {
+    let samples = tone(RATE, 2, 1.0, 440.0);
+    let raw: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+
+    let (converted, reference) = convert(
+        &raw,
+        RawFormat::F32LE,
+        &float_wav(&samples, RATE, 2),
+        "raw_f32le",
+    );
+    assert_eq!(converted.len(), 16000);
+    assert_eq!(converted, reference);
+}
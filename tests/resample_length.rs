@@ -0,0 +1,96 @@
+mod common;
+
+use common::{float_wav, write_fixture};
+use wavup::AudioConverterBuilder;
+
+const SOURCE_RATES: [u32; 6] = [8000, 11025, 22050, 44100, 48000, 96000];
+const TARGET_RATES: [u32; 2] = [16000, 48000];
+const CHUNK_SIZES: [usize; 4] = [64, 333, 1024, 4096];
+
+/// The amplitude and frequency of the test tone, low enough that consecutive output samples of a
+/// smooth sine differ by little at every target rate.
+const AMPLITUDE: f64 = 0.5;
+const FREQUENCY: f64 = 100.0;
+
+/// The length of the input, not a whole number of frames of any chunk size.
+const INPUT_SECS: f64 = 1.37;
+
+/// A mono sine of `frames` frames at `sample_rate`.
+fn sine(sample_rate: u32, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|frame| {
+            let t = frame as f64 / sample_rate as f64;
+            (AMPLITUDE * (2.0 * std::f64::consts::PI * FREQUENCY * t).sin()) as f32
+        })
+        .collect()
+}
+
+/// The largest second difference of the samples away from the edges, where the resampler filter
+/// settles. A dropped, repeated or misaligned frame at a chunk boundary shows up as a spike.
+fn max_curvature(samples: &[f32], margin: usize) -> f64 {
+    samples[margin..samples.len() - margin]
+        .windows(3)
+        .map(|w| (w[0] as f64 - 2.0 * w[1] as f64 + w[2] as f64).abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn resampled_length_and_continuity_hold_for_every_chunk_size() {
+    for source_rate in SOURCE_RATES {
+        let input_frames = (INPUT_SECS * source_rate as f64).round() as usize;
+        let input_path = write_fixture(
+            &format!("resample_length_{}.wav", source_rate),
+            &float_wav(&sine(source_rate, input_frames), source_rate, 1),
+        );
+
+        for target_rate in TARGET_RATES {
+            let expected =
+                (input_frames as f64 * target_rate as f64 / source_rate as f64).round() as i64;
+            // The second difference of the sine itself, with room for the filter ripple
+            let step = 2.0 * std::f64::consts::PI * FREQUENCY / target_rate as f64;
+            let limit = 1.5 * AMPLITUDE * step * step + 5e-4;
+            let margin = target_rate as usize / 10;
+
+            for chunk_size in CHUNK_SIZES {
+                let case = format!(
+                    "{} Hz to {} Hz in chunks of {}",
+                    source_rate, target_rate, chunk_size
+                );
+                let converter = AudioConverterBuilder::new("unused.wav", target_rate)
+                    .with_input_path(&input_path)
+                    .with_resampler_chunk_size(chunk_size)
+                    .build()
+                    .unwrap();
+
+                let buffered: Vec<f32> = converter.convert_to_samples().unwrap();
+                let mut streamed = Vec::new();
+                converter
+                    .convert_streaming(|block| {
+                        streamed.extend_from_slice(block);
+                        Ok(())
+                    })
+                    .unwrap();
+
+                for (path, samples) in [("buffered", &buffered), ("streamed", &streamed)] {
+                    assert!(
+                        (samples.len() as i64 - expected).abs() <= 1,
+                        "{} {}: {} frames instead of {}",
+                        path,
+                        case,
+                        samples.len(),
+                        expected
+                    );
+                    let curvature = max_curvature(samples, margin);
+                    assert!(
+                        curvature <= limit,
+                        "{} {}: a jump of {} between frames, at most {} expected",
+                        path,
+                        case,
+                        curvature,
+                        limit
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{flac_in_mkv, float_wav, read_wav, tmp_path, write_fixture};
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use wavup::{AudioConversionError, AudioConverterBuilder, OutputFormat};
+
+const INPUT_RATE: u32 = 11025;
+const OUTPUT_RATE: u32 = 8000;
+
+/// Seventy seconds of a mono chord at `sample_rate` whose notes drift, so every segment is
+/// different.
+fn input(sample_rate: u32) -> Vec<f32> {
+    (0..70 * sample_rate as usize)
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            0.3 * (2.0 * std::f32::consts::PI * (220.0 + t) * t).sin()
+                + 0.2 * (2.0 * std::f32::consts::PI * 1500.0 * t).sin()
+        })
+        .collect()
+}
+
+/// Convert the input at `input_path` with resuming enabled, cancel the conversion 45 seconds in
+/// and resume it, and check the result matches an uninterrupted conversion.
+fn assert_resumes_like_an_uninterrupted_conversion(input_path: &Path, input_rate: u32, name: &str) {
+    let converter = |output_path: &Path| {
+        AudioConverterBuilder::new(output_path, OUTPUT_RATE)
+            .with_input_path(input_path)
+            .with_resume(true)
+            .with_overwrite(true)
+    };
+
+    // A plain conversion resamples the whole input in one go
+    let expected_path = tmp_path(&format!("{}_uninterrupted.wav", name));
+    converter(&expected_path)
+        .with_resume(false)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    let (_, expected) = read_wav(&expected_path);
+
+    // Cancel the conversion 45 seconds in, past the first checkpoint at 30 seconds
+    let output_path = tmp_path(&format!("{}_interrupted.wav", name));
+    let _ = std::fs::remove_file(&output_path);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_at_midpoint = Arc::clone(&cancel);
+    let result = converter(&output_path)
+        .with_cancel_token(Arc::clone(&cancel))
+        .with_progress_callback(move |progress| {
+            if progress.frames_decoded > 45 * input_rate as u64 {
+                cancel_at_midpoint.store(true, Ordering::Relaxed);
+            }
+        })
+        .build()
+        .unwrap()
+        .convert_audio();
+    assert!(matches!(result, Err(AudioConversionError::Cancelled)));
+    assert!(std::fs::metadata(format!("{}.progress", output_path.display())).is_ok());
+
+    let report = converter(&output_path)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    let (_, resumed) = read_wav(&output_path);
+
+    assert_eq!(report.input_duration_secs, 70.0);
+    assert_eq!(resumed.len(), 70 * OUTPUT_RATE as usize);
+    assert_eq!(resumed, expected);
+}
+
+#[test]
+fn resumed_conversion_matches_an_uninterrupted_one() {
+    let input_path = write_fixture("resume.wav", &float_wav(&input(INPUT_RATE), INPUT_RATE, 1));
+    assert_resumes_like_an_uninterrupted_conversion(&input_path, INPUT_RATE, "resume");
+}
+
+#[test]
+fn resuming_an_input_with_millisecond_timestamps_skips_the_converted_frames() {
+    // FLAC frames of 4096 frames at 16 kHz last exactly 256 ms
+    let flac_path = tmp_path("resume_ms.flac");
+    AudioConverterBuilder::new(&flac_path, 16000)
+        .with_output_format(OutputFormat::Flac {
+            compression_level: 5,
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&input(16000), 16000, 1))
+        .unwrap();
+    let input_path = write_fixture(
+        "resume_ms.mkv",
+        &flac_in_mkv(&std::fs::read(flac_path).unwrap()),
+    );
+    assert_resumes_like_an_uninterrupted_conversion(&input_path, 16000, "resume_ms");
+}
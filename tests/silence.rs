@@ -0,0 +1,73 @@
+mod common;
+
+use common::{float_wav, read_wav, tmp_path, tone};
+use wavup::{AudioConverterBuilder, TrimOptions, Weighting};
+
+const RATE: u32 = 16000;
+
+/// One second of a 1 kHz tone followed by three seconds of 50 Hz rumble at -26 dBFS, loud enough
+/// to pass the default -40 dBFS threshold unweighted.
+fn tone_with_rumble_tail() -> Vec<u8> {
+    let mut samples = tone(RATE, 1, 1.0, 1000.0);
+    samples.extend(tone(RATE, 1, 3.0, 50.0).iter().map(|sample| sample * 0.1));
+    float_wav(&samples, RATE, 1)
+}
+
+/// The duration of the output after trimming the trailing silence with `weighting`.
+fn trimmed_secs(input: &[u8], weighting: Weighting, name: &str) -> f32 {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_trim_trailing_silence(TrimOptions::default())
+        .with_silence_weighting(weighting)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(input)
+        .unwrap();
+    let (_, samples) = read_wav(&output_path);
+    samples.len() as f32 / RATE as f32
+}
+
+#[test]
+fn a_weighting_trims_a_rumble_tail() {
+    let input = tone_with_rumble_tail();
+
+    // A-weighting attenuates 50 Hz by 30 dB, so the rumble counts as silence and only the tone
+    // and the 0.5 s padding are kept
+    let weighted = trimmed_secs(&input, Weighting::AWeighting, "silence_a_weighted.wav");
+    assert!((weighted - 1.5).abs() < 0.05, "kept {} s", weighted);
+
+    // Unweighted, the rumble counts as sound and nothing is trimmed
+    let unweighted = trimmed_secs(&input, Weighting::None, "silence_unweighted.wav");
+    assert_eq!(unweighted, 4.0);
+}
+
+/// The duration of the output after trimming trailing silence of at least one second.
+fn min_silence_trimmed_secs(samples: &[f32], name: &str) -> f32 {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_trim_trailing_silence(TrimOptions::default())
+        .with_min_trim_silence_secs(1.0)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, 1))
+        .unwrap();
+    let (_, samples) = read_wav(&output_path);
+    samples.len() as f32 / RATE as f32
+}
+
+#[test]
+fn min_trim_silence_keeps_abrupt_endings() {
+    // A tone followed by a quarter second of silence, shorter than the minimum, is kept whole
+    let mut abrupt = tone(RATE, 1, 1.0, 1000.0);
+    abrupt.extend(vec![0.0; RATE as usize / 4]);
+    let kept = min_silence_trimmed_secs(&abrupt, "silence_min_abrupt.wav");
+    assert_eq!(kept, 1.25);
+
+    // Two seconds of silence are trimmed down to the 0.5 s padding
+    let mut long_tail = tone(RATE, 1, 1.0, 1000.0);
+    long_tail.extend(vec![0.0; 2 * RATE as usize]);
+    let kept = min_silence_trimmed_secs(&long_tail, "silence_min_long_tail.wav");
+    assert!((kept - 1.5).abs() < 0.05, "kept {} s", kept);
+}
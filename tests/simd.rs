@@ -0,0 +1,94 @@
+#![cfg(feature = "simd")]
+
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use hound::WavReader;
+use wavup::{quantize, AudioConverterBuilder};
+
+const RATE: u32 = 8000;
+
+/// Samples on the edges of the rounding and saturation of 16-bit quantization: ties half an LSB
+/// either side of zero and of other codes, full scale, just below the top code and values far
+/// outside `[-1.0, 1.0]`.
+fn edge_samples() -> Vec<f32> {
+    let lsb = 1.0 / 32768.0;
+    let mut samples = vec![
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        1.0 - lsb,
+        1.0 - lsb / 2.0,
+        -1.0 + lsb / 2.0,
+        1.0 + lsb / 2.0,
+        -1.0 - lsb / 2.0,
+        1.5,
+        -1.5,
+        2.0,
+        -2.0,
+        1e6,
+        -1e6,
+    ];
+    for code in [0, 1, 2, 3, 100, 101, 16383, 32766] {
+        for offset in [0.5, 0.49, 0.51] {
+            let sample = (code as f32 + offset) * lsb;
+            samples.extend([sample, -sample]);
+        }
+    }
+    samples
+}
+
+/// Convert the mono `samples` to 16-bit WAV output and return the written samples.
+fn quantize_with_converter(name: &str, samples: &[f32]) -> Vec<i16> {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_bits_per_sample(16)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(samples, RATE, 1))
+        .unwrap();
+    WavReader::open(output_path)
+        .unwrap()
+        .samples::<i16>()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn vectorized_quantization_matches_the_scalar_path() {
+    let mut samples = edge_samples();
+    samples.extend(tone(RATE, 1, 0.1, 440.0));
+
+    let expected: Vec<i16> = samples
+        .iter()
+        .map(|sample| quantize(*sample, 16) as i16)
+        .collect();
+    assert_eq!(
+        quantize_with_converter("simd_quantize.wav", &samples),
+        expected
+    );
+}
+
+#[test]
+fn lengths_that_are_not_a_multiple_of_the_vector_width_quantize_every_sample() {
+    // The edge samples land in both the vectorized blocks and the scalar remainder
+    let samples = edge_samples();
+    for len in [1, 7, 8, 9, 15, 16, 17, 23] {
+        for start in [0, 3] {
+            let samples = &samples[start..start + len];
+            let expected: Vec<i16> = samples
+                .iter()
+                .map(|sample| quantize(*sample, 16) as i16)
+                .collect();
+            assert_eq!(
+                quantize_with_converter(&format!("simd_quantize_{}_{}.wav", start, len), samples),
+                expected,
+                "{} samples from {}",
+                len,
+                start
+            );
+        }
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{float_wav, read_wav, read_wav_bytes, tmp_path, tone, CountingSource};
+use std::{
+    io::Cursor,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use wavup::AudioConverterBuilder;
+
+const RATE: u32 = 16000;
+
+#[test]
+fn custom_media_source_converts_like_bytes() {
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+
+    let bytes_path = tmp_path("source_bytes.wav");
+    AudioConverterBuilder::new(&bytes_path, 8000)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&input)
+        .unwrap();
+    let (_, expected) = read_wav(&bytes_path);
+
+    for seekable in [true, false] {
+        let bytes_read = Arc::new(AtomicUsize::new(0));
+        let source = CountingSource {
+            inner: Cursor::new(input.clone()),
+            seekable,
+            bytes_read: Arc::clone(&bytes_read),
+        };
+
+        let source_path = tmp_path("source_custom.wav");
+        AudioConverterBuilder::new(&source_path, 8000)
+            .with_overwrite(true)
+            .build()
+            .unwrap()
+            .convert_audio_from_source(source)
+            .unwrap();
+
+        // The input is read through the custom source
+        assert!(bytes_read.load(Ordering::Relaxed) >= input.len());
+        let (_, samples) = read_wav(&source_path);
+        assert_eq!(samples, expected, "seekable: {}", seekable);
+    }
+}
+
+#[test]
+fn shared_bytes_convert_to_vec_in_place() {
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+    let converter = AudioConverterBuilder::new("", 8000).build().unwrap();
+    let expected = converter.convert_bytes_to_vec(input.clone()).unwrap();
+
+    // The converter reads the shared buffer itself and drops its handle once done
+    let shared: Arc<[u8]> = input.into();
+    let output = converter.convert_bytes_to_vec(Arc::clone(&shared)).unwrap();
+    assert_eq!(Arc::strong_count(&shared), 1);
+    assert_eq!(read_wav_bytes(&output), read_wav_bytes(&expected));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::{int16_wav, sample_path, tmp_path, tone};
+use std::{path::Path, time::Duration};
+use wavup::{AudioConverterBuilder, ConversionReport, OutputFormat};
+
+/// The report of converting the first two seconds of `input`.
+fn report(input: &Path) -> ConversionReport {
+    let name = input.file_name().unwrap().to_str().unwrap();
+    AudioConverterBuilder::new(tmp_path(&format!("source_info_{}.wav", name)), 16000)
+        .with_input_path(input)
+        .with_time_range(Duration::ZERO, Some(Duration::from_secs(2)))
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap()
+}
+
+#[test]
+fn flac_is_lossless_with_its_bit_depth() {
+    // A 16-bit tone encoded to FLAC by wavup
+    let samples: Vec<i16> = tone(16000, 1, 3.0, 440.0)
+        .iter()
+        .map(|sample| (sample * i16::MAX as f32) as i16)
+        .collect();
+    let flac_path = tmp_path("source_info.flac");
+    AudioConverterBuilder::new(&flac_path, 16000)
+        .with_output_format(OutputFormat::Flac {
+            compression_level: 5,
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&int16_wav(&samples, 16000, 1))
+        .unwrap();
+
+    let report = report(&flac_path);
+    assert_eq!(report.input_codec, Some("flac"));
+    assert_eq!(report.source_bit_depth, Some(16));
+    assert!(!report.is_lossy);
+}
+
+// There is no Opus fixture since symphonia cannot decode Opus; Vorbis stands in for the lossy
+// codecs without a bit depth
+#[test]
+fn vorbis_is_lossy_without_a_bit_depth() {
+    let report = report(&sample_path("example_vorbris.ogg"));
+    assert_eq!(report.input_codec, Some("vorbis"));
+    assert_eq!(report.source_bit_depth, None);
+    assert!(report.is_lossy);
+}
+
+#[cfg(feature = "mp3")]
+#[test]
+fn mp3_is_lossy() {
+    let report = report(&sample_path("mono_ch_audio.mp3"));
+    assert_eq!(report.source_bit_depth, None);
+    assert!(report.is_lossy);
+}
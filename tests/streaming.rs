@@ -0,0 +1,32 @@
+mod common;
+
+use common::{float_wav, tone, write_fixture};
+use wavup::AudioConverterBuilder;
+
+#[test]
+fn streamed_blocks_match_the_buffered_conversion() {
+    let input_path = write_fixture(
+        "streaming.wav",
+        &float_wav(&tone(44100, 2, 3.0, 440.0), 44100, 2),
+    );
+    let converter = AudioConverterBuilder::new("unused.wav", 16000)
+        .with_input_path(&input_path)
+        .with_target_channels(1)
+        .build()
+        .unwrap();
+
+    let mut blocks = 0;
+    let mut streamed = Vec::new();
+    converter
+        .convert_streaming(|block| {
+            blocks += 1;
+            streamed.extend_from_slice(block);
+            Ok(())
+        })
+        .unwrap();
+    let buffered: Vec<f32> = converter.convert_to_samples().unwrap();
+
+    assert!(blocks > 1, "only {} block", blocks);
+    assert_eq!(streamed.len(), 3 * 16000);
+    assert_eq!(streamed, buffered);
+}
@@ -0,0 +1,23 @@
+use wavup::{supported_codecs, supported_extensions, InputCodec};
+
+#[test]
+fn extensions_follow_the_enabled_features() {
+    // Opus is not listed: symphonia can demux Ogg Opus but has no decoder for it
+    let extensions = supported_extensions();
+    for extension in ["flac", "ogg", "oga", "wav"] {
+        assert!(extensions.contains(&extension), "{} missing", extension);
+    }
+    assert_eq!(extensions.contains(&"mp3"), cfg!(feature = "mp3"));
+    assert_eq!(extensions.contains(&"aac"), cfg!(feature = "aac"));
+    assert_eq!(extensions.contains(&"m4a"), cfg!(feature = "isomp4"));
+}
+
+#[test]
+fn codecs_follow_the_enabled_features() {
+    let codecs = supported_codecs();
+    for codec in [InputCodec::Flac, InputCodec::Pcm, InputCodec::Vorbis] {
+        assert!(codecs.contains(&codec), "{:?} missing", codec);
+    }
+    assert_eq!(codecs.contains(&InputCodec::Mp3), cfg!(feature = "mp3"));
+    assert_eq!(codecs.contains(&InputCodec::Aac), cfg!(feature = "aac"));
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone, write_fixture};
+use std::path::PathBuf;
+use wavup::{AudioConverterBuilder, AudioInspector, OutputFormat, Tags};
+
+const RATE: u32 = 16000;
+
+/// The `INFO` subchunks of the test tags, with values of even and odd lengths.
+const INFO: [(&[u8; 4], &str); 4] = [
+    (b"INAM", "Tone"),
+    (b"IART", "wavup"),
+    (b"IPRD", "Sines"),
+    (b"ICMT", "A test"),
+];
+
+/// The body of a `LIST` chunk of type `INFO` holding the `INFO` tags, with NUL-terminated values
+/// padded to even lengths.
+fn info_list() -> Vec<u8> {
+    let mut body = b"INFO".to_vec();
+    for (id, value) in INFO {
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(value.len() as u32 + 1).to_le_bytes());
+        body.extend_from_slice(value.as_bytes());
+        body.push(0);
+        if value.len() % 2 == 0 {
+            body.push(0);
+        }
+    }
+    body
+}
+
+/// A tone whose tags are in a `LIST` chunk placed before the `data` chunk, where the WAV reader
+/// looks for it.
+fn tagged_input(name: &str) -> PathBuf {
+    let mut wav = float_wav(&tone(RATE, 1, 1.0, 440.0), RATE, 1);
+    let body = info_list();
+    let mut list = b"LIST".to_vec();
+    list.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    list.extend_from_slice(&body);
+    let data_pos = wav.windows(4).position(|id| id == b"data").unwrap();
+    wav.splice(data_pos..data_pos, list);
+    let riff_len = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    write_fixture(name, &wav)
+}
+
+/// Convert the tagged input to `output_format` output, copying its tags, and return its path.
+fn convert(name: &str, output_format: OutputFormat) -> PathBuf {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(tagged_input(&format!("{}_input.wav", name)))
+        .with_output_format(output_format)
+        .with_copy_metadata(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    output_path
+}
+
+fn expected_tags() -> Tags {
+    Tags {
+        title: Some("Tone".to_string()),
+        artist: Some("wavup".to_string()),
+        album: Some("Sines".to_string()),
+        comment: Some("A test".to_string()),
+    }
+}
+
+#[test]
+fn wav_output_stores_the_tags_in_a_list_info_chunk() {
+    let output_path = convert("tags.wav", OutputFormat::Wav);
+    let bytes = std::fs::read(&output_path).unwrap();
+
+    // The LIST chunk follows the data chunk, where symphonia does not look for it, and values of
+    // even length get a pad byte after their NUL terminator
+    let list_pos = bytes.windows(4).rposition(|id| id == b"LIST").unwrap();
+    let data_pos = bytes.windows(4).position(|id| id == b"data").unwrap();
+    assert!(list_pos > data_pos);
+    let body = info_list();
+    let list_len = u32::from_le_bytes(bytes[list_pos + 4..list_pos + 8].try_into().unwrap());
+    assert_eq!(list_len as usize, body.len());
+    assert_eq!(bytes[list_pos + 8..], body);
+    let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    assert_eq!(riff_len as usize, bytes.len() - 8);
+}
+
+#[test]
+fn flac_output_stores_the_tags_in_a_vorbis_comment_block() {
+    let output_path = convert(
+        "tags.flac",
+        OutputFormat::Flac {
+            compression_level: 5,
+        },
+    );
+    let bytes = std::fs::read(&output_path).unwrap();
+
+    // The STREAMINFO block is followed by the VORBIS_COMMENT block, the last one
+    assert_eq!(&bytes[..4], b"fLaC");
+    assert_eq!(bytes[4], 0x00);
+    assert_eq!(bytes[42], 0x84);
+    let block_len = u32::from_be_bytes([0, bytes[43], bytes[44], bytes[45]]) as usize;
+    let block = &bytes[46..46 + block_len];
+
+    // The vendor string, then the fields as little-endian lengths and NAME=value strings
+    let vendor = concat!("wavup ", env!("CARGO_PKG_VERSION"));
+    let mut expected = (vendor.len() as u32).to_le_bytes().to_vec();
+    expected.extend_from_slice(vendor.as_bytes());
+    expected.extend_from_slice(&4u32.to_le_bytes());
+    for field in [
+        "TITLE=Tone",
+        "ARTIST=wavup",
+        "ALBUM=Sines",
+        "COMMENT=A test",
+    ] {
+        expected.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        expected.extend_from_slice(field.as_bytes());
+    }
+    assert_eq!(block, expected);
+
+    assert_eq!(
+        AudioInspector::probe(output_path.as_path()).unwrap().tags,
+        expected_tags()
+    );
+}
+
+#[test]
+fn tags_are_not_copied_by_default() {
+    let output_path = tmp_path("tags_default.wav");
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(tagged_input("tags_default_input.wav"))
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+    let bytes = std::fs::read(&output_path).unwrap();
+    assert!(!bytes.windows(4).any(|id| id == b"LIST"));
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{flac_in_mkv, float_wav, read_wav, tmp_path, tone, write_fixture};
+use std::{path::PathBuf, time::Duration};
+use wavup::{AudioConverterBuilder, OutputFormat};
+
+const RATE: u32 = 16000;
+
+/// Four minutes of a mono tone encoded to FLAC by wavup.
+fn long_flac() -> PathBuf {
+    let path = tmp_path("time_range_long.flac");
+    let converter = AudioConverterBuilder::new(&path, RATE)
+        .with_output_format(OutputFormat::Flac {
+            compression_level: 5,
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap();
+    converter
+        .convert_audio_from_bytes(&float_wav(&tone(RATE, 1, 240.0, 440.0), RATE, 1))
+        .unwrap();
+    path
+}
+
+#[test]
+fn seeking_into_wav_converts_only_the_window() {
+    // Four minutes of a mono tone
+    let input = write_fixture(
+        "time_range_long.wav",
+        &float_wav(&tone(RATE, 1, 240.0, 440.0), RATE, 1),
+    );
+
+    let full_path = tmp_path("time_range_wav_full.wav");
+    let full_report = AudioConverterBuilder::new(&full_path, RATE)
+        .with_input_path(&input)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    let window_path = tmp_path("time_range_wav_window.wav");
+    let window_report = AudioConverterBuilder::new(&window_path, RATE)
+        .with_input_path(&input)
+        .with_time_range(Duration::from_secs(120), Some(Duration::from_secs(125)))
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    // Only the requested window is written, and it is the same audio as in the full conversion
+    let (_, full) = read_wav(&full_path);
+    let (_, window) = read_wav(&window_path);
+    let start = 120 * RATE as usize;
+    assert_eq!(window.len(), 5 * RATE as usize);
+    assert_eq!(window, full[start..start + window.len()]);
+
+    // Seeking skips the packets before the window instead of decoding them
+    assert!(
+        window_report.decoded_packets * 20 < full_report.decoded_packets,
+        "decoded {} packets for the window and {} for the whole input",
+        window_report.decoded_packets,
+        full_report.decoded_packets
+    );
+}
+
+#[test]
+fn seeking_into_flac_decodes_only_the_window() {
+    let input = long_flac();
+
+    let full_path = tmp_path("time_range_flac_full.wav");
+    let full_report = AudioConverterBuilder::new(&full_path, RATE)
+        .with_input_path(&input)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    let window_path = tmp_path("time_range_flac_window.wav");
+    let window_report = AudioConverterBuilder::new(&window_path, RATE)
+        .with_input_path(&input)
+        .with_time_range(Duration::from_secs(120), Some(Duration::from_secs(125)))
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    // Only the requested window is written, and it is the same audio as in the full conversion
+    let (_, full) = read_wav(&full_path);
+    let (_, window) = read_wav(&window_path);
+    let start = 120 * RATE as usize;
+    assert_eq!(window.len(), 5 * RATE as usize);
+    assert_eq!(window, full[start..start + window.len()]);
+
+    // Seeking skips the packets before the window instead of decoding them
+    assert!(
+        window_report.decoded_packets * 20 < full_report.decoded_packets,
+        "decoded {} packets for the window and {} for the whole input",
+        window_report.decoded_packets,
+        full_report.decoded_packets
+    );
+}
+
+#[test]
+fn windows_of_inputs_with_millisecond_timestamps_are_cut_at_the_right_frames() {
+    // Packets of 4096 frames, i.e. 256 ms, so the window starts and ends inside packets
+    let input = write_fixture(
+        "time_range_ms.mkv",
+        &flac_in_mkv(&std::fs::read(long_flac()).unwrap()),
+    );
+
+    let full_path = tmp_path("time_range_ms_full.wav");
+    AudioConverterBuilder::new(&full_path, RATE)
+        .with_input_path(long_flac())
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    let window_path = tmp_path("time_range_ms_window.wav");
+    AudioConverterBuilder::new(&window_path, RATE)
+        .with_input_path(&input)
+        .with_time_range(
+            Duration::from_millis(60_100),
+            Some(Duration::from_millis(61_300)),
+        )
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio()
+        .unwrap();
+
+    let (_, full) = read_wav(&full_path);
+    let (_, window) = read_wav(&window_path);
+    let start = 60_100 * RATE as usize / 1000;
+    assert_eq!(window.len(), 1_200 * RATE as usize / 1000);
+    assert_eq!(window, full[start..start + window.len()]);
+}
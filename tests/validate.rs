@@ -0,0 +1,59 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone, write_fixture};
+use wavup::{AudioConversionError, AudioConverterBuilder, ConversionReport, OutputFormat};
+
+const RATE: u32 = 16000;
+
+/// Three seconds of a stereo tone encoded to FLAC by wavup.
+fn flac_bytes() -> Vec<u8> {
+    let path = tmp_path("validate_source.flac");
+    AudioConverterBuilder::new(&path, RATE)
+        .with_output_format(OutputFormat::Flac {
+            compression_level: 5,
+        })
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(&tone(RATE, 2, 3.0, 440.0), RATE, 2))
+        .unwrap();
+    std::fs::read(path).unwrap()
+}
+
+/// Validate the input file `name` holding `bytes`, checking that no output is written.
+fn validate(name: &str, bytes: &[u8]) -> Result<ConversionReport, AudioConversionError> {
+    let output_path = tmp_path(&format!("{}.wav", name));
+    let _ = std::fs::remove_file(&output_path);
+    let report = AudioConverterBuilder::new(&output_path, RATE)
+        .with_input_path(write_fixture(name, bytes))
+        .build()
+        .unwrap()
+        .validate();
+    assert!(!output_path.exists());
+    report
+}
+
+#[test]
+fn good_input_is_decoded_to_the_end() {
+    let report = validate("validate_good.flac", &flac_bytes()).unwrap();
+    assert!(!report.truncated);
+    assert!((report.input_duration_secs - 3.0).abs() < 1e-3);
+}
+
+#[test]
+fn truncated_input_is_flagged() {
+    let flac = flac_bytes();
+    let report = validate("validate_truncated.flac", &flac[..flac.len() / 2]).unwrap();
+    assert!(report.truncated);
+
+    let wav = float_wav(&tone(RATE, 2, 3.0, 440.0), RATE, 2);
+    let report = validate("validate_truncated.wav", &wav[..wav.len() / 2]).unwrap();
+    assert!(report.truncated);
+    assert!(report.input_duration_secs < 2.0);
+}
+
+#[test]
+fn undecodable_input_fails() {
+    let result = validate("validate_garbage.flac", &[0x5a; 4096]);
+    assert!(matches!(result, Err(AudioConversionError::ProbeError(_))));
+}
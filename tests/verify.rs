@@ -0,0 +1,44 @@
+mod common;
+
+use common::{float_wav, tmp_path, tone};
+use wavup::{AudioConversionError, AudioConverterBuilder};
+
+const RATE: u32 = 16000;
+
+#[test]
+fn verification_accepts_an_intact_output() {
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+    AudioConverterBuilder::new(tmp_path("verify_intact.wav"), 8000)
+        .with_verify_output(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&input)
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn verification_fails_when_the_writes_are_lost() {
+    // Stand in for a disk that silently drops the data: the temporary file the output is written
+    // to before being renamed into place accepts every write, but nothing can be read back
+    let output_path = tmp_path("verify_lost.wav");
+    let temp_path = tmp_path(&format!("verify_lost.wav.{}.tmp", std::process::id()));
+    let _ = std::fs::remove_file(&temp_path);
+    std::os::unix::fs::symlink("/dev/null", &temp_path).unwrap();
+
+    let input = float_wav(&tone(RATE, 2, 1.0, 440.0), RATE, 2);
+    let result = AudioConverterBuilder::new(&output_path, 8000)
+        .with_verify_output(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&input);
+    assert!(!temp_path.exists());
+
+    assert!(
+        matches!(result, Err(AudioConversionError::VerificationFailed(_))),
+        "{:?}",
+        result
+    );
+}
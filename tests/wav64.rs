@@ -0,0 +1,108 @@
+mod common;
+
+use common::{float_wav, tmp_path};
+use wavup::{AudioConverterBuilder, LargeFileFormat};
+
+const RATE: u32 = 8000;
+
+/// Three frames of each channel, so the data of 24-bit mono and 16-bit stereo output needs padding.
+const SAMPLES: [f32; 6] = [0.5, -0.5, 0.25, -0.25, 0.0, 0.125];
+
+/// Convert the first three frames of `SAMPLES` to `format` output with `channels` channels of
+/// `bits` bits, verifying its header, and return the bytes of the file.
+fn encode(name: &str, format: LargeFileFormat, channels: u16, bits: u16) -> Vec<u8> {
+    let output_path = tmp_path(name);
+    AudioConverterBuilder::new(&output_path, RATE)
+        .with_large_file(format)
+        .with_bits_per_sample(bits)
+        .with_verify_output(true)
+        .with_overwrite(true)
+        .build()
+        .unwrap()
+        .convert_audio_from_bytes(&float_wav(
+            &SAMPLES[..3 * channels as usize],
+            RATE,
+            channels,
+        ))
+        .unwrap();
+    std::fs::read(output_path).unwrap()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[test]
+fn rf64_output_stores_its_sizes_in_the_ds64_chunk() {
+    // 24-bit samples use WAVE_FORMAT_EXTENSIBLE, 16-bit stereo the plain fmt chunk
+    for (channels, bits, fmt_len) in [(1, 24, 40), (2, 16, 16)] {
+        let bytes = encode(
+            &format!("wav64_{}_{}.rf64", channels, bits),
+            LargeFileFormat::Rf64,
+            channels,
+            bits,
+        );
+        let data_len = 3 * channels as usize * bits as usize / 8;
+        let data_start = 56 + fmt_len + 8;
+
+        // The 32-bit sizes are -1 and the real ones are in the ds64 chunk
+        assert_eq!(&bytes[..4], b"RF64");
+        assert_eq!(read_u32(&bytes, 4), u32::MAX);
+        assert_eq!(&bytes[8..16], b"WAVEds64");
+        assert_eq!(read_u32(&bytes, 16), 28);
+        assert_eq!(read_u64(&bytes, 20), bytes.len() as u64 - 8);
+        assert_eq!(read_u64(&bytes, 28), data_len as u64);
+        assert_eq!(read_u64(&bytes, 36), 3);
+        assert_eq!(read_u32(&bytes, 44), 0);
+        assert_eq!(&bytes[48..52], b"fmt ");
+        assert_eq!(read_u32(&bytes, 52) as usize, fmt_len);
+        assert_eq!(&bytes[data_start - 8..data_start - 4], b"data");
+        assert_eq!(read_u32(&bytes, data_start - 4), u32::MAX);
+
+        // The data chunk is padded to an even length
+        assert_eq!(bytes.len(), data_start + data_len + data_len % 2);
+        assert!(bytes[data_start + data_len..].iter().all(|byte| *byte == 0));
+    }
+}
+
+#[test]
+fn w64_output_pads_its_chunks_to_8_bytes() {
+    for (channels, bits, fmt_len) in [(1, 24, 40), (2, 16, 16)] {
+        let bytes = encode(
+            &format!("wav64_{}_{}.w64", channels, bits),
+            LargeFileFormat::W64,
+            channels,
+            bits,
+        );
+        let data_len = 3 * channels as usize * bits as usize / 8;
+        let data_start = 64 + fmt_len + 24;
+
+        // Chunk ids are GUIDs and sizes are 64 bits and include the chunk header
+        assert_eq!(&bytes[..4], b"riff");
+        assert_eq!(read_u64(&bytes, 16), bytes.len() as u64);
+        assert_eq!(&bytes[24..28], b"wave");
+        assert_eq!(&bytes[40..44], b"fmt ");
+        assert_eq!(read_u64(&bytes, 56) as usize, 24 + fmt_len);
+        assert_eq!(&bytes[data_start - 24..data_start - 20], b"data");
+        assert_eq!(read_u64(&bytes, data_start - 8) as usize, 24 + data_len);
+
+        // The data chunk is padded to a multiple of 8 bytes
+        assert_eq!(bytes.len() % 8, 0);
+        assert_eq!(bytes.len(), data_start + data_len.next_multiple_of(8));
+        assert!(bytes[data_start + data_len..].iter().all(|byte| *byte == 0));
+    }
+}
+
+#[test]
+fn samples_follow_the_header_in_little_endian_order() {
+    // 0.5, -0.5 and 0.25 at 24 bits
+    let expected = [0x00, 0x00, 0x40, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x20];
+    let bytes = encode("wav64_samples.rf64", LargeFileFormat::Rf64, 1, 24);
+    assert_eq!(bytes[104..113], expected);
+    let bytes = encode("wav64_samples.w64", LargeFileFormat::W64, 1, 24);
+    assert_eq!(bytes[128..137], expected);
+}